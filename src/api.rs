@@ -1,33 +1,62 @@
 use std::{
     cmp::min,
+    future::Future,
     path::{Path, PathBuf},
-    time::Duration,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, SystemTime},
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
-use futures::StreamExt;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use reqwest::{Body, Client, StatusCode};
+use futures::{stream::BoxStream, Stream, StreamExt};
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
+use log::{debug, warn};
+use reqwest::{
+    header::{CONTENT_RANGE, ETAG, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE},
+    Body, Client, StatusCode,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::collections::HashMap;
 use time::OffsetDateTime;
-use tokio::fs::{create_dir_all, File};
-use tokio::io;
+use tokio::fs::{create_dir_all, File, OpenOptions};
+use tokio::{
+    io::{self, AsyncWriteExt},
+    sync::OnceCell,
+    time::sleep,
+};
 
 use crate::{
-    bundle::ApplicationBundle,
-    errors::{ApiError, EnvArgError, InputError},
+    backoff::Backoff,
+    bundle::{ApplicationBundle, LibraryBundle},
+    compression::{self, ZipOptions},
+    errors::{ApiError, EnvArgError, InputError, UploadError},
     filtering::model::SparseMarathonfile,
+    hash::{digest_file, md5_str, HashAlgorithm},
     pull::PullFileConfig,
+    push::PushFile,
+    retries::RetryQuotaOverride,
+    timeouts::TestTimeoutOverride,
+    upload_cache::UploadCache,
 };
 
-use tokio_util::io::ReaderStream;
+use tokio_util::{bytes::Bytes, io::ReaderStream};
+
+pub const DEFAULT_UPLOAD_RETRIES: u32 = 3;
+//Maximum size of a single uploaded file accepted by Marathon Cloud storage
+pub const DEFAULT_MAX_UPLOAD_SIZE_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+const UPLOAD_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(2);
+const UPLOAD_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+//Amount of archive data buffered between zipping and sending it
+const STREAM_BUFFER_SIZE: usize = 256 * 1024;
 
 #[async_trait]
 pub trait RapiClient {
     async fn get_token(&self) -> Result<String>;
+    #[allow(clippy::too_many_arguments)]
     async fn create_run(
         &self,
         app: Option<PathBuf>,
@@ -54,26 +83,55 @@ pub trait RapiClient {
         env_args: Option<Vec<String>>,
         test_env_args: Option<Vec<String>>,
         pull_file_config: Option<PullFileConfig>,
+        push_files: Option<Vec<PushFile>>,
+        app_data: Option<PathBuf>,
         concurrency_limit: Option<u32>,
         test_timeout_default: Option<u32>,
         test_timeout_max: Option<u32>,
         project: Option<String>,
         application_bundle: Option<Vec<ApplicationBundle>>,
-        library_bundle: Option<Vec<PathBuf>>,
+        library_bundle: Option<Vec<LibraryBundle>>,
+        additional_apks: Option<Vec<PathBuf>>,
         granted_permission: Option<Vec<String>>,
+        remote_artifacts: RemoteArtifacts,
+        run_options: RunOptions,
     ) -> Result<String>;
     async fn get_run(&self, id: &str) -> Result<TestRun>;
+    /// Most recent completed run matching `filter`, if any
+    async fn find_latest_run(&self, filter: &RunFilter) -> Result<Option<TestRun>>;
+    async fn cancel_run(&self, id: &str) -> Result<()>;
+    async fn get_run_events(
+        &self,
+        jwt_token: &str,
+        id: &str,
+        cursor: Option<u64>,
+    ) -> Result<TestRunEvents>;
+    async fn subscribe_run(
+        &self,
+        jwt_token: &str,
+        id: &str,
+    ) -> Result<BoxStream<'static, Result<TestRunStateChanged>>>;
+    async fn get_run_durations(
+        &self,
+        jwt_token: &str,
+        project: Option<&str>,
+        branch: Option<&str>,
+    ) -> Result<Vec<f64>>;
 
     async fn list_artifact(&self, jwt_token: &str, id: &str) -> Result<Vec<Artifact>>;
+    /// Downloads `artifact` into the file at `destination`
     async fn download_artifact(
         &self,
         jwt_token: &str,
         artifact: Artifact,
-        base_path: PathBuf,
-        run_id: &str,
+        destination: PathBuf,
     ) -> Result<()>;
 
     async fn get_devices_android(&self, jwt_token: &str) -> Result<Vec<AndroidDevice>>;
+
+    async fn get_devices_ios(&self, jwt_token: &str) -> Result<Vec<IosConfiguration>>;
+
+    async fn get_devices_real(&self, jwt_token: &str, platform: &str) -> Result<Vec<RealDevice>>;
 }
 
 #[derive(Clone)]
@@ -81,6 +139,11 @@ pub struct RapiReqwestClient {
     base_url: String,
     api_key: String,
     client: Client,
+    upload_retries: u32,
+    upload_cache: Option<Arc<Mutex<UploadCache>>>,
+    upload_checksum: HashAlgorithm,
+    max_upload_size: u64,
+    archive_streaming: Option<Arc<ZipOptions>>,
 }
 
 impl RapiReqwestClient {
@@ -95,6 +158,68 @@ impl RapiReqwestClient {
             ..Default::default()
         }
     }
+
+    pub fn with_upload_retries(self, upload_retries: u32) -> RapiReqwestClient {
+        RapiReqwestClient {
+            upload_retries,
+            ..self
+        }
+    }
+
+    pub fn with_upload_checksum(self, upload_checksum: HashAlgorithm) -> RapiReqwestClient {
+        RapiReqwestClient {
+            upload_checksum,
+            ..self
+        }
+    }
+
+    /// Files larger than `max_upload_size` bytes are rejected before anything is uploaded,
+    /// the default is the documented limit of Marathon Cloud storage. A lower limit returned
+    /// by the server for an upload takes precedence
+    pub fn with_max_upload_size(self, max_upload_size: u64) -> RapiReqwestClient {
+        RapiReqwestClient {
+            max_upload_size,
+            ..self
+        }
+    }
+
+    /// Directories are zipped with `options` while being uploaded instead of being rejected
+    pub fn with_archive_streaming(
+        self,
+        archive_streaming: Option<Arc<ZipOptions>>,
+    ) -> RapiReqwestClient {
+        RapiReqwestClient {
+            archive_streaming,
+            ..self
+        }
+    }
+
+    /// Reuses previous uploads of identical files for `ttl`, zero disables the cache
+    pub fn with_upload_cache(self, ttl: Duration) -> RapiReqwestClient {
+        let upload_cache = UploadCache::default_location()
+            .filter(|_| !ttl.is_zero())
+            .map(|path| Arc::new(Mutex::new(UploadCache::load(&path, ttl))));
+        RapiReqwestClient {
+            upload_cache,
+            ..self
+        }
+    }
+
+    async fn upload(&self, file_path: PathBuf, no_progress_bar: bool) -> Result<String> {
+        upload_to_s3(
+            &self.client,
+            self.base_url.clone(),
+            self.api_key.clone(),
+            file_path,
+            no_progress_bar,
+            self.upload_retries,
+            self.upload_cache.as_deref(),
+            self.upload_checksum,
+            self.max_upload_size,
+            self.archive_streaming.as_deref(),
+        )
+        .await
+    }
 }
 
 impl Default for RapiReqwestClient {
@@ -107,6 +232,11 @@ impl Default for RapiReqwestClient {
                 .pool_max_idle_per_host(16)
                 .build()
                 .unwrap(),
+            upload_retries: DEFAULT_UPLOAD_RETRIES,
+            upload_cache: None,
+            upload_checksum: HashAlgorithm::Md5,
+            max_upload_size: DEFAULT_MAX_UPLOAD_SIZE_BYTES,
+            archive_streaming: None,
         }
     }
 }
@@ -153,72 +283,83 @@ impl RapiClient for RapiReqwestClient {
         env_args: Option<Vec<String>>,
         test_env_args: Option<Vec<String>>,
         pull_file_config: Option<PullFileConfig>,
+        push_files: Option<Vec<PushFile>>,
+        app_data: Option<PathBuf>,
         concurrency_limit: Option<u32>,
         test_timeout_default: Option<u32>,
         test_timeout_max: Option<u32>,
         project: Option<String>,
         application_bundle: Option<Vec<ApplicationBundle>>,
-        library_bundle: Option<Vec<PathBuf>>,
+        library_bundle: Option<Vec<LibraryBundle>>,
+        additional_apks: Option<Vec<PathBuf>>,
         granted_permission: Option<Vec<String>>,
+        remote_artifacts: RemoteArtifacts,
+        run_options: RunOptions,
     ) -> Result<String> {
         let url = format!("{}/v2/run", self.base_url);
         let params = [("api_key", self.api_key.clone())];
         let url = reqwest::Url::parse_with_params(&url, &params)
             .map_err(|error| ApiError::InvalidParameters { error })?;
 
+        //Fail before spending time on uploads that storage would reject at the end
+        let local_files = test_app
+            .iter()
+            .chain(app.iter())
+            .chain(
+                application_bundle
+                    .iter()
+                    .flatten()
+                    .flat_map(|bundle| [&bundle.app_path, &bundle.test_app_path]),
+            )
+            .chain(
+                library_bundle
+                    .iter()
+                    .flatten()
+                    .map(|bundle| &bundle.test_app_path),
+            )
+            .chain(additional_apks.iter().flatten())
+            .chain(push_files.iter().flatten().map(|file| &file.local_path))
+            .chain(app_data.iter());
+        validate_upload_sizes(local_files, self.max_upload_size).await?;
+
         let mut s3_test_app_path = None;
         if let Some(test_app) = test_app {
-            s3_test_app_path = Some(
-                upload_to_s3(
-                    &self.client,
-                    self.base_url.clone(),
-                    self.api_key.clone(),
-                    test_app.clone(),
-                    no_progress_bar,
-                )
-                .await?,
-            );
+            s3_test_app_path = Some(self.upload(test_app.clone(), no_progress_bar).await?);
         }
 
         let mut s3_app_path = None;
         if let Some(app) = app {
-            s3_app_path = Some(
-                upload_to_s3(
-                    &self.client,
-                    self.base_url.clone(),
-                    self.api_key.clone(),
-                    app.clone(),
-                    no_progress_bar,
-                )
-                .await?,
-            );
+            s3_app_path = Some(self.upload(app.clone(), no_progress_bar).await?);
+        }
+
+        let mut s3_additional_app_paths = None;
+        if let Some(additional_apks) = additional_apks {
+            let mut paths = Vec::new();
+            for apk in additional_apks {
+                paths.push(self.upload(apk, no_progress_bar).await?);
+            }
+            s3_additional_app_paths = Some(paths);
         }
 
         let mut create_run_bundles: Vec<CreateRunBundle> = Vec::new();
 
         if let Some(app_bundles) = application_bundle {
             for app_bundle in app_bundles {
-                let s3_app_path = upload_to_s3(
-                    &self.client,
-                    self.base_url.clone(),
-                    self.api_key.clone(),
-                    app_bundle.app_path.clone(),
-                    no_progress_bar,
-                )
-                .await?;
+                let s3_app_path = self
+                    .upload(app_bundle.app_path.clone(), no_progress_bar)
+                    .await?;
 
-                let s3_test_app_path = upload_to_s3(
-                    &self.client,
-                    self.base_url.clone(),
-                    self.api_key.clone(),
-                    app_bundle.test_app_path.clone(),
-                    no_progress_bar,
-                )
-                .await?;
+                let s3_test_app_path = self
+                    .upload(app_bundle.test_app_path.clone(), no_progress_bar)
+                    .await?;
 
                 let create_run_bundle = CreateRunBundle {
                     s3_app_path: Some(s3_app_path),
                     s3_test_app_path: s3_test_app_path.clone(),
+                    filtering_configuration: app_bundle
+                        .filtering_configuration
+                        .and_then(|config| serde_json::to_string(&config).ok()),
+                    flavor: app_bundle.flavor,
                 };
                 create_run_bundles.push(create_run_bundle);
             }
@@ -226,23 +367,40 @@ impl RapiClient for RapiReqwestClient {
 
         if let Some(library_bundles) = library_bundle {
             for lib_bundle in library_bundles {
-                let s3_test_app_path = upload_to_s3(
-                    &self.client,
-                    self.base_url.clone(),
-                    self.api_key.clone(),
-                    lib_bundle.clone(),
-                    no_progress_bar,
-                )
-                .await?;
+                let s3_test_app_path = self
+                    .upload(lib_bundle.test_app_path.clone(), no_progress_bar)
+                    .await?;
 
                 let create_run_bundle = CreateRunBundle {
                     s3_app_path: None,
                     s3_test_app_path: s3_test_app_path.clone(),
+                    filtering_configuration: lib_bundle
+                        .filtering_configuration
+                        .and_then(|config| serde_json::to_string(&config).ok()),
+                    flavor: lib_bundle.flavor,
                 };
                 create_run_bundles.push(create_run_bundle);
             }
         }
 
+        let mut create_run_push_files = None;
+        if let Some(push_files) = push_files {
+            let mut files = Vec::new();
+            for push_file in push_files {
+                let s3_path = self.upload(push_file.local_path, no_progress_bar).await?;
+                files.push(CreateRunPushFile {
+                    s3_path,
+                    device_path: push_file.device_path,
+                });
+            }
+            create_run_push_files = Some(files);
+        }
+
+        let mut s3_app_data_path = None;
+        if let Some(app_data) = app_data {
+            s3_app_data_path = Some(self.upload(app_data, no_progress_bar).await?);
+        }
+
         let bundles = if create_run_bundles.is_empty() {
             None
         } else {
@@ -256,38 +414,54 @@ impl RapiClient for RapiReqwestClient {
             s3_test_app_path: s3_test_app_path.clone(),
             platform: platform.clone(),
             s3_app_path: s3_app_path.clone(),
-            analytics_read_only: analytics_read_only.clone(),
-            profiling: profiling,
-            mock_location: mock_location,
-            code_coverage: code_coverage.clone(),
-            concurrency_limit: concurrency_limit.clone(),
-            country: None,
+            s3_additional_app_paths,
+            app_url: remote_artifacts
+                .application
+                .as_ref()
+                .and_then(RemoteArtifact::url),
+            app_run_id: remote_artifacts
+                .application
+                .as_ref()
+                .and_then(RemoteArtifact::run_id),
+            test_app_url: remote_artifacts
+                .test_application
+                .as_ref()
+                .and_then(RemoteArtifact::url),
+            test_app_run_id: remote_artifacts
+                .test_application
+                .as_ref()
+                .and_then(RemoteArtifact::run_id),
+            analytics_read_only,
+            profiling,
+            mock_location,
+            code_coverage,
+            concurrency_limit,
             device: device.clone(),
             filtering_configuration: filtering_configuration
-                .map(|config| serde_json::to_string(&config).ok())
-                .flatten(),
+                .and_then(|config| serde_json::to_string(&config).ok()),
             flavor: flavor.clone(),
-            isolated: isolated.clone(),
-            language: None,
+            isolated,
             link: link.clone(),
             name: name.clone(),
             branch: branch.clone(),
             os_version: os_version.clone(),
             project: project.clone(),
             pull_file_config: pull_file_config
-                .map(|config| serde_json::to_string(&config).ok())
-                .flatten(),
-            retry_quota_test_preventive: retry_quota_test_preventive.clone(),
-            retry_quota_test_reactive: retry_quota_test_reactive.clone(),
-            retry_quota_test_uncompleted: retry_quota_test_uncompleted.clone(),
+                .and_then(|config| serde_json::to_string(&config).ok()),
+            push_files: create_run_push_files,
+            s3_app_data_path,
+            retry_quota_test_preventive,
+            retry_quota_test_reactive,
+            retry_quota_test_uncompleted,
             system_image: system_image.clone(),
             xcode_version: xcode_version.clone(),
-            test_timeout_default: test_timeout_default.clone(),
-            test_timeout_max: test_timeout_max.clone(),
+            test_timeout_default,
+            test_timeout_max,
             env_args: env_args_map,
             test_env_args: test_env_args_map,
-            bundles: bundles,
+            bundles,
             granted_permission: granted_permission.clone(),
+            options: run_options,
         };
 
         let response = self.client.post(url).json(&create_request).send().await?;
@@ -315,6 +489,131 @@ impl RapiClient for RapiReqwestClient {
         Ok(response)
     }
 
+    async fn find_latest_run(&self, filter: &RunFilter) -> Result<Option<TestRun>> {
+        let url = format!("{}/v1/run/latest", self.base_url);
+        let params: Vec<(&str, &str)> = [
+            ("api_key", Some(self.api_key.as_str())),
+            ("project", filter.project.as_deref()),
+            ("branch", filter.branch.as_deref()),
+            ("name", filter.name.as_deref()),
+            ("fingerprint", filter.fingerprint.as_deref()),
+            ("state", filter.state.as_deref()),
+            ("completed", Some("true")),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key, value)))
+        .collect();
+        let url = reqwest::Url::parse_with_params(&url, &params)
+            .map_err(|error| ApiError::InvalidParameters { error })?;
+
+        let response = self.client.get(url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = api_error_adapter(response)
+            .await?
+            .json::<TestRun>()
+            .await
+            .map_err(|error| ApiError::DeserializationFailure { error })?;
+        Ok(Some(response))
+    }
+
+    async fn cancel_run(&self, id: &str) -> Result<()> {
+        let url = format!("{}/v1/run/{}/cancel", self.base_url, id);
+        let params = [("api_key", self.api_key.clone())];
+        let url = reqwest::Url::parse_with_params(&url, &params)
+            .map_err(|error| ApiError::InvalidParameters { error })?;
+
+        let response = self.client.post(url).send().await?;
+        api_error_adapter(response).await?;
+        Ok(())
+    }
+
+    async fn get_run_events(
+        &self,
+        jwt_token: &str,
+        id: &str,
+        cursor: Option<u64>,
+    ) -> Result<TestRunEvents> {
+        let url = format!("{}/v1/run/{}/events", self.base_url, id);
+        let params: Vec<(&str, String)> = cursor
+            .map(|cursor| vec![("since", cursor.to_string())])
+            .unwrap_or_default();
+        let url = reqwest::Url::parse_with_params(&url, &params)
+            .map_err(|error| ApiError::InvalidParameters { error })?;
+
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", jwt_token))
+            .send()
+            .await?;
+        let response = api_error_adapter(response)
+            .await?
+            .json::<TestRunEvents>()
+            .await
+            .map_err(|error| ApiError::DeserializationFailure { error })?;
+        Ok(response)
+    }
+
+    async fn subscribe_run(
+        &self,
+        jwt_token: &str,
+        id: &str,
+    ) -> Result<BoxStream<'static, Result<TestRunStateChanged>>> {
+        let url = format!("{}/v1/run/{}/subscribe", self.base_url, id);
+
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", jwt_token))
+            .header("Accept", "text/event-stream")
+            .send()
+            .await?;
+        let response = api_error_adapter(response).await?;
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        if !is_event_stream {
+            anyhow::bail!("Run status subscription is not supported by the server");
+        }
+
+        let events = parse_sse(response.bytes_stream()).map(|data| {
+            data.and_then(|data| Ok(serde_json::from_str::<TestRunStateChanged>(&data)?))
+        });
+        Ok(events.boxed())
+    }
+
+    async fn get_run_durations(
+        &self,
+        jwt_token: &str,
+        project: Option<&str>,
+        branch: Option<&str>,
+    ) -> Result<Vec<f64>> {
+        let url = format!("{}/v1/run/durations", self.base_url);
+        let params: Vec<(&str, &str)> = [("project", project), ("branch", branch)]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect();
+        let url = reqwest::Url::parse_with_params(&url, &params)
+            .map_err(|error| ApiError::InvalidParameters { error })?;
+
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", jwt_token))
+            .send()
+            .await?;
+        let response = api_error_adapter(response)
+            .await?
+            .json::<RunDurations>()
+            .await
+            .map_err(|error| ApiError::DeserializationFailure { error })?;
+        Ok(response.durations)
+    }
+
     async fn list_artifact(&self, jwt_token: &str, id: &str) -> Result<Vec<Artifact>> {
         let url = format!("{}/v1/artifact/{}", self.base_url, id);
 
@@ -337,30 +636,65 @@ impl RapiClient for RapiReqwestClient {
         &self,
         jwt_token: &str,
         artifact: Artifact,
-        base_path: PathBuf,
-        run_id: &str,
+        absolute_path: PathBuf,
     ) -> Result<()> {
         let url = format!("{}/v1/artifact", self.base_url);
         let params = [("key", artifact.id.to_owned())];
         let url = reqwest::Url::parse_with_params(&url, &params)
             .map_err(|error| ApiError::InvalidParameters { error })?;
 
-        let id = artifact.id.strip_prefix('/').unwrap_or(&artifact.id);
-        let prefix_with_id = format!("{}/", run_id);
-        let relative_path = artifact.id.strip_prefix(&prefix_with_id).unwrap_or(id);
-
-        let relative_path = Path::new(&relative_path);
-        let mut absolute_path = base_path.clone();
-        absolute_path.push(relative_path);
-
-        let src = self
-            .client
-            .get(url)
-            .header("Authorization", format!("Bearer {}", jwt_token))
-            .send()
-            .await?;
-
-        let mut src = api_error_adapter(src).await?.bytes_stream();
+        //Complete files of the expected size are revalidated, partial downloads are resumed
+        //when the remote file is still the one they were started from
+        let (partial_path, validator_path) = partial_paths(&absolute_path);
+        let existing = tokio::fs::metadata(&absolute_path)
+            .await
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len());
+        let complete = existing.is_some() && (artifact.size.is_none() || existing == artifact.size);
+        let mut conditional = true;
+        let (response, resume_from) = loop {
+            let mut request = self
+                .client
+                .get(url.clone())
+                .header("Authorization", format!("Bearer {}", jwt_token));
+            let mut resume_from = None;
+            if conditional && complete {
+                //Storage answers 304 when its ETag is the MD5 of the local file
+                let digests = digest_file(&absolute_path, &[HashAlgorithm::Md5]).await?;
+                if let Some(md5) = digests.get(&HashAlgorithm::Md5) {
+                    request = request.header(IF_NONE_MATCH, format!("\"{}\"", md5.hex()));
+                }
+            } else if conditional {
+                let partial = tokio::fs::metadata(&partial_path)
+                    .await
+                    .map(|metadata| metadata.len())
+                    .unwrap_or_default();
+                let validator = tokio::fs::read_to_string(&validator_path).await.ok();
+                if let Some(validator) = validator.filter(|_| partial > 0) {
+                    //If the remote file changed, If-Range turns the request into a full download
+                    request = request
+                        .header(RANGE, format!("bytes={}-", partial))
+                        .header(IF_RANGE, validator.trim());
+                    resume_from = Some(partial);
+                }
+            }
+            let response = request.send().await?;
+            if response.status() == StatusCode::NOT_MODIFIED {
+                debug!("Skipping download of {}, it already exists", artifact.id);
+                return Ok(());
+            }
+            let unexpected_range = response.status() == StatusCode::PARTIAL_CONTENT
+                && (resume_from.is_none() || content_range_start(&response) != resume_from);
+            if resume_from.is_some()
+                && (response.status() == StatusCode::RANGE_NOT_SATISFIABLE || unexpected_range)
+            {
+                //The partial file can't be resumed, so it's downloaded from scratch
+                conditional = false;
+                continue;
+            }
+            break (api_error_adapter(response).await?, resume_from);
+        };
 
         let dst_dir = absolute_path.parent();
         if let Some(dst_dir) = dst_dir {
@@ -368,11 +702,31 @@ impl RapiClient for RapiReqwestClient {
                 create_dir_all(dst_dir).await?;
             }
         }
-        let mut dst = File::create(absolute_path).await?;
+        let mut dst = if response.status() == StatusCode::PARTIAL_CONTENT {
+            debug!(
+                "Resuming download of {} from {} bytes",
+                artifact.id,
+                resume_from.unwrap_or_default()
+            );
+            OpenOptions::new().append(true).open(&partial_path).await?
+        } else {
+            match validator(&response) {
+                Some(validator) => tokio::fs::write(&validator_path, validator).await?,
+                None => {
+                    let _ = tokio::fs::remove_file(&validator_path).await;
+                }
+            }
+            File::create(&partial_path).await?
+        };
+        let mut src = response.bytes_stream();
 
         while let Some(chunk) = src.next().await {
             io::copy(&mut chunk?.as_ref(), &mut dst).await?;
         }
+        dst.flush().await?;
+        drop(dst);
+        tokio::fs::rename(&partial_path, &absolute_path).await?;
+        let _ = tokio::fs::remove_file(&validator_path).await;
 
         Ok(())
     }
@@ -394,6 +748,43 @@ impl RapiClient for RapiReqwestClient {
 
         Ok(response)
     }
+
+    async fn get_devices_ios(&self, jwt_token: &str) -> Result<Vec<IosConfiguration>> {
+        let url = format!("{}/v1/devices/ios", self.base_url);
+
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", jwt_token))
+            .send()
+            .await?;
+        let response = api_error_adapter(response)
+            .await?
+            .json::<Vec<IosConfiguration>>()
+            .await
+            .map_err(|error| ApiError::DeserializationFailure { error })?;
+
+        Ok(response)
+    }
+
+    async fn get_devices_real(&self, jwt_token: &str, platform: &str) -> Result<Vec<RealDevice>> {
+        let url = format!("{}/v1/devices/real", self.base_url);
+
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", jwt_token))
+            .query(&[("platform", platform)])
+            .send()
+            .await?;
+        let response = api_error_adapter(response)
+            .await?
+            .json::<Vec<RealDevice>>()
+            .await
+            .map_err(|error| ApiError::DeserializationFailure { error })?;
+
+        Ok(response)
+    }
 }
 
 fn vec_to_hashmap(
@@ -409,7 +800,7 @@ fn vec_to_hashmap(
                     let value = key_value
                         .get(1)
                         .map(|val| val.to_string())
-                        .unwrap_or_else(|| "".to_string());
+                        .unwrap_or_default();
                     if value.is_empty() {
                         return Err(EnvArgError::MissingValue {
                             env_arg: arg.clone(),
@@ -428,6 +819,71 @@ fn vec_to_hashmap(
     }
 }
 
+//Extracts data payloads of server-sent events, see https://html.spec.whatwg.org/multipage/server-sent-events.html
+fn parse_sse<S, B, E>(stream: S) -> impl Stream<Item = Result<String>>
+where
+    S: Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<anyhow::Error>,
+{
+    async_stream::try_stream! {
+        let mut stream = stream;
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut data: Vec<String> = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(Into::into)?;
+            buffer.extend_from_slice(chunk.as_ref());
+            while let Some(position) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=position).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\n', '\r']);
+                if line.is_empty() {
+                    if !data.is_empty() {
+                        yield data.join("\n");
+                        data.clear();
+                    }
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value).to_owned());
+                }
+            }
+        }
+    }
+}
+
+//Offset of a partial response, e.g. 3 of `bytes 3-4/5`
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .parse()
+        .ok()
+}
+
+//Strong validator of the downloaded file, which If-Range compares against the remote file
+fn validator(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(ETAG)
+        .or_else(|| response.headers().get(LAST_MODIFIED))?
+        .to_str()
+        .ok()
+        .map(str::to_owned)
+}
+
+//Downloads are written next to the destination and moved there once they are complete
+fn partial_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let mut validator = partial.clone();
+    validator.push(".etag");
+    (partial.into(), validator.into())
+}
+
 async fn api_error_adapter(response: reqwest::Response) -> Result<reqwest::Response> {
     match response.error_for_status_ref() {
         Ok(_) => Ok(response),
@@ -454,20 +910,32 @@ async fn api_error_adapter(response: reqwest::Response) -> Result<reqwest::Respo
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn upload_to_s3(
     client: &Client,
     base_url_with_params: String,
     api_key: String,
     file_path: PathBuf,
     no_progress_bar: bool,
+    retries: u32,
+    cache: Option<&Mutex<UploadCache>>,
+    checksum_algorithm: HashAlgorithm,
+    max_upload_size: u64,
+    archive_streaming: Option<&ZipOptions>,
 ) -> Result<String> {
-    // Open file
-    let file = File::open(&file_path)
-        .await
-        .map_err(|error| InputError::OpenFileFailure {
-            path: file_path.clone(),
-            error,
-        })?;
+    if let Some(options) = archive_streaming.filter(|_| file_path.is_dir()) {
+        return stream_to_s3(
+            client,
+            base_url_with_params,
+            api_key,
+            file_path,
+            no_progress_bar,
+            retries,
+            max_upload_size,
+            options,
+        )
+        .await;
+    }
 
     // Extract filename from PathBuf
     let file_name = file_path
@@ -477,21 +945,325 @@ async fn upload_to_s3(
             path: file_path.clone(),
         })?;
 
-    // Request upload URL
-    let url = format!("{}/v2/upload/presigned-url", base_url_with_params);
-    let params = [("api_key", api_key.clone())];
-    let url = reqwest::Url::parse_with_params(&url, &params)
-        .map_err(|error| ApiError::InvalidParameters { error })?;
+    //Runs of a fan-out submit the same files at once, they share a single upload. The file is
+    //identified by its metadata, so that it's hashed only once as well
+    let metadata = tokio::fs::metadata(&file_path).await?;
+    let shared = shared_upload(&format!(
+        "{}|{}|{}|{}|{}|{:?}",
+        base_url_with_params,
+        md5_str(&api_key),
+        checksum_algorithm,
+        file_path.canonicalize()?.display(),
+        metadata.len(),
+        metadata.modified().ok()
+    ));
+    let s3_path = shared
+        .get_or_try_init(|| async {
+            //md5 is only computed when it's selected or required by the server, see below
+            let mut digests = digest_file(&file_path, &[checksum_algorithm]).await?;
+            let checksum = digests[&checksum_algorithm].hex();
+            let md5 = (checksum_algorithm == HashAlgorithm::Md5).then(|| checksum.clone());
+            //Uploads are only visible to the same account on the same server
+            let cache_key = format!(
+                "{}|{}|{}:{}",
+                base_url_with_params,
+                md5_str(&api_key),
+                checksum_algorithm,
+                checksum
+            );
+            let cached = cache.and_then(|cache| {
+                let cache = cache.lock().ok()?;
+                cache
+                    .get(&cache_key, SystemTime::now())
+                    .map(|path| path.to_owned())
+            });
+            if let Some(s3_path) = cached {
+                debug!(
+                    "Skipping upload of {}, it was uploaded recently as {}",
+                    file_path.display(),
+                    s3_path
+                );
+                return Ok(s3_path);
+            }
+            //The server looks up uploads by md5 only
+            if let Some(md5) = &md5 {
+                if let Some(s3_path) =
+                    find_uploaded(client, &base_url_with_params, &api_key, md5).await
+                {
+                    debug!(
+                        "Skipping upload of {}, the server already has it as {}",
+                        file_path.display(),
+                        s3_path
+                    );
+                    remember_upload(cache, cache_key, &s3_path);
+                    return Ok(s3_path);
+                }
+            }
+
+            let request_body = UploadRequest {
+                filename: file_name.to_string(),
+                md5,
+                checksum_algorithm: Some(checksum_algorithm),
+                checksum: Some(checksum),
+            };
+            let upload_url_response =
+                request_upload_url(client, &base_url_with_params, &api_key, &request_body).await?;
+            let size = metadata.len();
+            check_upload_size(&file_path, size, upload_url_response.limit(max_upload_size))?;
+            //Servers which don't support the selected checksum fall back to md5
+            if upload_url_response.checksum_algorithm == Some(HashAlgorithm::Md5)
+                && !digests.contains_key(&HashAlgorithm::Md5)
+            {
+                digests.extend(digest_file(&file_path, &[HashAlgorithm::Md5]).await?);
+            }
+            //Storage verifies md5 and sha256 on upload, other checksums are verified by the server afterwards
+            let checksum_header = match upload_url_response.checksum_algorithm {
+                Some(HashAlgorithm::Md5) => {
+                    Some(("Content-MD5", digests[&HashAlgorithm::Md5].base64()))
+                }
+                Some(HashAlgorithm::Sha256) => digests
+                    .get(&HashAlgorithm::Sha256)
+                    .map(|digest| ("x-amz-checksum-sha256", digest.base64())),
+                Some(HashAlgorithm::Xxh3) | None => None,
+            };
+
+            put_with_retries(&file_path, retries, || {
+                put_file(
+                    client,
+                    &upload_url_response.url,
+                    &file_path,
+                    no_progress_bar,
+                    checksum_header.clone(),
+                )
+            })
+            .await?;
+
+            remember_upload(cache, cache_key, &upload_url_response.file_path);
+            Ok::<_, anyhow::Error>(upload_url_response.file_path)
+        })
+        .await?;
+    Ok(s3_path.clone())
+}
+
+/// Uploads the archive of `dir` without writing it to disk. Its checksum is only known once it's
+/// sent, so neither deduplication nor checksum verification by storage is possible
+#[allow(clippy::too_many_arguments)]
+async fn stream_to_s3(
+    client: &Client,
+    base_url_with_params: String,
+    api_key: String,
+    dir: PathBuf,
+    no_progress_bar: bool,
+    retries: u32,
+    max_upload_size: u64,
+    options: &ZipOptions,
+) -> Result<String> {
+    let file_name = dir
+        .file_name()
+        .map(|val| PathBuf::from(val).with_extension("zip"))
+        .and_then(|val| val.to_str().map(str::to_owned))
+        .ok_or(InputError::InvalidFileName { path: dir.clone() })?;
 
     let request_body = UploadRequest {
-        filename: file_name.to_string(),
+        filename: file_name,
+        md5: None,
+        checksum_algorithm: None,
+        checksum: None,
     };
-    let upload_url_response = client.post(url).json(&request_body).send().await?;
-    let upload_url_response = api_error_adapter(upload_url_response)
+    let upload_url_response =
+        request_upload_url(client, &base_url_with_params, &api_key, &request_body).await?;
+    let limit = upload_url_response.limit(max_upload_size);
+
+    put_with_retries(&dir, retries, || {
+        put_directory(
+            client,
+            &upload_url_response.url,
+            &dir,
+            options,
+            limit,
+            no_progress_bar,
+        )
+    })
+    .await?;
+
+    Ok(upload_url_response.file_path)
+}
+
+async fn request_upload_url(
+    client: &Client,
+    base_url_with_params: &str,
+    api_key: &str,
+    request_body: &UploadRequest,
+) -> Result<UploadUrlResponse> {
+    let url = format!("{}/v2/upload/presigned-url", base_url_with_params);
+    let url = reqwest::Url::parse_with_params(&url, [("api_key", api_key)])
+        .map_err(|error| ApiError::InvalidParameters { error })?;
+
+    let response = client.post(url).json(request_body).send().await?;
+    api_error_adapter(response)
         .await?
         .json::<UploadUrlResponse>()
         .await
-        .map_err(|error| ApiError::DeserializationFailure { error })?;
+        .map_err(|error| ApiError::DeserializationFailure { error }.into())
+}
+
+/// Sends the file at `file_path` with `put` until it succeeds, retrying transient failures
+async fn put_with_retries<F, Fut>(file_path: &Path, retries: u32, mut put: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<reqwest::Result<reqwest::Response>>>,
+{
+    let file_path = file_path.to_path_buf();
+    let mut backoff = Backoff::new(UPLOAD_RETRY_INITIAL_DELAY, UPLOAD_RETRY_MAX_DELAY);
+    let mut attempt = 1;
+    loop {
+        let error = match put().await? {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) if response.status() == StatusCode::FORBIDDEN => {
+                return Err(UploadError::PresignedUrlExpired { path: file_path }.into());
+            }
+            Ok(response) => {
+                let status_code = response.status();
+                let body = response.text().await.unwrap_or_default();
+                //Data corrupted in transit is worth another attempt
+                if body.contains("BadDigest") || body.contains("InvalidDigest") {
+                    UploadError::ChecksumMismatch {
+                        path: file_path.clone(),
+                        attempts: attempt,
+                    }
+                } else {
+                    let transient = status_code.is_server_error()
+                        || status_code == StatusCode::REQUEST_TIMEOUT
+                        || status_code == StatusCode::TOO_MANY_REQUESTS;
+                    let error = UploadError::RequestFailedWithCode {
+                        path: file_path.clone(),
+                        attempts: attempt,
+                        status_code,
+                        body,
+                    };
+                    if !transient {
+                        return Err(error.into());
+                    }
+                    error
+                }
+            }
+            Err(error) => UploadError::Network {
+                path: file_path.clone(),
+                attempts: attempt,
+                error: error.without_url(),
+            },
+        };
+        if attempt > retries {
+            return Err(error.into());
+        }
+        let delay = backoff.next_delay();
+        warn!(
+            "Upload of {} failed, retrying in {}: {}",
+            file_path.display(),
+            HumanDuration(delay),
+            error
+        );
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn validate_upload_sizes(paths: impl Iterator<Item = &PathBuf>, limit: u64) -> Result<()> {
+    for path in paths {
+        let metadata =
+            tokio::fs::metadata(path)
+                .await
+                .map_err(|error| InputError::OpenFileFailure {
+                    path: path.clone(),
+                    error,
+                })?;
+        check_upload_size(path, metadata.len(), limit)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn check_upload_size(path: &Path, size: u64, limit: u64) -> Result<(), InputError> {
+    if size > limit {
+        return Err(InputError::FileTooLarge {
+            path: path.to_path_buf(),
+            size: HumanBytes(size).to_string(),
+            limit: HumanBytes(limit).to_string(),
+        });
+    }
+    Ok(())
+}
+
+//Uploads of this process by file, done or in progress. A failed upload is attempted again by
+//the next caller
+fn shared_upload(key: &str) -> Arc<OnceCell<String>> {
+    static UPLOADS: OnceLock<Mutex<HashMap<String, Arc<OnceCell<String>>>>> = OnceLock::new();
+    let mut uploads = UPLOADS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    uploads.entry(key.to_owned()).or_default().clone()
+}
+
+//The cache only saves time, failing to persist it doesn't fail the upload
+fn remember_upload(cache: Option<&Mutex<UploadCache>>, key: String, s3_path: &str) {
+    let Some(Ok(mut cache)) = cache.map(|cache| cache.lock()) else {
+        return;
+    };
+    let now = SystemTime::now();
+    cache.insert(key, s3_path.to_owned(), now);
+    if let Err(error) = cache.save(now) {
+        debug!("Failed to save upload cache: {}", error);
+    }
+}
+
+//Deduplication is an optimisation, any failure falls back to a regular upload
+async fn find_uploaded(
+    client: &Client,
+    base_url_with_params: &str,
+    api_key: &str,
+    md5: &str,
+) -> Option<String> {
+    let url = format!("{}/v2/upload/md5/{}", base_url_with_params, md5);
+    let url = reqwest::Url::parse_with_params(&url, [("api_key", api_key)]).ok()?;
+    let response = match client.get(url).send().await {
+        Ok(response) if response.status() == StatusCode::NOT_FOUND => return None,
+        Ok(response) => response,
+        Err(error) => {
+            debug!(
+                "Failed to check for existing upload: {}",
+                error.without_url()
+            );
+            return None;
+        }
+    };
+    match api_error_adapter(response).await {
+        Ok(response) => response
+            .json::<UploadedFileResponse>()
+            .await
+            .map(|response| response.file_path)
+            .ok(),
+        Err(error) => {
+            debug!("Failed to check for existing upload: {}", error);
+            None
+        }
+    }
+}
+
+//The request body is a stream of the file, so it is reopened for every attempt
+async fn put_file(
+    client: &Client,
+    url: &str,
+    file_path: &Path,
+    no_progress_bar: bool,
+    checksum_header: Option<(&str, String)>,
+) -> Result<reqwest::Result<reqwest::Response>> {
+    // Open file
+    let file = File::open(file_path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: file_path.to_path_buf(),
+            error,
+        })?;
 
     // Progress stuff
     let file_total_size = file.metadata().await?.len();
@@ -515,6 +1287,7 @@ async fn upload_to_s3(
         file_progress_bar = multi_progress.as_mut().unwrap().add(pb);
         file_progress_bar.set_style(sty.clone());
         let mut file_progress = 0u64;
+        let finished_progress_bar = file_progress_bar.clone();
         let file_stream = async_stream::stream! {
             while let Some(chunk) = file_reader.next().await {
                 let file_progress_bar = file_progress_bar.clone();
@@ -530,30 +1303,131 @@ async fn upload_to_s3(
             }
         };
         file_body = Body::wrap_stream(file_stream);
+        let mut request = client.put(url).header("Content-Length", file_total_size);
+        if let Some((name, value)) = checksum_header {
+            request = request.header(name, value);
+        }
+        let response = request.body(file_body).send().await;
+        finished_progress_bar.finish_and_clear();
+        Ok(response)
     } else {
         file_body = Body::wrap_stream(file_reader);
+        let mut request = client.put(url).header("Content-Length", file_total_size);
+        if let Some((name, value)) = checksum_header {
+            request = request.header(name, value);
+        }
+        Ok(request.body(file_body).send().await)
     }
+}
 
-    let s3_response = client
-        .put(upload_url_response.url.clone())
-        .header("Content-Length", file_total_size)
-        .body(file_body)
-        .send()
-        .await?;
-    api_error_adapter(s3_response).await?;
+//The archive is generated while it is sent, so every attempt zips the folder again
+async fn put_directory(
+    client: &Client,
+    url: &str,
+    dir: &Path,
+    options: &ZipOptions,
+    limit: u64,
+    no_progress_bar: bool,
+) -> Result<reqwest::Result<reqwest::Response>> {
+    let prefix = dir.parent().unwrap_or(dir);
+    let prefix_str = prefix
+        .to_str()
+        .ok_or(InputError::NonUTF8Path {
+            path: dir.to_path_buf(),
+        })?
+        .to_owned();
+    let entries = compression::bundle_entries(dir, prefix, options);
+    let level = options.level;
+    let (writer, reader) = io::duplex(STREAM_BUFFER_SIZE);
+    let zip = tokio::spawn(async move {
+        //The upload progress bar already shows how much of the archive is done
+        let progress = ProgressBar::hidden();
+        compression::zip_dir(
+            &mut entries.into_iter(),
+            &prefix_str,
+            writer,
+            level,
+            &progress,
+        )
+        .await
+    });
 
-    Ok(upload_url_response.file_path.clone())
+    let progress_bar = if no_progress_bar {
+        ProgressBar::hidden()
+    } else {
+        let pb = ProgressBar::new_spinner().with_style(ProgressStyle::with_template(
+            "{spinner:.blue} [{elapsed_precise}] {bytes} ({bytes_per_sec})",
+        )?);
+        pb.enable_steady_tick(Duration::from_millis(80));
+        pb
+    };
+    let sent = progress_bar.clone();
+    let streamed = Arc::new(AtomicU64::new(0));
+    let size = streamed.clone();
+    //Zipping failures must fail the request, otherwise storage would accept a truncated archive
+    let body = async_stream::try_stream! {
+        let mut chunks = ReaderStream::new(reader);
+        while let Some(chunk) = chunks.next().await {
+            let chunk: Bytes = chunk?;
+            sent.inc(chunk.len() as u64);
+            //The size of the archive is only known once it's sent, the upload is aborted right away
+            if size.fetch_add(chunk.len() as u64, Ordering::SeqCst) + chunk.len() as u64 > limit {
+                zip.abort();
+                Err(io::Error::other("archive exceeds the upload size limit"))?;
+            }
+            yield chunk;
+        }
+        zip.await.map_err(io::Error::other)?.map_err(io::Error::other)?;
+    };
+    let body: BoxStream<'static, io::Result<Bytes>> = body.boxed();
+    //Without Content-Length the body is sent with chunked transfer encoding
+    let response = client.put(url).body(Body::wrap_stream(body)).send().await;
+    progress_bar.finish_and_clear();
+    let streamed = streamed.load(Ordering::SeqCst);
+    if streamed > limit {
+        //Not worth a retry, the archive would be just as large
+        return Err(InputError::FileTooLarge {
+            path: dir.to_path_buf(),
+            size: format!("over {}", HumanBytes(streamed)),
+            limit: HumanBytes(limit).to_string(),
+        }
+        .into());
+    }
+    Ok(response)
 }
 
+#[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug)]
 struct UploadRequest {
     filename: String,
+    md5: Option<String>,
+    checksum_algorithm: Option<HashAlgorithm>,
+    checksum: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct UploadedFileResponse {
+    file_path: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct UploadUrlResponse {
     file_path: String,
     url: String,
+    /// Checksum the server agreed to verify, older servers don't verify uploads
+    #[serde(default)]
+    checksum_algorithm: Option<HashAlgorithm>,
+    /// Largest file the server accepts in bytes, older servers don't report it
+    #[serde(default)]
+    max_file_size: Option<u64>,
+}
+
+impl UploadUrlResponse {
+    //The lower of the server and the configured limit applies
+    fn limit(&self, configured: u64) -> u64 {
+        self.max_file_size
+            .map_or(configured, |server| server.min(configured))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -566,6 +1440,17 @@ struct CreateRunRequest {
     s3_test_app_path: Option<String>,
     #[serde(rename = "s3_app_path", default)]
     s3_app_path: Option<String>,
+    /// Split APKs and dynamic feature modules installed together with the application
+    #[serde(rename = "s3_additional_app_paths", default)]
+    s3_additional_app_paths: Option<Vec<String>>,
+    #[serde(rename = "app_url", default)]
+    app_url: Option<String>,
+    #[serde(rename = "app_run_id", default)]
+    app_run_id: Option<String>,
+    #[serde(rename = "test_app_url", default)]
+    test_app_url: Option<String>,
+    #[serde(rename = "test_app_run_id", default)]
+    test_app_run_id: Option<String>,
     #[serde(rename = "analytics_read_only", default)]
     analytics_read_only: Option<bool>,
     #[serde(rename = "profiling", default)]
@@ -576,8 +1461,6 @@ struct CreateRunRequest {
     code_coverage: Option<bool>,
     #[serde(rename = "concurrency_limit", default)]
     concurrency_limit: Option<u32>,
-    #[serde(rename = "country", default)]
-    country: Option<String>,
     #[serde(rename = "device", default)]
     device: Option<String>,
     #[serde(rename = "filtering_configuration", default)]
@@ -586,8 +1469,6 @@ struct CreateRunRequest {
     flavor: Option<String>,
     #[serde(rename = "isolated", default)]
     isolated: Option<bool>,
-    #[serde(rename = "language", default)]
-    language: Option<String>,
     #[serde(rename = "link", default)]
     link: Option<String>,
     #[serde(rename = "name", default)]
@@ -600,6 +1481,11 @@ struct CreateRunRequest {
     project: Option<String>,
     #[serde(rename = "pull_file_config", default)]
     pull_file_config: Option<String>,
+    /// Files pushed to the devices before the tests are executed
+    #[serde(rename = "push_files", default)]
+    push_files: Option<Vec<CreateRunPushFile>>,
+    #[serde(rename = "s3_app_data_path", default)]
+    s3_app_data_path: Option<String>,
     #[serde(rename = "retry_quota_test_preventive", default)]
     retry_quota_test_preventive: Option<u32>,
     #[serde(rename = "retry_quota_test_reactive", default)]
@@ -622,6 +1508,80 @@ struct CreateRunRequest {
     bundles: Option<Vec<CreateRunBundle>>,
     #[serde(rename = "granted_permission", default)]
     granted_permission: Option<Vec<String>>,
+    #[serde(flatten)]
+    options: RunOptions,
+}
+
+/// Optional device and test settings of a run
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[skip_serializing_none]
+pub struct RunOptions {
+    #[serde(rename = "language", default)]
+    pub language: Option<String>,
+    #[serde(rename = "country", default)]
+    pub country: Option<String>,
+    /// Launch arguments of the test runner, CommandLineArguments of the xctestrun on iOS
+    #[serde(rename = "command_line_args", default)]
+    pub command_line_args: Option<Vec<String>>,
+    /// Browsers of a web run
+    #[serde(rename = "browsers", default)]
+    pub browsers: Option<Vec<String>>,
+    /// Operating systems the browsers of a web run are started on
+    #[serde(rename = "browser_os", default)]
+    pub browser_os: Option<Vec<String>>,
+    /// Configuration of the Detox configuration file to run, passed to `detox test --configuration`
+    #[serde(rename = "detox_configuration", default)]
+    pub detox_configuration: Option<String>,
+    /// Detox configuration file with the aliases of the selected configuration resolved
+    #[serde(rename = "detox_config", default)]
+    pub detox_config: Option<serde_json::Value>,
+    /// simulator or real, Marathon Cloud uses simulators unless set
+    #[serde(rename = "device_class", default)]
+    pub device_class: Option<String>,
+    /// Device types of a run covering more than one, `device` is the first of them
+    #[serde(rename = "devices", default)]
+    pub devices: Option<Vec<String>>,
+    /// Network shaping of the devices: full, 3g, edge or offline-segments
+    #[serde(rename = "network_profile", default)]
+    pub network_profile: Option<String>,
+    /// Screen orientation of the devices: portrait or landscape
+    #[serde(rename = "orientation", default)]
+    pub orientation: Option<String>,
+    /// Every test runs in its own instrumentation with Android Test Orchestrator
+    #[serde(rename = "use_orchestrator", default)]
+    pub use_orchestrator: Option<bool>,
+    /// Application data is cleared after every test, requires the orchestrator
+    #[serde(rename = "clear_package_data", default)]
+    pub clear_package_data: Option<bool>,
+    /// Additional command line arguments of the emulators, e.g. -gpu swiftshader_indirect
+    #[serde(rename = "emulator_args", default)]
+    pub emulator_args: Option<Vec<String>>,
+    /// Simulated location of iOS simulators as latitude,longitude
+    #[serde(rename = "simulated_location", default)]
+    pub simulated_location: Option<String>,
+    /// RAM of the emulators in megabytes
+    #[serde(rename = "device_ram_mb", default)]
+    pub device_ram_mb: Option<u32>,
+    /// Storage of the emulators in megabytes
+    #[serde(rename = "device_storage_mb", default)]
+    pub device_storage_mb: Option<u32>,
+    /// Every runtime permission requested by the Android application is granted
+    #[serde(rename = "grant_all_permissions", default)]
+    pub grant_all_permissions: Option<bool>,
+    /// Timeouts of the tests matching a pattern, overriding the default test timeout
+    #[serde(rename = "test_timeout_overrides", default)]
+    pub test_timeout_overrides: Option<Vec<TestTimeoutOverride>>,
+    /// Retry quotas of the tests matching a pattern, overriding the quotas of the run
+    #[serde(rename = "retry_quota_overrides", default)]
+    pub retry_quota_overrides: Option<Vec<RetryQuotaOverride>>,
+    /// When screen recordings of tests are captured: on-failure, always or never
+    #[serde(rename = "record_video", default)]
+    pub record_video: Option<String>,
+    #[serde(rename = "screenshot_on_failure", default)]
+    pub screenshot_on_failure: Option<bool>,
+    /// Fingerprint of the binaries and the test configuration, see --skip-if-passed
+    #[serde(rename = "fingerprint", default)]
+    pub fingerprint: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -631,14 +1591,31 @@ struct CreateRunBundle {
 
     #[serde(rename = "s3_app_path", skip_serializing_if = "Option::is_none")]
     s3_app_path: Option<String>,
+
+    /// Filters of the tests of this bundle, serialized like the filters of the run
+    #[serde(
+        rename = "filtering_configuration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    filtering_configuration: Option<String>,
+
+    #[serde(rename = "flavor", skip_serializing_if = "Option::is_none")]
+    flavor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CreateRunPushFile {
+    #[serde(rename = "s3_path")]
+    s3_path: String,
+
+    #[serde(rename = "device_path")]
+    device_path: String,
 }
 
 #[derive(Deserialize)]
 pub struct CreateRunResponse {
     #[serde(rename = "run_id")]
     pub run_id: String,
-    #[serde(rename = "status")]
-    pub status: String,
 }
 
 #[derive(Deserialize)]
@@ -653,6 +1630,9 @@ pub struct TestRun {
     pub failed: Option<u32>,
     #[serde(rename = "ignored")]
     pub ignored: Option<u32>,
+    /// Total number of tests scheduled, once known by the backend
+    #[serde(rename = "total", default)]
+    pub total: Option<u32>,
     #[serde(rename = "completed", with = "time::serde::iso8601::option")]
     pub completed: Option<OffsetDateTime>,
     #[serde(rename = "total_run_time")]
@@ -661,6 +1641,114 @@ pub struct TestRun {
     pub error_message: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct TestRunStateChanged {
+    #[serde(rename = "state")]
+    pub state: String,
+}
+
+/// Binary which the backend fetches or reuses instead of a file uploaded by the CLI
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteArtifact {
+    Url(String),
+    Run(String),
+}
+
+impl RemoteArtifact {
+    pub fn from_args(url: Option<String>, run_id: Option<String>) -> Option<RemoteArtifact> {
+        url.map(RemoteArtifact::Url)
+            .or(run_id.map(RemoteArtifact::Run))
+    }
+
+    fn url(&self) -> Option<String> {
+        match self {
+            RemoteArtifact::Url(url) => Some(url.clone()),
+            RemoteArtifact::Run(_) => None,
+        }
+    }
+
+    fn run_id(&self) -> Option<String> {
+        match self {
+            RemoteArtifact::Url(_) => None,
+            RemoteArtifact::Run(id) => Some(id.clone()),
+        }
+    }
+}
+
+/// Criteria for looking up previous test runs, unset fields match any run
+#[derive(Debug, Clone, Default)]
+pub struct RunFilter {
+    pub project: Option<String>,
+    pub branch: Option<String>,
+    pub name: Option<String>,
+    /// Fingerprint of the binaries and the test configuration sent when the run was created
+    pub fingerprint: Option<String>,
+    pub state: Option<String>,
+}
+
+impl std::fmt::Display for RunFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let criteria: Vec<String> = [
+            ("project", &self.project),
+            ("branch", &self.branch),
+            ("name", &self.name),
+            ("fingerprint", &self.fingerprint),
+            ("state", &self.state),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_ref().map(|value| format!("{} = {}", key, value)))
+        .collect();
+        if criteria.is_empty() {
+            f.write_str("any test run")
+        } else {
+            f.write_str(&criteria.join(", "))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RemoteArtifacts {
+    pub application: Option<RemoteArtifact>,
+    pub test_application: Option<RemoteArtifact>,
+}
+
+#[derive(Deserialize)]
+pub struct RunDurations {
+    /// Wall-clock durations in seconds of recently finished runs
+    #[serde(rename = "durations")]
+    pub durations: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct TestRunEvents {
+    #[serde(rename = "events")]
+    pub events: Vec<TestEvent>,
+    #[serde(rename = "cursor")]
+    pub cursor: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TestEvent {
+    #[serde(rename = "type")]
+    pub event_type: TestEventType,
+    #[serde(rename = "test")]
+    pub test: String,
+    #[serde(rename = "device")]
+    pub device: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum TestEventType {
+    #[serde(rename = "started")]
+    Started,
+    #[serde(rename = "passed")]
+    Passed,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "ignored")]
+    Ignored,
+}
+
 #[derive(Deserialize)]
 pub struct GetTokenResponse {
     #[serde(rename = "token")]
@@ -671,10 +1759,41 @@ pub struct GetTokenResponse {
 pub struct Artifact {
     #[serde(rename = "id")]
     pub id: String,
-    #[serde(rename = "name")]
-    pub name: String,
     #[serde(rename = "is_file")]
     pub is_file: bool,
+    #[serde(rename = "size", default)]
+    pub size: Option<u64>,
+}
+
+/// Physical device model of the real device catalog
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RealDevice {
+    /// Model used by --device, e.g. pixel-8
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "manufacturer")]
+    pub manufacturer: String,
+    #[serde(rename = "osVersion")]
+    pub os_version: String,
+}
+
+/// Supported combination of iOS simulator, Xcode and iOS runtime
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct IosConfiguration {
+    /// Name used by --device, e.g. iPhone-15
+    #[serde(rename = "device")]
+    pub device: String,
+    #[serde(rename = "deviceType")]
+    pub device_type: String,
+    #[serde(rename = "xcodeVersion")]
+    pub xcode_version: String,
+    /// Version used by --os-version, e.g. 17.5
+    #[serde(rename = "osVersion")]
+    pub os_version: String,
+    #[serde(rename = "runtime")]
+    pub runtime: String,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -691,6 +1810,20 @@ pub struct AndroidDevice {
     pub height: u32,
     #[serde(rename = "dpi")]
     pub dpi: u32,
+    /// Largest RAM in megabytes which emulators of the device can be launched with
+    #[serde(
+        rename = "max_ram_mb",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_ram_mb: Option<u32>,
+    /// Largest storage in megabytes which emulators of the device can be launched with
+    #[serde(
+        rename = "max_storage_mb",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_storage_mb: Option<u32>,
 }
 
 #[cfg(test)]
@@ -752,6 +1885,28 @@ mod tests {
         assert_eq!(result, Ok(None));
     }
 
+    #[tokio::test]
+    async fn test_parse_sse_events_split_across_chunks() {
+        let chunks: Vec<Result<&[u8], std::io::Error>> = vec![
+            Ok(b": keep-alive\n\ndata: {\"state\":"),
+            Ok(b"\"running\"}\r\n\r\nevent: update\ndata: first\n"),
+            Ok(b"data: second\n\n"),
+        ];
+
+        let events: Vec<String> = parse_sse(futures::stream::iter(chunks))
+            .map(|x| x.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            events,
+            vec![
+                r#"{"state":"running"}"#.to_string(),
+                "first\nsecond".to_string()
+            ]
+        );
+    }
+
     #[test]
     fn test_vec_to_hashmap_empty_vector() {
         let input = Some(vec![]);
@@ -760,4 +1915,18 @@ mod tests {
 
         assert_eq!(result, Ok(Some(HashMap::new())));
     }
+
+    #[test]
+    fn test_upload_limit_is_the_lower_of_server_and_configured() {
+        let response = |max_file_size| UploadUrlResponse {
+            file_path: "uploads/app.apk".into(),
+            url: "https://storage/app.apk".into(),
+            checksum_algorithm: None,
+            max_file_size,
+        };
+
+        assert_eq!(response(Some(2)).limit(1024), 2);
+        assert_eq!(response(Some(4096)).limit(1024), 1024);
+        assert_eq!(response(None).limit(1024), 1024);
+    }
 }
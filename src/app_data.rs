@@ -0,0 +1,68 @@
+use std::path::{Component, Path};
+
+use crate::errors::InputError;
+
+/// Checks that `path` is a zip archive which can be unpacked into the data directory of the
+/// application: every entry must stay inside of it
+pub async fn validate(path: &Path) -> Result<(), InputError> {
+    let invalid = |message: String| InputError::InvalidAppData {
+        path: path.to_owned(),
+        message,
+    };
+    if !path.is_file() {
+        return Err(invalid("file doesn't exist".to_owned()));
+    }
+    let reader = async_zip::tokio::read::fs::ZipFileReader::new(path)
+        .await
+        .map_err(|error| invalid(format!("not a zip archive, {}", error)))?;
+    for entry in reader.file().entries() {
+        let filename = entry
+            .filename()
+            .as_str()
+            .map_err(|error| invalid(error.to_string()))?;
+        let escapes = Path::new(filename).components().any(|component| {
+            matches!(
+                component,
+                Component::ParentDir | Component::RootDir | Component::Prefix(_)
+            )
+        });
+        if escapes {
+            return Err(invalid(format!(
+                "entry {} would be unpacked outside of the data directory",
+                filename
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::zip;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_validate() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("data.zip");
+        zip(
+            &archive,
+            &[
+                ("databases/app.db", b"sqlite"),
+                ("shared_prefs/session.xml", b"<map/>"),
+            ],
+        )
+        .await?;
+        validate(&archive).await?;
+
+        zip(&archive, &[("../../other.app/db", b"sqlite")]).await?;
+        assert!(validate(&archive).await.is_err());
+
+        let text = dir.path().join("data.txt");
+        tokio::fs::write(&text, b"text").await?;
+        assert!(validate(&text).await.is_err());
+        assert!(validate(&dir.path().join("missing.zip")).await.is_err());
+        Ok(())
+    }
+}
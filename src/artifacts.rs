@@ -1,8 +1,10 @@
+use regex::Regex;
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
-use std::path::Path;
-use std::path::PathBuf;
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
 
 use ::futures::{stream, StreamExt, TryStreamExt};
 use anyhow::Result;
@@ -11,6 +13,90 @@ use log::debug;
 
 use crate::api::{Artifact, RapiClient, RapiReqwestClient};
 use crate::errors::ArtifactError;
+use crate::report::junit;
+
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Categories of run artifacts, each stored in its own remote folder
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactType {
+    /// Device logs of tests
+    #[clap(name = "logs")]
+    Logs,
+    /// Screen recordings of tests
+    #[clap(name = "video")]
+    Video,
+    /// JUnit xml reports
+    #[clap(name = "junit")]
+    Junit,
+    /// Allure results
+    #[clap(name = "allure")]
+    Allure,
+    /// Code coverage reports
+    #[clap(name = "coverage")]
+    Coverage,
+    /// Xcode result bundles of iOS runs, one per device
+    #[clap(name = "xcresult")]
+    Xcresult,
+    /// Profiling results, e.g. Instruments traces of iOS runs
+    #[clap(name = "profiling")]
+    Profiling,
+}
+
+impl ArtifactType {
+    /// Globs matching the artifacts of this type relative to the run
+    pub fn globs(self) -> &'static [&'static str] {
+        match self {
+            ArtifactType::Logs => &["logs/**"],
+            ArtifactType::Video => &["video/**"],
+            ArtifactType::Junit => &["tests/**/*.xml"],
+            ArtifactType::Allure => &["report/allure-results/**"],
+            ArtifactType::Coverage => &["coverage/**"],
+            ArtifactType::Xcresult => &["**/*.xcresult/**"],
+            ArtifactType::Profiling => &["profiling/**", "**/*.trace/**"],
+        }
+    }
+}
+
+/// Placeholders available in `--path-template`
+pub const PATH_TEMPLATE_PLACEHOLDERS: [&str; 6] = [
+    "{path}",
+    "{dir}",
+    "{filename}",
+    "{type}",
+    "{device}",
+    "{test}",
+];
+
+/// Where downloaded artifacts are placed inside the output folder
+#[derive(Debug, Clone, Default)]
+pub enum ArtifactLayout {
+    /// Same tree as in Marathon Cloud, e.g. `video/omni/<device>/<test>.mp4`
+    #[default]
+    Remote,
+    /// All files directly in the output folder
+    Flat,
+    /// Path built from `PATH_TEMPLATE_PLACEHOLDERS`
+    Template(String),
+}
+
+/// Which artifacts of a run are downloaded and how
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    pub only_failed: bool,
+    /// Number of files downloaded at the same time, one per CPU by default
+    pub concurrency: Option<NonZeroUsize>,
+    /// Download the remaining files when some of them fail
+    pub keep_going: bool,
+    pub layout: ArtifactLayout,
+}
+
+impl DownloadOptions {
+    fn concurrency(&self) -> usize {
+        self.concurrency
+            .map_or_else(num_cpus::get, NonZeroUsize::get)
+    }
+}
 
 pub async fn fetch_artifact_list(
     client: &RapiReqwestClient,
@@ -21,16 +107,16 @@ pub async fn fetch_artifact_list(
     let mut list: Vec<String> = vec![id.to_owned()];
 
     loop {
-        let stats: Vec<Artifact> = stream::iter(list.clone().into_iter())
+        let stats: Vec<Artifact> = stream::iter(list.clone())
             .map(|dir| {
                 let client = client.clone();
                 let token = token.to_owned();
-                tokio::spawn(async move { client.list_artifact(&token, &dir).await.unwrap() })
+                tokio::spawn(async move { client.list_artifact(&token, &dir).await })
             })
             .buffer_unordered(num_cpus::get())
+            .map(|result| result.map_err(|error| ArtifactError::ListFailed { error })?)
             .try_concat()
-            .await
-            .map_err(|error| ArtifactError::ListFailed { error })?;
+            .await?;
 
         list.clear();
         for f in stats {
@@ -49,102 +135,349 @@ pub async fn fetch_artifact_list(
     Ok(artifacts)
 }
 
+/// Downloads `artifacts` into `path` and returns the local path of every file inside `path`
+/// by its path inside the run
 pub async fn download_artifacts(
     client: &RapiReqwestClient,
     run_id: &str,
     artifacts: Vec<Artifact>,
-    path: &PathBuf,
+    path: &Path,
     token: &str,
+    options: &DownloadOptions,
     no_progress_bar: bool,
-) -> Result<()> {
+) -> Result<HashMap<String, PathBuf>> {
     debug!("Downloading {} artifacts:", artifacts.len());
 
     artifacts.iter().for_each(|f| debug!("{}", f.id));
 
+    let total = artifacts.len();
+    let local_paths = local_paths(&artifacts, run_id, &options.layout);
+    let downloaded = artifacts
+        .iter()
+        .map(|artifact| relative_path(&artifact.id, run_id).to_owned())
+        .zip(local_paths.iter().cloned())
+        .collect();
     let mut progress_bar: Option<ProgressBar> = None;
     if !no_progress_bar {
-        progress_bar = Some(ProgressBar::new(artifacts.len() as u64))
+        progress_bar = Some(ProgressBar::new(total as u64))
     }
 
-    stream::iter(artifacts.into_iter())
-        .map(|artifact| {
+    let mut downloads = stream::iter(artifacts.into_iter().zip(local_paths))
+        .map(|(artifact, local_path)| {
             let client = client.clone();
             let token = token.to_owned();
-            let base_path = path.clone();
-            let run_id = run_id.to_owned().clone();
+            let destination = path.join(local_path);
             let progress_bar = progress_bar.clone();
             tokio::spawn(async move {
-                for _try in 1..=3 {
-                    let download_result = &client
-                        .download_artifact(&token, artifact.clone(), base_path.clone(), &run_id)
+                let mut attempt = 1;
+                loop {
+                    let download_result = client
+                        .download_artifact(&token, artifact.clone(), destination.clone())
                         .await;
                     match download_result {
                         Ok(_) => {
                             if let Some(progress_bar) = progress_bar {
                                 progress_bar.inc(1);
                             }
-                            return;
+                            return Ok(());
                         }
-                        Err(error) => {
-                            if _try < 4 {
-                                debug!("Error fetching {}, retrying", artifact.id);
-                                continue;
-                            } else {
-                                panic!(
-                                    "Error fetching {}. All {} retries failed. {}",
-                                    artifact.id, 3, error
-                                );
-                            }
+                        Err(error) if attempt < DOWNLOAD_ATTEMPTS => {
+                            debug!("Error fetching {}, retrying: {}", artifact.id, error);
+                            attempt += 1;
                         }
+                        Err(error) => return Err((artifact.id, error)),
                     }
                 }
             })
         })
-        .buffer_unordered(num_cpus::get())
-        .try_collect()
-        .await
-        .map_err(|error| ArtifactError::DownloadFailed { error })?;
+        .buffer_unordered(options.concurrency());
+
+    let mut failures = Vec::new();
+    while let Some(result) = downloads.next().await {
+        if let Err(failure) = result.map_err(|error| ArtifactError::DownloadFailed { error })? {
+            failures.push(failure);
+            if !options.keep_going {
+                break;
+            }
+        }
+    }
+    drop(downloads);
+    if !failures.is_empty() {
+        if let Some(progress_bar) = progress_bar {
+            progress_bar.abandon();
+        }
+        let summary = failures
+            .iter()
+            .map(|(id, error)| format!("\t{}: {:#}", id, error))
+            .collect::<Vec<String>>()
+            .join("\n");
+        return Err(ArtifactError::IncompleteDownload {
+            failed: failures.len(),
+            total,
+            summary,
+        }
+        .into());
+    }
 
     if let Some(progress_bar) = progress_bar {
         progress_bar.finish_with_message("done");
     }
-    Ok(())
+    Ok(downloaded)
 }
 
-pub async fn patch_allure_paths(output: &Path) -> Result<()> {
-    // Define the required path
-    let required_path = output.join("report/allure-results");
-
-    // Check if the required path exists
-    if !required_path.exists() {
-        debug!("Directory {:?} does not exist", required_path);
-        return Ok(());
+/// Downloads the JUnit reports of the run into `path` and returns their test cases. Reports are
+/// kept at their remote path regardless of the layout of `options`
+pub async fn download_test_results(
+    client: &RapiReqwestClient,
+    run_id: &str,
+    artifacts: &[Artifact],
+    path: &Path,
+    token: &str,
+    options: &DownloadOptions,
+    no_progress_bar: bool,
+) -> Result<Vec<junit::TestCase>> {
+    let reports: Vec<Artifact> = artifacts
+        .iter()
+        .filter(|artifact| is_junit_report(artifact, run_id))
+        .cloned()
+        .collect();
+    let report_paths: Vec<PathBuf> = reports
+        .iter()
+        .map(|artifact| path.join(relative_path(&artifact.id, run_id)))
+        .collect();
+    let options = DownloadOptions {
+        layout: ArtifactLayout::Remote,
+        ..options.clone()
+    };
+    download_artifacts(
+        client,
+        run_id,
+        reports,
+        path,
+        token,
+        &options,
+        no_progress_bar,
+    )
+    .await?;
+
+    let mut testcases = Vec::new();
+    for report_path in report_paths {
+        //Reports written by test frameworks aren't guaranteed to be valid UTF-8
+        let report = fs::read(&report_path)?;
+        testcases.extend(junit::parse(&String::from_utf8_lossy(&report)));
     }
+    Ok(testcases)
+}
 
-    // Iterate over each file in the required path
-    match fs::read_dir(&required_path) {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("json") {
-                        if let Err(e) = patch_file(&path).await {
-                            panic!("Failed to patch file {:?}: {}", path, e);
-                        }
+/// Keeps the JUnit reports and the artifacts of failed tests. The reports are downloaded into
+/// a temporary folder to find out which tests failed, the output only gets the selected files
+pub async fn select_failed(
+    client: &RapiReqwestClient,
+    run_id: &str,
+    artifacts: Vec<Artifact>,
+    token: &str,
+    options: &DownloadOptions,
+    no_progress_bar: bool,
+) -> Result<Vec<Artifact>> {
+    let reports_dir = tempfile::tempdir()?;
+    let failed: HashSet<String> = download_test_results(
+        client,
+        run_id,
+        &artifacts,
+        reports_dir.path(),
+        token,
+        options,
+        no_progress_bar,
+    )
+    .await?
+    .iter()
+    .filter(|testcase| testcase.is_failure())
+    .map(junit::TestCase::id)
+    .collect();
+    Ok(select_failed_test_artifacts(artifacts, run_id, &failed))
+}
+
+/// Keeps the JUnit reports and the artifacts which belong to `failed` tests
+pub fn select_failed_test_artifacts(
+    artifacts: Vec<Artifact>,
+    run_id: &str,
+    failed: &HashSet<String>,
+) -> Vec<Artifact> {
+    debug!("Found {} failed tests", failed.len());
+    artifacts
+        .into_iter()
+        .filter(|artifact| {
+            is_junit_report(artifact, run_id)
+                || test_candidates(relative_path(&artifact.id, run_id))
+                    .any(|test| failed.contains(test))
+        })
+        .collect()
+}
+
+/// Test ids a file may belong to. Marathon names test files and folders after `<class>#<method>`,
+/// optionally followed by a batch suffix and an extension, e.g. `com.example.MainTest#testLogin[0]-5c3e.log`.
+/// Names of parameterized tests may contain both separators, so every prefix ending at one is a candidate
+pub fn test_candidates(relative_path: &str) -> impl Iterator<Item = &str> {
+    relative_path
+        .split('/')
+        .filter(|segment| segment.contains('#'))
+        .flat_map(|segment| {
+            segment
+                .match_indices(['-', '.'])
+                .map(|(index, _)| &segment[..index])
+                .chain([segment])
+        })
+}
+
+fn is_junit_report(artifact: &Artifact, run_id: &str) -> bool {
+    let relative_path = relative_path(&artifact.id, run_id);
+    relative_path.starts_with("tests/") && relative_path.ends_with(".xml")
+}
+
+/// Artifact ids are prefixed with the run id, this is their path inside the run
+pub fn relative_path<'a>(id: &'a str, run_id: &str) -> &'a str {
+    let stripped = id.strip_prefix('/').unwrap_or(id);
+    stripped
+        .strip_prefix(run_id)
+        .and_then(|path| path.strip_prefix('/'))
+        .unwrap_or(stripped)
+}
+
+/// Paths of `artifacts` inside the output folder, in the same order
+fn local_paths(artifacts: &[Artifact], run_id: &str, layout: &ArtifactLayout) -> Vec<PathBuf> {
+    let relative_paths = artifacts
+        .iter()
+        .map(|artifact| relative_path(&artifact.id, run_id));
+    match layout {
+        ArtifactLayout::Remote => relative_paths.map(PathBuf::from).collect(),
+        ArtifactLayout::Template(template) => {
+            let rendered: Vec<(&str, PathBuf)> = relative_paths
+                .map(|relative_path| (relative_path, render_path_template(template, relative_path)))
+                .collect();
+            let mut seen = HashSet::new();
+            let duplicates: HashSet<&PathBuf> = rendered
+                .iter()
+                .map(|(_, path)| path)
+                .filter(|path| !seen.insert(*path))
+                .collect();
+            //Files rendered to the same path are named after their whole remote path instead
+            rendered
+                .iter()
+                .map(|(relative_path, path)| {
+                    if duplicates.contains(path) {
+                        path.with_file_name(relative_path.replace('/', "_"))
+                    } else {
+                        path.clone()
                     }
-                }
-            }
+                })
+                .collect()
         }
-        Err(e) => {
-            panic!("Failed to read directory {:?}: {}", required_path, e);
+        ArtifactLayout::Flat => {
+            let relative_paths: Vec<&str> = relative_paths.collect();
+            let mut seen = HashSet::new();
+            let duplicates: HashSet<&str> = relative_paths
+                .iter()
+                .map(|path| file_name(path))
+                .filter(|name| !seen.insert(*name))
+                .collect();
+            //Files sharing a name keep their whole path to stay apart
+            relative_paths
+                .into_iter()
+                .map(|path| {
+                    if duplicates.contains(file_name(path)) {
+                        PathBuf::from(path.replace('/', "_"))
+                    } else {
+                        PathBuf::from(file_name(path))
+                    }
+                })
+                .collect()
         }
     }
+}
+
+fn file_name(relative_path: &str) -> &str {
+    relative_path.rsplit('/').next().unwrap_or(relative_path)
+}
+
+//Marathon stores test files as <type>/<pool>/<device>/<class>#<method>-<batch>.<ext>
+fn render_path_template(template: &str, relative_path: &str) -> PathBuf {
+    let segments: Vec<&str> = relative_path.split('/').collect();
+    let filename = file_name(relative_path);
+    let dir = relative_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let device = if segments.len() >= 4 {
+        segments[2]
+    } else {
+        "unknown"
+    };
+    let test = match filename.split_once('#') {
+        Some((class, method)) => {
+            let method = method.split(['-', '.']).next().unwrap_or(method);
+            format!("{}#{}", class, method)
+        }
+        None => "unknown".to_owned(),
+    };
+    let rendered = template
+        .replace("{path}", relative_path)
+        .replace("{dir}", dir)
+        .replace("{filename}", filename)
+        .replace("{type}", segments[0])
+        .replace("{device}", device)
+        .replace("{test}", &test);
+    //Empty placeholders must not produce absolute paths
+    rendered
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != "..")
+        .collect()
+}
+
+/// Checks that `template` only uses known placeholders
+pub fn validate_path_template(template: &str) -> std::result::Result<(), String> {
+    let placeholder = Regex::new(r"\{[^}]*\}").unwrap();
+    for found in placeholder.find_iter(template) {
+        if !PATH_TEMPLATE_PLACEHOLDERS.contains(&found.as_str()) {
+            return Err(format!(
+                "unknown placeholder '{}', supported placeholders are {}",
+                found.as_str(),
+                PATH_TEMPLATE_PLACEHOLDERS.join(", ")
+            ));
+        }
+    }
+    if !template.contains("{filename}") && !template.contains("{path}") {
+        return Err("template has to contain {filename} or {path}".to_owned());
+    }
     Ok(())
 }
 
-async fn patch_file(path: &Path) -> io::Result<()> {
+/// Points the attachments of Allure results to the downloaded logs and videos. `downloaded` holds
+/// the local paths of the files inside `output` by their remote path, as returned by `download_artifacts`
+pub async fn patch_allure_paths(
+    output: &Path,
+    downloaded: &HashMap<String, PathBuf>,
+) -> Result<()> {
+    let results = downloaded.iter().filter(|(remote_path, _)| {
+        remote_path.starts_with("report/allure-results/") && remote_path.ends_with(".json")
+    });
+    for (_, local_path) in results {
+        let path = output.join(local_path);
+        if !path.is_file() {
+            debug!("Allure result {:?} does not exist", path);
+            continue;
+        }
+        let dir = local_path.parent().unwrap_or(Path::new(""));
+        if let Err(e) = patch_file(&path, dir, downloaded).await {
+            panic!("Failed to patch file {:?}: {}", path, e);
+        }
+    }
+    Ok(())
+}
+
+async fn patch_file(
+    path: &Path,
+    dir: &Path,
+    downloaded: &HashMap<String, PathBuf>,
+) -> io::Result<()> {
     // Read the JSON file
-    let mut file = File::open(&path)?;
+    let mut file = File::open(path)?;
     let mut content = String::new();
     file.read_to_string(&mut content)?;
 
@@ -164,8 +497,12 @@ async fn patch_file(path: &Path) -> io::Result<()> {
                         .find("logs/omni")
                         .or_else(|| source_str.find("video/omni"))
                     {
-                        let new_path = format!("../../{}", &source_str[index..]);
-                        *source = Value::String(new_path);
+                        let remote_path = &source_str[index..];
+                        let local_path = downloaded
+                            .get(remote_path)
+                            .cloned()
+                            .unwrap_or_else(|| PathBuf::from(remote_path));
+                        *source = Value::String(relative_to(&local_path, dir));
                     }
                 }
             }
@@ -173,13 +510,30 @@ async fn patch_file(path: &Path) -> io::Result<()> {
     }
 
     // Write the patched JSON back to the file
-    let mut file = File::create(&path)?;
+    let mut file = File::create(path)?;
     file.write_all(serde_json::to_string_pretty(&json_value)?.as_bytes())?;
     file.flush()?;
 
     Ok(())
 }
 
+//Path of `path` relative to the folder `base`, both inside the output folder. Allure expects `/` separators
+fn relative_to(path: &Path, base: &Path) -> String {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    base[common..]
+        .iter()
+        .map(|_| "..".into())
+        .chain(
+            path[common..]
+                .iter()
+                .map(|x| x.as_os_str().to_string_lossy()),
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,12 +551,143 @@ mod tests {
         std::fs::read_to_string(fixture_path).expect("Failed to read fixture")
     }
 
+    #[test]
+    fn test_render_path_template() {
+        let path = "video/omni/emulator-5554/com.example.MainTest#testFailed-5c3e.mp4";
+        assert_eq!(
+            render_path_template("{device}/{test}/{filename}", path),
+            PathBuf::from(
+                "emulator-5554/com.example.MainTest#testFailed/com.example.MainTest#testFailed-5c3e.mp4"
+            )
+        );
+        assert_eq!(
+            render_path_template("{type}/{filename}", "report/index.html"),
+            PathBuf::from("report/index.html")
+        );
+        assert_eq!(
+            render_path_template("{dir}/{filename}", "index.html"),
+            PathBuf::from("index.html")
+        );
+        assert!(validate_path_template("{device}/{filename}").is_ok());
+        assert!(validate_path_template("{serial}/{filename}").is_err());
+        assert!(validate_path_template("{device}").is_err());
+    }
+
+    #[test]
+    fn test_select_failed_test_artifacts() {
+        let artifact = |id: &str| Artifact {
+            id: format!("run-1/{}", id),
+            is_file: true,
+            size: None,
+        };
+        let artifacts = vec![
+            artifact("tests/omni/emulator-5554/junit.xml"),
+            artifact("logs/omni/emulator-5554/com.example.MainTest#testLogin-5c3e.log"),
+            artifact("logs/omni/emulator-5554/com.example.MainTest#testLoginFails-5c3e.log"),
+            artifact("video/omni/emulator-5554/com.example.MainTest#testSum[1.5]-5c3e.mp4"),
+            artifact("video/omni/emulator-5554/com.example.MainTest#testSum[2.5]-5c3e.mp4"),
+            artifact("screenshots/omni/emulator-5554/com.example.MainTest#testLogin/1.png"),
+        ];
+        let failed = HashSet::from([
+            "com.example.MainTest#testLogin".to_owned(),
+            "com.example.MainTest#testSum[1.5]".to_owned(),
+        ]);
+
+        let selected: Vec<String> = select_failed_test_artifacts(artifacts, "run-1", &failed)
+            .into_iter()
+            .map(|artifact| artifact.id)
+            .collect();
+
+        assert_eq!(
+            selected,
+            vec![
+                "run-1/tests/omni/emulator-5554/junit.xml",
+                "run-1/logs/omni/emulator-5554/com.example.MainTest#testLogin-5c3e.log",
+                "run-1/video/omni/emulator-5554/com.example.MainTest#testSum[1.5]-5c3e.mp4",
+                "run-1/screenshots/omni/emulator-5554/com.example.MainTest#testLogin/1.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flat_layout_keeps_duplicates_apart() {
+        let artifact = |id: &str| Artifact {
+            id: id.to_owned(),
+            is_file: true,
+            size: None,
+        };
+        let artifacts = vec![
+            artifact("run-1/tests/omni/emulator-1/junit.xml"),
+            artifact("run-1/tests/omni/emulator-2/junit.xml"),
+            artifact("run-1/logs/omni/emulator-1/test.log"),
+        ];
+        assert_eq!(
+            local_paths(&artifacts, "run-1", &ArtifactLayout::Flat),
+            vec![
+                PathBuf::from("tests_omni_emulator-1_junit.xml"),
+                PathBuf::from("tests_omni_emulator-2_junit.xml"),
+                PathBuf::from("test.log"),
+            ]
+        );
+    }
+
+    fn remote_layout(result: &str) -> HashMap<String, PathBuf> {
+        let path = format!("report/allure-results/{}", result);
+        HashMap::from([(path.clone(), PathBuf::from(path))])
+    }
+
+    #[test]
+    fn test_template_layout_keeps_duplicates_apart() {
+        let artifact = |id: &str| Artifact {
+            id: format!("run-1/{}", id),
+            is_file: true,
+            size: None,
+        };
+        let artifacts = vec![
+            artifact("logs/omni/emulator-1/test.log"),
+            artifact("logs/retry/emulator-1/test.log"),
+            artifact("video/omni/emulator-1/test.mp4"),
+        ];
+
+        assert_eq!(
+            local_paths(
+                &artifacts,
+                "run-1",
+                &ArtifactLayout::Template("{device}/{filename}".to_owned())
+            ),
+            vec![
+                PathBuf::from("emulator-1/logs_omni_emulator-1_test.log"),
+                PathBuf::from("emulator-1/logs_retry_emulator-1_test.log"),
+                PathBuf::from("emulator-1/test.mp4"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relative_to() {
+        assert_eq!(
+            relative_to(
+                Path::new("logs/omni/emulator-1/test.log"),
+                Path::new("report/allure-results")
+            ),
+            "../../logs/omni/emulator-1/test.log"
+        );
+        assert_eq!(
+            relative_to(Path::new("emulator-1/test.log"), Path::new("emulator-1")),
+            "test.log"
+        );
+        assert_eq!(
+            relative_to(Path::new("test.log"), Path::new("")),
+            "test.log"
+        );
+    }
+
     #[tokio::test]
     async fn test_patch_allure_paths_directory_does_not_exist() {
         let temp_dir = tempdir().unwrap();
         let output_path = temp_dir.path().join("non_existing");
 
-        let result = patch_allure_paths(&output_path).await;
+        let result = patch_allure_paths(&output_path, &HashMap::new()).await;
         assert!(result.is_ok());
     }
 
@@ -212,7 +697,7 @@ mod tests {
         let allure_results_path = temp_dir.path().join("report/allure-results");
         fs::create_dir_all(&allure_results_path).unwrap();
 
-        let result = patch_allure_paths(temp_dir.path()).await;
+        let result = patch_allure_paths(temp_dir.path(), &HashMap::new()).await;
         assert!(result.is_ok());
     }
 
@@ -230,7 +715,7 @@ mod tests {
         file.write_all(original_json.as_bytes()).unwrap();
         file.flush().unwrap();
 
-        let result = patch_allure_paths(temp_dir.path()).await;
+        let result = patch_allure_paths(temp_dir.path(), &remote_layout("sample.json")).await;
         assert!(result.is_ok());
 
         let mut file = File::open(&json_file_path).unwrap();
@@ -257,7 +742,7 @@ mod tests {
         file.write_all(original_json.as_bytes()).unwrap();
         file.flush().unwrap();
 
-        let result = patch_allure_paths(temp_dir.path()).await;
+        let result = patch_allure_paths(temp_dir.path(), &remote_layout("sample.json")).await;
         assert!(result.is_ok());
 
         let mut file = File::open(&json_file_path).unwrap();
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Exponential backoff with equal jitter: each delay is picked from [current/2, current]
+/// and the base interval doubles after every attempt up to the cap.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        let max = max.max(initial);
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    pub fn polling(initial: Duration) -> Self {
+        Self::new(initial, MAX_POLL_INTERVAL)
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let half = self.current / 2;
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=half);
+        let delay = half + jitter;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_exponentially_within_jitter_bounds() {
+        let mut backoff = Backoff::new(Duration::from_secs(4), Duration::from_secs(60));
+
+        for expected in [4, 8, 16, 32, 60, 60] {
+            let delay = backoff.next_delay();
+            assert!(delay >= Duration::from_secs(expected) / 2);
+            assert!(delay <= Duration::from_secs(expected));
+        }
+    }
+
+    #[test]
+    fn test_cap_is_never_below_initial_interval() {
+        let mut backoff = Backoff::polling(Duration::from_secs(120));
+
+        for _ in 0..3 {
+            assert!(backoff.next_delay() <= Duration::from_secs(120));
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(60));
+        backoff.next_delay();
+        backoff.next_delay();
+
+        backoff.reset();
+
+        assert!(backoff.next_delay() <= Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use log::debug;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::errors::InputError;
+
+use super::{ApplicationBundle, LibraryBundle};
+
+/// Module map, the bundles built from every module of a repository
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ModuleMap {
+    pub modules: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+/// Bundles of the modules with changes since `reference`, e.g. origin/main. The changes
+/// are the files changed in the working tree since the merge base of `reference` and HEAD,
+/// and the untracked files which aren't ignored
+pub async fn changed_bundles(module_map: &Path, reference: &str) -> Result<HashSet<PathBuf>> {
+    let content =
+        tokio::fs::read(module_map)
+            .await
+            .map_err(|error| InputError::OpenFileFailure {
+                path: module_map.to_owned(),
+                error,
+            })?;
+    let map: ModuleMap =
+        serde_yaml::from_slice(&content).map_err(|error| InputError::InvalidModuleMap {
+            path: module_map.to_owned(),
+            message: error.to_string(),
+        })?;
+    let dir = normalize(module_map.parent().unwrap_or(Path::new("")));
+
+    let root = git(&dir, reference, &["rev-parse", "--show-toplevel"]).await?;
+    let root = normalize(Path::new(root.trim()));
+    //A reference starting with - isn't an option
+    let mut changes = git(
+        &dir,
+        reference,
+        &[
+            "diff",
+            "--name-only",
+            "--merge-base",
+            "--end-of-options",
+            reference,
+        ],
+    )
+    .await?;
+    changes += &git(
+        &dir,
+        reference,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--full-name",
+            "--",
+            ":/",
+        ],
+    )
+    .await?;
+    let changed_files: Vec<PathBuf> = changes
+        .lines()
+        .filter(|x| !x.is_empty())
+        .map(|x| root.join(x))
+        .collect();
+    debug!("{} files changed since {}", changed_files.len(), reference);
+    Ok(select(&map, &dir, &changed_files))
+}
+
+/// Keeps the bundles selected by `changed_bundles`. An application bundle is kept if either
+/// of its applications is selected
+pub fn retain(
+    selection: &HashSet<PathBuf>,
+    application_bundles: &mut Vec<ApplicationBundle>,
+    library_bundles: &mut Vec<LibraryBundle>,
+) {
+    application_bundles.retain(|x| {
+        selection.contains(&normalize(&x.app_path))
+            || selection.contains(&normalize(&x.test_app_path))
+    });
+    library_bundles.retain(|x| selection.contains(&normalize(&x.test_app_path)));
+}
+
+fn select(map: &ModuleMap, dir: &Path, changed_files: &[PathBuf]) -> HashSet<PathBuf> {
+    let mut selection = HashSet::new();
+    for (module, bundles) in &map.modules {
+        let module = normalize(&dir.join(module));
+        if changed_files.iter().any(|x| x.starts_with(&module)) {
+            debug!("Module {} changed", module.display());
+            selection.extend(bundles.iter().map(|x| normalize(&dir.join(x))));
+        }
+    }
+    selection
+}
+
+//Bundles are compared by absolute path, the files may not exist for removed modules
+fn normalize(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|x| x.join(path))
+            .unwrap_or_else(|_| path.to_owned())
+    })
+}
+
+async fn git(dir: &Path, reference: &str, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+    debug!("Running {:?}", command);
+    let output = command
+        .output()
+        .await
+        .map_err(|error| InputError::GitDiffFailed {
+            reference: reference.to_owned(),
+            message: error.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(InputError::GitDiffFailed {
+            reference: reference.to_owned(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        }
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = normalize(dir.path());
+        let map: ModuleMap = serde_yaml::from_str(
+            "modules:
+  feature/login: [apks/login-androidTest.apk]
+  feature/profile: [apks/profile-androidTest.apk]
+  core: [apks/core-androidTest.apk, apks/core-app.apk]
+",
+        )?;
+        let changed_files = vec![
+            dir.join("feature/login/src/main/Login.kt"),
+            dir.join("core/build.gradle"),
+            dir.join("feature/profiles/README.md"),
+        ];
+        assert_eq!(
+            select(&map, &dir, &changed_files),
+            HashSet::from([
+                dir.join("apks/login-androidTest.apk"),
+                dir.join("apks/core-androidTest.apk"),
+                dir.join("apks/core-app.apk"),
+            ])
+        );
+
+        let mut application_bundles = vec![ApplicationBundle {
+            app_path: dir.join("apks/core-app.apk"),
+            test_app_path: dir.join("apks/app-androidTest.apk"),
+            filtering_configuration: None,
+            flavor: None,
+        }];
+        let mut library_bundles = vec![
+            LibraryBundle::from(dir.join("apks/login-androidTest.apk")),
+            LibraryBundle::from(dir.join("apks/profile-androidTest.apk")),
+        ];
+        retain(
+            &select(&map, &dir, &changed_files),
+            &mut application_bundles,
+            &mut library_bundles,
+        );
+        assert_eq!(application_bundles.len(), 1);
+        assert_eq!(
+            library_bundles
+                .iter()
+                .map(|x| x.test_app_path.clone())
+                .collect::<Vec<_>>(),
+            vec![dir.join("apks/login-androidTest.apk")]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::{errors::InputError, filtering};
+
+use super::{ApplicationBundle, LibraryBundle};
+
+/// Bundle file listing the application and library bundles of a run, an alternative to
+/// `--application-bundle` and `--library-bundle` for paths with commas and for many modules
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BundleFile {
+    #[serde(default)]
+    pub application_bundles: Vec<ApplicationBundleEntry>,
+    #[serde(default)]
+    pub library_bundles: Vec<LibraryBundleEntry>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ApplicationBundleEntry {
+    pub application: PathBuf,
+    pub test_application: PathBuf,
+    pub filter_file: Option<PathBuf>,
+    pub flavor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LibraryBundleEntry {
+    pub test_application: PathBuf,
+    pub filter_file: Option<PathBuf>,
+    pub flavor: Option<String>,
+}
+
+/// Reads the bundle file at `path`. Relative paths in the file are resolved against the
+/// directory of the file
+pub async fn load(path: &Path) -> Result<(Vec<ApplicationBundle>, Vec<LibraryBundle>)> {
+    let content = tokio::fs::read(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+    let file: BundleFile =
+        serde_yaml::from_slice(&content).map_err(|error| InputError::InvalidBundleFile {
+            path: path.to_owned(),
+            message: error.to_string(),
+        })?;
+    if file.application_bundles.is_empty() && file.library_bundles.is_empty() {
+        return Err(InputError::InvalidBundleFile {
+            path: path.to_owned(),
+            message: "no bundles found".into(),
+        }
+        .into());
+    }
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut application_bundles = Vec::new();
+    for entry in file.application_bundles {
+        application_bundles.push(ApplicationBundle {
+            app_path: existing(dir, &entry.application)?,
+            test_app_path: existing(dir, &entry.test_application)?,
+            filtering_configuration: filtering_configuration(dir, entry.filter_file).await?,
+            flavor: entry.flavor,
+        });
+    }
+    let mut library_bundles = Vec::new();
+    for entry in file.library_bundles {
+        //A glob adds a bundle per matching test application, all with the same filters
+        let filtering_configuration = filtering_configuration(dir, entry.filter_file).await?;
+        for test_app_path in super::expand_glob(&dir.join(&entry.test_application))? {
+            library_bundles.push(LibraryBundle {
+                test_app_path: existing(Path::new(""), &test_app_path)?,
+                filtering_configuration: filtering_configuration.clone(),
+                flavor: entry.flavor.clone(),
+            });
+        }
+    }
+    Ok((application_bundles, library_bundles))
+}
+
+fn existing(dir: &Path, path: &Path) -> Result<PathBuf, InputError> {
+    let path = dir.join(path);
+    if !path.exists() {
+        return Err(InputError::InvalidFileName { path });
+    }
+    Ok(path)
+}
+
+async fn filtering_configuration(
+    dir: &Path,
+    filter_file: Option<PathBuf>,
+) -> Result<Option<filtering::model::SparseMarathonfile>> {
+    match filter_file {
+        Some(filter_file) => Ok(Some(
+            filtering::convert::convert(existing(dir, &filter_file)?).await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for file in ["app.apk", "app-test.apk", "lib, with comma.apk"] {
+            tokio::fs::write(dir.path().join(file), b"apk").await?;
+        }
+        tokio::fs::write(
+            dir.path().join("filter.yaml"),
+            "filteringConfiguration:\n  allowlist:\n    - type: \"fully-qualified-class-name\"\n      values: [\"com.example.FeatureTest\"]\n",
+        )
+        .await?;
+        let bundle_file = dir.path().join("bundles.yml");
+        tokio::fs::write(
+            &bundle_file,
+            r#"
+application-bundles:
+  - application: app.apk
+    test-application: app-test.apk
+    filter-file: filter.yaml
+    flavor: native
+library-bundles:
+  - test-application: "lib, with comma.apk"
+  - test-application: "app*.apk"
+"#,
+        )
+        .await?;
+
+        let (application_bundles, library_bundles) = load(&bundle_file).await?;
+        assert_eq!(application_bundles.len(), 1);
+        assert_eq!(application_bundles[0].app_path, dir.path().join("app.apk"));
+        assert_eq!(application_bundles[0].flavor.as_deref(), Some("native"));
+        assert!(application_bundles[0].filtering_configuration.is_some());
+        assert_eq!(
+            library_bundles[0].test_app_path,
+            dir.path().join("lib, with comma.apk")
+        );
+        assert!(library_bundles[0].filtering_configuration.is_none());
+        assert_eq!(
+            library_bundles[1..]
+                .iter()
+                .map(|x| x.test_app_path.clone())
+                .collect::<Vec<_>>(),
+            vec![dir.path().join("app-test.apk"), dir.path().join("app.apk")]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_missing_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let bundle_file = dir.path().join("bundles.yml");
+        tokio::fs::write(
+            &bundle_file,
+            "library-bundles:\n  - test-application: missing.apk\n",
+        )
+        .await?;
+        let Err(error) = load(&bundle_file).await else {
+            panic!("missing test application should be rejected");
+        };
+        assert!(error.to_string().contains("missing.apk"));
+
+        tokio::fs::write(&bundle_file, "application-bundles: []\n").await?;
+        assert!(load(&bundle_file).await.is_err());
+        Ok(())
+    }
+}
@@ -1,10 +1,87 @@
-use crate::errors::InputError;
-use std::path::PathBuf;
+use crate::{errors::InputError, filtering::model::SparseMarathonfile};
+use globset::GlobBuilder;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
 
-#[derive(Debug)]
+pub mod changes;
+pub mod file;
+
+#[derive(Debug, Clone)]
 pub struct ApplicationBundle {
     pub app_path: PathBuf,
     pub test_app_path: PathBuf,
+    /// Filters applied to the tests of this bundle only
+    pub filtering_configuration: Option<SparseMarathonfile>,
+    /// Flavor of this bundle, the flavor of the run is used otherwise
+    pub flavor: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LibraryBundle {
+    pub test_app_path: PathBuf,
+    /// Filters applied to the tests of this bundle only
+    pub filtering_configuration: Option<SparseMarathonfile>,
+    /// Flavor of this bundle, the flavor of the run is used otherwise
+    pub flavor: Option<String>,
+}
+
+impl From<PathBuf> for LibraryBundle {
+    fn from(test_app_path: PathBuf) -> Self {
+        LibraryBundle {
+            test_app_path,
+            filtering_configuration: None,
+            flavor: None,
+        }
+    }
+}
+
+/// Files matching the glob `pattern`, e.g. `modules/**/androidTest/debug/*.apk`, sorted by path.
+/// `*` doesn't match across directories, `**` does. Paths without glob characters are kept as is
+pub fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, InputError> {
+    let is_glob = |component: &Component| {
+        component
+            .as_os_str()
+            .to_string_lossy()
+            .contains(['*', '?', '[', '{'])
+    };
+    if !pattern.components().any(|x| is_glob(&x)) {
+        return Ok(vec![pattern.to_owned()]);
+    }
+    //Only the directory in front of the first glob component is walked
+    let root: PathBuf = pattern.components().take_while(|x| !is_glob(x)).collect();
+    let relative: PathBuf = pattern.components().skip_while(|x| !is_glob(x)).collect();
+    let invalid = |message: String| InputError::InvalidGlob {
+        pattern: pattern.to_string_lossy().into_owned(),
+        message,
+    };
+    let matcher = GlobBuilder::new(&relative.to_string_lossy())
+        .literal_separator(true)
+        .build()
+        .map_err(|error| invalid(error.to_string()))?
+        .compile_matcher();
+
+    let walk_root = if root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        root.as_path()
+    };
+    let mut paths: Vec<PathBuf> = WalkDir::new(walk_root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(walk_root).ok()?;
+            matcher.is_match(relative).then(|| root.join(relative))
+        })
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(InputError::NoGlobMatches {
+            pattern: pattern.to_string_lossy().into_owned(),
+        });
+    }
+    Ok(paths)
 }
 
 pub fn transform_and_validate_bundle(
@@ -34,9 +111,58 @@ pub fn transform_and_validate_bundle(
         let bundle = ApplicationBundle {
             app_path,
             test_app_path,
+            filtering_configuration: None,
+            flavor: None,
         };
         bundles.push(bundle);
     }
 
     Ok(bundles)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_glob() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for module in ["feature/b", "feature/a", "core"] {
+            let apks = dir
+                .path()
+                .join(module)
+                .join("outputs/apk/androidTest/debug");
+            std::fs::create_dir_all(&apks)?;
+            std::fs::write(apks.join("test.apk"), b"apk")?;
+            std::fs::write(apks.join("output-metadata.json"), b"{}")?;
+        }
+
+        let pattern = dir.path().join("**/outputs/apk/androidTest/debug/*.apk");
+        assert_eq!(
+            expand_glob(&pattern)?,
+            ["core", "feature/a", "feature/b"]
+                .iter()
+                .map(|module| {
+                    dir.path()
+                        .join(module)
+                        .join("outputs/apk/androidTest/debug/test.apk")
+                })
+                .collect::<Vec<_>>()
+        );
+        //A single star doesn't match nested modules
+        assert_eq!(
+            expand_glob(&dir.path().join("*/outputs/apk/androidTest/debug/*.apk"))?.len(),
+            1
+        );
+
+        let path = dir
+            .path()
+            .join("core/outputs/apk/androidTest/debug/test.apk");
+        assert_eq!(expand_glob(&path)?, vec![path]);
+        assert!(matches!(
+            expand_glob(&dir.path().join("**/*.aab")),
+            Err(InputError::NoGlobMatches { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const CACHE_DIR_ENV: &str = "MARATHON_CLOUD_CACHE_DIR";
+
+/// Directory of the local caches. MARATHON_CLOUD_CACHE_DIR replaces the cache directory of the
+/// platform, which isn't affected by XDG_CACHE_HOME on macOS and Windows
+pub fn dir() -> Option<PathBuf> {
+    match std::env::var_os(CACHE_DIR_ENV) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => dirs::cache_dir().map(|dir| dir.join("marathon-cloud")),
+    }
+}
+
+/// Seconds since the Unix epoch, as stored in the cache files. Times before it are 0
+pub(crate) fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
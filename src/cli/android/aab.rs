@@ -0,0 +1,245 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use async_zip::tokio::read::fs::ZipFileReader;
+use log::debug;
+use tokio::process::Command;
+
+use crate::{cli::BundletoolArgs, errors::InputError};
+
+const BASE_MANIFEST: &str = "base/manifest/AndroidManifest.xml";
+const UNIVERSAL_APK: &str = "universal.apk";
+const BASE_SPLIT: &str = "splits/base-master.apk";
+
+pub(crate) fn is_aab(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("aab"))
+}
+
+pub(crate) fn is_apks(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("apks"))
+}
+
+/// Application of an APK set generated by `bundletool build-apks`, extracted to `work_dir`.
+/// The universal APK is used if the set has one. Otherwise the base APK is the application
+/// and all other splits, including dynamic feature modules, are installed together with it
+pub(crate) async fn apk_set(apks: &Path, work_dir: &Path) -> Result<(PathBuf, Vec<PathBuf>)> {
+    let reader = ZipFileReader::new(apks)
+        .await
+        .map_err(|error| InputError::InvalidAab {
+            path: apks.to_owned(),
+            message: error.to_string(),
+        })?;
+    let names: Vec<String> = reader
+        .file()
+        .entries()
+        .iter()
+        .map(|entry| entry.filename().as_str().unwrap_or_default().to_owned())
+        .collect();
+    if let Some(index) = names.iter().position(|x| x == UNIVERSAL_APK) {
+        let apk = work_dir.join(UNIVERSAL_APK);
+        extract(&reader, index, &apk).await?;
+        return Ok((apk, vec![]));
+    }
+
+    let base =
+        names
+            .iter()
+            .position(|x| x == BASE_SPLIT)
+            .ok_or_else(|| InputError::InvalidAab {
+                path: apks.to_owned(),
+                message: format!("neither {} nor {} found", UNIVERSAL_APK, BASE_SPLIT),
+            })?;
+    let application = work_dir.join("base-master.apk");
+    extract(&reader, base, &application).await?;
+
+    let mut splits: Vec<(usize, &str)> = names
+        .iter()
+        .enumerate()
+        .filter(|(index, name)| {
+            *index != base && name.starts_with("splits/") && name.ends_with(".apk")
+        })
+        .filter_map(|(index, name)| Some((index, name.rsplit('/').next()?)))
+        .collect();
+    splits.sort_by_key(|(_, name)| name.to_owned());
+    let mut additional = Vec::new();
+    for (index, name) in splits {
+        let apk = work_dir.join(name);
+        extract(&reader, index, &apk).await?;
+        additional.push(apk);
+    }
+    debug!(
+        "Installing {} splits of {} with the base APK",
+        additional.len(),
+        apks.display()
+    );
+    Ok((application, additional))
+}
+
+/// Generates a universal APK of the Android App Bundle at `aab` in `work_dir` with bundletool.
+/// bundletool signs the APK with the debug keystore unless a keystore is set
+pub(crate) async fn universal_apk(
+    aab: &Path,
+    args: &BundletoolArgs,
+    work_dir: &Path,
+) -> Result<PathBuf> {
+    validate(aab).await?;
+
+    let apks = work_dir.join("universal.apks");
+    let mut command = match &args.bundletool {
+        Some(jar) => {
+            let mut command = Command::new("java");
+            command.arg("-jar").arg(jar);
+            command
+        }
+        None => Command::new("bundletool"),
+    };
+    command
+        .arg("build-apks")
+        .arg("--mode=universal")
+        .arg("--overwrite")
+        .arg(format!("--bundle={}", aab.display()))
+        .arg(format!("--output={}", apks.display()));
+    if let Some(keystore) = &args.keystore {
+        command.arg(format!("--ks={}", keystore.display()));
+    }
+    for (arg, value) in [
+        ("--ks-pass", &args.keystore_password),
+        ("--ks-key-alias", &args.key_alias),
+        ("--key-pass", &args.key_password),
+    ] {
+        if let Some(value) = value {
+            command.arg(format!("{}={}", arg, value));
+        }
+    }
+    debug!("Running {:?}", command);
+    let output = command
+        .output()
+        .await
+        .map_err(|error| InputError::BundletoolNotFound { error })?;
+    if !output.status.success() {
+        return Err(InputError::BundletoolFailed {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+        .into());
+    }
+
+    let name = aab
+        .file_stem()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "application".to_owned());
+    let (universal, _) = apk_set(&apks, work_dir).await?;
+    let apk = work_dir.join(format!("{}.apk", name));
+    tokio::fs::rename(universal, &apk).await?;
+    Ok(apk)
+}
+
+//Only the base module is required, everything else is optional in an App Bundle
+async fn validate(aab: &Path) -> Result<()> {
+    let invalid = |message: String| InputError::InvalidAab {
+        path: aab.to_owned(),
+        message,
+    };
+    let reader = ZipFileReader::new(aab)
+        .await
+        .map_err(|error| invalid(error.to_string()))?;
+    let has_manifest = reader
+        .file()
+        .entries()
+        .iter()
+        .any(|entry| entry.filename().as_str().is_ok_and(|x| x == BASE_MANIFEST));
+    if !has_manifest {
+        return Err(invalid(format!("{} is missing", BASE_MANIFEST)).into());
+    }
+    Ok(())
+}
+
+async fn extract(reader: &ZipFileReader, index: usize, apk: &Path) -> Result<()> {
+    let mut data = Vec::new();
+    reader
+        .reader_with_entry(index)
+        .await?
+        .read_to_end_checked(&mut data)
+        .await?;
+    tokio::fs::write(apk, data).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::zip;
+
+    #[test]
+    fn test_is_aab() {
+        assert!(is_aab(Path::new("app-release.aab")));
+        assert!(is_aab(Path::new("build/app-release.AAB")));
+        assert!(!is_aab(Path::new("app-release.apk")));
+    }
+
+    #[tokio::test]
+    async fn test_validate() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let aab = dir.path().join("app.aab");
+        zip(&aab, &[(BASE_MANIFEST, b"manifest")]).await?;
+        validate(&aab).await?;
+
+        let apk = dir.path().join("app.apk");
+        zip(&apk, &[("AndroidManifest.xml", b"manifest")]).await?;
+        let Err(error) = validate(&apk).await else {
+            panic!("APK should be rejected");
+        };
+        assert!(error.to_string().contains(BASE_MANIFEST));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apk_set_with_universal_apk() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let apks = dir.path().join("universal.apks");
+        zip(
+            &apks,
+            &[("toc.pb", b"toc"), (UNIVERSAL_APK, b"universal apk")],
+        )
+        .await?;
+        let work_dir = tempfile::tempdir()?;
+        let (apk, additional) = apk_set(&apks, work_dir.path()).await?;
+        assert_eq!(tokio::fs::read(&apk).await?, b"universal apk");
+        assert!(additional.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apk_set_with_splits() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let apks = dir.path().join("app.apks");
+        zip(
+            &apks,
+            &[
+                ("toc.pb", b"toc"),
+                ("splits/feature-master.apk", b"feature"),
+                (BASE_SPLIT, b"base"),
+                ("splits/base-xxhdpi.apk", b"density"),
+                ("standalones/standalone-x86_64.apk", b"standalone"),
+            ],
+        )
+        .await?;
+        let work_dir = tempfile::tempdir()?;
+        let (apk, additional) = apk_set(&apks, work_dir.path()).await?;
+        assert_eq!(tokio::fs::read(&apk).await?, b"base");
+        assert_eq!(
+            additional,
+            vec![
+                work_dir.path().join("base-xxhdpi.apk"),
+                work_dir.path().join("feature-master.apk"),
+            ]
+        );
+
+        let invalid = dir.path().join("invalid.apks");
+        zip(&invalid, &[("toc.pb", b"toc")]).await?;
+        assert!(apk_set(&invalid, work_dir.path()).await.is_err());
+        Ok(())
+    }
+}
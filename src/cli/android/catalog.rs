@@ -0,0 +1,165 @@
+use anyhow::Result;
+
+use crate::{
+    api::{AndroidDevice, RapiClient, RapiReqwestClient},
+    cli::catalog,
+    errors::ConfigurationError,
+};
+
+//Form factors are accepted in addition to the device ids of the catalog
+const FORM_FACTORS: [&str; 4] = ["phone", "tv", "watch", "wear"];
+const MAX_SUGGESTIONS: usize = 3;
+
+async fn fetch(base_url: &str, api_key: &str) -> Result<Vec<AndroidDevice>> {
+    let client = RapiReqwestClient::new(base_url, api_key);
+    let token = client.get_token().await?;
+    client.get_devices_android(&token).await
+}
+
+/// Checks `device` and the requested RAM and storage in megabytes against the Android catalog
+/// before anything is uploaded. The check is skipped if the catalog can't be fetched and wasn't
+/// cached before. Only the cache is used `offline`
+pub(crate) async fn validate_device(
+    base_url: &str,
+    api_key: &str,
+    device: &str,
+    ram: Option<u32>,
+    storage: Option<u32>,
+    offline: bool,
+) -> Result<()> {
+    if FORM_FACTORS.contains(&device) {
+        return Ok(());
+    }
+    let devices = match offline {
+        true => catalog::cached("android-catalog", base_url),
+        false => catalog::load("android-catalog", base_url, fetch(base_url, api_key)).await,
+    };
+    match devices {
+        Some(devices) => check_device(&devices, device, ram, storage),
+        None => Ok(()),
+    }
+}
+
+fn check_device(
+    devices: &[AndroidDevice],
+    device: &str,
+    ram: Option<u32>,
+    storage: Option<u32>,
+) -> Result<()> {
+    if let Some(entry) = devices.iter().find(|x| x.id == device) {
+        return check_limit("RAM", ram, entry.max_ram_mb, device).and(check_limit(
+            "storage",
+            storage,
+            entry.max_storage_mb,
+            device,
+        ));
+    }
+    let mut candidates: Vec<(usize, &str)> = devices
+        .iter()
+        .map(|x| x.id.as_str())
+        .chain(FORM_FACTORS)
+        .map(|id| (distance(&device.to_lowercase(), &id.to_lowercase()), id))
+        .collect();
+    candidates.sort();
+    let suggestions: Vec<&str> = candidates
+        .iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, id)| *id)
+        .collect();
+    Err(ConfigurationError::UnsupportedRunConfiguration {
+        message: format!(
+            "Device {} isn't supported. Closest matches: {}. Use `marathon-cloud devices android` to get a list of supported devices",
+            device,
+            suggestions.join(", ")
+        ),
+    }
+    .into())
+}
+
+//Devices without a limit in the catalog accept any size
+fn check_limit(
+    resource: &str,
+    requested: Option<u32>,
+    limit: Option<u32>,
+    device: &str,
+) -> Result<()> {
+    match (requested, limit) {
+        (Some(requested), Some(limit)) if requested > limit => {
+            Err(ConfigurationError::UnsupportedRunConfiguration {
+                message: format!(
+                    "Device {} supports up to {}M of {}, {}M requested",
+                    device, limit, resource, requested
+                ),
+            }
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+//Levenshtein distance
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != *y);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str) -> AndroidDevice {
+        AndroidDevice {
+            name: id.to_owned(),
+            id: id.to_owned(),
+            manufacturer: "Google".to_owned(),
+            width: 1080,
+            height: 2400,
+            dpi: 420,
+            max_ram_mb: None,
+            max_storage_mb: None,
+        }
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("pixel-7", "pixel-7"), 0);
+        assert_eq!(distance("pixel7", "pixel-7"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "tv"), 2);
+    }
+
+    #[test]
+    fn test_check_device() {
+        let devices = vec![device("pixel-7"), device("pixel-7-pro"), device("nexus-5")];
+        assert!(check_device(&devices, "pixel-7", None, None).is_ok());
+
+        let error = check_device(&devices, "Pixel7", None, None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Closest matches: pixel-7, "), "{}", error);
+        assert!(!error.contains("nexus-5"), "{}", error);
+    }
+
+    #[test]
+    fn test_check_device_limits() {
+        let devices = vec![AndroidDevice {
+            max_ram_mb: Some(4096),
+            ..device("pixel-7")
+        }];
+        assert!(check_device(&devices, "pixel-7", Some(4096), Some(65536)).is_ok());
+
+        let error = check_device(&devices, "pixel-7", Some(8192), None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("up to 4096M of RAM"), "{}", error);
+    }
+}
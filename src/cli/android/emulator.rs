@@ -0,0 +1,97 @@
+use std::fmt::Display;
+
+//Options of the emulator command line which don't interfere with how Marathon Cloud
+//manages emulators, with whether they take a value
+const ALLOWED_OPTIONS: &[(&str, bool)] = &[
+    ("camera-back", true),
+    ("camera-front", true),
+    ("dns-server", true),
+    ("gpu", true),
+    ("netdelay", true),
+    ("netspeed", true),
+    ("timezone", true),
+    ("no-audio", false),
+    ("no-boot-anim", false),
+    ("no-snapshot", false),
+];
+
+/// Emulator launch option in the form `NAME[=VALUE]`, e.g. `gpu=swiftshader_indirect`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulatorArg {
+    name: String,
+    value: Option<String>,
+}
+
+impl EmulatorArg {
+    /// Command line arguments of the emulator, e.g. `-gpu swiftshader_indirect`
+    pub fn command_line(&self) -> Vec<String> {
+        std::iter::once(format!("-{}", self.name))
+            .chain(self.value.clone())
+            .collect()
+    }
+}
+
+impl Display for EmulatorArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.name, value),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+pub(crate) fn parse(arg: &str) -> Result<EmulatorArg, String> {
+    let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name, Some(value.to_owned())),
+        None => (arg, None),
+    };
+    let name = name.trim_start_matches('-');
+    let takes_value = ALLOWED_OPTIONS
+        .iter()
+        .find(|(option, _)| *option == name)
+        .map(|(_, takes_value)| *takes_value)
+        .ok_or_else(|| {
+            let allowed: Vec<&str> = ALLOWED_OPTIONS.iter().map(|(option, _)| *option).collect();
+            format!(
+                "unsupported emulator option '{}', supported options are [{}]",
+                name,
+                allowed.join(", ")
+            )
+        })?;
+    match (&value, takes_value) {
+        (Some(value), true) if !value.is_empty() => {}
+        (_, true) => {
+            return Err(format!(
+                "emulator option '{}' requires a value, e.g. {}=VALUE",
+                name, name
+            ))
+        }
+        (Some(_), false) => return Err(format!("emulator option '{}' doesn't take a value", name)),
+        (None, false) => {}
+    }
+    Ok(EmulatorArg {
+        name: name.to_owned(),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let gpu = parse("gpu=swiftshader_indirect").unwrap();
+        assert_eq!(gpu.command_line(), vec!["-gpu", "swiftshader_indirect"]);
+        assert_eq!(gpu.to_string(), "gpu=swiftshader_indirect");
+        assert_eq!(
+            parse("-no-snapshot").unwrap().command_line(),
+            vec!["-no-snapshot"]
+        );
+
+        assert!(parse("gpu").is_err());
+        assert!(parse("no-snapshot=true").is_err());
+        assert!(parse("wipe-data").is_err());
+        assert!(parse("qemu=-m 4096").is_err());
+    }
+}
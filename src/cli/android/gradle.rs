@@ -0,0 +1,235 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::debug;
+use serde::Deserialize;
+use walkdir::WalkDir;
+
+use crate::errors::InputError;
+
+const OUTPUT_METADATA: &str = "output-metadata.json";
+
+/// APKs built by Gradle for a module and variant
+#[derive(Debug, PartialEq)]
+pub(crate) struct GradleOutputs {
+    /// Missing for library modules, which only have a test APK
+    pub application: Option<PathBuf>,
+    pub test_application: PathBuf,
+}
+
+//Written by the Android Gradle plugin next to the APKs of every variant
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputMetadata {
+    variant_name: String,
+    #[serde(default)]
+    elements: Vec<OutputElement>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputElement {
+    output_file: String,
+    #[serde(default)]
+    filters: Vec<serde_json::Value>,
+}
+
+/// Finds the APKs of `variant`, e.g. debug or freeDebug, in the Gradle build outputs of the
+/// module at `module`. The output metadata of the Android Gradle plugin is used when present,
+/// otherwise the APK in the directory of the variant
+pub(crate) fn discover(module: &Path, variant: &str) -> Result<GradleOutputs> {
+    let apk_dir = module.join("build").join("outputs").join("apk");
+    let not_found = |message: String| InputError::GradleOutputNotFound {
+        module: module.to_owned(),
+        variant: variant.to_owned(),
+        message,
+    };
+    if !apk_dir.is_dir() {
+        return Err(not_found(format!(
+            "{} doesn't exist, please build the module first",
+            apk_dir.display()
+        ))
+        .into());
+    }
+
+    let test_variant = format!("{}AndroidTest", variant);
+    let test_application = find(&apk_dir, &test_variant, &apk_dir.join("androidTest"))
+        .map_err(not_found)?
+        .ok_or_else(|| not_found(format!("no APK of {} found", test_variant)))?;
+    let application = find(&apk_dir, variant, &apk_dir).map_err(not_found)?;
+    Ok(GradleOutputs {
+        application,
+        test_application,
+    })
+}
+
+fn find(
+    apk_dir: &Path,
+    variant: &str,
+    fallback_dir: &Path,
+) -> std::result::Result<Option<PathBuf>, String> {
+    for entry in WalkDir::new(apk_dir)
+        .max_depth(4)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name() == OUTPUT_METADATA)
+    {
+        let metadata: OutputMetadata = match std::fs::read(entry.path())
+            .map_err(|error| error.to_string())
+            .and_then(|data| serde_json::from_slice(&data).map_err(|error| error.to_string()))
+        {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                debug!("Ignoring {}: {}", entry.path().display(), error);
+                continue;
+            }
+        };
+        if metadata.variant_name != variant {
+            continue;
+        }
+        let dir = entry.path().parent().unwrap_or(apk_dir);
+        //ABI and density splits have filters, the universal APK has none
+        let apks: Vec<&OutputElement> = metadata
+            .elements
+            .iter()
+            .filter(|x| x.filters.is_empty())
+            .collect();
+        return match apks.as_slice() {
+            [apk] => Ok(Some(dir.join(&apk.output_file))),
+            [] => Err(format!(
+                "no universal APK of {} found in {}",
+                variant,
+                entry.path().display()
+            )),
+            _ => Err(format!(
+                "more than one APK of {} found in {}",
+                variant,
+                entry.path().display()
+            )),
+        };
+    }
+
+    //Older versions of the Android Gradle plugin don't write output metadata
+    let dir = fallback_dir.join(variant_path(variant.trim_end_matches("AndroidTest")));
+    let apks: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|x| x == "apk"))
+                .collect()
+        })
+        .unwrap_or_default();
+    match apks.as_slice() {
+        [] => Ok(None),
+        [apk] => Ok(Some(apk.clone())),
+        _ => Err(format!("more than one APK found in {}", dir.display())),
+    }
+}
+
+//The APKs of freeDebug are in free/debug, the build type is the last word of the variant
+fn variant_path(variant: &str) -> PathBuf {
+    match variant.rfind(|c: char| c.is_ascii_uppercase()) {
+        Some(index) if index > 0 => {
+            let (flavor, build_type) = variant.split_at(index);
+            let mut build_type = build_type.to_owned();
+            build_type[..1].make_ascii_lowercase();
+            Path::new(flavor).join(build_type)
+        }
+        _ => PathBuf::from(variant),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apk(module: &Path, dir: &str, name: &str, variant: Option<&str>) {
+        let dir = module.join("build/outputs/apk").join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(name), b"apk").unwrap();
+        if let Some(variant) = variant {
+            let metadata = format!(
+                r#"{{"version":3,"artifactType":{{"type":"APK","kind":"Directory"}},"applicationId":"com.example","variantName":"{}","elements":[{{"type":"SINGLE","filters":[],"outputFile":"{}"}}],"elementType":"File"}}"#,
+                variant, name
+            );
+            std::fs::write(dir.join(OUTPUT_METADATA), metadata).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_discover_with_output_metadata() -> Result<()> {
+        let module = tempfile::tempdir()?;
+        let module = module.path();
+        apk(
+            module,
+            "free/debug",
+            "app-free-debug.apk",
+            Some("freeDebug"),
+        );
+        apk(
+            module,
+            "paid/debug",
+            "app-paid-debug.apk",
+            Some("paidDebug"),
+        );
+        apk(
+            module,
+            "androidTest/free/debug",
+            "app-free-debug-androidTest.apk",
+            Some("freeDebugAndroidTest"),
+        );
+
+        assert_eq!(
+            discover(module, "freeDebug")?,
+            GradleOutputs {
+                application: Some(module.join("build/outputs/apk/free/debug/app-free-debug.apk")),
+                test_application: module.join(
+                    "build/outputs/apk/androidTest/free/debug/app-free-debug-androidTest.apk"
+                ),
+            }
+        );
+        assert!(discover(module, "paidDebug").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_without_output_metadata() -> Result<()> {
+        let module = tempfile::tempdir()?;
+        let module = module.path();
+        apk(module, "debug", "app-debug.apk", None);
+        apk(
+            module,
+            "androidTest/debug",
+            "app-debug-androidTest.apk",
+            None,
+        );
+        let outputs = discover(module, "debug")?;
+        assert_eq!(
+            outputs.application,
+            Some(module.join("build/outputs/apk/debug/app-debug.apk"))
+        );
+
+        //Library modules only have a test APK
+        let library = tempfile::tempdir()?;
+        apk(
+            library.path(),
+            "androidTest/debug",
+            "lib-debug-androidTest.apk",
+            None,
+        );
+        assert_eq!(discover(library.path(), "debug")?.application, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_variant_path() {
+        assert_eq!(variant_path("debug"), PathBuf::from("debug"));
+        assert_eq!(variant_path("freeDebug"), PathBuf::from("free/debug"));
+        assert_eq!(
+            variant_path("freeStagingRelease"),
+            PathBuf::from("freeStaging/release")
+        );
+    }
+}
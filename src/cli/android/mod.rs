@@ -1,17 +1,36 @@
-use crate::{errors::InputError, pull::parse_pull_args};
+use crate::{
+    errors::InputError,
+    pull::parse_pull_args,
+    push::{parse_push_args, PushFile},
+};
 use anyhow::Result;
 use std::{fmt::Display, path::PathBuf};
 
 use crate::{
-    bundle,
-    cli::{self, AnalyticsArgs, ApiArgs, CommonRunArgs, RetryArgs},
+    api::RunOptions,
+    bundle::{self, LibraryBundle},
+    cli::{
+        self, detox, fan_out, AnalyticsArgs, ApiArgs, BundletoolArgs, CommonRunArgs, DetoxArgs,
+        LocaleArgs, RemoteArtifactArgs, RetryArgs,
+    },
     errors::ConfigurationError,
     filtering,
+    formatter::{Formatter, StandardFormatter},
     interactor::TriggerTestRunInteractor,
     pull::PullFileConfig,
+    retries, timeouts,
 };
 
-use super::ProfilingArgs;
+mod aab;
+mod catalog;
+pub(crate) mod emulator;
+mod gradle;
+pub(crate) mod permissions;
+
+use super::{
+    model::{DeviceClass, NetworkProfile, Orientation, Platform},
+    ProfilingArgs,
+};
 
 #[derive(Debug, clap::ValueEnum, Clone)]
 pub enum SystemImage {
@@ -30,7 +49,7 @@ impl Display for SystemImage {
     }
 }
 
-#[derive(Debug, clap::ValueEnum, Clone)]
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq)]
 pub enum OsVersion {
     #[clap(name = "10")]
     Android10,
@@ -67,6 +86,8 @@ pub enum Flavor {
     JsJestAppium,
     #[clap(name = "python-robotframework-appium")]
     PythonRobotFrameworkAppium,
+    #[clap(name = "js-detox")]
+    JsDetox,
 }
 
 impl Display for Flavor {
@@ -75,88 +96,19 @@ impl Display for Flavor {
             Flavor::Native => f.write_str("native"),
             Flavor::JsJestAppium => f.write_str("js-jest-appium"),
             Flavor::PythonRobotFrameworkAppium => f.write_str("python-robotframework-appium"),
+            Flavor::JsDetox => f.write_str("js-detox"),
         }
     }
 }
 
-pub(crate) async fn run(
-    application: Option<std::path::PathBuf>,
-    test_application: Option<std::path::PathBuf>,
-    os_version: Option<OsVersion>,
-    system_image: Option<SystemImage>,
-    device: Option<String>,
-    common: CommonRunArgs,
-    api_args: ApiArgs,
-    flavor: Option<Flavor>,
-    instrumentation_arg: Option<Vec<String>>,
-    retry_args: RetryArgs,
-    analytics_args: AnalyticsArgs,
-    profiling_args: ProfilingArgs,
-    pull_files: Option<Vec<String>>,
-    application_bundle: Option<Vec<String>>,
-    library_bundle: Option<Vec<PathBuf>>,
-    mock_location: bool,
-) -> Result<bool> {
-    if application.is_none()
-        && test_application.is_none()
-        && application_bundle.is_none()
-        && library_bundle.is_none()
-    {
-        return Err(ConfigurationError::UnsupportedRunConfiguration {
-            message:
-                "Please set up APKs for testing. The following argument combinations are possible:
---application <APPLICATION> --test-application <TEST_APPLICATION> - for application testing
---application-bundle <APPLICATION>,<TEST_APPLICATION> - advanced mode that allows setting up one or more application bundles for testing
---library-bundle <TEST_APPLICATION> - advanced mode that allows setting up one or more library bundles for testing"
-                    .into(),
-        }
-        .into());
-    }
-
-    if application.is_some()
-        && test_application.is_none()
-        && application_bundle.is_none()
-        && library_bundle.is_none()
-    {
-        return Err(ConfigurationError::UnsupportedRunConfiguration {
-            message: "Please set up Testing APK:
---test-application <TEST_APPLICATION>"
-                .into(),
-        }
-        .into());
-    }
-
-    if application.is_none()
-        && test_application.is_some()
-        && application_bundle.is_none()
-        && library_bundle.is_none()
-    {
-        return Err(ConfigurationError::UnsupportedRunConfiguration {
-            message: "Please set up Application APK:
---application <TEST_APPLICATION>
-If you are interesting in library testing then please use advance mode with --library-bundle argument"
-                .into(),
-        }
-        .into());
-    }
-
-    if let Some(bundles) = &application_bundle {
-        if bundles.len() > 1 && mock_location {
-            return Err(ConfigurationError::UnsupportedRunConfiguration {
-                message: "Mock location access doesn't support multiple application bundles".into(),
-            }
-            .into());
-        }
-    }
-
-    if application_bundle.is_none() && application.is_none() && mock_location {
-        return Err(ConfigurationError::UnsupportedRunConfiguration {
-            message: "There is no Application where mock location can be used".into(),
-        }
-        .into());
-    }
-
-    match (device.as_deref(), &flavor, &system_image, &os_version) {
+//Combinations of device type, flavor, system image and os version that aren't supported
+fn check_device(
+    device: Option<&str>,
+    flavor: &Option<Flavor>,
+    system_image: &Option<SystemImage>,
+    os_version: &Option<OsVersion>,
+) -> Result<()> {
+    match (device, flavor, system_image, os_version) {
         (Some("watch"), _, Some(SystemImage::Default) | None, Some(_) | None)
         | (
             Some("watch"),
@@ -194,13 +146,15 @@ If you are interesting in library testing then please use advance mode with --li
         }
         (
             Some("tv") | Some("watch"),
-            Some(Flavor::JsJestAppium) | Some(Flavor::PythonRobotFrameworkAppium),
+            Some(Flavor::JsJestAppium)
+            | Some(Flavor::PythonRobotFrameworkAppium)
+            | Some(Flavor::JsDetox),
             _,
             _,
         ) => {
             return Err(ConfigurationError::UnsupportedRunConfiguration {
                 message:
-                    "js-jest-appium and python-robotframework-appium only support 'phone' devices"
+                    "js-jest-appium, python-robotframework-appium and js-detox only support 'phone' devices"
                         .into(),
             }
             .into());
@@ -213,100 +167,583 @@ If you are interesting in library testing then please use advance mode with --li
         }
         _ => {}
     }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run(
+    from_gradle: Option<PathBuf>,
+    gradle_variant: String,
+    application: Option<std::path::PathBuf>,
+    bundletool_args: BundletoolArgs,
+    additional_apk: Option<Vec<PathBuf>>,
+    test_application: Option<std::path::PathBuf>,
+    remote_artifact_args: RemoteArtifactArgs,
+    os_versions: Vec<OsVersion>,
+    system_image: Option<SystemImage>,
+    device: Option<Vec<String>>,
+    device_class: Option<DeviceClass>,
+    network_profile: Option<NetworkProfile>,
+    orientation: Option<Orientation>,
+    common: CommonRunArgs,
+    api_args: ApiArgs,
+    flavor: Option<Flavor>,
+    detox_args: DetoxArgs,
+    locale_args: LocaleArgs,
+    instrumentation_arg: Option<Vec<String>>,
+    retry_args: RetryArgs,
+    analytics_args: AnalyticsArgs,
+    profiling_args: ProfilingArgs,
+    pull_files: Option<Vec<String>>,
+    push_file: Option<Vec<String>>,
+    application_bundle: Option<Vec<String>>,
+    library_bundle: Option<Vec<PathBuf>>,
+    bundle_file: Option<PathBuf>,
+    changed_since: Option<String>,
+    module_map: Option<PathBuf>,
+    mock_location: bool,
+    use_orchestrator: bool,
+    clear_package_data: bool,
+    emulator_arg: Vec<emulator::EmulatorArg>,
+    device_ram: Option<u32>,
+    device_storage: Option<u32>,
+    grant_permission: Option<Vec<String>>,
+    grant_all_runtime_permissions: bool,
+) -> Result<bool> {
+    let (application, test_application, library_bundle) = match from_gradle {
+        Some(module) => {
+            let outputs = gradle::discover(&module, &gradle_variant)?;
+            let formatter = StandardFormatter::new(1);
+            match outputs.application {
+                Some(application) => {
+                    formatter.message(&format!("Using application {}", application.display()));
+                    formatter.message(&format!(
+                        "Using test application {}",
+                        outputs.test_application.display()
+                    ));
+                    (
+                        Some(application),
+                        Some(outputs.test_application),
+                        library_bundle,
+                    )
+                }
+                None => {
+                    formatter.message(&format!(
+                        "Using library test application {}",
+                        outputs.test_application.display()
+                    ));
+                    (None, None, Some(vec![outputs.test_application]))
+                }
+            }
+        }
+        None => (application, test_application, library_bundle),
+    };
+    let mut checks = cli::validate::Checks::new(common.validate_only);
+    let bundle_file = match &bundle_file {
+        Some(path) => checks.check(bundle::file::load(path).await)?,
+        None => None,
+    };
+    let has_bundles =
+        application_bundle.is_some() || library_bundle.is_some() || bundle_file.is_some();
+    let has_application = application.is_some() || remote_artifact_args.has_application();
+    let has_test_application =
+        test_application.is_some() || remote_artifact_args.has_test_application();
+    if !has_application && !has_test_application && !has_bundles {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message:
+                "Please set up APKs for testing. The following argument combinations are possible:
+--application <APPLICATION> --test-application <TEST_APPLICATION> - for application testing
+--application-bundle <APPLICATION>,<TEST_APPLICATION> - advanced mode that allows setting up one or more application bundles for testing
+--library-bundle <TEST_APPLICATION> - advanced mode that allows setting up one or more library bundles for testing
+--bundle-file <BUNDLE_FILE> - advanced mode that allows setting up application and library bundles in a file"
+                    .into(),
+        })?;
+    }
+
+    if has_application && !has_test_application && !has_bundles {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message: "Please set up Testing APK:
+--test-application <TEST_APPLICATION>"
+                .into(),
+        })?;
+    }
+
+    if !has_application && has_test_application && !has_bundles {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message: "Please set up Application APK:
+--application <TEST_APPLICATION>
+If you are interesting in library testing then please use advance mode with --library-bundle argument"
+                .into(),
+        })?;
+    }
+
+    let application_bundle_count = application_bundle.as_ref().map_or(0, Vec::len)
+        + bundle_file
+            .as_ref()
+            .map_or(0, |(application_bundles, _)| application_bundles.len());
+    if application_bundle_count > 1 && mock_location {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message: "Mock location access doesn't support multiple application bundles".into(),
+        })?;
+    }
+
+    if application_bundle_count == 0 && !has_application && mock_location {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message: "There is no Application where mock location can be used".into(),
+        })?;
+    }
+
+    if use_orchestrator && !matches!(flavor, None | Some(Flavor::Native)) {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message: "Android Test Orchestrator is only supported by the native flavor".into(),
+        })?;
+    }
+
+    let mut devices: Vec<String> = Vec::new();
+    for device in device.into_iter().flatten() {
+        if !devices.contains(&device) {
+            devices.push(device);
+        }
+    }
+    //Settings are checked for every device type, or once for the default device
+    let targets: Vec<Option<&str>> = if devices.is_empty() {
+        vec![None]
+    } else {
+        devices.iter().map(|x| Some(x.as_str())).collect()
+    };
+
+    //Every OS version and language is submitted as a separate run
+    let os_versions = fan_out::values(os_versions);
+    let languages = fan_out::values(locale_args.language);
+
+    if device_class == Some(DeviceClass::Real) {
+        let message = if mock_location {
+            Some("Mock location isn't supported on real devices")
+        } else if !emulator_arg.is_empty() {
+            Some("Emulator options aren't supported on real devices")
+        } else if device_ram.is_some() || device_storage.is_some() {
+            Some("RAM and storage of real devices are determined by --device")
+        } else if system_image.is_some() || os_versions.iter().any(Option::is_some) {
+            Some("System image and os version of real devices are determined by --device")
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                message: message.into(),
+            })?;
+        }
+        for device in &targets {
+            checks.check(
+                cli::validate::real_device(
+                    &api_args,
+                    &Platform::Android,
+                    *device,
+                    common.validate_only,
+                )
+                .await,
+            )?;
+        }
+    } else {
+        for device in &devices {
+            checks.check(
+                catalog::validate_device(
+                    &api_args.base_url,
+                    &api_args.api_key,
+                    device,
+                    device_ram,
+                    device_storage,
+                    common.validate_only,
+                )
+                .await,
+            )?;
+        }
+    }
+
+    for device in targets {
+        for os_version in &os_versions {
+            checks.check(check_device(device, &flavor, &system_image, os_version))?;
+        }
+    }
 
     if let Some(app_path) = application.clone() {
         if !app_path.exists() {
-            return Err(InputError::InvalidFileName { path: app_path })?;
+            checks.fail(InputError::InvalidFileName { path: app_path })?;
         }
     }
 
     if let Some(app_path) = test_application.clone() {
         if !app_path.exists() {
-            return Err(InputError::InvalidFileName { path: app_path })?;
+            checks.fail(InputError::InvalidFileName { path: app_path })?;
+        }
+    }
+
+    //The universal APK lives in the temporary directory until the run is submitted
+    if let Some(apks) = &additional_apk {
+        if !has_application {
+            checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                message: "Additional APKs are installed together with the application, please set up --application".into(),
+            })?;
+        }
+        for apk in apks {
+            if !apk.is_file() {
+                checks.fail(InputError::InvalidFileName { path: apk.clone() })?;
+            }
         }
     }
 
-    let mut transformed_application_bundle = None;
-    if let Some(application_bundle) = application_bundle {
-        transformed_application_bundle =
-            Some(bundle::transform_and_validate_bundle(application_bundle)?);
+    let mut aab_work_dir = None;
+    let (application, additional_apks) = match application {
+        Some(path) if aab::is_aab(&path) => {
+            let work_dir = aab_work_dir.insert(tempfile::tempdir()?);
+            let apk =
+                checks.check(aab::universal_apk(&path, &bundletool_args, work_dir.path()).await)?;
+            (apk, additional_apk)
+        }
+        Some(path) if aab::is_apks(&path) => {
+            let work_dir = aab_work_dir.insert(tempfile::tempdir()?);
+            let (apk, splits) = match checks.check(aab::apk_set(&path, work_dir.path()).await)? {
+                Some((apk, splits)) => (Some(apk), splits),
+                None => (None, Vec::new()),
+            };
+            let additional: Vec<PathBuf> = splits
+                .into_iter()
+                .chain(additional_apk.into_iter().flatten())
+                .collect();
+            (apk, (!additional.is_empty()).then_some(additional))
+        }
+        application => (application, additional_apk),
+    };
+
+    let run_options = RunOptions {
+        device_class: device_class.map(|x| x.to_string()),
+        devices: (devices.len() > 1).then(|| devices.clone()),
+        network_profile: network_profile.map(|x| x.to_string()),
+        orientation: orientation.map(|x| x.to_string()),
+        use_orchestrator: use_orchestrator.then_some(true),
+        clear_package_data: clear_package_data.then_some(true),
+        emulator_args: (!emulator_arg.is_empty())
+            .then(|| emulator_arg.iter().flat_map(|x| x.command_line()).collect()),
+        device_ram_mb: device_ram,
+        device_storage_mb: device_storage,
+        grant_all_permissions: grant_all_runtime_permissions.then_some(true),
+        retry_quota_overrides: match &retry_args.retry_policy {
+            Some(path) => checks.check(retries::load(path).await)?,
+            None => None,
+        },
+        record_video: common.record_video.as_ref().map(|x| x.to_string()),
+        screenshot_on_failure: common.screenshot_on_failure.then_some(true),
+        test_timeout_overrides: match &common.test_timeout_overrides {
+            Some(path) => checks.check(timeouts::load(path, None).await)?,
+            None => None,
+        },
+        country: locale_args.country,
+        ..checks
+            .check(
+                detox::run_options(
+                    &detox_args,
+                    matches!(flavor, Some(Flavor::JsDetox)),
+                    "Android",
+                )
+                .await,
+            )?
+            .unwrap_or_default()
+    };
+
+    let (file_application_bundles, file_library_bundles) = bundle_file.unwrap_or_default();
+    for flavor in file_application_bundles
+        .iter()
+        .filter_map(|x| x.flavor.as_ref())
+        .chain(
+            file_library_bundles
+                .iter()
+                .filter_map(|x| x.flavor.as_ref()),
+        )
+    {
+        if <Flavor as clap::ValueEnum>::from_str(flavor, true).is_err() {
+            checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                message: format!("Unsupported flavor {} of a bundle", flavor),
+            })?;
+        }
     }
 
+    let mut application_bundles = match application_bundle {
+        Some(application_bundle) => checks
+            .check(bundle::transform_and_validate_bundle(application_bundle))?
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    application_bundles.extend(file_application_bundles);
+
+    let library_bundle = match library_bundle {
+        Some(patterns) => {
+            let mut paths = Vec::new();
+            for pattern in patterns {
+                paths.extend(
+                    checks
+                        .check(bundle::expand_glob(&pattern))?
+                        .unwrap_or_default(),
+                );
+            }
+            Some(paths)
+        }
+        None => None,
+    };
     if let Some(lib_bundles) = library_bundle.clone() {
         for bundle in lib_bundles {
             if !bundle.exists() {
-                return Err(InputError::InvalidFileName { path: bundle })?;
+                checks.fail(InputError::InvalidFileName { path: bundle })?;
             }
         }
     }
+    let mut library_bundles: Vec<LibraryBundle> = library_bundle
+        .into_iter()
+        .flatten()
+        .map(LibraryBundle::from)
+        .collect();
+    library_bundles.extend(file_library_bundles);
+
+    let changes = match (&changed_since, &module_map) {
+        (Some(reference), Some(module_map)) => checks
+            .check(bundle::changes::changed_bundles(module_map, reference).await)?
+            .map(|selection| (reference, selection)),
+        _ => None,
+    };
+    if let Some((reference, selection)) = changes {
+        let count = application_bundles.len() + library_bundles.len();
+        bundle::changes::retain(&selection, &mut application_bundles, &mut library_bundles);
+        let formatter = StandardFormatter::new(1);
+        formatter.message(&format!(
+            "{} of {} bundles changed since {}",
+            application_bundles.len() + library_bundles.len(),
+            count,
+            reference
+        ));
+        if application_bundles.is_empty() && library_bundles.is_empty() && !has_application {
+            formatter.message("No bundles to test, skipping the run");
+            return Ok(true);
+        }
+    }
+    let transformed_application_bundle =
+        (!application_bundles.is_empty()).then_some(application_bundles);
+    let library_bundle = (!library_bundles.is_empty()).then_some(library_bundles);
 
-    let filter_file = common.filter_file.map(filtering::convert::convert);
+    let filter_file = common.filter_file.clone().map(filtering::convert::convert);
     let filtering_configuration = match filter_file {
-        Some(future) => Some(future.await?),
+        Some(future) => checks.check(future.await)?,
         None => None,
     };
+    let filtering_configuration = filtering::convert::with_allowlist_filter(
+        filtering_configuration,
+        common.test_filter_args.clone().filter(),
+    );
+    let filtering_configuration = filtering::convert::with_allowlist_filter(
+        filtering_configuration,
+        checks.check(common.sharding_args.filter())?.flatten(),
+    );
+    if let (Some(cnf), Some(test_application)) = (&filtering_configuration, &test_application) {
+        checks.check(
+            filtering::matcher::check_android(
+                &cnf.filtering_configuration,
+                test_application,
+                common.strict_filter,
+            )
+            .await,
+        )?;
+    }
 
     let retry_args = cli::validate::retry_args(retry_args);
-    cli::validate::result_file_args(&common.result_file_args)?;
+    checks.check(cli::validate::result_file_args(&common.result_file_args))?;
 
     let pull_file_config: Option<PullFileConfig> = match pull_files {
-        Some(args) => Some(parse_pull_args(args)?),
+        Some(args) => checks.check(parse_pull_args(args))?,
+        None => None,
+    };
+    let push_files: Option<Vec<PushFile>> = match push_file {
+        Some(args) => checks.check(parse_push_args(args))?,
         None => None,
     };
 
     if let Some(limit) = common.concurrency_limit {
         if limit == 0 {
-            return Err(InputError::NonPositiveValue {
+            checks.fail(InputError::NonPositiveValue {
                 arg: "--concurrency-limit".to_owned(),
             })?;
         }
     }
 
+    if common.validate_only {
+        let files = application
+            .iter()
+            .chain(test_application.iter())
+            .chain(additional_apks.iter().flatten())
+            .chain(
+                transformed_application_bundle
+                    .iter()
+                    .flatten()
+                    .flat_map(|bundle| [&bundle.app_path, &bundle.test_app_path]),
+            )
+            .chain(
+                library_bundle
+                    .iter()
+                    .flatten()
+                    .map(|bundle| &bundle.test_app_path),
+            )
+            .chain(push_files.iter().flatten().map(|file| &file.local_path))
+            .cloned();
+        return cli::validate::uploads(
+            checks,
+            files,
+            common.app_data.as_ref(),
+            common.upload_checksum,
+            common.max_upload_size,
+        )
+        .await;
+    }
+
     let present_wait: bool = match common.wait {
         None => true,
         Some(true) => true,
         Some(false) => false,
     };
 
-    TriggerTestRunInteractor {}
-        .execute(
-            &api_args.base_url,
-            &api_args.api_key,
-            common.name,
-            common.link,
-            common.branch,
-            present_wait,
-            common.isolated,
-            common.ignore_test_failures,
-            common.code_coverage,
-            retry_args.retry_quota_test_uncompleted,
-            retry_args.retry_quota_test_preventive,
-            retry_args.retry_quota_test_reactive,
-            analytics_args.analytics_read_only,
-            profiling_args.profiling,
-            mock_location,
-            filtering_configuration,
-            &common.output,
-            application,
-            test_application,
-            os_version.map(|x| x.to_string()),
-            system_image.map(|x| x.to_string()),
-            device,
-            None,
-            flavor.map(|x| x.to_string()),
-            "Android".to_owned(),
-            common.progress_args.no_progress_bars,
-            common.result_file_args.result_file,
-            instrumentation_arg,
-            None,
-            pull_file_config,
-            common.concurrency_limit,
-            None,
-            None,
-            common.project,
-            transformed_application_bundle,
-            library_bundle,
-            None,
-        )
-        .await
+    let variants: Vec<(Option<OsVersion>, Option<String>)> = os_versions
+        .iter()
+        .flat_map(|os_version| {
+            languages
+                .iter()
+                .map(move |language| (os_version.clone(), language.clone()))
+        })
+        .collect();
+    let count = variants.len();
+    let fan_out = count > 1;
+    let result_files = match (&common.result_file_args.result_file, fan_out) {
+        (Some(result_file), true) => Some(fan_out::ResultFiles::new(result_file.clone())?),
+        _ => None,
+    };
+    let runs: Vec<(String, _)> = variants
+        .into_iter()
+        .enumerate()
+        .map(|(index, (os_version, language))| {
+            //Only the values which differ between the runs tell them apart
+            let mut description = Vec::new();
+            let mut dir = PathBuf::new();
+            if let (Some(os_version), true) = (&os_version, os_versions.len() > 1) {
+                description.push(format!("Android {}", os_version));
+                dir.push(os_version.to_string());
+            }
+            if let (Some(language), true) = (&language, languages.len() > 1) {
+                description.push(language.clone());
+                dir.push(language);
+            }
+            let label = description.join(", ");
+            let common = match fan_out {
+                true => fan_out::variant(
+                    &common,
+                    &label,
+                    &dir,
+                    result_files.as_ref().map(|x| x.run(index)),
+                ),
+                false => common.clone(),
+            };
+            let base_url = api_args.base_url.clone();
+            let api_key = api_args.api_key.clone();
+            let filtering_configuration = filtering_configuration.clone();
+            let application = application.clone();
+            let test_application = test_application.clone();
+            let system_image = system_image.clone();
+            let device = devices.first().cloned();
+            let flavor = flavor.clone();
+            let instrumentation_arg = instrumentation_arg.clone();
+            let pull_file_config = pull_file_config.clone();
+            let push_files = push_files.clone();
+            let application_bundle = transformed_application_bundle.clone();
+            let library_bundle = library_bundle.clone();
+            let additional_apks = additional_apks.clone();
+            let grant_permission = grant_permission.clone();
+            let remote_artifacts = remote_artifact_args.clone().remote_artifacts();
+            let run_options = RunOptions {
+                language,
+                ..run_options.clone()
+            };
+            let retry_args = retry_args.clone();
+            let analytics_read_only = analytics_args.analytics_read_only;
+            let profiling = profiling_args.profiling;
+            let run = async move {
+                TriggerTestRunInteractor {}
+                    .execute(
+                        &base_url,
+                        &api_key,
+                        common.name,
+                        common.link,
+                        common.branch,
+                        present_wait,
+                        common.stream_logs,
+                        common.cancel_on_interrupt,
+                        common.run_timeout,
+                        common.tui,
+                        common.ndjson_progress,
+                        common.upload_retries,
+                        common.upload_cache_ttl,
+                        common.upload_checksum,
+                        common.max_upload_size,
+                        common.isolated,
+                        common.ignore_test_failures,
+                        common.code_coverage,
+                        retry_args.retry_quota_test_uncompleted,
+                        retry_args.retry_quota_test_preventive,
+                        retry_args.retry_quota_test_reactive,
+                        analytics_read_only,
+                        profiling,
+                        mock_location,
+                        filtering_configuration,
+                        &common.output,
+                        common.artifact_download_args.download_options(),
+                        application,
+                        test_application,
+                        os_version.map(|x| x.to_string()),
+                        system_image.map(|x| x.to_string()),
+                        device,
+                        None,
+                        flavor.map(|x| x.to_string()),
+                        "Android".to_owned(),
+                        common.progress_args.no_progress_bars,
+                        common.polling_args.poll_interval(),
+                        common.result_file_args.result_file,
+                        instrumentation_arg,
+                        None,
+                        pull_file_config,
+                        push_files,
+                        common.app_data,
+                        common.concurrency_limit,
+                        None,
+                        None,
+                        common.project,
+                        application_bundle,
+                        library_bundle,
+                        additional_apks,
+                        grant_permission,
+                        remote_artifacts,
+                        run_options,
+                        None,
+                        common.skip_if_passed,
+                    )
+                    .await
+            };
+            (label, run)
+        })
+        .collect();
+
+    if fan_out {
+        let results = fan_out::wait_all(runs).await;
+        if let Some(result_files) = &result_files {
+            result_files.aggregate(count).await?;
+        }
+        fan_out::outcome(results)
+    } else {
+        match runs.into_iter().next() {
+            Some((_, run)) => run.await,
+            None => Ok(true),
+        }
+    }
 }
@@ -0,0 +1,79 @@
+const PREFIX: &str = "android.permission.";
+
+//Runtime permissions of the platform, only these can be granted with pm grant
+const RUNTIME_PERMISSIONS: &[&str] = &[
+    "ACCEPT_HANDOVER",
+    "ACCESS_BACKGROUND_LOCATION",
+    "ACCESS_COARSE_LOCATION",
+    "ACCESS_FINE_LOCATION",
+    "ACCESS_MEDIA_LOCATION",
+    "ACTIVITY_RECOGNITION",
+    "ANSWER_PHONE_CALLS",
+    "BLUETOOTH_ADVERTISE",
+    "BLUETOOTH_CONNECT",
+    "BLUETOOTH_SCAN",
+    "BODY_SENSORS",
+    "BODY_SENSORS_BACKGROUND",
+    "CALL_PHONE",
+    "CAMERA",
+    "GET_ACCOUNTS",
+    "NEARBY_WIFI_DEVICES",
+    "POST_NOTIFICATIONS",
+    "READ_CALENDAR",
+    "READ_CALL_LOG",
+    "READ_CONTACTS",
+    "READ_EXTERNAL_STORAGE",
+    "READ_MEDIA_AUDIO",
+    "READ_MEDIA_IMAGES",
+    "READ_MEDIA_VIDEO",
+    "READ_MEDIA_VISUAL_USER_SELECTED",
+    "READ_PHONE_NUMBERS",
+    "READ_PHONE_STATE",
+    "READ_SMS",
+    "RECEIVE_MMS",
+    "RECEIVE_SMS",
+    "RECEIVE_WAP_PUSH",
+    "RECORD_AUDIO",
+    "SEND_SMS",
+    "USE_SIP",
+    "UWB_RANGING",
+    "WRITE_CALENDAR",
+    "WRITE_CALL_LOG",
+    "WRITE_CONTACTS",
+    "WRITE_EXTERNAL_STORAGE",
+];
+
+/// Runtime permission by its full name, e.g. `android.permission.CAMERA`, or without the
+/// `android.permission.` prefix
+pub(crate) fn parse(value: &str) -> Result<String, String> {
+    let name = value.strip_prefix(PREFIX).unwrap_or(value);
+    if RUNTIME_PERMISSIONS.contains(&name) {
+        Ok(format!("{}{}", PREFIX, name))
+    } else {
+        Err(format!(
+            "'{}' isn't a runtime permission of Android, e.g. {}CAMERA. Install-time permissions are granted on installation",
+            value, PREFIX
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("android.permission.CAMERA").unwrap(),
+            "android.permission.CAMERA"
+        );
+        assert_eq!(
+            parse("POST_NOTIFICATIONS").unwrap(),
+            "android.permission.POST_NOTIFICATIONS"
+        );
+
+        assert!(parse("camera").is_err());
+        assert!(parse("android.permission.INTERNET").is_err());
+        assert!(parse("com.example.permission.C2D_MESSAGE").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{cache::unix_seconds, hash::md5_str};
+
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize)]
+struct CachedCatalog<T> {
+    fetched_at: u64,
+    configurations: Vec<T>,
+}
+
+fn default_location(name: &str, base_url: &str) -> Option<PathBuf> {
+    crate::cache::dir().map(|dir| dir.join(file_name(name, base_url)))
+}
+
+//Catalogs of different environments are cached separately
+fn file_name(name: &str, base_url: &str) -> String {
+    let environment = md5_str(base_url.trim_end_matches('/'));
+    format!("{}-{}.json", name, &environment[..12])
+}
+
+/// Device catalog of `base_url` cached as `name` for a day. A stale cache is used when the catalog can't be
+/// fetched, None is returned if there is no cache either
+pub(crate) async fn load<T, F>(name: &str, base_url: &str, fetch: F) -> Option<Vec<T>>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    F: Future<Output = Result<Vec<T>>>,
+{
+    let location = default_location(name, base_url);
+    let now = SystemTime::now();
+    if let Some(cached) = location
+        .as_deref()
+        .and_then(|path| read_cache(path, Some((CACHE_TTL, now))))
+    {
+        return Some(cached);
+    }
+
+    match fetch.await {
+        Ok(configurations) if !configurations.is_empty() => {
+            if let Some(path) = &location {
+                if let Err(error) = write_cache(path, &configurations, now) {
+                    debug!("Failed to cache {}: {}", name, error);
+                }
+            }
+            Some(configurations)
+        }
+        result => {
+            match result {
+                Ok(_) => warn!("Fetched {} is empty", name),
+                Err(error) => warn!("Failed to fetch {}: {}", name, error),
+            }
+            location.as_deref().and_then(|path| read_cache(path, None))
+        }
+    }
+}
+
+/// Device catalog of `base_url` cached as `name` regardless of its age, nothing is fetched
+pub(crate) fn cached<T: DeserializeOwned>(name: &str, base_url: &str) -> Option<Vec<T>> {
+    default_location(name, base_url).and_then(|path| read_cache(&path, None))
+}
+
+//Entries older than the ttl are ignored when it is set
+fn read_cache<T: DeserializeOwned>(
+    path: &Path,
+    ttl: Option<(Duration, SystemTime)>,
+) -> Option<Vec<T>> {
+    let cached: CachedCatalog<T> = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    if let Some((ttl, now)) = ttl {
+        let age = unix_seconds(now).saturating_sub(cached.fetched_at);
+        if age > ttl.as_secs() {
+            return None;
+        }
+    }
+    Some(cached.configurations).filter(|x| !x.is_empty())
+}
+
+fn write_cache<T: Serialize + Clone>(
+    path: &Path,
+    configurations: &[T],
+    now: SystemTime,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let cached = CachedCatalog {
+        fetched_at: unix_seconds(now),
+        configurations: configurations.to_vec(),
+    };
+    //Write to a temporary file first so that concurrent CLI invocations never observe a partial catalog
+    let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&tmp, serde_json::to_string(&cached)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("marathon-cloud").join("catalog.json");
+        let now = SystemTime::now();
+        assert_eq!(read_cache::<String>(&path, None), None);
+
+        let configurations = vec!["pixel-7".to_owned(), "tv".to_owned()];
+        write_cache(&path, &configurations, now).unwrap();
+
+        assert_eq!(
+            read_cache(&path, Some((CACHE_TTL, now))),
+            Some(configurations.clone())
+        );
+        let later = now + CACHE_TTL + Duration::from_secs(1);
+        assert_eq!(read_cache::<String>(&path, Some((CACHE_TTL, later))), None);
+        assert_eq!(read_cache(&path, None), Some(configurations));
+    }
+
+    #[test]
+    fn test_file_name_depends_on_base_url() {
+        assert_eq!(
+            file_name("ios-catalog", "https://cloud.marathonlabs.io/api"),
+            file_name("ios-catalog", "https://cloud.marathonlabs.io/api/")
+        );
+        assert_ne!(
+            file_name("ios-catalog", "https://cloud.marathonlabs.io/api"),
+            file_name("ios-catalog", "https://staging.marathonlabs.io/api")
+        );
+        assert!(file_name("ios-catalog", "http://127.0.0.1:1/api").starts_with("ios-catalog-"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::{
+    api::RunOptions,
+    errors::{ConfigurationError, InputError},
+};
+
+use super::DetoxArgs;
+
+/// Selected configuration of a Detox configuration file
+#[derive(Debug, PartialEq)]
+pub(crate) struct DetoxConfiguration {
+    pub name: String,
+    /// Configuration file with `apps` and `devices` of the selected configuration resolved
+    pub config: Value,
+}
+
+/// Run options of a run with the js-detox flavor. The Detox arguments are rejected for other flavors
+pub(crate) async fn run_options(
+    args: &DetoxArgs,
+    is_detox: bool,
+    platform: &str,
+) -> Result<RunOptions> {
+    let path = match (&args.detox_config, is_detox) {
+        (Some(path), true) => path,
+        (None, false) => return Ok(RunOptions::default()),
+        (Some(_), false) => {
+            return Err(ConfigurationError::UnsupportedRunConfiguration {
+                message: "--detox-config is only supported by --flavor js-detox".into(),
+            }
+            .into())
+        }
+        (None, true) => {
+            return Err(ConfigurationError::UnsupportedRunConfiguration {
+                message: "Please set up the Detox configuration file:
+--detox-config <DETOX_CONFIG>"
+                    .into(),
+            }
+            .into())
+        }
+    };
+    let configuration = load(path, args.detox_configuration.as_deref(), platform).await?;
+    Ok(RunOptions {
+        detox_configuration: Some(configuration.name),
+        detox_config: Some(configuration.config),
+        ..Default::default()
+    })
+}
+
+/// Reads the Detox configuration file at `path` and selects the configuration for `platform`.
+/// JavaScript configuration files can't be evaluated, only JSON files and the `detox` section
+/// of package.json are supported
+pub(crate) async fn load(
+    path: &Path,
+    name: Option<&str>,
+    platform: &str,
+) -> Result<DetoxConfiguration> {
+    let invalid = |message: String| InputError::InvalidDetoxConfig {
+        path: path.to_owned(),
+        message,
+    };
+    if path
+        .extension()
+        .is_some_and(|x| x == "js" || x == "cjs" || x == "mjs")
+    {
+        return Err(invalid(
+            "JavaScript configuration files aren't supported, please provide the configuration as .detoxrc.json".into(),
+        )
+        .into());
+    }
+    let content = tokio::fs::read(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+    let mut config: Value =
+        serde_json::from_slice(&content).map_err(|error| invalid(error.to_string()))?;
+    if path.file_name().is_some_and(|x| x == "package.json") {
+        config = config
+            .get_mut("detox")
+            .map(Value::take)
+            .ok_or_else(|| invalid("package.json has no detox section".into()))?;
+    }
+    select(config, name, platform).map_err(|message| invalid(message).into())
+}
+
+fn select(
+    mut config: Value,
+    name: Option<&str>,
+    platform: &str,
+) -> std::result::Result<DetoxConfiguration, String> {
+    //Device types of Detox are prefixed with the platform, e.g. android.emulator or ios.simulator
+    let prefix = format!("{}.", platform.to_lowercase());
+    let configurations = config
+        .get("configurations")
+        .and_then(Value::as_object)
+        .filter(|x| !x.is_empty())
+        .ok_or("no configurations found")?;
+    let matching: Vec<&String> = configurations
+        .iter()
+        .filter(|(_, configuration)| {
+            device_type(&config, configuration).is_some_and(|x| x.starts_with(&prefix))
+        })
+        .map(|(name, _)| name)
+        .collect();
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None if matching.len() == 1 => matching[0].clone(),
+        None => {
+            return Err(format!(
+                "please select one of the {} configurations {:?} with --detox-configuration",
+                platform, matching
+            ))
+        }
+    };
+    let configuration = configurations
+        .get(&name)
+        .ok_or_else(|| format!("configuration {} is missing", name))?
+        .clone();
+
+    let device = resolve(&config, "devices", configuration.get("device"))
+        .ok_or_else(|| format!("device of configuration {} is missing", name))?;
+    match device.get("type").and_then(Value::as_str) {
+        Some(device_type) if device_type.starts_with(&prefix) => {}
+        device_type => {
+            return Err(format!(
+                "configuration {} uses device type {} which isn't supported on {}",
+                name,
+                device_type.unwrap_or("unknown"),
+                platform
+            ))
+        }
+    }
+    let apps: Vec<Value> = match (configuration.get("app"), configuration.get("apps")) {
+        (Some(app), _) => vec![app.clone()],
+        (None, Some(Value::Array(apps))) => apps.clone(),
+        _ => vec![],
+    }
+    .iter()
+    .map(|app| resolve(&config, "apps", Some(app)))
+    .collect::<Option<_>>()
+    .filter(|x: &Vec<Value>| !x.is_empty())
+    .ok_or_else(|| format!("apps of configuration {} are missing", name))?;
+
+    //Aliases are resolved so that the configuration doesn't depend on the other entries of the file
+    let mut resolved = configuration;
+    resolved["device"] = device;
+    if let Some(resolved) = resolved.as_object_mut() {
+        resolved.remove("app");
+    }
+    resolved["apps"] = Value::Array(apps);
+    config["configurations"] = Value::Object([(name.clone(), resolved)].into_iter().collect());
+    Ok(DetoxConfiguration { name, config })
+}
+
+fn device_type<'a>(config: &'a Value, configuration: &'a Value) -> Option<&'a str> {
+    match configuration.get("device")? {
+        Value::String(alias) => config.get("devices")?.get(alias)?.get("type")?.as_str(),
+        device => device.get("type")?.as_str(),
+    }
+}
+
+//Entries of a configuration are either inline or an alias of an entry in `section`
+fn resolve(config: &Value, section: &str, entry: Option<&Value>) -> Option<Value> {
+    match entry? {
+        Value::String(alias) => config.get(section)?.get(alias).cloned(),
+        entry @ Value::Object(_) => Some(entry.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixture() -> std::path::PathBuf {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        Path::new(&manifest_dir)
+            .join("fixture")
+            .join("detox")
+            .join(".detoxrc.json")
+    }
+
+    #[tokio::test]
+    async fn test_load() -> Result<()> {
+        let configuration = load(&fixture(), None, "iOS").await?;
+        assert_eq!(configuration.name, "ios.sim.release");
+        assert_eq!(
+            configuration.config["configurations"],
+            json!({
+                "ios.sim.release": {
+                    "device": {"type": "ios.simulator", "device": {"type": "iPhone 15"}},
+                    "apps": [{"type": "ios.app", "binaryPath": "ios/build/Build/Products/Release-iphonesimulator/sample.app"}]
+                }
+            })
+        );
+        assert_eq!(configuration.config["testRunner"]["args"]["$0"], "jest");
+
+        let Err(error) = load(&fixture(), None, "Android").await else {
+            panic!("configuration should be required");
+        };
+        assert!(error.to_string().contains("android.emu.release"));
+
+        let configuration = load(&fixture(), Some("android.att.release"), "Android").await?;
+        assert_eq!(
+            configuration.config["configurations"]["android.att.release"]["apps"]
+                .as_array()
+                .map(|x| x.len()),
+            Some(2)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_configuration_of_other_platform() {
+        let Err(error) = load(&fixture(), Some("ios.sim.release"), "Android").await else {
+            panic!("configuration of iOS should be rejected");
+        };
+        assert!(error.to_string().contains("ios.simulator"));
+    }
+
+    #[test]
+    fn test_select_requires_apps() {
+        let config = json!({
+            "configurations": {
+                "android": {"device": {"type": "android.emulator"}}
+            }
+        });
+        assert_eq!(
+            select(config, None, "Android").unwrap_err(),
+            "apps of configuration android are missing"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use console::style;
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+use crate::{
+    api::{RapiClient, RapiReqwestClient},
+    errors::{ApiError, ConfigurationError},
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+//Archives of iOS applications are generated in the temporary directory
+const MIN_FREE_SPACE_KB: u64 = 1024 * 1024;
+
+enum Outcome {
+    Passed(String),
+    Failed {
+        message: String,
+        remediation: String,
+    },
+    Skipped(String),
+}
+
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.outcome {
+            Outcome::Passed(message) => {
+                write!(f, "{} {}: {}", style("OK  ").green(), self.name, message)
+            }
+            Outcome::Skipped(message) => {
+                write!(f, "{} {}: {}", style("SKIP").yellow(), self.name, message)
+            }
+            Outcome::Failed {
+                message,
+                remediation,
+            } => write!(
+                f,
+                "{} {}: {}\n     {}",
+                style("FAIL").red(),
+                self.name,
+                message,
+                remediation
+            ),
+        }
+    }
+}
+
+fn failed(message: impl Into<String>, remediation: impl Into<String>) -> Outcome {
+    Outcome::Failed {
+        message: message.into(),
+        remediation: remediation.into(),
+    }
+}
+
+/// Checks the environment of the CLI and prints a remediation for every failed check
+pub(crate) async fn run(base_url: &str, api_key: Option<&str>) -> Result<bool> {
+    let mut checks = Vec::new();
+
+    let date = match connectivity(base_url).await {
+        Ok(date) => {
+            let message = match proxy(base_url) {
+                Some(proxy) => format!("{} is reachable via proxy {}", base_url, proxy),
+                None => format!("{} is reachable", base_url),
+            };
+            checks.push(Check {
+                name: "Connectivity",
+                outcome: Outcome::Passed(message),
+            });
+            Some(date)
+        }
+        Err(error) => {
+            checks.push(Check {
+                name: "Connectivity",
+                outcome: failed(
+                    format!("{} isn't reachable: {}", base_url, error),
+                    "Check the network connection, the proxy settings (HTTPS_PROXY, NO_PROXY) and --base-url",
+                ),
+            });
+            None
+        }
+    };
+
+    checks.push(Check {
+        name: "API key",
+        outcome: match (api_key.filter(|x| !x.is_empty()), &date) {
+            (None, _) => failed("no API key", "Pass --api-key or set MARATHON_CLOUD_API_KEY"),
+            (Some(_), None) => Outcome::Skipped("API isn't reachable".to_owned()),
+            (Some(api_key), Some(_)) => authentication(base_url, api_key).await,
+        },
+    });
+
+    checks.push(Check {
+        name: "Clock",
+        outcome: match date.flatten() {
+            Some(date) => clock(&date, OffsetDateTime::now_utc()),
+            None => Outcome::Skipped("time of the API is unknown".to_owned()),
+        },
+    });
+
+    let temp_dir = std::env::temp_dir();
+    checks.push(Check {
+        name: "Disk space",
+        outcome: disk_space(&temp_dir).await,
+    });
+
+    let mut dirs = vec![("temporary", temp_dir)];
+    if let Some(cache_dir) = crate::cache::dir() {
+        dirs.push(("cache", cache_dir));
+    }
+    if let Ok(current_dir) = std::env::current_dir() {
+        dirs.push(("current", current_dir));
+    }
+    for (kind, dir) in dirs {
+        checks.push(Check {
+            name: "Write permissions",
+            outcome: writable(kind, &dir),
+        });
+    }
+
+    for check in &checks {
+        println!("{}", check);
+    }
+    let failures = checks
+        .iter()
+        .filter(|x| matches!(x.outcome, Outcome::Failed { .. }))
+        .count();
+    if failures > 0 {
+        return Err(ConfigurationError::EnvironmentChecksFailed {
+            failed: failures,
+            total: checks.len(),
+        }
+        .into());
+    }
+    Ok(true)
+}
+
+//Any response is fine, the Date header is used to check the clock
+async fn connectivity(base_url: &str) -> Result<Option<String>> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(CONNECT_TIMEOUT * 3)
+        .build()?;
+    let response = client.get(base_url).send().await?;
+    Ok(response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|x| x.to_str().ok())
+        .map(str::to_owned))
+}
+
+//Proxies are configured with environment variables, credentials aren't printed
+fn proxy(base_url: &str) -> Option<String> {
+    let scheme = url::Url::parse(base_url).ok()?.scheme().to_owned();
+    let names = [
+        format!("{}_proxy", scheme),
+        format!("{}_PROXY", scheme.to_uppercase()),
+        "all_proxy".to_owned(),
+        "ALL_PROXY".to_owned(),
+    ];
+    let proxy = names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|x| !x.is_empty()))?;
+    match url::Url::parse(&proxy) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            Some(url.to_string())
+        }
+        Err(_) => Some(proxy),
+    }
+}
+
+async fn authentication(base_url: &str, api_key: &str) -> Outcome {
+    match RapiReqwestClient::new(base_url, api_key).get_token().await {
+        Ok(_) => Outcome::Passed("API key is valid".to_owned()),
+        Err(error) => match error.downcast_ref::<ApiError>() {
+            Some(ApiError::InvalidAuthenticationToken { .. }) => failed(
+                "API key was rejected",
+                "Double check the API key, a new one can be created in the settings of Marathon Cloud",
+            ),
+            _ => failed(
+                format!("API key couldn't be checked: {}", error),
+                "Retry later, the API may be unavailable",
+            ),
+        },
+    }
+}
+
+fn clock(date: &str, now: OffsetDateTime) -> Outcome {
+    let Ok(server) = OffsetDateTime::parse(date, &Rfc2822) else {
+        return Outcome::Skipped(format!("unsupported Date header {}", date));
+    };
+    let skew = (now - server).unsigned_abs();
+    if skew > MAX_CLOCK_SKEW {
+        failed(
+            format!("local clock is off by {}s", skew.as_secs()),
+            "Synchronize the system clock, uploads are rejected when presigned urls look expired",
+        )
+    } else {
+        Outcome::Passed(format!("local clock is off by {}s", skew.as_secs()))
+    }
+}
+
+//df reports the available space of the file system in kilobytes, it isn't available on Windows
+async fn disk_space(dir: &Path) -> Outcome {
+    let output = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .output()
+        .await;
+    let available = output
+        .ok()
+        .filter(|x| x.status.success())
+        .and_then(|x| available_kb(&String::from_utf8_lossy(&x.stdout)));
+    match available {
+        Some(kb) if kb < MIN_FREE_SPACE_KB => failed(
+            format!("{}M available in {}", kb / 1024, dir.display()),
+            "Free up disk space, or use --work-dir or TMPDIR to generate archives on a larger volume",
+        ),
+        Some(kb) => Outcome::Passed(format!("{}M available in {}", kb / 1024, dir.display())),
+        None => Outcome::Skipped(format!("free space of {} is unknown", dir.display())),
+    }
+}
+
+fn available_kb(df: &str) -> Option<u64> {
+    df.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()
+}
+
+fn writable(kind: &str, dir: &PathBuf) -> Outcome {
+    let result = std::fs::create_dir_all(dir).and_then(|_| tempfile::tempfile_in(dir));
+    match result {
+        Ok(_) => Outcome::Passed(format!("{} directory {} is writable", kind, dir.display())),
+        Err(error) => failed(
+            format!(
+                "{} directory {} isn't writable: {}",
+                kind,
+                dir.display(),
+                error
+            ),
+            "Fix the permissions of the directory or run from another one",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        let now = OffsetDateTime::parse("Thu, 15 Oct 2026 10:00:00 +0000", &Rfc2822).unwrap();
+        assert!(matches!(
+            clock("Thu, 15 Oct 2026 10:01:00 GMT", now),
+            Outcome::Passed(_)
+        ));
+        assert!(matches!(
+            clock("Thu, 15 Oct 2026 09:00:00 GMT", now),
+            Outcome::Failed { .. }
+        ));
+        assert!(matches!(clock("yesterday", now), Outcome::Skipped(_)));
+    }
+
+    #[test]
+    fn test_available_kb() {
+        let df = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/sda1        102400000  51200000  51200000      50% /\n";
+        assert_eq!(available_kb(df), Some(51200000));
+        assert_eq!(available_kb(""), None);
+    }
+}
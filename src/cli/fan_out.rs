@@ -0,0 +1,237 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use futures::{stream::FuturesUnordered, StreamExt};
+use log::debug;
+use tempfile::TempDir;
+
+use crate::{
+    formatter::{Formatter, StandardFormatter},
+    interactor::write_result_file,
+    progress::{TestRunFinished, TestRunsFinished},
+};
+
+use super::CommonRunArgs;
+
+/// Result files of the runs of a fan-out, which are aggregated into a single result file
+/// once all runs are finished
+pub(crate) struct ResultFiles {
+    dir: TempDir,
+    result_file: PathBuf,
+}
+
+impl ResultFiles {
+    pub(crate) fn new(result_file: PathBuf) -> Result<Self> {
+        Ok(ResultFiles {
+            dir: tempfile::tempdir()?,
+            result_file,
+        })
+    }
+
+    /// Result file of the run at `index`
+    pub(crate) fn run(&self, index: usize) -> PathBuf {
+        self.dir.path().join(format!("{}.json", index))
+    }
+
+    /// Writes the results of the first `count` runs. Runs which haven't finished, e.g. because
+    /// of an error or because they weren't waited for, are left out
+    pub(crate) async fn aggregate(&self, count: usize) -> Result<()> {
+        let mut runs = Vec::new();
+        for index in 0..count {
+            let Ok(data) = tokio::fs::read(self.run(index)).await else {
+                continue;
+            };
+            match serde_json::from_slice::<TestRunFinished>(&data) {
+                Ok(run) => runs.push(run),
+                Err(error) => debug!("Run {} has no result: {}", index, error),
+            }
+        }
+        write_result_file(&self.result_file, TestRunsFinished::from(runs)).await
+    }
+}
+
+/// Arguments of one of several runs submitted at once. `description`, e.g. "Android 13, de",
+/// tells the run apart by name in the aggregated result file and outputs go to `dir`
+pub(crate) fn variant(
+    common: &CommonRunArgs,
+    description: &str,
+    dir: &Path,
+    result_file: Option<PathBuf>,
+) -> CommonRunArgs {
+    let mut common = common.clone();
+    common.name = Some(match common.name {
+        Some(name) => format!("{} ({})", name, description),
+        None => description.to_owned(),
+    });
+    common.output = common.output.map(|x| x.join(dir));
+    common.result_file_args.result_file = result_file;
+    //Progress of concurrent runs would be interleaved
+    common.tui = false;
+    common.progress_args.no_progress_bars = true;
+    common
+}
+
+/// Distinct values in the order of the command line, a single `None` if there are none
+pub(crate) fn values<T: PartialEq>(values: Vec<T>) -> Vec<Option<T>> {
+    let mut distinct: Vec<Option<T>> = Vec::new();
+    for value in values.into_iter().map(Some) {
+        if !distinct.contains(&value) {
+            distinct.push(value);
+        }
+    }
+    if distinct.is_empty() {
+        distinct.push(None);
+    }
+    distinct
+}
+
+/// Waits for all `runs` concurrently, reports every run as soon as it is finished and prints
+/// a summary at the end. Results are labeled and in the order of `runs`
+pub(crate) async fn wait_all<F>(runs: Vec<(String, F)>) -> Vec<(String, Result<bool>)>
+where
+    F: Future<Output = Result<bool>>,
+{
+    let total = runs.len();
+    let (labels, runs): (Vec<String>, Vec<F>) = runs.into_iter().unzip();
+    let mut pending: FuturesUnordered<_> = runs
+        .into_iter()
+        .enumerate()
+        .map(|(index, run)| async move { (index, run.await) })
+        .collect();
+
+    let mut formatter = StandardFormatter::new(total as u32);
+    let mut results: Vec<Option<Result<bool>>> = (0..total).map(|_| None).collect();
+    while let Some((index, result)) = pending.next().await {
+        formatter.stage(&format!("{} {}", labels[index], describe(&result)));
+        results[index] = Some(result);
+    }
+    let results: Vec<(String, Result<bool>)> = labels
+        .into_iter()
+        .zip(results.into_iter().flatten())
+        .collect();
+
+    formatter.message("Summary:");
+    for (label, result) in &results {
+        formatter.message(&format!("  {}: {}", label, describe(result)));
+    }
+    results
+}
+
+/// Aggregated exit status of runs: an error listing every failed run, otherwise whether all
+/// passed. The exit code follows the error of the first failed run
+pub(crate) fn outcome(results: Vec<(String, Result<bool>)>) -> Result<bool> {
+    let mut passed = true;
+    let mut errors = Vec::new();
+    for (label, result) in results {
+        match result {
+            Ok(result) => passed &= result,
+            Err(error) => errors.push((label, error)),
+        }
+    }
+    if errors.len() > 1 {
+        let failures: Vec<String> = errors
+            .iter()
+            .map(|(label, error)| format!("  {}: {}", label, error))
+            .collect();
+        let message = format!("{} runs failed:\n{}", errors.len(), failures.join("\n"));
+        return Err(errors.swap_remove(0).1.context(message));
+    }
+    match errors.pop() {
+        Some((_, error)) => Err(error),
+        None => Ok(passed),
+    }
+}
+
+pub(crate) fn describe(result: &Result<bool>) -> String {
+    match result {
+        Ok(true) => "passed".to_owned(),
+        Ok(false) => "failed".to_owned(),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_result_files_are_aggregated() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let result_files = ResultFiles::new(dir.path().join("result.json"))?;
+        let finished = r#"{"id":"run-1","name":"Android 13","report":"https://cloud.marathonlabs.io/report/run-1","state":"passed","passed":1,"failed":0,"ignored":0,"billable_time":1.5}"#;
+        tokio::fs::write(result_files.run(0), finished).await?;
+        tokio::fs::write(result_files.run(1), r#"{"id":"run-2"}"#).await?;
+        result_files.aggregate(3).await?;
+
+        let result: serde_json::Value =
+            serde_json::from_slice(&tokio::fs::read(dir.path().join("result.json")).await?)?;
+        assert_eq!(result["runs"][0]["name"], "Android 13");
+        assert_eq!(result["runs"].as_array().map(Vec::len), Some(1));
+        assert_eq!(result["totals"]["runs"], 1);
+        assert_eq!(result["totals"]["state"], "passed");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_all() {
+        let run = |result: Result<bool>| async move { result };
+        let results = wait_all(vec![
+            ("a".to_owned(), run(Ok(false))),
+            ("b".to_owned(), run(Err(anyhow::anyhow!("b failed")))),
+            ("c".to_owned(), run(Ok(true))),
+        ])
+        .await;
+        assert_eq!(
+            results
+                .iter()
+                .map(|(label, result)| format!("{}: {}", label, describe(result)))
+                .collect::<Vec<_>>(),
+            vec!["a: failed", "b: b failed", "c: passed"]
+        );
+    }
+
+    #[test]
+    fn test_outcome() {
+        let labeled = |results: Vec<Result<bool>>| {
+            ["a", "b", "c"]
+                .into_iter()
+                .map(str::to_owned)
+                .zip(results)
+                .collect::<Vec<_>>()
+        };
+        assert!(outcome(labeled(vec![Ok(true), Ok(true)])).unwrap());
+        assert!(!outcome(labeled(vec![Ok(false), Ok(true)])).unwrap());
+        let error =
+            outcome(labeled(vec![Ok(false), Err(anyhow::anyhow!("b failed"))])).unwrap_err();
+        assert_eq!(error.to_string(), "b failed");
+
+        let error = outcome(labeled(vec![
+            Ok(false),
+            Err(
+                crate::errors::ConfigurationError::UnsupportedRunConfiguration {
+                    message: "b".into(),
+                }
+                .into(),
+            ),
+            Err(anyhow::anyhow!("c failed")),
+        ]))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "2 runs failed:\n  b: Unsupported run configuration: b\n  c: c failed"
+        );
+        assert_eq!(
+            crate::errors::ExitCode::from_error(&error),
+            crate::errors::ExitCode::Configuration
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(values(vec!["de", "fr", "de"]), vec![Some("de"), Some("fr")]);
+        assert_eq!(values(Vec::<&str>::new()), vec![None]);
+    }
+}
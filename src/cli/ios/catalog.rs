@@ -0,0 +1,177 @@
+use anyhow::Result;
+
+use crate::{
+    api::{IosConfiguration, RapiClient, RapiReqwestClient},
+    cli::catalog,
+};
+
+//Used when the catalog can't be fetched and wasn't cached before. The first entry is the default
+const BUILTIN: &[(&str, &str, &str, &str, &str)] = &[
+    ("iPhone-15", "iPhone-15", "15.4", "iOS", "17.5"),
+    ("iPhone-15-Pro", "iPhone-15-Pro", "15.4", "iOS", "17.5"),
+    (
+        "iPhone-15-Pro-Max",
+        "iPhone-15-Pro-Max",
+        "15.4",
+        "iOS",
+        "17.5",
+    ),
+    ("iPhone-11", "iPhone-11", "15.4", "iOS", "17.5"),
+    ("iPhone-16", "iPhone-16", "16.2", "iOS", "18.2"),
+    ("iPhone-16-Pro", "iPhone-16-Pro", "16.2", "iOS", "18.2"),
+    (
+        "iPhone-16-Pro-Max",
+        "iPhone-16-Pro-Max",
+        "16.2",
+        "iOS",
+        "18.2",
+    ),
+    ("iPhone-11", "iPhone-11", "16.2", "iOS", "18.2"),
+    (
+        "iPad-10th-generation",
+        "iPad-10th-generation",
+        "15.4",
+        "iOS",
+        "17.5",
+    ),
+    (
+        "iPad-Air-5th-generation",
+        "iPad-Air-5th-generation",
+        "15.4",
+        "iOS",
+        "17.5",
+    ),
+    (
+        "iPad-Pro-11-inch-4th-generation",
+        "iPad-Pro-11-inch-4th-generation-8GB",
+        "15.4",
+        "iOS",
+        "17.5",
+    ),
+    (
+        "iPad-10th-generation",
+        "iPad-10th-generation",
+        "16.2",
+        "iOS",
+        "18.2",
+    ),
+    (
+        "iPad-Air-11-inch-M2",
+        "iPad-Air-11-inch-M2",
+        "16.2",
+        "iOS",
+        "18.2",
+    ),
+    (
+        "iPad-Pro-11-inch-M4",
+        "iPad-Pro-11-inch-M4-8GB",
+        "16.2",
+        "iOS",
+        "18.2",
+    ),
+    (
+        "iPad-Pro-13-inch-M4",
+        "iPad-Pro-13-inch-M4-8GB",
+        "16.2",
+        "iOS",
+        "18.2",
+    ),
+    (
+        "Apple-TV-4K-3rd-generation",
+        "Apple-TV-4K-3rd-generation-4K",
+        "15.4",
+        "tvOS",
+        "17.5",
+    ),
+    (
+        "Apple-TV-4K-3rd-generation",
+        "Apple-TV-4K-3rd-generation-4K",
+        "16.2",
+        "tvOS",
+        "18.2",
+    ),
+    (
+        "Apple-Watch-Series-9-45mm",
+        "Apple-Watch-Series-9-45mm",
+        "15.4",
+        "watchOS",
+        "10.5",
+    ),
+    (
+        "Apple-Watch-Ultra-2-49mm",
+        "Apple-Watch-Ultra-2-49mm",
+        "15.4",
+        "watchOS",
+        "10.5",
+    ),
+    (
+        "Apple-Watch-Series-10-46mm",
+        "Apple-Watch-Series-10-46mm",
+        "16.2",
+        "watchOS",
+        "11.2",
+    ),
+    (
+        "Apple-Watch-Ultra-2-49mm",
+        "Apple-Watch-Ultra-2-49mm",
+        "16.2",
+        "watchOS",
+        "11.2",
+    ),
+];
+
+pub(crate) fn builtin() -> Vec<IosConfiguration> {
+    BUILTIN
+        .iter()
+        .map(
+            |(device, device_type, xcode_version, os, os_version)| IosConfiguration {
+                device: device.to_string(),
+                device_type: format!("com.apple.CoreSimulator.SimDeviceType.{}", device_type),
+                xcode_version: xcode_version.to_string(),
+                os_version: os_version.to_string(),
+                runtime: format!(
+                    "com.apple.CoreSimulator.SimRuntime.{}-{}",
+                    os,
+                    os_version.replace('.', "-")
+                ),
+            },
+        )
+        .collect()
+}
+
+/// Supported iOS configurations. The catalog of the API is cached for a day, a stale
+/// cache or the catalog shipped with the CLI are used when it can't be fetched. Nothing is
+/// fetched `offline`
+pub(crate) async fn load(base_url: &str, api_key: &str, offline: bool) -> Vec<IosConfiguration> {
+    let catalog = match offline {
+        true => catalog::cached("ios-catalog", base_url),
+        false => catalog::load("ios-catalog", base_url, fetch(base_url, api_key)).await,
+    };
+    catalog.unwrap_or_else(builtin)
+}
+
+async fn fetch(base_url: &str, api_key: &str) -> Result<Vec<IosConfiguration>> {
+    let client = RapiReqwestClient::new(base_url, api_key);
+    let token = client.get_token().await?;
+    client.get_devices_ios(&token).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin() {
+        let catalog = builtin();
+        assert_eq!(
+            catalog[0],
+            IosConfiguration {
+                device: "iPhone-15".to_owned(),
+                device_type: "com.apple.CoreSimulator.SimDeviceType.iPhone-15".to_owned(),
+                xcode_version: "15.4".to_owned(),
+                os_version: "17.5".to_owned(),
+                runtime: "com.apple.CoreSimulator.SimRuntime.iOS-17-5".to_owned(),
+            }
+        );
+    }
+}
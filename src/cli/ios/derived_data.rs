@@ -0,0 +1,196 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use walkdir::WalkDir;
+
+use crate::errors::InputError;
+
+pub(crate) const DEFAULT_DERIVED_DATA: &str = "~/Library/Developer/Xcode/DerivedData";
+
+/// Products of the most recent build found in DerivedData
+#[derive(Debug, PartialEq)]
+pub(crate) enum Products {
+    /// xctestrun file of `xcodebuild build-for-testing`
+    Xctestrun(PathBuf),
+    /// Application and UI test runner of `xcodebuild build`
+    Bundles {
+        application: PathBuf,
+        test_application: PathBuf,
+    },
+}
+
+/// Finds the products of `scheme` built with `configuration` for `sdk`, e.g. iphonesimulator,
+/// in the DerivedData folder at `derived_data`. An xctestrun file is preferred, otherwise
+/// the UI test runner and the application are used. The most recent build wins
+pub(crate) fn discover(
+    derived_data: &Path,
+    scheme: Option<&str>,
+    configuration: &str,
+    sdk: &str,
+) -> Result<Products> {
+    let not_found = |message: String| InputError::DerivedDataProductsNotFound {
+        path: derived_data.to_owned(),
+        message,
+    };
+    if !derived_data.is_dir() {
+        return Err(not_found("DerivedData folder doesn't exist".into()).into());
+    }
+    let products_dirs: Vec<PathBuf> = std::fs::read_dir(derived_data)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("Build").join("Products"))
+        .filter(|path| path.is_dir())
+        .collect();
+
+    //Paths in the xctestrun file contain the products folder of the configuration
+    let configuration_dir = format!("{}-{}", configuration, sdk);
+    let xctestrun = products_dirs
+        .iter()
+        .flat_map(|dir| files(dir, 1))
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            name.ends_with(".xctestrun")
+                && name.contains(&format!("_{}", sdk))
+                && scheme.map_or(true, |scheme| name.starts_with(&format!("{}_", scheme)))
+        })
+        .filter(|path| {
+            std::fs::read_to_string(path)
+                .is_ok_and(|content| content.contains(&format!("/{}/", configuration_dir)))
+        })
+        .max_by_key(|path| modified(path));
+    if let Some(xctestrun) = xctestrun {
+        return Ok(Products::Xctestrun(xctestrun));
+    }
+
+    let mut candidates = Vec::new();
+    for dir in &products_dirs {
+        let dir = dir.join(&configuration_dir);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let bundles: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|x| x == "app"))
+            .collect();
+        let (runners, applications): (Vec<PathBuf>, Vec<PathBuf>) = bundles
+            .into_iter()
+            .partition(|path| path.to_string_lossy().ends_with("-Runner.app"));
+        let runner = match scheme {
+            Some(scheme) => runners.into_iter().find(|path| {
+                path.file_name()
+                    .is_some_and(|x| x.to_string_lossy().starts_with(scheme))
+            }),
+            None if runners.len() == 1 => runners.into_iter().next(),
+            None => None,
+        };
+        if let (Some(runner), [application]) = (runner, applications.as_slice()) {
+            candidates.push((runner, application.clone()));
+        }
+    }
+    candidates
+        .into_iter()
+        .max_by_key(|(runner, _)| modified(runner))
+        .map(|(test_application, application)| Products::Bundles {
+            application,
+            test_application,
+        })
+        .ok_or_else(|| {
+            not_found(format!(
+                "no xctestrun file or UI test runner of {} {} found, please build the scheme for testing first",
+                scheme.unwrap_or("any scheme"),
+                configuration_dir
+            ))
+            .into()
+        })
+}
+
+fn files(dir: &Path, depth: usize) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .max_depth(depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn modified(path: &Path) -> SystemTime {
+    std::fs::metadata(path)
+        .and_then(|x| x.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn products(derived_data: &Path, project: &str) -> PathBuf {
+        let dir = derived_data.join(project).join("Build/Products");
+        std::fs::create_dir_all(dir.join("Debug-iphonesimulator/sample.app")).unwrap();
+        std::fs::create_dir_all(dir.join("Debug-iphonesimulator/sampleUITests-Runner.app"))
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_discover_xctestrun() -> Result<()> {
+        let derived_data = tempfile::tempdir()?;
+        let dir = products(derived_data.path(), "sample-abc");
+        let xctestrun = dir.join("sample_iphonesimulator17.5-arm64.xctestrun");
+        std::fs::write(
+            &xctestrun,
+            "<string>__TESTROOT__/Debug-iphonesimulator/sample.app</string>",
+        )?;
+        std::fs::write(
+            dir.join("other_iphonesimulator17.5-arm64.xctestrun"),
+            "<string>__TESTROOT__/Debug-iphonesimulator/other.app</string>",
+        )?;
+
+        assert_eq!(
+            discover(
+                derived_data.path(),
+                Some("sample"),
+                "Debug",
+                "iphonesimulator"
+            )?,
+            Products::Xctestrun(xctestrun)
+        );
+        //Release builds of the scheme aren't there, neither are the runners
+        assert!(discover(
+            derived_data.path(),
+            Some("sample"),
+            "Release",
+            "iphonesimulator"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_bundles() -> Result<()> {
+        let derived_data = tempfile::tempdir()?;
+        let dir = products(derived_data.path(), "sample-abc");
+        assert_eq!(
+            discover(derived_data.path(), None, "Debug", "iphonesimulator")?,
+            Products::Bundles {
+                application: dir.join("Debug-iphonesimulator/sample.app"),
+                test_application: dir.join("Debug-iphonesimulator/sampleUITests-Runner.app"),
+            }
+        );
+        assert!(discover(
+            derived_data.path(),
+            Some("other"),
+            "Debug",
+            "iphonesimulator"
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -1,219 +1,298 @@
-use std::ffi::OsStr;
-use std::fmt::Display;
+use std::{ffi::OsStr, fmt::Display};
 
 use anyhow::Result;
-use std::collections::HashSet;
+use indicatif::MultiProgress;
+use log::debug;
+use std::{collections::HashSet, sync::Arc};
 use tokio::fs::File;
-use walkdir::WalkDir;
 
 use crate::{
-    cli::{self},
-    compression,
+    api::{IosConfiguration, RunOptions},
+    cli::{
+        self, detox,
+        model::{DeviceClass, Location, NetworkProfile, Orientation, OutputFormat, Platform},
+    },
+    compression::{self, CompressionLevel, ZipCache, ZipOptions},
     errors::ConfigurationError,
+    formatter::{Formatter, StandardFormatter},
     interactor::TriggerTestRunInteractor,
+    plist, retries, timeouts,
 };
 use crate::{errors::InputError, filtering};
 
-#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
-pub enum IosDevice {
-    #[clap(name = "iPhone-11")]
-    IPhone11,
-    #[clap(name = "iPhone-15")]
-    IPhone15,
-    #[clap(name = "iPhone-15-Pro")]
-    IPhone15Pro,
-    #[clap(name = "iPhone-15-Pro-Max")]
-    IPhone15ProMax,
-    #[clap(name = "iPhone-16")]
-    IPhone16,
-    #[clap(name = "iPhone-16-Pro")]
-    IPhone16Pro,
-    #[clap(name = "iPhone-16-Pro-Max")]
-    IPhone16ProMax,
-}
+mod catalog;
+mod derived_data;
+mod xctestrun;
 
-impl Display for IosDevice {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            IosDevice::IPhone11 => f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-11"),
-            IosDevice::IPhone15 => f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-15"),
-            IosDevice::IPhone15Pro => {
-                f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-15-Pro")
-            }
-            IosDevice::IPhone15ProMax => {
-                f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-15-Pro-Max")
-            }
-            IosDevice::IPhone16 => f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-16"),
-            IosDevice::IPhone16Pro => {
-                f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-16-Pro")
-            }
-            IosDevice::IPhone16ProMax => {
-                f.write_str("com.apple.CoreSimulator.SimDeviceType.iPhone-16-Pro-Max")
-            }
-        }
-    }
-}
+pub(crate) use derived_data::DEFAULT_DERIVED_DATA;
+
+//Used unless set by --test-timeout-default or the xctestplan
+const DEFAULT_TEST_TIMEOUT_SECONDS: u32 = 300;
 
-#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
-pub enum OsVersion {
-    #[clap(name = "17.5")]
-    Ios17_5,
-    #[clap(name = "18.2")]
-    Ios18_2,
+#[derive(Debug, clap::ValueEnum, Clone)]
+pub enum Flavor {
+    #[clap(name = "native")]
+    Native,
+    #[clap(name = "js-detox")]
+    JsDetox,
 }
 
-impl Display for OsVersion {
+impl Display for Flavor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            OsVersion::Ios17_5 => f.write_str("com.apple.CoreSimulator.SimRuntime.iOS-17-5"),
-            OsVersion::Ios18_2 => f.write_str("com.apple.CoreSimulator.SimRuntime.iOS-18-2"),
+            Flavor::Native => f.write_str("native"),
+            Flavor::JsDetox => f.write_str("js-detox"),
         }
     }
 }
 
-#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
-pub enum XcodeVersion {
-    #[clap(name = "15.4")]
-    Xcode15_4,
-    #[clap(name = "16.2")]
-    Xcode16_2,
+/// .app and .xctest folders which are zipped before the upload
+pub(crate) fn is_bundle_dir(path: &std::path::Path) -> bool {
+    let supported_extensions_dir = ["app", "xctest"];
+    path.is_dir()
+        && path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| supported_extensions_dir.contains(&ext))
+}
+
+fn is_xcarchive(path: &std::path::Path) -> bool {
+    path.is_dir() && path.extension().and_then(OsStr::to_str) == Some("xcarchive")
 }
 
-impl Display for XcodeVersion {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            XcodeVersion::Xcode15_4 => f.write_str("15.4"),
-            XcodeVersion::Xcode16_2 => f.write_str("16.2"),
-        }
+/// Application embedded in an .xcarchive, as referenced by ApplicationPath in its Info.plist
+async fn xcarchive_application(path: &std::path::Path) -> Result<std::path::PathBuf> {
+    let invalid = |message: String| InputError::InvalidXcarchive {
+        path: path.to_owned(),
+        message,
+    };
+    let products = path.join("Products");
+    let info = tokio::fs::read(path.join("Info.plist"))
+        .await
+        .map_err(|error| invalid(format!("can't read Info.plist: {}", error)))?;
+    let info = plist::parse(&info).map_err(|error| invalid(error.to_string()))?;
+    let application = match info
+        .get("ApplicationProperties")
+        .and_then(|x| x.get("ApplicationPath"))
+        .and_then(plist::Value::as_str)
+    {
+        Some(application_path) => products.join(application_path),
+        //Archives of frameworks or with custom settings don't reference an application
+        None => std::fs::read_dir(products.join("Applications"))
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|x| x.path()))
+            .find(|x| x.extension().and_then(OsStr::to_str) == Some("app"))
+            .ok_or_else(|| invalid("no application found in Products/Applications".into()))?,
+    };
+    if !is_bundle_dir(&application) {
+        return Err(invalid(format!("{} is not an application", application.display())).into());
     }
+    Ok(application)
 }
 
-pub(crate) async fn ensure_format(path: std::path::PathBuf) -> Result<std::path::PathBuf> {
-    let supported_extensions_file = vec!["zip", "ipa"];
-    let supported_extensions_dir = vec!["app", "xctest"];
-    if path.is_file()
+/// Application of an archive, or `path` itself if it's an ipa or zip file or a bundle folder
+async fn check_format(path: std::path::PathBuf) -> Result<std::path::PathBuf> {
+    let path = if is_xcarchive(&path) {
+        let application = xcarchive_application(&path).await?;
+        debug!("Using {} of {}", application.display(), path.display());
+        application
+    } else {
+        path
+    };
+    let supported_extensions_file = ["zip", "ipa"];
+    let supported_file = path.is_file()
         && path
             .extension()
             .and_then(OsStr::to_str)
-            .is_some_and(|ext| supported_extensions_file.contains(&ext))
-    {
+            .is_some_and(|ext| supported_extensions_file.contains(&ext));
+    if supported_file || is_bundle_dir(&path) {
         Ok(path)
-    } else if path.is_dir()
-        && path
-            .extension()
-            .and_then(OsStr::to_str)
-            .is_some_and(|ext| supported_extensions_dir.contains(&ext))
-    {
-        let dst = &path.with_extension("zip");
-        let dst_file = File::create(dst).await?;
-
-        let walkdir = WalkDir::new(&path);
-        let it = walkdir.into_iter();
-        let prefix = &path
-            .parent()
-            .unwrap_or(&path)
-            .to_str()
-            .ok_or(InputError::NonUTF8Path { path: path.clone() })?;
-
-        compression::zip_dir(&mut it.filter_map(|e| e.ok()), prefix, dst_file).await?;
-        Ok(dst.to_owned())
     } else {
         Err(InputError::UnsupportedArtifact {
             path,
             supported_files: "[ipa,zip]".into(),
-            supported_folders: "[app,xctest]".into(),
+            supported_folders: "[app,xctest,xcarchive]".into(),
         }
         .into())
     }
 }
 
-pub(crate) fn get_supported_configs(
-) -> Vec<(Option<IosDevice>, Option<XcodeVersion>, Option<OsVersion>)> {
-    vec![
-        (
-            Some(IosDevice::IPhone15),
-            Some(XcodeVersion::Xcode15_4),
-            Some(OsVersion::Ios17_5),
-        ),
-        (
-            Some(IosDevice::IPhone15Pro),
-            Some(XcodeVersion::Xcode15_4),
-            Some(OsVersion::Ios17_5),
-        ),
-        (
-            Some(IosDevice::IPhone15ProMax),
-            Some(XcodeVersion::Xcode15_4),
-            Some(OsVersion::Ios17_5),
-        ),
-        (
-            Some(IosDevice::IPhone11),
-            Some(XcodeVersion::Xcode15_4),
-            Some(OsVersion::Ios17_5),
-        ),
-        (
-            Some(IosDevice::IPhone16),
-            Some(XcodeVersion::Xcode16_2),
-            Some(OsVersion::Ios18_2),
-        ),
-        (
-            Some(IosDevice::IPhone16Pro),
-            Some(XcodeVersion::Xcode16_2),
-            Some(OsVersion::Ios18_2),
-        ),
-        (
-            Some(IosDevice::IPhone16ProMax),
-            Some(XcodeVersion::Xcode16_2),
-            Some(OsVersion::Ios18_2),
-        ),
-        (
-            Some(IosDevice::IPhone11),
-            Some(XcodeVersion::Xcode16_2),
-            Some(OsVersion::Ios18_2),
-        ),
-    ]
-}
+/// Directories are zipped into `work_dir`, leaving the source location untouched.
+/// Entries excluded by `options` relative to the parent of `path` are skipped.
+/// Archives are replaced by their application
+pub(crate) async fn ensure_format(
+    path: std::path::PathBuf,
+    work_dir: &std::path::Path,
+    options: &ZipOptions,
+) -> Result<std::path::PathBuf> {
+    let path = check_format(path).await?;
+    if !is_bundle_dir(&path) {
+        return Ok(path);
+    }
+    //.app and .xctest bundles may share a name, so each kind gets its own folder
+    let extension = path.extension().and_then(OsStr::to_str).unwrap_or_default();
+    let file_name =
+        std::path::PathBuf::from(path.file_name().unwrap_or_default()).with_extension("zip");
 
-pub(crate) async fn infer_parameters(
-    device: Option<IosDevice>,
-    xcode_version: Option<XcodeVersion>,
-    os_version: Option<OsVersion>,
-) -> Result<(IosDevice, XcodeVersion, OsVersion)> {
-    let supported_configs = get_supported_configs();
+    let prefix = &path
+        .parent()
+        .unwrap_or(&path)
+        .to_str()
+        .ok_or(InputError::NonUTF8Path { path: path.clone() })?;
+    let entries = compression::bundle_entries(&path, std::path::Path::new(prefix), options);
 
-    // Filter out configurations that match the provided parameters
-    let filtered_configs: Vec<&(Option<IosDevice>, Option<XcodeVersion>, Option<OsVersion>)> =
-        supported_configs
-            .iter()
-            .filter(|(d, x, o)| {
-                (device.is_none() || d == &device)
-                    && (xcode_version.is_none() || x == &xcode_version)
-                    && (os_version.is_none() || o == &os_version)
-            })
-            .collect();
+    let cache_key = match &options.cache {
+        Some(cache) => {
+            let key = compression::fingerprint(
+                &entries,
+                std::path::Path::new(prefix),
+                &format!("{}|{}", extension, options.cache_key_prefix()),
+            )?;
+            if let Some(cached) = cache.get(&key, &file_name) {
+                debug!("Reusing {} for {}", cached.display(), path.display());
+                return Ok(cached);
+            }
+            Some(key)
+        }
+        None => None,
+    };
+
+    let dst = &work_dir.join(extension).join(&file_name);
+    tokio::fs::create_dir_all(work_dir.join(extension)).await?;
+    let dst_file = File::create(dst).await?;
+    let progress_bar = options.progress_bar(format!(
+        "Zipping {}",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ))?;
+    compression::zip_dir(
+        &mut entries.into_iter(),
+        prefix,
+        dst_file,
+        options.level,
+        &progress_bar,
+    )
+    .await?;
+    progress_bar.finish_and_clear();
 
-    // If no valid configuration is found, return an error
-    if filtered_configs.is_empty() {
-        return Err(anyhow::anyhow!("Invalid parameters"));
+    if let (Some(cache), Some(key)) = (&options.cache, cache_key) {
+        //The archive in the work dir is still usable when caching fails
+        match cache.store(&key, dst) {
+            Ok(cached) => return Ok(cached),
+            Err(error) => debug!("Failed to cache {}: {}", dst.display(), error),
+        }
     }
+    Ok(dst.to_owned())
+}
 
-    // If only one valid configuration is found, use it
-    if filtered_configs.len() == 1 {
-        let (final_device, final_xcode, final_os) = filtered_configs[0];
-        return Ok((
-            final_device.clone().unwrap(),
-            final_xcode.clone().unwrap(),
-            final_os.clone().unwrap(),
-        ));
+/// Configuration of the catalog matching the provided parameters, which have to be specific
+/// enough to select exactly one
+pub(crate) fn infer_parameters<'a>(
+    catalog: &'a [IosConfiguration],
+    device: Option<&str>,
+    xcode_version: Option<&str>,
+    os_version: Option<&str>,
+) -> Result<&'a IosConfiguration> {
+    // Filter out configurations that match the provided parameters
+    let filtered_configs: Vec<&IosConfiguration> = catalog
+        .iter()
+        .filter(|x| {
+            device.map_or(true, |device| x.device == device)
+                && xcode_version.map_or(true, |version| x.xcode_version == version)
+                && os_version.map_or(true, |version| x.os_version == version)
+        })
+        .collect();
+
+    match &filtered_configs[..] {
+        // If no valid configuration is found, return an error
+        [] => Err(anyhow::anyhow!("Invalid parameters")),
+        // If only one valid configuration is found, use it
+        [config] => Ok(config),
+        // If multiple configurations are still valid, we need more specific parameters
+        _ => Err(anyhow::anyhow!(
+            "Ambiguous parameters, please provide more specific input."
+        )),
     }
+}
 
-    // If multiple configurations are still valid, we need more specific parameters
-    if filtered_configs.len() > 1 {
-        return Err(anyhow::anyhow!(
+/// Configurations of `devices` sharing the same Xcode and runtime. Without devices the
+/// configuration is inferred from the Xcode and runtime alone
+pub(crate) fn infer_device_parameters<'a>(
+    catalog: &'a [IosConfiguration],
+    devices: &[String],
+    xcode_version: Option<&str>,
+    os_version: Option<&str>,
+) -> Result<Vec<&'a IosConfiguration>> {
+    let Some((first, rest)) = devices.split_first() else {
+        return infer_parameters(catalog, None, xcode_version, os_version).map(|x| vec![x]);
+    };
+    let candidates: Vec<Vec<&IosConfiguration>> = catalog
+        .iter()
+        .filter(|x| {
+            x.device == *first
+                && xcode_version.map_or(true, |version| x.xcode_version == version)
+                && os_version.map_or(true, |version| x.os_version == version)
+        })
+        .filter_map(|config| {
+            let mut configs = vec![config];
+            for device in rest {
+                configs.push(catalog.iter().find(|x| {
+                    x.device == *device
+                        && x.xcode_version == config.xcode_version
+                        && x.runtime == config.runtime
+                })?);
+            }
+            Some(configs)
+        })
+        .collect();
+
+    match &candidates[..] {
+        [] => Err(anyhow::anyhow!("Invalid parameters")),
+        [configs] => Ok(configs.clone()),
+        _ => Err(anyhow::anyhow!(
             "Ambiguous parameters, please provide more specific input."
+        )),
+    }
+}
+
+fn config_args(config: &IosConfiguration) -> String {
+    format!(
+        "--xcode-version {} --os-version {} --device {}",
+        config.xcode_version, config.os_version, config.device
+    )
+}
+
+//Lists the supported combinations, the first one of the catalog is the default
+fn supported_configs_message(catalog: &[IosConfiguration]) -> String {
+    let mut message =
+        "\nPlease set --xcode-version, --os-version, and --device correctly.\nSupported iOS settings combinations are:\n"
+            .to_owned();
+    for (index, config) in catalog.iter().enumerate() {
+        message.push_str(&format!(
+            "    {}{}\n",
+            config_args(config),
+            if index == 0 { " => Default" } else { "" }
         ));
     }
+    message.push_str("First example: If you choose --xcode-version 15.4 --device iPhone-15-Pro then the --os-version will be inferred (17.5).
+Second example: If you choose --device iPhone-11 then you will receive an error because --os-version and --xcode-version params are ambiguous.");
+    message
+}
 
-    Ok((device.unwrap(), xcode_version.unwrap(), os_version.unwrap()))
+/// Prints the supported combinations of device, Xcode and iOS runtime
+pub(crate) async fn list_configs(api_args: &super::ApiArgs, format: &OutputFormat) -> Result<()> {
+    let catalog = catalog::load(&api_args.base_url, &api_args.api_key, false).await;
+    match format {
+        OutputFormat::Text => {
+            for config in &catalog {
+                println!("{}", config_args(config));
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&catalog)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&catalog)?),
+    }
+    Ok(())
 }
 
 fn get_allowed_permissions() -> HashSet<&'static str> {
@@ -233,12 +312,25 @@ fn get_allowed_permissions() -> HashSet<&'static str> {
     ])
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn run(
-    application: std::path::PathBuf,
-    test_application: std::path::PathBuf,
-    os_version: Option<OsVersion>,
-    device: Option<IosDevice>,
-    xcode_version: Option<XcodeVersion>,
+    from_derived_data: Option<String>,
+    scheme: Option<String>,
+    configuration: String,
+    application: Option<std::path::PathBuf>,
+    test_application: Option<std::path::PathBuf>,
+    xctestrun_args: super::XctestrunArgs,
+    remote_artifact_args: super::RemoteArtifactArgs,
+    os_version: Option<String>,
+    device: Option<Vec<String>>,
+    device_class: Option<DeviceClass>,
+    network_profile: Option<NetworkProfile>,
+    orientation: Option<Orientation>,
+    simulated_location: Option<Location>,
+    xcode_version: Option<String>,
+    flavor: Option<Flavor>,
+    detox_args: super::DetoxArgs,
+    locale_args: super::LocaleArgs,
     common: super::CommonRunArgs,
     api_args: super::ApiArgs,
     xctestrun_env: Option<Vec<String>>,
@@ -247,63 +339,390 @@ pub(crate) async fn run(
     xctestplan_target_name: Option<String>,
     retry_args: super::RetryArgs,
     analytics_args: super::AnalyticsArgs,
+    profiling_args: super::ProfilingArgs,
     test_timeout_default: Option<u32>,
     test_timeout_max: Option<u32>,
     granted_permission: Option<Vec<String>>,
+    work_dir: Option<std::path::PathBuf>,
+    keep_archives: bool,
+    zip_exclude: Option<Vec<String>>,
+    compression: CompressionLevel,
+    no_zip_cache: bool,
+    stream_archives: bool,
 ) -> Result<bool> {
-    let (device, xcode_version, os_version) = if device.is_none()
-        && xcode_version.is_none()
-        && os_version.is_none()
-    {
-        (None, None, None)
+    let (application, test_application, xctestrun_args) = match from_derived_data {
+        Some(derived_data) => {
+            let derived_data = std::path::PathBuf::from(shellexpand::tilde(&derived_data).as_ref());
+            let sdk = match device_class {
+                Some(DeviceClass::Real) => "iphoneos",
+                _ => "iphonesimulator",
+            };
+            let formatter = StandardFormatter::new(1);
+            match derived_data::discover(&derived_data, scheme.as_deref(), &configuration, sdk)? {
+                derived_data::Products::Xctestrun(xctestrun) => {
+                    formatter.message(&format!("Using xctestrun {}", xctestrun.display()));
+                    (
+                        None,
+                        None,
+                        super::XctestrunArgs {
+                            xctestrun: Some(xctestrun),
+                            ..xctestrun_args
+                        },
+                    )
+                }
+                derived_data::Products::Bundles {
+                    application,
+                    test_application,
+                } => {
+                    formatter.message(&format!("Using application {}", application.display()));
+                    formatter.message(&format!(
+                        "Using test application {}",
+                        test_application.display()
+                    ));
+                    (Some(application), Some(test_application), xctestrun_args)
+                }
+            }
+        }
+        None => (application, test_application, xctestrun_args),
+    };
+
+    let mut checks = cli::validate::Checks::new(common.validate_only);
+    let run_options = RunOptions {
+        device_class: device_class.as_ref().map(|x| x.to_string()),
+        network_profile: network_profile.map(|x| x.to_string()),
+        orientation: orientation.map(|x| x.to_string()),
+        simulated_location: simulated_location.as_ref().map(|x| x.to_string()),
+        country: locale_args.country,
+        ..checks
+            .check(
+                detox::run_options(&detox_args, matches!(flavor, Some(Flavor::JsDetox)), "iOS")
+                    .await,
+            )?
+            .unwrap_or_default()
+    };
+
+    let mut devices: Vec<String> = Vec::new();
+    for device in device.into_iter().flatten() {
+        if !devices.contains(&device) {
+            devices.push(device);
+        }
+    }
+
+    //Real devices aren't part of the simulator catalog, the model is passed as is
+    let (devices, xcode_version, os_version) = if device_class == Some(DeviceClass::Real) {
+        if granted_permission.is_some() {
+            checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                message: "Granting permissions isn't supported on real devices".into(),
+            })?;
+        }
+        if simulated_location.is_some() {
+            checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                message: "Simulated location isn't supported on real devices".into(),
+            })?;
+        }
+        if os_version.is_some() {
+            checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                message: "OS version of real devices is determined by --device".into(),
+            })?;
+        }
+        if devices.is_empty() {
+            checks.check(
+                cli::validate::real_device(&api_args, &Platform::iOS, None, common.validate_only)
+                    .await,
+            )?;
+        }
+        for device in &devices {
+            checks.check(
+                cli::validate::real_device(
+                    &api_args,
+                    &Platform::iOS,
+                    Some(device),
+                    common.validate_only,
+                )
+                .await,
+            )?;
+        }
+        (devices, xcode_version, None)
+    } else if devices.is_empty() && xcode_version.is_none() && os_version.is_none() {
+        (vec![], None, None)
     } else {
-        match infer_parameters(device, xcode_version, os_version).await {
-            Ok((dev, xcode, os)) => (Some(dev), Some(xcode), Some(os)),
+        let catalog =
+            catalog::load(&api_args.base_url, &api_args.api_key, common.validate_only).await;
+        match infer_device_parameters(
+            &catalog,
+            &devices,
+            xcode_version.as_deref(),
+            os_version.as_deref(),
+        ) {
+            Ok(configs) => (
+                configs.iter().map(|x| x.device_type.clone()).collect(),
+                Some(configs[0].xcode_version.clone()),
+                Some(configs[0].runtime.clone()),
+            ),
             Err(_) => {
-                return Err(ConfigurationError::UnsupportedRunConfiguration {
-                    message: "
-Please set --xcode-version, --os-version, and --device correctly.
-Supported iOS settings combinations are:
-    --xcode-version 15.4 --os-version 17.5 --device iPhone-15 => Default
-    --xcode-version 15.4 --os-version 17.5 --device iPhone-15-Pro
-    --xcode-version 15.4 --os-version 17.5 --device iPhone-15-Pro-Max
-    --xcode-version 15.4 --os-version 17.5 --device iPhone-11
-    --xcode-version 16.2 --os-version 18.2 --device iPhone-16
-    --xcode-version 16.2 --os-version 18.2 --device iPhone-16-Pro
-    --xcode-version 16.2 --os-version 18.2 --device iPhone-16-Pro-Max
-    --xcode-version 16.2 --os-version 18.2 --device iPhone-11
-First example: If you choose --xcode-version 15.4 --device iPhone-15-Pro then the --os-version will be inferred (17.5).
-Second example: If you choose --device iPhone-11 then you will receive an error because --os-version and --xcode-version params are ambiguous."
-                        .into(),
-                }.into());
+                checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+                    message: supported_configs_message(&catalog),
+                })?;
+                (vec![], None, None)
+            }
+        }
+    };
+
+    let run_options = RunOptions {
+        devices: (devices.len() > 1).then(|| devices.clone()),
+        ..run_options
+    };
+
+    //Every language is submitted as a separate run, identical uploads are reused. Languages
+    //don't change the checks, so a validated run is checked once
+    let mut languages = cli::fan_out::values(locale_args.language);
+    if common.validate_only {
+        languages.truncate(1);
+    }
+    let mut checks = Some(checks);
+    let count = languages.len();
+    let fan_out = count > 1;
+    let result_files = match (&common.result_file_args.result_file, fan_out) {
+        (Some(result_file), true) => Some(cli::fan_out::ResultFiles::new(result_file.clone())?),
+        _ => None,
+    };
+    let runs: Vec<(String, _)> = languages
+        .into_iter()
+        .enumerate()
+        .map(|(index, language)| {
+            let label = language.clone().unwrap_or_default();
+            let common = match fan_out {
+                true => cli::fan_out::variant(
+                    &common,
+                    &label,
+                    std::path::Path::new(&label),
+                    result_files.as_ref().map(|x| x.run(index)),
+                ),
+                false => common.clone(),
+            };
+            let run = submit(
+                "iOS".to_owned(),
+                application.clone(),
+                test_application.clone(),
+                xctestrun_args.clone(),
+                remote_artifact_args.clone(),
+                devices.first().cloned(),
+                xcode_version.clone(),
+                os_version.clone(),
+                flavor.as_ref().map(|x| x.to_string()),
+                RunOptions {
+                    language,
+                    ..run_options.clone()
+                },
+                common,
+                api_args.clone(),
+                xctestrun_env.clone(),
+                xctestrun_test_env.clone(),
+                xctestplan_filter_file.clone(),
+                xctestplan_target_name.clone(),
+                retry_args.clone(),
+                analytics_args.clone(),
+                profiling_args.profiling,
+                test_timeout_default,
+                test_timeout_max,
+                granted_permission.clone(),
+                work_dir.clone(),
+                keep_archives,
+                zip_exclude.clone(),
+                compression,
+                no_zip_cache,
+                stream_archives,
+                checks
+                    .take()
+                    .unwrap_or_else(|| cli::validate::Checks::new(false)),
+            );
+            (label, run)
+        })
+        .collect();
+
+    if fan_out {
+        let results = cli::fan_out::wait_all(runs).await;
+        if let Some(result_files) = &result_files {
+            result_files.aggregate(count).await?;
+        }
+        cli::fan_out::outcome(results)
+    } else {
+        match runs.into_iter().next() {
+            Some((_, run)) => run.await,
+            None => Ok(true),
+        }
+    }
+}
+
+/// Packages the bundles of an XCTest run and submits it for `platform`
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn submit(
+    platform: String,
+    application: Option<std::path::PathBuf>,
+    test_application: Option<std::path::PathBuf>,
+    xctestrun_args: super::XctestrunArgs,
+    remote_artifact_args: super::RemoteArtifactArgs,
+    device: Option<String>,
+    xcode_version: Option<String>,
+    os_version: Option<String>,
+    flavor: Option<String>,
+    run_options: RunOptions,
+    common: super::CommonRunArgs,
+    api_args: super::ApiArgs,
+    xctestrun_env: Option<Vec<String>>,
+    xctestrun_test_env: Option<Vec<String>>,
+    xctestplan_filter_file: Option<std::path::PathBuf>,
+    xctestplan_target_name: Option<String>,
+    retry_args: super::RetryArgs,
+    analytics_args: super::AnalyticsArgs,
+    profiling: bool,
+    test_timeout_default: Option<u32>,
+    test_timeout_max: Option<u32>,
+    granted_permission: Option<Vec<String>>,
+    work_dir: Option<std::path::PathBuf>,
+    keep_archives: bool,
+    zip_exclude: Option<Vec<String>>,
+    compression: CompressionLevel,
+    no_zip_cache: bool,
+    stream_archives: bool,
+    mut checks: cli::validate::Checks,
+) -> Result<bool> {
+    let (application, test_application) = match xctestrun_args.xctestrun {
+        Some(path) => {
+            let products = checks.check(
+                xctestrun::products(
+                    &path,
+                    xctestrun_args.xctestrun_products_dir.as_deref(),
+                    xctestrun_args.xctestrun_target_name.as_deref(),
+                )
+                .await,
+            )?;
+            match products {
+                Some(products) => (Some(products.application), Some(products.test_application)),
+                None => (None, None),
             }
         }
+        None => (application, test_application),
+    };
+
+    //Settings of the test plan apply unless overridden on the command line
+    let plan_options = match &xctestplan_filter_file {
+        Some(path) => checks
+            .check(filtering::convert::xctestplan_options(path).await)?
+            .unwrap_or_default(),
+        None => Default::default(),
+    };
+    let xctestrun_env: Vec<String> = plan_options
+        .env
+        .into_iter()
+        .chain(xctestrun_env.into_iter().flatten())
+        .collect();
+    let xctestrun_env = (!xctestrun_env.is_empty()).then_some(xctestrun_env);
+    let test_timeout_default = test_timeout_default
+        .or(plan_options.test_timeout_default)
+        .or(Some(DEFAULT_TEST_TIMEOUT_SECONDS));
+    let test_timeout_max = test_timeout_max.or(plan_options.test_timeout_max);
+    let run_options = RunOptions {
+        language: run_options.language.or(plan_options.language),
+        country: run_options.country.or(plan_options.region),
+        command_line_args: (!plan_options.command_line_args.is_empty())
+            .then_some(plan_options.command_line_args),
+        retry_quota_overrides: match &retry_args.retry_policy {
+            Some(path) => checks.check(retries::load(path).await)?,
+            None => None,
+        },
+        record_video: common.record_video.as_ref().map(|x| x.to_string()),
+        screenshot_on_failure: common.screenshot_on_failure.then_some(true),
+        test_timeout_overrides: match &common.test_timeout_overrides {
+            Some(path) => checks.check(timeouts::load(path, test_timeout_max).await)?,
+            None => None,
+        },
+        ..run_options
     };
 
-    let filtering_configuration = if xctestplan_filter_file.is_some() {
-        Some(
+    let filtering_configuration = if let Some(xctestplan_filter_file) = xctestplan_filter_file {
+        checks.check(
             filtering::convert::convert_xctestplan(
-                xctestplan_filter_file.unwrap(),
+                xctestplan_filter_file,
                 xctestplan_target_name,
+                test_application.as_deref(),
             )
-            .await?,
-        )
+            .await,
+        )?
     } else {
         let filter_file = common.filter_file.map(filtering::convert::convert);
         match filter_file {
-            Some(future) => Some(future.await?),
+            Some(future) => checks.check(future.await)?,
             None => None,
         }
     };
-    let application = ensure_format(application).await?;
-    let test_application = ensure_format(test_application).await?;
+    let filtering_configuration = filtering::convert::with_allowlist_filter(
+        filtering_configuration,
+        common.test_filter_args.filter(),
+    );
+    let filtering_configuration = filtering::convert::with_allowlist_filter(
+        filtering_configuration,
+        checks.check(common.sharding_args.filter())?.flatten(),
+    );
+    let work_dir = match &work_dir {
+        Some(work_dir) => {
+            tokio::fs::create_dir_all(work_dir).await?;
+            tempfile::Builder::new()
+                .prefix("marathon-cloud-")
+                .tempdir_in(work_dir)?
+        }
+        None => tempfile::Builder::new()
+            .prefix("marathon-cloud-")
+            .tempdir()?,
+    };
+    //Bundles are only checked, not zipped, when the run is validated
+    let zip_cache = if no_zip_cache || common.validate_only {
+        None
+    } else {
+        ZipCache::default_location()
+    };
+    let mut zip_options = ZipOptions::new(zip_exclude.unwrap_or_default(), compression, zip_cache)?;
+    if !common.progress_args.no_progress_bars {
+        zip_options = zip_options.with_progress(MultiProgress::new());
+    }
+    let zip_options = Arc::new(zip_options);
+    //Application and test application are zipped in parallel
+    let prepare = |path: Option<std::path::PathBuf>| {
+        let work_dir = work_dir.path().to_path_buf();
+        let options = zip_options.clone();
+        let validate_only = common.validate_only;
+        tokio::spawn(async move {
+            match path {
+                Some(path) if validate_only => check_format(path).await.map(Some),
+                //Folders are zipped during the upload instead
+                Some(path) if stream_archives && is_bundle_dir(&path) => Ok(Some(path)),
+                Some(path) => ensure_format(path, &work_dir, &options).await.map(Some),
+                None => Ok(None),
+            }
+        })
+    };
+    let (application, test_application) =
+        tokio::try_join!(prepare(application), prepare(test_application))?;
+    let application = checks.check(application)?.flatten();
+    let test_application = checks.check(test_application)?.flatten();
+    //Generated archives are removed once the run is submitted unless asked otherwise
+    let _work_dir = if keep_archives && !common.validate_only {
+        let path = work_dir.into_path();
+        StandardFormatter::new(1).message(&format!(
+            "Generated archives are kept in {}",
+            path.display()
+        ));
+        None
+    } else {
+        Some(work_dir)
+    };
 
     let retry_args = cli::validate::retry_args(retry_args);
-    cli::validate::result_file_args(&common.result_file_args)?;
+    checks.check(cli::validate::result_file_args(&common.result_file_args))?;
 
     if let Some(limit) = common.concurrency_limit {
         if limit == 0 {
-            return Err(InputError::NonPositiveValue {
+            checks.fail(InputError::NonPositiveValue {
                 arg: "--concurrency-limit".to_owned(),
             })?;
         }
@@ -311,7 +730,7 @@ Second example: If you choose --device iPhone-11 then you will receive an error
 
     if let Some(limit) = test_timeout_default {
         if limit == 0 {
-            return Err(InputError::NonPositiveValue {
+            checks.fail(InputError::NonPositiveValue {
                 arg: "--test-timeout-default".to_owned(),
             })?;
         }
@@ -319,7 +738,7 @@ Second example: If you choose --device iPhone-11 then you will receive an error
 
     if let Some(limit) = test_timeout_max {
         if limit == 0 {
-            return Err(InputError::NonPositiveValue {
+            checks.fail(InputError::NonPositiveValue {
                 arg: "--test-timeout-max".to_owned(),
             })?;
         }
@@ -334,12 +753,23 @@ Second example: If you choose --device iPhone-11 then you will receive an error
             .collect();
 
         if !invalid_permissions.is_empty() {
-            return Err(InputError::IncorrectPermission {
+            checks.fail(InputError::IncorrectPermission {
                 permissions: invalid_permissions,
             })?;
         }
     }
 
+    if common.validate_only {
+        return cli::validate::uploads(
+            checks,
+            application.into_iter().chain(test_application),
+            common.app_data.as_ref(),
+            common.upload_checksum,
+            common.max_upload_size,
+        )
+        .await;
+    }
+
     let present_wait: bool = match common.wait {
         None => true,
         Some(true) => true,
@@ -354,6 +784,15 @@ Second example: If you choose --device iPhone-11 then you will receive an error
             common.link,
             common.branch,
             present_wait,
+            common.stream_logs,
+            common.cancel_on_interrupt,
+            common.run_timeout,
+            common.tui,
+            common.ndjson_progress,
+            common.upload_retries,
+            common.upload_cache_ttl,
+            common.upload_checksum,
+            common.max_upload_size,
             common.isolated,
             common.ignore_test_failures,
             common.code_coverage,
@@ -361,30 +800,39 @@ Second example: If you choose --device iPhone-11 then you will receive an error
             retry_args.retry_quota_test_preventive,
             retry_args.retry_quota_test_reactive,
             analytics_args.analytics_read_only,
-            false,
+            profiling,
             false,
             filtering_configuration,
             &common.output,
-            Some(application),
-            Some(test_application),
-            os_version.map(|x| x.to_string()),
-            None,
-            device.map(|x| x.to_string()),
-            xcode_version.map(|x| x.to_string()),
+            common.artifact_download_args.download_options(),
+            application,
+            test_application,
+            os_version,
             None,
-            "iOS".to_owned(),
+            device,
+            xcode_version,
+            flavor,
+            platform,
             common.progress_args.no_progress_bars,
+            common.polling_args.poll_interval(),
             common.result_file_args.result_file,
             xctestrun_env,
             xctestrun_test_env,
             None,
+            None,
+            common.app_data,
             common.concurrency_limit,
             test_timeout_default,
             test_timeout_max,
             common.project,
             None,
             None,
+            None,
             granted_permission,
+            remote_artifact_args.remote_artifacts(),
+            run_options,
+            stream_archives.then(|| zip_options.clone()),
+            common.skip_if_passed,
         )
         .await
 }
@@ -394,73 +842,321 @@ mod tests {
     use super::*;
 
     #[tokio::test]
-    async fn test_infer_parameters_ambiguous_device_should_error() {
-        let provided_device = Some(IosDevice::IPhone11);
+    async fn test_ensure_format_zips_directory_into_work_dir() -> Result<()> {
+        let source = tempfile::tempdir()?;
+        let work_dir = tempfile::tempdir()?;
+        let app = source.path().join("Sample.app");
+        std::fs::create_dir(&app)?;
+        std::fs::write(app.join("Sample"), b"binary")?;
 
-        let result = infer_parameters(provided_device, None, None).await;
-        assert!(result.is_err());
+        let options = ZipOptions::new(vec![], CompressionLevel::Default, None)?;
+
+        let zip = ensure_format(app, work_dir.path(), &options).await?;
+
+        assert_eq!(zip, work_dir.path().join("app").join("Sample.zip"));
+        assert!(zip.is_file());
+        assert!(!source.path().join("Sample.zip").exists());
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_infer_parameters_device_and_xcode_version_provided() -> Result<()> {
-        let provided_device = Some(IosDevice::IPhone15);
-        let provided_xcode_version = Some(XcodeVersion::Xcode15_4);
-        let expected_os_version = OsVersion::Ios17_5;
+    async fn test_ensure_format_skips_excluded_entries() -> Result<()> {
+        let source = tempfile::tempdir()?;
+        let work_dir = tempfile::tempdir()?;
+        let app = source.path().join("Sample.app");
+        std::fs::create_dir_all(app.join("Sample.dSYM").join("Contents"))?;
+        std::fs::write(
+            app.join("Sample.dSYM").join("Contents").join("Info.plist"),
+            b"",
+        )?;
+        std::fs::write(app.join(".DS_Store"), b"")?;
+        std::fs::write(app.join("Sample"), b"binary")?;
+        let options = ZipOptions::new(
+            vec!["**/.DS_Store".to_owned(), "**/*.dSYM".to_owned()],
+            CompressionLevel::Default,
+            None,
+        )?;
+
+        let zip = ensure_format(app, work_dir.path(), &options).await?;
+
+        let reader = async_zip::base::read::mem::ZipFileReader::new(std::fs::read(zip)?).await?;
+        let names: Vec<&str> = reader
+            .file()
+            .entries()
+            .iter()
+            .filter_map(|entry| entry.filename().as_str().ok())
+            .collect();
+        assert_eq!(names, vec!["Sample.app/Sample"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ensure_format_uses_application_of_xcarchive() -> Result<()> {
+        let source = tempfile::tempdir()?;
+        let work_dir = tempfile::tempdir()?;
+        let archive = source.path().join("Sample 2024-01-01.xcarchive");
+        let app = archive
+            .join("Products")
+            .join("Applications")
+            .join("Sample.app");
+        std::fs::create_dir_all(&app)?;
+        std::fs::create_dir_all(archive.join("dSYMs").join("Sample.app.dSYM"))?;
+        std::fs::write(app.join("Sample"), b"binary")?;
+        std::fs::write(
+            archive.join("Info.plist"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+	<key>ApplicationProperties</key>
+	<dict>
+		<key>ApplicationPath</key>
+		<string>Applications/Sample.app</string>
+	</dict>
+</dict>
+</plist>"#,
+        )?;
+        let options = ZipOptions::new(vec![], CompressionLevel::Default, None)?;
 
-        let (inferred_device, inferred_xcode_version, inferred_os_version) =
-            infer_parameters(provided_device, provided_xcode_version, None).await?;
+        let zip = ensure_format(archive.clone(), work_dir.path(), &options).await?;
 
-        assert_eq!(inferred_device, IosDevice::IPhone15);
-        assert_eq!(inferred_xcode_version, XcodeVersion::Xcode15_4);
-        assert_eq!(inferred_os_version, expected_os_version);
+        assert_eq!(zip, work_dir.path().join("app").join("Sample.zip"));
+        let reader = async_zip::base::read::mem::ZipFileReader::new(std::fs::read(zip)?).await?;
+        let names: Vec<&str> = reader
+            .file()
+            .entries()
+            .iter()
+            .filter_map(|entry| entry.filename().as_str().ok())
+            .collect();
+        assert_eq!(names, vec!["Sample.app/Sample"]);
 
+        std::fs::remove_file(archive.join("Info.plist"))?;
+        assert!(ensure_format(archive, work_dir.path(), &options)
+            .await
+            .is_err());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_infer_parameters_ambiguous_xcode_version_should_error() {
-        let provided_xcode_version = Some(XcodeVersion::Xcode15_4);
+    async fn test_ensure_format_keeps_zip_files() -> Result<()> {
+        let source = tempfile::tempdir()?;
+        let zip = source.path().join("Sample.zip");
+        std::fs::write(&zip, b"zip")?;
 
-        let result = infer_parameters(None, provided_xcode_version, None).await;
-        assert!(result.is_err());
+        let options = ZipOptions::new(vec![], CompressionLevel::Default, None)?;
+
+        assert_eq!(
+            ensure_format(zip.clone(), source.path(), &options).await?,
+            zip
+        );
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_infer_parameters_complete_input_valid() -> Result<()> {
-        let provided_device = Some(IosDevice::IPhone15);
-        let provided_xcode_version = Some(XcodeVersion::Xcode15_4);
-        let provided_os_version = Some(OsVersion::Ios17_5);
+    async fn test_ensure_format_reuses_cached_archive() -> Result<()> {
+        let source = tempfile::tempdir()?;
+        let cache_dir = tempfile::tempdir()?;
+        let app = source.path().join("Sample.app");
+        std::fs::create_dir(&app)?;
+        std::fs::write(app.join("Sample"), b"binary")?;
+        let options = ZipOptions::new(
+            vec![],
+            CompressionLevel::Default,
+            Some(ZipCache::new(cache_dir.path().to_path_buf())),
+        )?;
+
+        let first_work_dir = tempfile::tempdir()?;
+        let first = ensure_format(app.clone(), first_work_dir.path(), &options).await?;
+        //The cached archive is used even when the work dir is gone
+        drop(first_work_dir);
+        let second_work_dir = tempfile::tempdir()?;
+        let second = ensure_format(app.clone(), second_work_dir.path(), &options).await?;
+
+        assert!(first.starts_with(cache_dir.path()));
+        assert_eq!(first, second);
+        assert!(!second_work_dir.path().join("app").exists());
+        Ok(())
+    }
 
-        let (inferred_device, inferred_xcode_version, inferred_os_version) =
-            infer_parameters(provided_device, provided_xcode_version, provided_os_version).await?;
+    #[test]
+    fn test_infer_parameters_ambiguous_device_should_error() {
+        let catalog = catalog::builtin();
 
-        assert_eq!(inferred_device, IosDevice::IPhone15);
-        assert_eq!(inferred_xcode_version, XcodeVersion::Xcode15_4);
-        assert_eq!(inferred_os_version, OsVersion::Ios17_5);
+        let result = infer_parameters(&catalog, Some("iPhone-11"), None, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_infer_parameters_device_and_xcode_version_provided() -> Result<()> {
+        let catalog = catalog::builtin();
 
+        let config = infer_parameters(&catalog, Some("iPhone-15"), Some("15.4"), None)?;
+
+        assert_eq!(config.device, "iPhone-15");
+        assert_eq!(
+            config.device_type,
+            "com.apple.CoreSimulator.SimDeviceType.iPhone-15"
+        );
+        assert_eq!(config.xcode_version, "15.4");
+        assert_eq!(config.os_version, "17.5");
+        assert_eq!(
+            config.runtime,
+            "com.apple.CoreSimulator.SimRuntime.iOS-17-5"
+        );
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_infer_parameters_invalid_device_and_xcode_combination_should_error() {
-        let provided_os_version = Some(OsVersion::Ios17_5);
-        let provided_xcode_version = Some(XcodeVersion::Xcode15_4);
+    #[test]
+    fn test_infer_parameters_ambiguous_xcode_version_should_error() {
+        let catalog = catalog::builtin();
 
-        let result = infer_parameters(None, provided_xcode_version, provided_os_version).await;
+        let result = infer_parameters(&catalog, None, Some("15.4"), None);
         assert!(result.is_err());
     }
 
-    #[tokio::test]
-    async fn test_infer_parameters_valid_for_iphone_16() -> Result<()> {
-        let provided_device = Some(IosDevice::IPhone16);
+    #[test]
+    fn test_infer_parameters_complete_input_valid() -> Result<()> {
+        let catalog = catalog::builtin();
+
+        let config = infer_parameters(&catalog, Some("iPhone-15"), Some("15.4"), Some("17.5"))?;
+
+        assert_eq!(config.device, "iPhone-15");
+        assert_eq!(config.xcode_version, "15.4");
+        assert_eq!(config.os_version, "17.5");
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_parameters_invalid_device_and_xcode_combination_should_error() {
+        let catalog = catalog::builtin();
+
+        let result = infer_parameters(&catalog, None, Some("15.4"), Some("17.5"));
+        assert!(result.is_err());
+        let result = infer_parameters(&catalog, Some("iPhone-16"), Some("15.4"), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_infer_parameters_valid_for_iphone_16() -> Result<()> {
+        let catalog = catalog::builtin();
+
+        let config = infer_parameters(&catalog, Some("iPhone-16"), None, None)?;
+
+        assert_eq!(config.device, "iPhone-16");
+        assert_eq!(config.xcode_version, "16.2");
+        assert_eq!(config.os_version, "18.2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_parameters_for_ipad() -> Result<()> {
+        let catalog = catalog::builtin();
+
+        let config = infer_parameters(&catalog, Some("iPad-Pro-11-inch-M4"), None, None)?;
+        assert_eq!(
+            config.device_type,
+            "com.apple.CoreSimulator.SimDeviceType.iPad-Pro-11-inch-M4-8GB"
+        );
+        assert_eq!(config.xcode_version, "16.2");
+
+        assert!(infer_parameters(&catalog, Some("iPad-10th-generation"), None, None).is_err());
+        let config = infer_parameters(&catalog, Some("iPad-10th-generation"), None, Some("17.5"))?;
+        assert_eq!(config.xcode_version, "15.4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_parameters_uses_catalog() -> Result<()> {
+        let mut catalog = catalog::builtin();
+        catalog.push(IosConfiguration {
+            device: "iPhone-17".to_owned(),
+            device_type: "com.apple.CoreSimulator.SimDeviceType.iPhone-17".to_owned(),
+            xcode_version: "26.0".to_owned(),
+            os_version: "26.0".to_owned(),
+            runtime: "com.apple.CoreSimulator.SimRuntime.iOS-26-0".to_owned(),
+        });
+
+        let config = infer_parameters(&catalog, Some("iPhone-17"), None, None)?;
+        assert_eq!(config.xcode_version, "26.0");
+        assert!(supported_configs_message(&catalog)
+            .contains("--xcode-version 26.0 --os-version 26.0 --device iPhone-17\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_parameters_for_tv_and_watch() -> Result<()> {
+        let catalog = catalog::builtin();
 
-        let (inferred_device, inferred_xcode_version, inferred_os_version) =
-            infer_parameters(provided_device, None, None).await?;
+        let config = infer_parameters(&catalog, Some("Apple-Watch-Series-9-45mm"), None, None)?;
+        assert_eq!(
+            config.runtime,
+            "com.apple.CoreSimulator.SimRuntime.watchOS-10-5"
+        );
+        assert_eq!(config.xcode_version, "15.4");
 
-        assert_eq!(inferred_device, IosDevice::IPhone16);
-        assert_eq!(inferred_xcode_version, XcodeVersion::Xcode16_2);
-        assert_eq!(inferred_os_version, OsVersion::Ios18_2);
+        assert!(
+            infer_parameters(&catalog, Some("Apple-TV-4K-3rd-generation"), None, None).is_err()
+        );
+        let config = infer_parameters(
+            &catalog,
+            Some("Apple-TV-4K-3rd-generation"),
+            Some("16.2"),
+            None,
+        )?;
+        assert_eq!(
+            config.device_type,
+            "com.apple.CoreSimulator.SimDeviceType.Apple-TV-4K-3rd-generation-4K"
+        );
+        assert_eq!(
+            config.runtime,
+            "com.apple.CoreSimulator.SimRuntime.tvOS-18-2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_device_parameters() -> Result<()> {
+        let catalog = catalog::builtin();
+        let devices =
+            |devices: &[&str]| -> Vec<String> { devices.iter().map(|x| x.to_string()).collect() };
+
+        let configs = infer_device_parameters(
+            &catalog,
+            &devices(&["iPhone-15", "iPad-Air-5th-generation"]),
+            None,
+            None,
+        )?;
+        assert_eq!(
+            configs
+                .iter()
+                .map(|x| x.device.as_str())
+                .collect::<Vec<_>>(),
+            vec!["iPhone-15", "iPad-Air-5th-generation"]
+        );
+        assert!(configs.iter().all(|x| x.xcode_version == "15.4"));
+
+        //iPhone-11 alone is ambiguous, iPhone-16 only runs with Xcode 16.2
+        let configs =
+            infer_device_parameters(&catalog, &devices(&["iPhone-11", "iPhone-16"]), None, None)?;
+        assert!(configs.iter().all(|x| x.xcode_version == "16.2"));
+
+        //Simulators of different operating systems can't share a runtime
+        assert!(infer_device_parameters(
+            &catalog,
+            &devices(&["iPhone-15", "Apple-TV-4K-3rd-generation"]),
+            Some("15.4"),
+            None
+        )
+        .is_err());
+        assert!(infer_device_parameters(
+            &catalog,
+            &devices(&["iPhone-15", "iPhone-16"]),
+            None,
+            None
+        )
+        .is_err());
 
+        let configs = infer_device_parameters(&catalog, &[], Some("16.2"), Some("18.2"));
+        assert!(configs.is_err());
         Ok(())
     }
 }
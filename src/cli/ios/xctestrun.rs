@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::debug;
+
+use crate::{errors::InputError, plist};
+
+/// Application and test application of a test target in a .xctestrun file
+#[derive(Debug, PartialEq)]
+pub(crate) struct XctestrunProducts {
+    pub application: PathBuf,
+    pub test_application: PathBuf,
+}
+
+struct XctestrunTarget<'a> {
+    name: &'a str,
+    options: &'a plist::Value,
+}
+
+/// Resolves the bundles of a test target in the .xctestrun file at `path`. `__TESTROOT__` is
+/// `products_dir`, the folder containing the file by default
+pub(crate) async fn products(
+    path: &Path,
+    products_dir: Option<&Path>,
+    target_name: Option<&str>,
+) -> Result<XctestrunProducts> {
+    let invalid = |message: String| InputError::InvalidXctestrun {
+        path: path.to_owned(),
+        message,
+    };
+    let content = tokio::fs::read(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+    let xctestrun = plist::parse(&content).map_err(|error| invalid(error.to_string()))?;
+    let targets = targets(&xctestrun).map_err(invalid)?;
+
+    let target = match target_name {
+        Some(name) => targets.iter().find(|x| x.name == name),
+        None if targets.len() == 1 => targets.first(),
+        None => None,
+    }
+    .ok_or_else(|| {
+        invalid(format!(
+            "please select one of the test targets {:?} with --xctestrun-target-name",
+            targets.iter().map(|x| x.name).collect::<Vec<_>>()
+        ))
+    })?;
+
+    let products_dir = match products_dir {
+        Some(products_dir) => products_dir.to_owned(),
+        None => path
+            .parent()
+            .filter(|x| !x.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_owned(),
+    };
+    let option = |key: &str| {
+        target
+            .options
+            .get(key)
+            .and_then(plist::Value::as_str)
+            .ok_or_else(|| invalid(format!("{} of test target {} is missing", key, target.name)))
+    };
+    let test_root = products_dir.to_str().ok_or(InputError::NonUTF8Path {
+        path: products_dir.clone(),
+    })?;
+    let test_host = option("TestHostPath")?.replace("__TESTROOT__", test_root);
+    let is_ui_test = target
+        .options
+        .get("IsUITestBundle")
+        .and_then(plist::Value::as_bool)
+        .unwrap_or(false);
+    //UI tests run the target application from the runner app, unit tests are hosted by the application
+    let products = if is_ui_test {
+        XctestrunProducts {
+            application: PathBuf::from(
+                option("UITargetAppPath")?.replace("__TESTROOT__", test_root),
+            ),
+            test_application: PathBuf::from(test_host),
+        }
+    } else {
+        XctestrunProducts {
+            test_application: PathBuf::from(
+                option("TestBundlePath")?
+                    .replace("__TESTHOST__", &test_host)
+                    .replace("__TESTROOT__", test_root),
+            ),
+            application: PathBuf::from(test_host),
+        }
+    };
+    debug!(
+        "Using {} and {} of test target {}",
+        products.application.display(),
+        products.test_application.display(),
+        target.name
+    );
+    for path in [&products.application, &products.test_application] {
+        if !path.exists() {
+            return Err(InputError::InvalidFileName { path: path.clone() }.into());
+        }
+    }
+    Ok(products)
+}
+
+//Format version 1 has a top-level entry per target, version 2 lists them per test configuration
+fn targets(xctestrun: &plist::Value) -> std::result::Result<Vec<XctestrunTarget<'_>>, String> {
+    let version = xctestrun
+        .get("__xctestrun_metadata__")
+        .and_then(|x| x.get("FormatVersion"))
+        .and_then(plist::Value::as_str)
+        .unwrap_or("1");
+    let targets: Vec<XctestrunTarget<'_>> = match version {
+        "1" => xctestrun
+            .as_dict()
+            .unwrap_or_default()
+            .iter()
+            .filter(|(name, options)| {
+                !name.starts_with("__") && options.get("TestHostPath").is_some()
+            })
+            .map(|(name, options)| XctestrunTarget { name, options })
+            .collect(),
+        "2" => {
+            let configurations = xctestrun
+                .get("TestConfigurations")
+                .and_then(plist::Value::as_array)
+                .unwrap_or_default();
+            if configurations.len() > 1 {
+                debug!(
+                    "Using the first of {} test configurations",
+                    configurations.len()
+                );
+            }
+            configurations
+                .first()
+                .and_then(|x| x.get("TestTargets"))
+                .and_then(plist::Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|options| {
+                    let name = options.get("BlueprintName")?.as_str()?;
+                    Some(XctestrunTarget { name, options })
+                })
+                .collect()
+        }
+        version => return Err(format!("unsupported format version {}", version)),
+    };
+    if targets.is_empty() {
+        return Err("no test targets found".to_owned());
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_products() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("xctestrun")
+            .join("sample-app_iphonesimulator17.5-arm64.xctestrun");
+        let products_dir = tempfile::tempdir()?;
+        let build = products_dir.path().join("Debug-iphonesimulator");
+        let app = build.join("sample-app.app");
+        let runner = build.join("sample-appUITests-Runner.app");
+        std::fs::create_dir_all(app.join("PlugIns").join("sample-appTests.xctest"))?;
+        std::fs::create_dir_all(&runner)?;
+
+        let ui_tests = products(
+            &fixture,
+            Some(products_dir.path()),
+            Some("sample-appUITests"),
+        )
+        .await?;
+        assert_eq!(
+            ui_tests,
+            XctestrunProducts {
+                application: app.clone(),
+                test_application: runner,
+            }
+        );
+
+        let unit_tests =
+            products(&fixture, Some(products_dir.path()), Some("sample-appTests")).await?;
+        assert_eq!(
+            unit_tests,
+            XctestrunProducts {
+                application: app.clone(),
+                test_application: app.join("PlugIns").join("sample-appTests.xctest"),
+            }
+        );
+
+        let Err(error) = products(&fixture, Some(products_dir.path()), None).await else {
+            panic!("test target should be required");
+        };
+        assert!(error.to_string().contains("sample-appUITests"));
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+
+use crate::{api::RunOptions, compression::CompressionLevel};
+
+use super::ios;
+
+/// macOS runs use the XCTest pipeline of iOS. Versions are validated by Marathon Cloud
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run(
+    application: Option<std::path::PathBuf>,
+    test_application: Option<std::path::PathBuf>,
+    xctestrun_args: super::XctestrunArgs,
+    remote_artifact_args: super::RemoteArtifactArgs,
+    os_version: Option<String>,
+    xcode_version: Option<String>,
+    common: super::CommonRunArgs,
+    api_args: super::ApiArgs,
+    xctestrun_env: Option<Vec<String>>,
+    xctestrun_test_env: Option<Vec<String>>,
+    xctestplan_filter_file: Option<std::path::PathBuf>,
+    xctestplan_target_name: Option<String>,
+    retry_args: super::RetryArgs,
+    analytics_args: super::AnalyticsArgs,
+    test_timeout_default: Option<u32>,
+    test_timeout_max: Option<u32>,
+    work_dir: Option<std::path::PathBuf>,
+    keep_archives: bool,
+    zip_exclude: Option<Vec<String>>,
+    compression: CompressionLevel,
+    no_zip_cache: bool,
+    stream_archives: bool,
+) -> Result<bool> {
+    let checks = super::validate::Checks::new(common.validate_only);
+    ios::submit(
+        "macOS".to_owned(),
+        application,
+        test_application,
+        xctestrun_args,
+        remote_artifact_args,
+        None,
+        xcode_version,
+        os_version,
+        None,
+        RunOptions::default(),
+        common,
+        api_args,
+        xctestrun_env,
+        xctestrun_test_env,
+        xctestplan_filter_file,
+        xctestplan_target_name,
+        retry_args,
+        analytics_args,
+        false,
+        test_timeout_default,
+        test_timeout_max,
+        None,
+        work_dir,
+        keep_archives,
+        zip_exclude,
+        compression,
+        no_zip_cache,
+        stream_archives,
+        checks,
+    )
+    .await
+}
@@ -0,0 +1,314 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+use anyhow::Result;
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+
+use crate::errors::InputError;
+
+use super::{fan_out, ResultFileArgs, RunArgs, RunCommands};
+
+/// Matrix configuration file, a list of run configurations submitted at once
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Matrix {
+    pub configurations: Vec<MatrixConfiguration>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct MatrixConfiguration {
+    pub name: Option<String>,
+    pub platform: MatrixPlatform,
+    pub application: Option<PathBuf>,
+    /// Test application, the test bundle for web
+    pub test_application: Option<PathBuf>,
+    /// Device types, browsers for web
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// A separate run is submitted for every OS version
+    #[serde(default, deserialize_with = "scalars")]
+    pub os_versions: Vec<String>,
+    pub flavor: Option<String>,
+    pub filter_file: Option<PathBuf>,
+    /// Any other arguments of the run command of the platform
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MatrixPlatform {
+    Android,
+    #[allow(non_camel_case_types)]
+    #[serde(rename = "ios")]
+    iOS,
+    Web,
+}
+
+impl Display for MatrixPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatrixPlatform::Android => write!(f, "android"),
+            MatrixPlatform::iOS => write!(f, "ios"),
+            MatrixPlatform::Web => write!(f, "web"),
+        }
+    }
+}
+
+//OS versions are usually written as numbers, e.g. [13, 14]
+fn scalars<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values = Vec::<serde_yaml::Value>::deserialize(deserializer)?;
+    values
+        .into_iter()
+        .map(|value| match value {
+            serde_yaml::Value::String(x) => Ok(x),
+            serde_yaml::Value::Number(x) => Ok(x.to_string()),
+            value => Err(serde::de::Error::custom(format!(
+                "expected a string or a number, got {:?}",
+                value
+            ))),
+        })
+        .collect()
+}
+
+type RunFuture = Pin<Box<dyn Future<Output = Result<bool>>>>;
+
+/// Single run of a matrix: a configuration with one of its OS versions
+#[derive(Debug)]
+pub(crate) struct MatrixRun {
+    pub label: String,
+    pub args: Vec<String>,
+}
+
+pub(crate) async fn load(path: &Path) -> Result<Matrix> {
+    let content = tokio::fs::read(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+    let matrix: Matrix =
+        serde_yaml::from_slice(&content).map_err(|error| InputError::InvalidMatrixConfig {
+            path: path.to_owned(),
+            message: error.to_string(),
+        })?;
+    if matrix.configurations.is_empty() {
+        return Err(InputError::InvalidMatrixConfig {
+            path: path.to_owned(),
+            message: "no configurations found".into(),
+        }
+        .into());
+    }
+    Ok(matrix)
+}
+
+/// Command line arguments of every run of the matrix, one run per configuration and OS version
+pub(crate) fn runs(matrix: &Matrix, base_url: &str, api_key: &str) -> Vec<MatrixRun> {
+    let mut runs = Vec::new();
+    for (index, configuration) in matrix.configurations.iter().enumerate() {
+        let name = configuration
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{} #{}", configuration.platform, index + 1));
+        let os_versions: Vec<Option<&String>> = if configuration.os_versions.is_empty() {
+            vec![None]
+        } else {
+            configuration.os_versions.iter().map(Some).collect()
+        };
+        for os_version in os_versions {
+            let label = match os_version {
+                Some(os_version) if configuration.os_versions.len() > 1 => {
+                    format!("{} ({})", name, os_version)
+                }
+                _ => name.clone(),
+            };
+            let mut args: Vec<String> = vec![
+                "run".into(),
+                configuration.platform.to_string(),
+                "--name".into(),
+                label.clone(),
+                "--base-url".into(),
+                base_url.into(),
+                "--api-key".into(),
+                api_key.into(),
+                "--no-progress-bars".into(),
+            ];
+            let mut arg = |name: &str, value: String| {
+                args.push(name.to_owned());
+                args.push(value);
+            };
+            let path = |path: &Path| path.to_string_lossy().into_owned();
+            if let Some(application) = &configuration.application {
+                arg("--application", path(application));
+            }
+            if let Some(test_application) = &configuration.test_application {
+                match configuration.platform {
+                    MatrixPlatform::Web => arg("--test-bundle", path(test_application)),
+                    _ => arg("--test-application", path(test_application)),
+                }
+            }
+            match configuration.platform {
+                MatrixPlatform::Web => {
+                    for browser in &configuration.devices {
+                        arg("--browser", browser.clone());
+                    }
+                }
+                _ if !configuration.devices.is_empty() => {
+                    arg("--device", configuration.devices.join(","))
+                }
+                _ => {}
+            }
+            if let Some(os_version) = os_version {
+                match configuration.platform {
+                    MatrixPlatform::Web => arg("--os", os_version.clone()),
+                    _ => arg("--os-version", os_version.clone()),
+                }
+            }
+            if let Some(flavor) = &configuration.flavor {
+                arg("--flavor", flavor.clone());
+            }
+            if let Some(filter_file) = &configuration.filter_file {
+                arg("--filter-file", path(filter_file));
+            }
+            args.extend(configuration.args.iter().cloned());
+            runs.push(MatrixRun { label, args });
+        }
+    }
+    runs
+}
+
+pub(crate) async fn run(
+    config: PathBuf,
+    result_file_args: ResultFileArgs,
+    base_url: String,
+    api_key: String,
+    validate_only: bool,
+) -> Result<bool> {
+    super::validate::result_file_args(&result_file_args)?;
+    let matrix = load(&config).await?;
+    let runs = runs(&matrix, &base_url, &api_key);
+
+    let result_files = match result_file_args.result_file {
+        Some(result_file) => Some(fan_out::ResultFiles::new(result_file)?),
+        None => None,
+    };
+    let mut commands = Vec::new();
+    for (index, run) in runs.iter().enumerate() {
+        let mut args = run.args.clone();
+        if let Some(result_files) = &result_files {
+            args.push("--result-file".into());
+            args.push(result_files.run(index).to_string_lossy().into());
+        }
+        let command = RunArgs::try_parse_from(args)
+            .map_err(|error| InputError::InvalidMatrixConfig {
+                path: config.clone(),
+                message: format!("{}: {}", run.label, error.render().to_string().trim()),
+            })?
+            .command;
+        commands.push(match validate_only {
+            true => command.validate_only(),
+            false => command,
+        });
+    }
+
+    let futures: Vec<(String, RunFuture)> = runs
+        .iter()
+        .zip(commands)
+        .map(|(run, command): (&MatrixRun, RunCommands)| {
+            let future: RunFuture = Box::pin(super::run(command));
+            (run.label.clone(), future)
+        })
+        .collect();
+    let results = fan_out::wait_all(futures).await;
+
+    if let Some(result_files) = &result_files {
+        result_files.aggregate(runs.len()).await?;
+    }
+    fan_out::outcome(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATRIX: &str = r#"
+configurations:
+  - name: phones
+    platform: android
+    application: app.apk
+    test-application: test.apk
+    devices: [phone, tv]
+    os-versions: [13, 14]
+    filter-file: filter.yaml
+    args: ["--isolated", "true"]
+  - platform: ios
+    application: sample.app
+    test-application: sampleUITests-Runner.app
+    os-versions: ["17.5"]
+  - platform: web
+    test-application: e2e.zip
+    flavor: playwright
+    devices: [chromium, firefox]
+"#;
+
+    #[test]
+    fn test_runs() {
+        let matrix: Matrix = serde_yaml::from_str(MATRIX).unwrap();
+        let runs = runs(&matrix, "http://localhost", "key");
+        assert_eq!(
+            runs.iter().map(|x| x.label.as_str()).collect::<Vec<_>>(),
+            vec!["phones (13)", "phones (14)", "ios #2", "web #3"]
+        );
+        assert_eq!(
+            runs[0].args[9..],
+            [
+                "--application",
+                "app.apk",
+                "--test-application",
+                "test.apk",
+                "--device",
+                "phone,tv",
+                "--os-version",
+                "13",
+                "--filter-file",
+                "filter.yaml",
+                "--isolated",
+                "true"
+            ]
+        );
+        assert!(runs[2]
+            .args
+            .ends_with(&["--os-version".into(), "17.5".into()]));
+        assert_eq!(
+            runs[3].args[9..],
+            [
+                "--test-bundle",
+                "e2e.zip",
+                "--browser",
+                "chromium",
+                "--browser",
+                "firefox",
+                "--flavor",
+                "playwright"
+            ]
+        );
+        for run in runs {
+            assert!(RunArgs::try_parse_from(run.args).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let matrix = "configurations:\n  - platform: android\n    device: phone\n";
+        assert!(serde_yaml::from_str::<Matrix>(matrix).is_err());
+    }
+}
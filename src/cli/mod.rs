@@ -1,15 +1,29 @@
 mod android;
+mod catalog;
+mod detox;
+mod doctor;
+mod fan_out;
 mod ios;
+mod macos;
+mod matrix;
 pub mod model;
 mod validate;
+mod web;
 
 use anyhow::Result;
 use clap::CommandFactory;
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, FromArgMatches, Parser, Subcommand};
+use std::num::{NonZeroU32, NonZeroUsize};
 use std::path::PathBuf;
 
-use crate::errors::default_error_handler;
-use crate::interactor::{DownloadArtifactsInteractor, GetDeviceCatalogInteractor};
+use crate::api::{RemoteArtifact, RemoteArtifacts, RunFilter};
+use crate::artifacts::{ArtifactLayout, ArtifactType, DownloadOptions};
+use crate::errors::{default_error_handler, ExitCode, InputError};
+use crate::filtering::{self, model::Filter};
+use crate::interactor::{
+    DownloadArtifactsInteractor, GetDeviceCatalogInteractor, WaitRunsInteractor,
+};
+use crate::report;
 
 #[derive(Parser)]
 #[command(
@@ -19,7 +33,17 @@ use crate::interactor::{DownloadArtifactsInteractor, GetDeviceCatalogInteractor}
     author,
     version,
     about,
-    arg_required_else_help = true
+    arg_required_else_help = true,
+    after_help = "Exit codes:
+  0   Success
+  1   Test run finished with test failures
+  2   Invalid configuration or input
+  3   Authentication failure
+  4   Network or API failure
+  5   Test run was cancelled
+  6   Test run exceeded --run-timeout
+  10  Unexpected error
+  130 Interrupted by user"
 )]
 pub struct Cli {
     #[command(subcommand)]
@@ -29,8 +53,24 @@ pub struct Cli {
 }
 
 impl Cli {
+    //Runs are checked without the API, the API key of the validate command isn't required
+    fn parser() -> clap::Command {
+        Self::command().mut_subcommand("validate", |validate| {
+            let runs: Vec<String> = validate
+                .get_subcommands()
+                .map(|x| x.get_name().to_owned())
+                .collect();
+            runs.iter().fold(validate, |validate, name| {
+                validate.mut_subcommand(name, |run| {
+                    run.mut_arg("api_key", |arg| arg.required(false).default_value(""))
+                })
+            })
+        })
+    }
+
     pub async fn run() -> Result<()> {
-        let cli = Cli::parse();
+        let cli = Cli::from_arg_matches(&Cli::parser().get_matches())
+            .unwrap_or_else(|error| error.exit());
         simple_logger::SimpleLogger::new()
             .env()
             .with_level(cli.verbose.log_level_filter())
@@ -38,121 +78,132 @@ impl Cli {
             .unwrap();
 
         let result = match cli.command {
-            Some(Commands::Run(args)) => {
-                let run_cmd = args.command;
-                match run_cmd {
-                    RunCommands::Android {
-                        application,
-                        test_application,
-                        os_version,
-                        system_image,
-                        device,
-                        common,
-                        api_args,
-                        flavor,
-                        instrumentation_arg,
-                        retry_args,
-                        analytics_args,
-                        pull_files,
-                        application_bundle,
-                        library_bundle,
-                        profiling_args,
-                        mock_location,
-                    } => {
-                        android::run(
-                            application,
-                            test_application,
-                            os_version,
-                            system_image,
-                            device,
-                            common,
-                            api_args,
-                            flavor,
-                            instrumentation_arg,
-                            retry_args,
-                            analytics_args,
-                            profiling_args,
-                            pull_files,
-                            application_bundle,
-                            library_bundle,
-                            mock_location,
-                        )
-                        .await
-                    }
-                    RunCommands::iOS {
-                        application,
-                        test_application,
-                        os_version,
-                        device,
-                        xcode_version,
-                        common,
-                        api_args,
-                        xctestrun_env,
-                        xctestrun_test_env,
-                        xctestplan_filter_file,
-                        xctestplan_target_name,
-                        retry_args,
-                        analytics_args,
-                        test_timeout_default,
-                        test_timeout_max,
-                        granted_permission,
-                    } => {
-                        ios::run(
-                            application,
-                            test_application,
-                            os_version,
-                            device,
-                            xcode_version,
-                            common,
-                            api_args,
-                            xctestrun_env,
-                            xctestrun_test_env,
-                            xctestplan_filter_file,
-                            xctestplan_target_name,
-                            retry_args,
-                            analytics_args,
-                            test_timeout_default,
-                            test_timeout_max,
-                            granted_permission,
-                        )
-                        .await
-                    }
-                }
-            }
+            Some(Commands::Run(args)) => run(args.command).await,
+            Some(Commands::Validate(args)) => run(args.command.validate_only()).await,
             Some(Commands::Download(args)) => {
                 let interactor = DownloadArtifactsInteractor {};
-                let _ = interactor
+                interactor
                     .execute(
                         &args.api_args.base_url,
                         &args.api_args.api_key,
-                        &args.id,
+                        args.id,
+                        args.latest.then_some(RunFilter {
+                            project: args.project,
+                            branch: args.branch,
+                            name: args.name,
+                            ..Default::default()
+                        }),
                         args.wait,
                         &args.output,
-                        args.glob,
+                        include_globs(args.glob, args.artifact_type),
+                        args.exclude_glob,
+                        args.artifact_download_args.download_options(),
                         args.progress_args.no_progress_bars,
+                        args.polling_args.poll_interval(),
                     )
-                    .await;
-                Ok(true)
+                    .await
+                    .map(|_| true)
+            }
+            Some(Commands::Wait(args)) => {
+                validate::result_file_args(&args.result_file_args)?;
+                let mut ids: Vec<String> = Vec::new();
+                for id in args.id {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+                WaitRunsInteractor {}
+                    .execute(
+                        &args.api_args.base_url,
+                        &args.api_args.api_key,
+                        ids,
+                        &args.output,
+                        args.artifact_download_args.download_options(),
+                        args.progress_args.no_progress_bars,
+                        args.polling_args.poll_interval(),
+                        args.result_file_args.result_file,
+                    )
+                    .await
             }
             Some(Commands::Devices(args)) => {
                 let run_cmd = args.command;
                 let interactor = GetDeviceCatalogInteractor {};
                 match run_cmd {
                     DevicesCommands::Android {
+                        device_class,
                         api_args,
                         progress_args,
-                    } => {
-                        let _ = interactor
-                            .execute(
-                                &api_args.base_url,
-                                &api_args.api_key,
-                                &model::Platform::Android,
-                                progress_args.no_progress_bars,
-                            )
-                            .await;
-                    }
+                    } => interactor
+                        .execute(
+                            &api_args.base_url,
+                            &api_args.api_key,
+                            &model::Platform::Android,
+                            &device_class,
+                            progress_args.no_progress_bars,
+                        )
+                        .await
+                        .map(|_| true),
+                    DevicesCommands::iOS {
+                        device_class,
+                        api_args,
+                        progress_args,
+                    } => interactor
+                        .execute(
+                            &api_args.base_url,
+                            &api_args.api_key,
+                            &model::Platform::iOS,
+                            &device_class,
+                            progress_args.no_progress_bars,
+                        )
+                        .await
+                        .map(|_| true),
                 }
-                Ok(true)
+            }
+            Some(Commands::Report(args)) => match args.command {
+                ReportCommands::JunitMerge { inputs, output } => {
+                    report::junit_merge(inputs, &output).await.map(|_| true)
+                }
+                ReportCommands::Html {
+                    id,
+                    output,
+                    api_args,
+                    progress_args,
+                } => report::html_report(
+                    &api_args.base_url,
+                    &api_args.api_key,
+                    &id,
+                    &output,
+                    progress_args.no_progress_bars,
+                )
+                .await
+                .map(|_| true),
+                ReportCommands::Xcresult {
+                    id,
+                    output,
+                    api_args,
+                    progress_args,
+                } => report::xcresult_report(
+                    &api_args.base_url,
+                    &api_args.api_key,
+                    &id,
+                    &output,
+                    progress_args.no_progress_bars,
+                )
+                .await
+                .map(|_| true),
+                ReportCommands::XcresultMerge { inputs, output } => {
+                    report::xcresult_merge(inputs, &output).await.map(|_| true)
+                }
+                ReportCommands::Coverage {
+                    inputs,
+                    output,
+                    lcov_binary,
+                } => report::coverage_merge(inputs, &output, lcov_binary)
+                    .await
+                    .map(|_| true),
+            },
+            Some(Commands::Doctor(args)) => {
+                doctor::run(&args.base_url, args.api_key.as_deref()).await
             }
             Some(Commands::Completions { shell }) => {
                 let mut app = Self::command();
@@ -164,25 +215,297 @@ impl Cli {
         };
 
         match result {
-            Ok(true) => ::std::process::exit(0),
-            Ok(false) => ::std::process::exit(1),
+            Ok(true) => ::std::process::exit(ExitCode::Success.code()),
+            Ok(false) => ::std::process::exit(ExitCode::TestFailures.code()),
             Err(error) => {
+                let exit_code = ExitCode::from_error(&error);
                 let stderr = std::io::stderr();
                 default_error_handler(error.into(), &mut stderr.lock());
-                ::std::process::exit(1);
+                ::std::process::exit(exit_code.code());
             }
         }
     }
 }
 
+async fn run(command: RunCommands) -> Result<bool> {
+    match command {
+        RunCommands::Android {
+            from_gradle,
+            gradle_variant,
+            application,
+            bundletool_args,
+            additional_apk,
+            test_application,
+            remote_artifact_args,
+            os_version,
+            system_image,
+            device,
+            device_class,
+            network_profile,
+            orientation,
+            common,
+            api_args,
+            flavor,
+            detox_args,
+            locale_args,
+            instrumentation_arg,
+            retry_args,
+            analytics_args,
+            pull_files,
+            push_file,
+            application_bundle,
+            library_bundle,
+            bundle_file,
+            changed_since,
+            module_map,
+            profiling_args,
+            mock_location,
+            use_orchestrator,
+            clear_package_data,
+            emulator_arg,
+            device_ram,
+            device_storage,
+            grant_permission,
+            grant_all_runtime_permissions,
+        } => {
+            android::run(
+                from_gradle,
+                gradle_variant,
+                application,
+                bundletool_args,
+                additional_apk,
+                test_application,
+                remote_artifact_args,
+                os_version.unwrap_or_default(),
+                system_image,
+                device,
+                device_class,
+                network_profile,
+                orientation,
+                common,
+                api_args,
+                flavor,
+                detox_args,
+                locale_args,
+                instrumentation_arg,
+                retry_args,
+                analytics_args,
+                profiling_args,
+                pull_files,
+                push_file,
+                application_bundle,
+                library_bundle,
+                bundle_file,
+                changed_since,
+                module_map,
+                mock_location,
+                use_orchestrator,
+                clear_package_data,
+                emulator_arg,
+                device_ram,
+                device_storage,
+                grant_permission,
+                grant_all_runtime_permissions,
+            )
+            .await
+        }
+        RunCommands::iOS {
+            list_configs: true,
+            format,
+            api_args,
+            ..
+        } => ios::list_configs(&api_args, &format).await.map(|_| true),
+        RunCommands::iOS {
+            from_derived_data,
+            scheme,
+            configuration,
+            application,
+            test_application,
+            xctestrun_args,
+            remote_artifact_args,
+            os_version,
+            device,
+            device_class,
+            network_profile,
+            orientation,
+            simulated_location,
+            xcode_version,
+            flavor,
+            detox_args,
+            locale_args,
+            common,
+            api_args,
+            xctestrun_env,
+            xctestrun_test_env,
+            xctestplan_filter_file,
+            xctestplan_target_name,
+            retry_args,
+            analytics_args,
+            profiling_args,
+            test_timeout_default,
+            test_timeout_max,
+            granted_permission,
+            work_dir,
+            keep_archives,
+            zip_exclude,
+            compression,
+            no_zip_cache,
+            stream_archives,
+            ..
+        } => {
+            ios::run(
+                from_derived_data,
+                scheme,
+                configuration,
+                application,
+                test_application,
+                xctestrun_args,
+                remote_artifact_args,
+                os_version,
+                device,
+                device_class,
+                network_profile,
+                orientation,
+                simulated_location,
+                xcode_version,
+                flavor,
+                detox_args,
+                locale_args,
+                common,
+                api_args,
+                xctestrun_env,
+                xctestrun_test_env,
+                xctestplan_filter_file,
+                xctestplan_target_name,
+                retry_args,
+                analytics_args,
+                profiling_args,
+                test_timeout_default,
+                test_timeout_max,
+                granted_permission,
+                work_dir,
+                keep_archives,
+                zip_exclude,
+                compression,
+                no_zip_cache,
+                stream_archives,
+            )
+            .await
+        }
+        RunCommands::macOS {
+            application,
+            test_application,
+            xctestrun_args,
+            remote_artifact_args,
+            os_version,
+            xcode_version,
+            common,
+            api_args,
+            xctestrun_env,
+            xctestrun_test_env,
+            xctestplan_filter_file,
+            xctestplan_target_name,
+            retry_args,
+            analytics_args,
+            test_timeout_default,
+            test_timeout_max,
+            work_dir,
+            keep_archives,
+            zip_exclude,
+            compression,
+            no_zip_cache,
+            stream_archives,
+        } => {
+            macos::run(
+                application,
+                test_application,
+                xctestrun_args,
+                remote_artifact_args,
+                os_version,
+                xcode_version,
+                common,
+                api_args,
+                xctestrun_env,
+                xctestrun_test_env,
+                xctestplan_filter_file,
+                xctestplan_target_name,
+                retry_args,
+                analytics_args,
+                test_timeout_default,
+                test_timeout_max,
+                work_dir,
+                keep_archives,
+                zip_exclude,
+                compression,
+                no_zip_cache,
+                stream_archives,
+            )
+            .await
+        }
+        RunCommands::Web {
+            test_bundle,
+            flavor,
+            browser,
+            os,
+            env,
+            common,
+            api_args,
+            retry_args,
+            analytics_args,
+        } => {
+            web::run(
+                test_bundle,
+                flavor,
+                browser,
+                os,
+                env,
+                common,
+                api_args,
+                retry_args,
+                analytics_args,
+            )
+            .await
+        }
+        RunCommands::Matrix {
+            config,
+            result_file_args,
+            api_args,
+            validate_only,
+        } => {
+            //Runs of the matrix are dispatched by `run` again
+            Box::pin(matrix::run(
+                config,
+                result_file_args,
+                api_args.base_url,
+                api_args.api_key,
+                validate_only,
+            ))
+            .await
+        }
+    }
+}
+
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     #[clap(about = "Submit a test run")]
     Run(RunArgs),
+    #[clap(
+        about = "Check the arguments of a test run locally without submitting it and report every failed check. Accepts the arguments of the run command, the API key is optional"
+    )]
+    Validate(RunArgs),
     #[clap(about = "Get supported devices")]
     Devices(DevicesArgs),
     #[clap(about = "Download artifacts from a previous test run")]
     Download(DownloadArgs),
+    #[clap(about = "Process results of test runs locally")]
+    Report(ReportArgs),
+    #[clap(about = "Wait for one or more test runs to finish")]
+    Wait(WaitArgs),
+    #[clap(
+        about = "Check the API key, connectivity, clock, disk space and write permissions of this environment"
+    )]
+    Doctor(DoctorArgs),
     #[clap(about = "Output shell completion code for the specified shell (bash, zsh, fish)")]
     Completions { shell: clap_complete::Shell },
 }
@@ -205,18 +528,97 @@ struct CommonRunArgs {
 
     #[arg(
         long,
-        help = "Test filters supplied as a YAML file following the schema at https://docs.marathonlabs.io/runner/configuration/filtering/#filtering-logic. 
+        help = "Test filters supplied as a YAML or JSON file following the schema at https://docs.marathonlabs.io/runner/configuration/filtering/#filtering-logic. 
 For iOS see also https://docs.marathonlabs.io/runner/next/ios#test-plans.
 Please be aware that if you use the 'annotation' filter type on Android, you should add the 'com.malinskiy.adam:android-junit4-test-annotation-producer:<version>' test dependency to parse custom test annotations."
     )]
     filter_file: Option<PathBuf>,
 
+    #[command(flatten)]
+    test_filter_args: TestFilterArgs,
+
+    #[command(flatten)]
+    sharding_args: ShardingArgs,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Fail instead of warning when the filters match none of the tests in the test application. Only Android test APKs are checked locally"
+    )]
+    strict_filter: bool,
+
     #[arg(
         long,
         help = "Wait for test run to finish if true, exits after triggering a run if false"
     )]
     wait: Option<bool>,
 
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Print test progress (started, passed, failed) as it happens while waiting for the test run to finish"
+    )]
+    stream_logs: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Cancel the test run in Marathon Cloud without asking when interrupted (Ctrl+C) while waiting for it to finish"
+    )]
+    cancel_on_interrupt: bool,
+
+    #[arg(
+        long,
+        value_parser = humantime::parse_duration,
+        help = "Maximum wall-clock time to wait for the test run, e.g. 45m or 1h30m. When exceeded, the test run is cancelled and available artifacts are downloaded"
+    )]
+    run_timeout: Option<std::time::Duration>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Show a live view of the test run (devices, per-test status, failures so far, elapsed and billable time) while waiting for it to finish. Falls back to regular output when not running in a terminal"
+    )]
+    tui: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Print the status of the test run as a line of JSON at every check while waiting for it, e.g. {\"id\":\"...\",\"state\":\"running\",\"passed\":3,\"failed\":0,\"ignored\":0,\"total\":10,\"eta_seconds\":120}. The estimate is based on previous runs of the project and branch. Replaces progress bars and --tui"
+    )]
+    ndjson_progress: bool,
+
+    #[arg(
+        long,
+        default_value_t = crate::api::DEFAULT_UPLOAD_RETRIES,
+        help = "Number of times a failed upload is retried on network errors and transient server errors"
+    )]
+    upload_retries: u32,
+
+    #[arg(
+        long,
+        default_value = "0s",
+        value_parser = humantime::parse_duration,
+        help = "How long uploaded applications are remembered locally so that submitting identical files again skips the upload, e.g. 12h. The cache is disabled by default"
+    )]
+    upload_cache_ttl: std::time::Duration,
+
+    #[arg(
+        value_enum,
+        long,
+        default_value_t = crate::hash::HashAlgorithm::Md5,
+        help = "Checksum used to verify the integrity of uploads when supported by the server. Only md5 lets the server skip uploads of files it already has"
+    )]
+    upload_checksum: crate::hash::HashAlgorithm,
+
+    #[arg(
+        long,
+        default_value = "4G",
+        value_parser = validate::megabytes,
+        help = "Largest file which is uploaded, in megabytes unless suffixed with M or G, e.g. 4G. The default is the documented limit of Marathon Cloud storage, the server may enforce a lower one"
+    )]
+    max_upload_size: u32,
+
     #[arg(
         long,
         help = "Name for run, for example it could be description of commit"
@@ -247,9 +649,15 @@ Please be aware that if you use the 'annotation' filter type on Android, you sho
     )]
     code_coverage: Option<bool>,
 
+    #[command(flatten)]
+    artifact_download_args: ArtifactDownloadArgs,
+
     #[command(flatten)]
     progress_args: ProgressArgs,
 
+    #[command(flatten)]
+    polling_args: PollingArgs,
+
     #[command(flatten)]
     result_file_args: ResultFileArgs,
 
@@ -262,6 +670,94 @@ Warning: Using this argument may BREAK the 15-minute run promise!"
 
     #[arg(long, help = "The unique identifier (slug) for the project")]
     project: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Reuse the results of a previous passed test run with identical binaries (md5), devices, OS versions and filters instead of submitting a new one. Runs submitted with this flag are recorded for later lookups. Ignored for binaries fetched by Marathon Cloud, e.g. with --application-url"
+    )]
+    skip_if_passed: bool,
+
+    #[arg(
+        long,
+        help = "YAML file with timeouts in seconds of the tests matching a pattern, overriding the default test timeout. * matches any characters, the first matching pattern applies.
+Example:
+overrides:
+  - pattern: com.example.video.*
+    timeout: 600"
+    )]
+    test_timeout_overrides: Option<PathBuf>,
+
+    #[arg(
+        value_enum,
+        long,
+        help = "When screen recordings of tests are captured. Recording only failures reduces the volume of artifacts"
+    )]
+    record_video: Option<model::VideoPolicy>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Capture a screenshot of the device when a test fails"
+    )]
+    screenshot_on_failure: bool,
+
+    #[arg(
+        long,
+        help = "Zip archive unpacked into the data directory of the application before the tests, e.g. with pre-populated databases. Supported by Android, iOS and macOS runs"
+    )]
+    app_data: Option<PathBuf>,
+
+    //Set by the validate command
+    #[arg(skip)]
+    validate_only: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct WaitArgs {
+    #[arg(
+        long,
+        required = true,
+        help = "Test run id. Can be repeated to wait for several test runs concurrently, example: --id A --id B"
+    )]
+    id: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Output folder for the artifacts of the test runs, every test run is downloaded into a subfolder named after its id"
+    )]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    artifact_download_args: ArtifactDownloadArgs,
+
+    #[command(flatten)]
+    api_args: ApiArgs,
+
+    #[command(flatten)]
+    progress_args: ProgressArgs,
+
+    #[command(flatten)]
+    polling_args: PollingArgs,
+
+    #[command(flatten)]
+    result_file_args: ResultFileArgs,
+}
+
+//The API key is optional, a missing key is reported as a failed check
+#[derive(Debug, Args)]
+struct DoctorArgs {
+    #[arg(long, env("MARATHON_CLOUD_API_KEY"), help = "Marathon Cloud API key")]
+    api_key: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "https://cloud.marathonlabs.io/api",
+        help = "Base url for Marathon Cloud API"
+    )]
+    base_url: String,
 }
 
 #[derive(Debug, Args)]
@@ -270,8 +766,29 @@ struct DownloadArgs {
     #[arg(short, long, help = "Output folder for test run results")]
     output: PathBuf,
 
-    #[arg(long, help = "Test run id")]
-    id: String,
+    #[arg(long, required_unless_present = "latest", help = "Test run id")]
+    id: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "id",
+        help = "Download the most recent completed test run instead of passing --id. Narrow it down with --project, --branch and --name"
+    )]
+    latest: bool,
+
+    #[arg(
+        long,
+        requires = "latest",
+        help = "Project slug of the run for --latest"
+    )]
+    project: Option<String>,
+
+    #[arg(long, requires = "latest", help = "Branch of the run for --latest")]
+    branch: Option<String>,
+
+    #[arg(long, requires = "latest", help = "Name of the run for --latest")]
+    name: Option<String>,
 
     #[arg(
         long,
@@ -282,9 +799,26 @@ struct DownloadArgs {
 
     #[arg(
         long,
-        help = "Only files matching this glob will be downloaded, i.e. 'tests/**' will download only the JUnit xml files"
+        help = "Only files matching this glob will be downloaded, i.e. 'tests/**' will download only the JUnit xml files. Can be repeated to download files matching any of the globs, example: --glob 'tests/**' --glob 'report/allure-results/**'"
+    )]
+    glob: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        help = "Only download artifacts of these types, combined with --glob. Example: --artifact-type junit,allure"
+    )]
+    artifact_type: Option<Vec<ArtifactType>>,
+
+    #[arg(
+        long,
+        help = "Files matching this glob will not be downloaded, even if they match --glob. Can be repeated, example: --exclude-glob 'video/**' --exclude-glob '**/*.log'"
     )]
-    glob: Option<String>,
+    exclude_glob: Option<Vec<String>>,
+
+    #[command(flatten)]
+    artifact_download_args: ArtifactDownloadArgs,
 
     #[command(flatten)]
     api_args: ApiArgs,
@@ -292,10 +826,98 @@ struct DownloadArgs {
     #[command(flatten)]
     progress_args: ProgressArgs,
 
+    #[command(flatten)]
+    polling_args: PollingArgs,
+
     #[command(flatten)]
     result_file_args: ResultFileArgs,
 }
 
+#[derive(Debug, clap::Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ReportArgs {
+    #[command(subcommand)]
+    command: ReportCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum ReportCommands {
+    #[clap(
+        about = "Merge JUnit reports of one or more runs into a single report. Retried tests are reported once, as passed if any attempt passed"
+    )]
+    JunitMerge {
+        #[arg(
+            required = true,
+            help = "JUnit xml files or folders to search for them, e.g. output folders of downloaded runs"
+        )]
+        inputs: Vec<PathBuf>,
+
+        #[arg(short, long, help = "Path of the merged JUnit report")]
+        output: PathBuf,
+    },
+    #[clap(
+        about = "Render a single-file HTML summary of a test run with totals, slowest tests and failures"
+    )]
+    Html {
+        #[arg(long, help = "Test run id")]
+        id: String,
+
+        #[arg(short, long, help = "Path of the HTML file")]
+        output: PathBuf,
+
+        #[command(flatten)]
+        api_args: ApiArgs,
+
+        #[command(flatten)]
+        progress_args: ProgressArgs,
+    },
+    #[clap(
+        about = "Download the per-device result bundles of an iOS test run and merge them into a single .xcresult. Requires Xcode"
+    )]
+    Xcresult {
+        #[arg(long, help = "Test run id")]
+        id: String,
+
+        #[arg(short, long, help = "Path of the merged .xcresult bundle")]
+        output: PathBuf,
+
+        #[command(flatten)]
+        api_args: ApiArgs,
+
+        #[command(flatten)]
+        progress_args: ProgressArgs,
+    },
+    #[clap(
+        about = "Merge local .xcresult bundles, e.g. downloaded with --artifact-type xcresult, into a single bundle. Requires Xcode"
+    )]
+    XcresultMerge {
+        #[arg(
+            required = true,
+            help = ".xcresult bundles or folders to search for them"
+        )]
+        inputs: Vec<PathBuf>,
+
+        #[arg(short, long, help = "Path of the merged .xcresult bundle")]
+        output: PathBuf,
+    },
+    #[clap(
+        about = "Merge per-device coverage files, e.g. downloaded with --artifact-type coverage, into a single file. Supports JaCoCo .exec, LLVM .profraw/.profdata and lcov .info files"
+    )]
+    Coverage {
+        #[arg(required = true, help = "Coverage files or folders to search for them")]
+        inputs: Vec<PathBuf>,
+
+        #[arg(short, long, help = "Path of the merged coverage file")]
+        output: PathBuf,
+
+        #[arg(
+            long,
+            help = "Instrumented binary of the iOS application. When set, merged LLVM profiles are exported as lcov. Requires Xcode"
+        )]
+        lcov_binary: Option<PathBuf>,
+    },
+}
+
 #[derive(Debug, clap::Parser)]
 #[command(args_conflicts_with_subcommands = true)]
 struct DevicesArgs {
@@ -307,6 +929,29 @@ struct DevicesArgs {
 enum DevicesCommands {
     #[clap(about = "Print supported Android devices")]
     Android {
+        #[arg(
+            value_enum,
+            long,
+            default_value_t = model::DeviceClass::Simulator,
+            help = "Print emulators or real device models"
+        )]
+        device_class: model::DeviceClass,
+        #[command(flatten)]
+        api_args: ApiArgs,
+        #[command(flatten)]
+        progress_args: ProgressArgs,
+    },
+    #[allow(non_camel_case_types)]
+    #[command(name = "ios")]
+    #[clap(about = "Print supported iOS devices")]
+    iOS {
+        #[arg(
+            value_enum,
+            long,
+            default_value_t = model::DeviceClass::Simulator,
+            help = "Print simulator configurations or real device models"
+        )]
+        device_class: model::DeviceClass,
         #[command(flatten)]
         api_args: ApiArgs,
         #[command(flatten)]
@@ -314,7 +959,7 @@ enum DevicesCommands {
     },
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Clone)]
 #[command(args_conflicts_with_subcommands = true)]
 struct ApiArgs {
     #[arg(long, env("MARATHON_CLOUD_API_KEY"), help = "Marathon Cloud API key")]
@@ -328,7 +973,7 @@ struct ApiArgs {
     base_url: String,
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Clone)]
 #[command(args_conflicts_with_subcommands = true)]
 pub(crate) struct RetryArgs {
     #[arg(
@@ -352,6 +997,20 @@ pub(crate) struct RetryArgs {
 
     #[arg(long, default_value_t = false, help = "Disable all retries")]
     no_retries: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "no_retries",
+        help = "YAML file with retry quotas of the tests matching a pattern, overriding the quotas above. * matches any characters, the first matching pattern applies.
+Example:
+overrides:
+  - pattern: com.example.unit.*
+    preventive: 0
+    reactive: 0
+  - pattern: com.example.e2e.*
+    reactive: 3"
+    )]
+    retry_policy: Option<PathBuf>,
 }
 
 impl RetryArgs {
@@ -365,11 +1024,12 @@ impl RetryArgs {
             retry_quota_test_preventive,
             retry_quota_test_reactive,
             no_retries: false,
+            retry_policy: None,
         }
     }
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Args, Clone)]
 #[command(args_conflicts_with_subcommands = true)]
 struct AnalyticsArgs {
     #[arg(
@@ -382,7 +1042,11 @@ struct AnalyticsArgs {
 #[derive(Debug, Args)]
 #[command(args_conflicts_with_subcommands = true)]
 struct ProfilingArgs {
-    #[arg(long, default_value_t = false, help = "Profile tests")]
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Profile tests. Results are downloaded with the other artifacts, see --artifact-type profiling of the download command"
+    )]
     profiling: bool,
 }
 
@@ -395,25 +1059,365 @@ struct ProgressArgs {
 
 #[derive(Debug, Args, Clone)]
 #[command(args_conflicts_with_subcommands = true)]
-struct ResultFileArgs {
+struct PollingArgs {
     #[arg(
         long,
-        help = "Result file path in a machine-readable format. You can specify the format via extension [yaml,json]"
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Initial interval in seconds between test run status checks. The interval grows exponentially with jitter up to 60 seconds"
     )]
-    result_file: Option<PathBuf>,
+    poll_interval: u64,
 }
 
-#[derive(Debug, Subcommand)]
-enum RunCommands {
-    #[clap(about = "Run tests for Android")]
-    Android {
-        #[arg(
+impl PollingArgs {
+    fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.poll_interval)
+    }
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct RemoteArtifactArgs {
+    #[arg(
+        long,
+        conflicts_with_all = &["application", "application_run_id"],
+        value_parser = validate::artifact_url,
+        help = "Application url which Marathon Cloud downloads instead of uploading a local file, example: https://example.com/sample.apk"
+    )]
+    application_url: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "application",
+        help = "Reuse the application uploaded for a previous test run with this id instead of uploading a local file"
+    )]
+    application_run_id: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = &["test_application", "test_application_run_id"],
+        value_parser = validate::artifact_url,
+        help = "Test application url which Marathon Cloud downloads instead of uploading a local file, example: https://example.com/testSample.apk"
+    )]
+    test_application_url: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "test_application",
+        help = "Reuse the test application uploaded for a previous test run with this id instead of uploading a local file"
+    )]
+    test_application_run_id: Option<String>,
+}
+
+impl RemoteArtifactArgs {
+    fn remote_artifacts(self) -> RemoteArtifacts {
+        RemoteArtifacts {
+            application: RemoteArtifact::from_args(self.application_url, self.application_run_id),
+            test_application: RemoteArtifact::from_args(
+                self.test_application_url,
+                self.test_application_run_id,
+            ),
+        }
+    }
+
+    fn has_application(&self) -> bool {
+        self.application_url.is_some() || self.application_run_id.is_some()
+    }
+
+    fn has_test_application(&self) -> bool {
+        self.test_application_url.is_some() || self.test_application_run_id.is_some()
+    }
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct XctestrunArgs {
+    #[arg(
+        long,
+        conflicts_with_all = &["application", "test_application", "application_url", "application_run_id", "test_application_url", "test_application_run_id"],
+        help = "xctestrun file generated by 'xcodebuild build-for-testing', used instead of --application and --test-application. Example: Build/Products/sample-app_iphonesimulator17.5-arm64.xctestrun"
+    )]
+    xctestrun: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "xctestrun",
+        help = "Products directory the paths of --xctestrun are relative to (__TESTROOT__), the folder containing the xctestrun file by default"
+    )]
+    xctestrun_products_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "xctestrun",
+        help = "Test target of --xctestrun to run, required if the file has multiple test targets"
+    )]
+    xctestrun_target_name: Option<String>,
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct BundletoolArgs {
+    #[arg(
+        long,
+        help = "bundletool jar used to convert an .aab --application into a universal APK, bundletool on the PATH by default"
+    )]
+    bundletool: Option<PathBuf>,
+
+    #[arg(
+        long = "bundletool-ks",
+        help = "Keystore to sign the universal APK of an .aab --application with, the debug keystore by default"
+    )]
+    keystore: Option<PathBuf>,
+
+    #[arg(
+        long = "bundletool-ks-pass",
+        requires = "keystore",
+        help = "Password of --bundletool-ks in bundletool format, example: pass:android or file:/path/to/password"
+    )]
+    keystore_password: Option<String>,
+
+    #[arg(
+        long = "bundletool-ks-key-alias",
+        requires = "keystore",
+        help = "Key alias in --bundletool-ks"
+    )]
+    key_alias: Option<String>,
+
+    #[arg(
+        long = "bundletool-key-pass",
+        requires = "keystore",
+        help = "Password of the key in bundletool format, example: pass:android"
+    )]
+    key_password: Option<String>,
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct DetoxArgs {
+    #[arg(
+        long,
+        help = "Detox configuration file for --flavor js-detox, .detoxrc.json or package.json with a detox section. Example: .detoxrc.json"
+    )]
+    detox_config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "detox_config",
+        help = "Configuration of --detox-config to run, required if the file has multiple configurations for the platform"
+    )]
+    detox_configuration: Option<String>,
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct LocaleArgs {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = validate::language,
+        help = "Language of the devices, ISO 639 code, example: de. Can be repeated or comma-separated to submit a run per language, which are waited for concurrently. Outputs of the runs are downloaded into subfolders named after the language and --result-file contains the results of all runs then"
+    )]
+    language: Vec<String>,
+
+    #[arg(
+        long,
+        value_parser = validate::country,
+        help = "Country (region) of the devices, ISO 3166-1 alpha-2 code, example: DE"
+    )]
+    country: Option<String>,
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ArtifactDownloadArgs {
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Only download files of failed tests and the JUnit reports. --glob and --exclude-glob apply to both"
+    )]
+    only_failed: bool,
+
+    #[arg(
+        long,
+        help = "Number of artifacts downloaded in parallel, one per CPU by default. Raise it on fast networks, lower it to throttle downloads"
+    )]
+    download_concurrency: Option<NonZeroUsize>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Keep downloading the remaining artifacts when some of them fail. The command still fails and lists the failed files at the end"
+    )]
+    keep_going: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "path_template",
+        help = "Put all downloaded files directly into the output folder. Files sharing a name keep their remote path joined with '_'"
+    )]
+    flatten: bool,
+
+    #[arg(
+        long,
+        value_parser = validate::path_template,
+        help = "Layout of downloaded files inside the output folder. Supported placeholders: {path}, {dir}, {filename}, {type}, {device}, {test}, example: --path-template '{device}/{test}/{filename}'"
+    )]
+    path_template: Option<String>,
+}
+
+impl ArtifactDownloadArgs {
+    fn download_options(self) -> DownloadOptions {
+        DownloadOptions {
+            only_failed: self.only_failed,
+            concurrency: self.download_concurrency,
+            keep_going: self.keep_going,
+            layout: match (self.flatten, self.path_template) {
+                (_, Some(template)) => ArtifactLayout::Template(template),
+                (true, None) => ArtifactLayout::Flat,
+                (false, None) => ArtifactLayout::Remote,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct TestFilterArgs {
+    #[arg(
+        long = "test",
+        value_name = "TEST",
+        help = "Run only this test, e.g. com.example.MainTest#testLogin. Can be repeated. Tests selected with --test, --class, --package and --annotation are combined, and added to the allowlist of --filter-file if any"
+    )]
+    tests: Vec<String>,
+
+    #[arg(
+        long = "class",
+        value_name = "CLASS",
+        help = "Run only tests of this fully qualified class, e.g. com.example.MainTest. Can be repeated"
+    )]
+    classes: Vec<String>,
+
+    #[arg(
+        long = "package",
+        value_name = "PACKAGE",
+        help = "Run only tests in this package, e.g. com.example.login. Can be repeated"
+    )]
+    packages: Vec<String>,
+
+    #[arg(
+        long = "annotation",
+        value_name = "ANNOTATION",
+        help = "Run only tests with this annotation, e.g. com.example.Smoke. Can be repeated"
+    )]
+    annotations: Vec<String>,
+}
+
+impl TestFilterArgs {
+    fn filter(self) -> Option<Filter> {
+        filtering::convert::inline_filter(self.tests, self.classes, self.packages, self.annotations)
+    }
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ShardingArgs {
+    #[arg(
+        long,
+        requires = "shard_count",
+        help = "Run only the shard with this index, counting from 0. Use with --shard-count to split a test suite across several CI jobs, e.g. --shard-index 0 --shard-count 4"
+    )]
+    shard_index: Option<u32>,
+
+    #[arg(
+        long,
+        requires = "shard_index",
+        help = "Number of shards the test suite is split into"
+    )]
+    shard_count: Option<NonZeroU32>,
+}
+
+impl ShardingArgs {
+    fn filter(&self) -> Result<Option<Filter>> {
+        match (self.shard_index, self.shard_count) {
+            (Some(index), Some(count)) => {
+                if index >= count.get() {
+                    return Err(InputError::InvalidShard {
+                        index,
+                        count: count.get(),
+                    })?;
+                }
+                Ok(Some(filtering::convert::fragmentation_filter(
+                    index,
+                    count.get(),
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+//Artifact types are shorthands for the globs of their remote folders
+fn include_globs(
+    globs: Option<Vec<String>>,
+    artifact_types: Option<Vec<ArtifactType>>,
+) -> Option<Vec<String>> {
+    if globs.is_none() && artifact_types.is_none() {
+        return None;
+    }
+    let mut include = globs.unwrap_or_default();
+    for artifact_type in artifact_types.unwrap_or_default() {
+        include.extend(artifact_type.globs().iter().map(|glob| glob.to_string()));
+    }
+    Some(include)
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ResultFileArgs {
+    #[arg(
+        long,
+        help = "Result file path in a machine-readable format. You can specify the format via extension [yaml,json]"
+    )]
+    result_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum RunCommands {
+    #[clap(about = "Run tests for Android")]
+    Android {
+        #[arg(
+            long,
+            conflicts_with_all = &["application", "test_application", "application_bundle", "library_bundle", "bundle_file"],
+            help = "Gradle module directory, example: ./app. The APK and the androidTest APK of --gradle-variant are taken from the build outputs of the module, only the androidTest APK for library modules"
+        )]
+        from_gradle: Option<PathBuf>,
+
+        #[arg(
+            long,
+            default_value = "debug",
+            requires = "from_gradle",
+            help = "Build variant of --from-gradle, example: freeDebug"
+        )]
+        gradle_variant: String,
+
+        #[arg(
             short,
             long,
-            help = "application filepath, example: /home/user/workspace/sample.apk"
+            help = "application filepath, example: /home/user/workspace/sample.apk. An Android App Bundle (.aab) is converted into a universal APK with bundletool, the splits of an APK set (.apks) are installed together"
         )]
         application: Option<PathBuf>,
 
+        #[arg(
+            long,
+            help = "APK installed together with --application, e.g. a split APK or dynamic feature module. Can be repeated"
+        )]
+        additional_apk: Option<Vec<PathBuf>>,
+
+        #[command(flatten)]
+        bundletool_args: BundletoolArgs,
+
         #[arg(
             short,
             long,
@@ -421,8 +1425,16 @@ enum RunCommands {
         )]
         test_application: Option<PathBuf>,
 
-        #[arg(value_enum, long, help = "OS version")]
-        os_version: Option<android::OsVersion>,
+        #[command(flatten)]
+        remote_artifact_args: RemoteArtifactArgs,
+
+        #[arg(
+            value_enum,
+            long,
+            value_delimiter = ',',
+            help = "OS version. Can be repeated or comma-separated to submit a run per OS version, which are waited for concurrently. Outputs of the runs are downloaded into subfolders named after the OS version and --result-file contains the results of all runs then"
+        )]
+        os_version: Option<Vec<android::OsVersion>>,
 
         #[arg(value_enum, long, help = "Runtime system image")]
         system_image: Option<android::SystemImage>,
@@ -430,13 +1442,41 @@ enum RunCommands {
         #[arg(
             value_enum,
             long,
-            help = "Device type id. Use `marathon-cloud devices android` to get a list of supported devices"
+            value_delimiter = ',',
+            help = "Device type id, or the model of a real device with --device-class real. Use `marathon-cloud devices android` to get a list of supported devices. Can be repeated or comma-separated to cover several device types in one run, example: --device phone,tv"
+        )]
+        device: Option<Vec<String>>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Run on emulators or real devices, simulator by default. Mock location isn't supported on real devices"
         )]
-        device: Option<String>,
+        device_class: Option<model::DeviceClass>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Network shaping of the devices, full speed by default. offline-segments interrupts the connectivity periodically"
+        )]
+        network_profile: Option<model::NetworkProfile>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Screen orientation of the devices, portrait by default"
+        )]
+        orientation: Option<model::Orientation>,
 
         #[arg(value_enum, long, help = "Test flavor")]
         flavor: Option<android::Flavor>,
 
+        #[command(flatten)]
+        detox_args: DetoxArgs,
+
+        #[command(flatten)]
+        locale_args: LocaleArgs,
+
         #[command(flatten)]
         common: CommonRunArgs,
 
@@ -464,6 +1504,14 @@ Note: Files with the same name and path from different devices may overwrite eac
         )]
         pull_files: Option<Vec<String>>,
 
+        #[arg(
+            long,
+            help = "Push a file to the devices before the test run, e.g. images, OBB expansion files or fixture databases. Can be repeated.
+The format is 'LOCAL_PATH:DEVICE_PATH' where DEVICE_PATH is an absolute path on the device.
+Example: 'fixtures/image.png:/sdcard/Pictures/image.png'"
+        )]
+        push_file: Option<Vec<String>>,
+
         #[arg(
             long,
             conflicts_with_all = &["application", "test_application"],
@@ -478,43 +1526,223 @@ Example: '--application-bundle apks/feature1-app-debug.apk,apks/feature1-app-deb
             conflicts_with_all = &["application", "test_application"],
             help = "Library bundle containing the library test apk. Library testing requires only Test APK.
 The format is '<test_apk_path>'.
-Example: '--library-bundle apks/library1-debug-androidTest.apk --library-bundle apks/library2-debug-androidTest.apk'"
+Example: '--library-bundle apks/library1-debug-androidTest.apk --library-bundle apks/library2-debug-androidTest.apk'
+Globs are expanded in path order, '*' matches within a directory and '**' across directories.
+Example: '--library-bundle 'modules/**/outputs/apk/androidTest/debug/*.apk'"
         )]
         library_bundle: Option<Vec<PathBuf>>,
 
+        #[arg(
+            long,
+            conflicts_with_all = &["application", "test_application"],
+            help = "YAML file listing application-bundles (application, test-application) and library-bundles (test-application), each with an optional filter-file and flavor. Relative paths are resolved against the directory of the file, test-application of library bundles can be a glob.
+Example:
+application-bundles:
+  - application: apks/feature1-app-debug.apk
+    test-application: apks/feature1-app-debug-androidTest.apk
+    filter-file: filters/feature1.yaml
+library-bundles:
+  - test-application: apks/library1-debug-androidTest.apk"
+        )]
+        bundle_file: Option<PathBuf>,
+
+        #[arg(
+            long,
+            requires = "module_map",
+            help = "Submit only the application and library bundles of the modules changed since this git revision, example: origin/main. Changes are taken from the merge base of the revision and HEAD up to the working tree, including untracked files which are not ignored"
+        )]
+        changed_since: Option<String>,
+
+        #[arg(
+            long,
+            requires = "changed_since",
+            help = "YAML file mapping the directory of every module to the bundles built from it, for --changed-since. Relative paths are resolved against the directory of the file.
+Example:
+modules:
+  feature/login:
+    - feature/login/build/outputs/apk/androidTest/debug/login-debug-androidTest.apk"
+        )]
+        module_map: Option<PathBuf>,
+
         #[arg(
             long,
             default_value_t = false,
             help = "Allow mock location access for application"
         )]
         mock_location: bool,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Run every test in its own instrumentation with Android Test Orchestrator. Only supported by the native flavor"
+        )]
+        use_orchestrator: bool,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            requires = "use_orchestrator",
+            help = "Clear the data of the application after every test (clearPackageData of Android Test Orchestrator)"
+        )]
+        clear_package_data: bool,
+
+        #[arg(
+            long,
+            value_parser = android::emulator::parse,
+            help = "Emulator launch option in the form NAME[=VALUE], can be repeated. Supported options are camera-back, camera-front, dns-server, gpu, netdelay, netspeed, timezone, no-audio, no-boot-anim and no-snapshot.
+Example: '--emulator-arg gpu=swiftshader_indirect --emulator-arg dns-server=8.8.8.8'"
+        )]
+        emulator_arg: Vec<android::emulator::EmulatorArg>,
+
+        #[arg(
+            long,
+            value_parser = validate::megabytes,
+            help = "RAM of the emulators, in megabytes unless suffixed with M or G, e.g. 4G. Limited by the device, see `marathon-cloud devices android`"
+        )]
+        device_ram: Option<u32>,
+
+        #[arg(
+            long,
+            value_parser = validate::megabytes,
+            help = "Storage of the emulators, in megabytes unless suffixed with M or G, e.g. 8G. Limited by the device, see `marathon-cloud devices android`"
+        )]
+        device_storage: Option<u32>,
+
+        #[arg(
+            long,
+            value_parser = android::permissions::parse,
+            conflicts_with = "grant_all_runtime_permissions",
+            help = "Grant a runtime permission to the application before the tests, can be repeated. The android.permission. prefix is optional.
+Example: '--grant-permission android.permission.CAMERA --grant-permission POST_NOTIFICATIONS'"
+        )]
+        grant_permission: Option<Vec<String>>,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Grant every runtime permission requested by the application before the tests"
+        )]
+        grant_all_runtime_permissions: bool,
     },
     #[allow(non_camel_case_types)]
     #[command(name = "ios")]
-    #[clap(about = "Run tests for iOS")]
+    #[clap(about = "Run tests for iOS, tvOS and watchOS simulators")]
     iOS {
+        #[arg(
+            long,
+            num_args = 0..=1,
+            default_missing_value = ios::DEFAULT_DERIVED_DATA,
+            conflicts_with_all = &["application", "test_application", "xctestrun"],
+            help = "Use the products of the most recent build in DerivedData, ~/Library/Developer/Xcode/DerivedData by default. The xctestrun file of --scheme and --configuration is preferred, otherwise the UI test runner and the application are used"
+        )]
+        from_derived_data: Option<String>,
+
+        #[arg(
+            long,
+            requires = "from_derived_data",
+            help = "Scheme of --from-derived-data, the only UI test runner is used by default"
+        )]
+        scheme: Option<String>,
+
+        #[arg(
+            long,
+            default_value = "Debug",
+            requires = "from_derived_data",
+            help = "Build configuration of --from-derived-data, example: Release"
+        )]
+        configuration: String,
+
         #[arg(
             short,
             long,
-            help = "application filepath, example: /home/user/workspace/sample.zip"
+            required_unless_present_any = &["application_url", "application_run_id", "xctestrun", "from_derived_data", "list_configs"],
+            help = "application filepath, example: /home/user/workspace/sample.zip. The application of an .xcarchive is used as well"
         )]
-        application: PathBuf,
+        application: Option<PathBuf>,
 
         #[arg(
             short,
             long,
+            required_unless_present_any = &["test_application_url", "test_application_run_id", "xctestrun", "from_derived_data", "list_configs"],
             help = "test application filepath, example: /home/user/workspace/sampleUITests-Runner.zip"
         )]
-        test_application: PathBuf,
+        test_application: Option<PathBuf>,
 
-        #[arg(value_enum, long, help = "iOS runtime version")]
-        os_version: Option<ios::OsVersion>,
+        #[command(flatten)]
+        xctestrun_args: XctestrunArgs,
 
-        #[arg(value_enum, long, help = "Device type")]
-        device: Option<ios::IosDevice>,
+        #[command(flatten)]
+        remote_artifact_args: RemoteArtifactArgs,
+
+        #[arg(
+            long,
+            help = "Runtime version of the simulator, example: 17.5 for iOS or tvOS, 10.5 for watchOS. Supported combinations of device, Xcode and runtime are fetched from Marathon Cloud, see --list-configs"
+        )]
+        os_version: Option<String>,
+
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Device type, example: iPhone-15, iPad-Air-11-inch-M2, Apple-TV-4K-3rd-generation or Apple-Watch-Series-9-45mm. With --device-class real the model of a real device, see `marathon-cloud devices ios --device-class real`. Can be repeated or comma-separated to cover several device types with the same Xcode and runtime in one run"
+        )]
+        device: Option<Vec<String>>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Run on simulators or real devices, simulator by default. Granting permissions isn't supported on real devices"
+        )]
+        device_class: Option<model::DeviceClass>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Network shaping of the devices, full speed by default. offline-segments interrupts the connectivity periodically"
+        )]
+        network_profile: Option<model::NetworkProfile>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Screen orientation of the devices, portrait by default"
+        )]
+        orientation: Option<model::Orientation>,
+
+        #[arg(
+            long,
+            value_parser = validate::location,
+            allow_hyphen_values = true,
+            help = "Simulated GPS location of the simulators as LATITUDE,LONGITUDE in decimal degrees, e.g. 52.52,13.405"
+        )]
+        simulated_location: Option<model::Location>,
+
+        #[arg(long, help = "Xcode version, example: 15.4")]
+        xcode_version: Option<String>,
+
+        #[arg(value_enum, long, help = "Test flavor")]
+        flavor: Option<ios::Flavor>,
+
+        #[command(flatten)]
+        detox_args: DetoxArgs,
+
+        #[command(flatten)]
+        locale_args: LocaleArgs,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Print the supported combinations of --device, --xcode-version and --os-version instead of running tests"
+        )]
+        list_configs: bool,
 
-        #[arg(value_enum, long, help = "Xcode version")]
-        xcode_version: Option<ios::XcodeVersion>,
+        #[arg(
+            value_enum,
+            long,
+            requires = "list_configs",
+            default_value_t = model::OutputFormat::Text,
+            help = "Output format of --list-configs"
+        )]
+        format: model::OutputFormat,
 
         #[command(flatten)]
         common: CommonRunArgs,
@@ -528,6 +1756,9 @@ Example: '--library-bundle apks/library1-debug-androidTest.apk --library-bundle
         #[command(flatten)]
         analytics_args: AnalyticsArgs,
 
+        #[command(flatten)]
+        profiling_args: ProfilingArgs,
+
         #[arg(
             long,
             help = "xctestrun environment variable (EnvironmentVariables item), example FOO=BAR"
@@ -540,16 +1771,21 @@ Example: '--library-bundle apks/library1-debug-androidTest.apk --library-bundle
         )]
         xctestrun_test_env: Option<Vec<String>>,
 
-        #[arg(long, help = "Test filters supplied as .xctestplan file")]
+        #[arg(
+            long,
+            help = "Test filters supplied as .xctestplan file. Environment variables, launch arguments, language, region and test timeouts of the plan are applied as well, command line arguments take precedence"
+        )]
         xctestplan_filter_file: Option<PathBuf>,
 
-        #[arg(long, help = "Target name to use for test filtering in .xctestplan")]
+        #[arg(
+            long,
+            help = "Target name to use for test filtering in .xctestplan. Detected from the test application if the plan has multiple targets"
+        )]
         xctestplan_target_name: Option<String>,
 
         #[arg(
             long,
-            default_value = "300",
-            help = "Default timeout for each test in seconds"
+            help = "Default timeout for each test in seconds, 300 unless set by the test timeouts of --xctestplan-filter-file"
         )]
         test_timeout_default: Option<u32>,
 
@@ -566,5 +1802,246 @@ Important: Granting is conducted before each test batch (not each test). If you
 Available permissions: calendar, contacts-limited, contacts, location, location-always, photos-add, photos, media-library, microphone, motion, reminders, siri."
         )]
         granted_permission: Option<Vec<String>>,
+
+        #[arg(
+            long,
+            help = "Directory for archives generated from .app and .xctest folders. A temporary directory is used by default"
+        )]
+        work_dir: Option<PathBuf>,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Keep archives generated from .app and .xctest folders after submitting the run, e.g. for debugging"
+        )]
+        keep_archives: bool,
+
+        #[arg(
+            long,
+            help = "Glob of files and folders to leave out when zipping .app and .xctest folders, relative to the folder containing them. Can be repeated, example: --zip-exclude '**/.DS_Store' --zip-exclude '**/*.dSYM'"
+        )]
+        zip_exclude: Option<Vec<String>>,
+
+        #[arg(
+            value_enum,
+            long,
+            default_value_t = crate::compression::CompressionLevel::Default,
+            help = "Compression used when zipping .app and .xctest folders. 'store' skips compression, which is faster for bundles of already compressed files"
+        )]
+        compression: crate::compression::CompressionLevel,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Always zip .app and .xctest folders instead of reusing archives generated by previous invocations for unchanged folders. Folders are compared by the paths, sizes and modification times of their files, not by their contents"
+        )]
+        no_zip_cache: bool,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            conflicts_with_all = ["work_dir", "keep_archives"],
+            help = "Zip .app and .xctest folders while uploading them instead of writing the archives to disk first. Saves disk space for large bundles, but identical uploads are no longer skipped and failed uploads zip the folder again"
+        )]
+        stream_archives: bool,
+    },
+    #[allow(non_camel_case_types)]
+    #[command(name = "macos")]
+    #[clap(about = "Run tests for macOS")]
+    macOS {
+        #[arg(
+            short,
+            long,
+            required_unless_present_any = &["application_url", "application_run_id", "xctestrun"],
+            help = "application filepath, example: /home/user/workspace/sample.zip. The application of an .xcarchive is used as well"
+        )]
+        application: Option<PathBuf>,
+
+        #[arg(
+            short,
+            long,
+            required_unless_present_any = &["test_application_url", "test_application_run_id", "xctestrun"],
+            help = "test application filepath, example: /home/user/workspace/sampleUITests-Runner.zip"
+        )]
+        test_application: Option<PathBuf>,
+
+        #[command(flatten)]
+        xctestrun_args: XctestrunArgs,
+
+        #[command(flatten)]
+        remote_artifact_args: RemoteArtifactArgs,
+
+        #[arg(long, help = "macOS version of the runner, example: 14")]
+        os_version: Option<String>,
+
+        #[arg(long, help = "Xcode version, example: 15.4")]
+        xcode_version: Option<String>,
+
+        #[command(flatten)]
+        common: CommonRunArgs,
+
+        #[command(flatten)]
+        api_args: ApiArgs,
+
+        #[command(flatten)]
+        retry_args: RetryArgs,
+
+        #[command(flatten)]
+        analytics_args: AnalyticsArgs,
+
+        #[arg(
+            long,
+            help = "xctestrun environment variable (EnvironmentVariables item), example FOO=BAR"
+        )]
+        xctestrun_env: Option<Vec<String>>,
+
+        #[arg(
+            long,
+            help = "xctestrun testing environment variable (TestingEnvironmentVariables item), example FOO=BAR"
+        )]
+        xctestrun_test_env: Option<Vec<String>>,
+
+        #[arg(
+            long,
+            help = "Test filters supplied as .xctestplan file. Environment variables, launch arguments, language, region and test timeouts of the plan are applied as well, command line arguments take precedence"
+        )]
+        xctestplan_filter_file: Option<PathBuf>,
+
+        #[arg(
+            long,
+            help = "Target name to use for test filtering in .xctestplan. Detected from the test application if the plan has multiple targets"
+        )]
+        xctestplan_target_name: Option<String>,
+
+        #[arg(
+            long,
+            help = "Default timeout for each test in seconds, 300 unless set by the test timeouts of --xctestplan-filter-file"
+        )]
+        test_timeout_default: Option<u32>,
+
+        #[arg(
+            long,
+            help = "Maximum test timeout in seconds, overriding all other test timeout settings"
+        )]
+        test_timeout_max: Option<u32>,
+
+        #[arg(
+            long,
+            help = "Directory for archives generated from .app and .xctest folders. A temporary directory is used by default"
+        )]
+        work_dir: Option<PathBuf>,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Keep archives generated from .app and .xctest folders after submitting the run, e.g. for debugging"
+        )]
+        keep_archives: bool,
+
+        #[arg(
+            long,
+            help = "Glob of files and folders to leave out when zipping .app and .xctest folders, relative to the folder containing them. Can be repeated, example: --zip-exclude '**/.DS_Store' --zip-exclude '**/*.dSYM'"
+        )]
+        zip_exclude: Option<Vec<String>>,
+
+        #[arg(
+            value_enum,
+            long,
+            default_value_t = crate::compression::CompressionLevel::Default,
+            help = "Compression used when zipping .app and .xctest folders. 'store' skips compression, which is faster for bundles of already compressed files"
+        )]
+        compression: crate::compression::CompressionLevel,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Always zip .app and .xctest folders instead of reusing archives generated by previous invocations for unchanged folders. Folders are compared by the paths, sizes and modification times of their files, not by their contents"
+        )]
+        no_zip_cache: bool,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            conflicts_with_all = ["work_dir", "keep_archives"],
+            help = "Zip .app and .xctest folders while uploading them instead of writing the archives to disk first. Saves disk space for large bundles, but identical uploads are no longer skipped and failed uploads zip the folder again"
+        )]
+        stream_archives: bool,
     },
+    #[clap(about = "Run browser tests of a Playwright or Cypress project")]
+    Web {
+        #[arg(
+            long,
+            help = "test bundle filepath, an archive of the test project in .zip, .tgz or .tar.gz format, example: /home/user/workspace/e2e.zip"
+        )]
+        test_bundle: PathBuf,
+
+        #[arg(value_enum, long, help = "Test framework of the test bundle")]
+        flavor: web::Flavor,
+
+        #[arg(
+            value_enum,
+            long,
+            required = true,
+            help = "Browser to run the tests in. Can be repeated to run the tests in every browser, example: --browser chromium --browser firefox"
+        )]
+        browser: Vec<web::Browser>,
+
+        #[arg(
+            value_enum,
+            long,
+            help = "Operating system of the browsers. Can be repeated to run the tests on every operating system, linux by default"
+        )]
+        os: Vec<web::Platform>,
+
+        #[arg(
+            long,
+            help = "Environment variable of the test process, example: BASE_URL=https://staging.example.com"
+        )]
+        env: Option<Vec<String>>,
+
+        #[command(flatten)]
+        common: CommonRunArgs,
+
+        #[command(flatten)]
+        api_args: ApiArgs,
+
+        #[command(flatten)]
+        retry_args: RetryArgs,
+
+        #[command(flatten)]
+        analytics_args: AnalyticsArgs,
+    },
+    #[clap(
+        about = "Submit every configuration of a matrix configuration file and wait for all of them"
+    )]
+    Matrix {
+        #[arg(
+            long,
+            help = "Matrix configuration file in YAML format. Every entry of `configurations` declares a platform, application, test-application, devices, os-versions, flavor, filter-file and any other arguments of the run command as args"
+        )]
+        config: PathBuf,
+
+        #[command(flatten)]
+        result_file_args: ResultFileArgs,
+
+        #[command(flatten)]
+        api_args: ApiArgs,
+
+        #[arg(skip)]
+        validate_only: bool,
+    },
+}
+
+impl RunCommands {
+    /// The same run with every local check of its arguments, but nothing is uploaded or submitted
+    fn validate_only(mut self) -> Self {
+        match &mut self {
+            RunCommands::Android { common, .. }
+            | RunCommands::iOS { common, .. }
+            | RunCommands::macOS { common, .. }
+            | RunCommands::Web { common, .. } => common.validate_only = true,
+            RunCommands::Matrix { validate_only, .. } => *validate_only = true,
+        }
+        self
+    }
 }
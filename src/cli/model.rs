@@ -15,3 +15,109 @@ impl Display for Platform {
         }
     }
 }
+
+/// Format of listings printed to stdout
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[clap(name = "text")]
+    Text,
+    #[clap(name = "json")]
+    Json,
+    #[clap(name = "yaml")]
+    Yaml,
+}
+
+/// Kind of device tests run on
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum DeviceClass {
+    #[clap(name = "simulator")]
+    Simulator,
+    #[clap(name = "real")]
+    Real,
+}
+
+impl Display for DeviceClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceClass::Simulator => f.write_str("simulator"),
+            DeviceClass::Real => f.write_str("real"),
+        }
+    }
+}
+
+/// Network shaping of the devices of a run
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum NetworkProfile {
+    #[clap(name = "full")]
+    Full,
+    #[clap(name = "3g")]
+    ThreeG,
+    #[clap(name = "edge")]
+    Edge,
+    /// Full speed interrupted by periods without connectivity
+    #[clap(name = "offline-segments")]
+    OfflineSegments,
+}
+
+impl Display for NetworkProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkProfile::Full => f.write_str("full"),
+            NetworkProfile::ThreeG => f.write_str("3g"),
+            NetworkProfile::Edge => f.write_str("edge"),
+            NetworkProfile::OfflineSegments => f.write_str("offline-segments"),
+        }
+    }
+}
+
+/// Screen orientation of the devices of a run
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum Orientation {
+    #[clap(name = "portrait")]
+    Portrait,
+    #[clap(name = "landscape")]
+    Landscape,
+}
+
+impl Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Orientation::Portrait => f.write_str("portrait"),
+            Orientation::Landscape => f.write_str("landscape"),
+        }
+    }
+}
+
+/// When screen recordings of tests are captured
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum VideoPolicy {
+    #[clap(name = "on-failure")]
+    OnFailure,
+    #[clap(name = "always")]
+    Always,
+    #[clap(name = "never")]
+    Never,
+}
+
+impl Display for VideoPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoPolicy::OnFailure => f.write_str("on-failure"),
+            VideoPolicy::Always => f.write_str("always"),
+            VideoPolicy::Never => f.write_str("never"),
+        }
+    }
+}
+
+/// Simulated GPS location of the devices of a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.latitude, self.longitude)
+    }
+}
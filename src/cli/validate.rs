@@ -1,5 +1,106 @@
-use crate::{cli::RetryArgs, errors::InputError};
+use std::path::PathBuf;
+
+use crate::{
+    api::{self, RapiClient, RapiReqwestClient},
+    app_data,
+    cli::{model::Platform, RetryArgs},
+    errors::{ConfigurationError, InputError},
+    formatter::{Formatter, StandardFormatter},
+    hash::{self, HashAlgorithm},
+};
 use anyhow::Result;
+use log::debug;
+
+/// Failures of the checks of a run. A run stops at the first failure, but when only its
+/// arguments are validated every failure is collected and reported at once
+pub(crate) struct Checks {
+    collect: bool,
+    failures: Vec<anyhow::Error>,
+}
+
+impl Checks {
+    pub(crate) fn new(collect: bool) -> Checks {
+        Checks {
+            collect,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Value of a passed check, `None` once the failure of the check is collected
+    pub(crate) fn check<T, E: Into<anyhow::Error>>(
+        &mut self,
+        result: Result<T, E>,
+    ) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.collect => {
+                self.failures.push(error.into());
+                Ok(None)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub(crate) fn fail(&mut self, error: impl Into<anyhow::Error>) -> Result<()> {
+        self.check(Err::<(), _>(error)).map(|_| ())
+    }
+
+    /// Fails with every collected failure, the first one determines the exit code
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if self.failures.len() > 1 {
+            let failures: Vec<String> = self
+                .failures
+                .iter()
+                .map(|error| format!("  {}", error.to_string().trim_end().replace('\n', "\n    ")))
+                .collect();
+            let message = format!(
+                "{} checks failed:\n{}",
+                self.failures.len(),
+                failures.join("\n")
+            );
+            return Err(self.failures.swap_remove(0).context(message));
+        }
+        match self.failures.pop() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Last checks of a run which is only validated: the files which would be uploaded are read in
+/// full to compute their checksum and have to fit the upload limit. Folders are zipped while
+/// they're uploaded and missing files are already reported by the other checks of the run
+pub(crate) async fn uploads(
+    mut checks: Checks,
+    files: impl IntoIterator<Item = PathBuf>,
+    app_data: Option<&PathBuf>,
+    checksum: HashAlgorithm,
+    max_upload_size: u32,
+) -> Result<bool> {
+    if let Some(app_data) = app_data {
+        checks.check(app_data::validate(app_data).await)?;
+    }
+    let limit = u64::from(max_upload_size) * 1024 * 1024;
+    for path in files.into_iter().chain(app_data.cloned()) {
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(digests) = checks.check(hash::digest_file(&path, &[checksum]).await)? {
+            debug!(
+                "{} {}: {}",
+                checksum,
+                path.display(),
+                digests[&checksum].hex()
+            );
+        }
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            checks.check(api::check_upload_size(&path, metadata.len(), limit))?;
+        }
+    }
+    checks.finish()?;
+    StandardFormatter::new(1).message("Configuration is valid, nothing was submitted");
+    Ok(true)
+}
 
 pub(crate) fn retry_args(retry_args: RetryArgs) -> RetryArgs {
     if retry_args.no_retries {
@@ -29,3 +130,124 @@ pub(crate) fn result_file_args(result_file_args: &super::ResultFileArgs) -> Resu
         Ok(())
     }
 }
+
+/// Checks that `device` is a model of the real device catalog of `platform`. Only the
+/// presence of the model is checked `offline`
+pub(crate) async fn real_device(
+    api_args: &super::ApiArgs,
+    platform: &Platform,
+    device: Option<&str>,
+    offline: bool,
+) -> Result<()> {
+    let platform = platform.to_string().to_lowercase();
+    let Some(device) = device else {
+        return Err(ConfigurationError::UnsupportedRunConfiguration {
+            message: format!(
+                "Please select the model of the real device with --device. Use `marathon-cloud devices {} --device-class real` to get a list of supported models",
+                platform
+            ),
+        }
+        .into());
+    };
+    if offline {
+        return Ok(());
+    }
+    let client = RapiReqwestClient::new(&api_args.base_url, &api_args.api_key);
+    let token = client.get_token().await?;
+    let devices = client.get_devices_real(&token, &platform).await?;
+    if devices.iter().any(|x| x.id == device) {
+        Ok(())
+    } else {
+        Err(ConfigurationError::UnsupportedRunConfiguration {
+            message: format!(
+                "Real device {} isn't supported. Supported models: {}",
+                device,
+                devices
+                    .iter()
+                    .map(|x| x.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+        .into())
+    }
+}
+
+pub(crate) fn artifact_url(value: &str) -> std::result::Result<String, String> {
+    match url::Url::parse(value) {
+        Ok(url) if url.scheme() == "https" || url.scheme() == "http" => Ok(value.to_owned()),
+        Ok(url) => Err(format!(
+            "unsupported url scheme '{}', expected http or https",
+            url.scheme()
+        )),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+pub(crate) fn path_template(value: &str) -> std::result::Result<String, String> {
+    crate::artifacts::validate_path_template(value).map(|_| value.to_owned())
+}
+
+pub(crate) fn location(value: &str) -> std::result::Result<super::model::Location, String> {
+    let coordinate = |value: &str, limit: f64| {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|x| x.abs() <= limit)
+    };
+    match value.split_once(',') {
+        Some((latitude, longitude)) => {
+            match (coordinate(latitude, 90.0), coordinate(longitude, 180.0)) {
+                (Some(latitude), Some(longitude)) => Ok(super::model::Location {
+                    latitude,
+                    longitude,
+                }),
+                _ => Err(
+                    "latitude must be within [-90, 90] and longitude within [-180, 180]".to_owned(),
+                ),
+            }
+        }
+        None => Err("expected LATITUDE,LONGITUDE in decimal degrees, e.g. 52.52,13.405".to_owned()),
+    }
+}
+
+//Sizes are megabytes unless suffixed, e.g. 4096, 4096M or 4G
+pub(crate) fn megabytes(value: &str) -> std::result::Result<u32, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let (number, multiplier) = match upper.strip_suffix("GB").or_else(|| upper.strip_suffix('G')) {
+        Some(number) => (number, 1024),
+        None => (
+            upper
+                .strip_suffix("MB")
+                .or_else(|| upper.strip_suffix('M'))
+                .unwrap_or(&upper),
+            1,
+        ),
+    };
+    number
+        .parse::<u32>()
+        .ok()
+        .and_then(|x| x.checked_mul(multiplier))
+        .filter(|x| *x > 0)
+        .ok_or_else(|| {
+            "expected a size in megabytes or with a unit, e.g. 4096, 4096M or 4G".to_owned()
+        })
+}
+
+//Codes are normalized to the case used by Android and iOS, e.g. de and DE
+pub(crate) fn language(value: &str) -> std::result::Result<String, String> {
+    if (2..=3).contains(&value.len()) && value.chars().all(|x| x.is_ascii_alphabetic()) {
+        Ok(value.to_ascii_lowercase())
+    } else {
+        Err("expected an ISO 639 language code of 2 or 3 letters, e.g. en".to_owned())
+    }
+}
+
+pub(crate) fn country(value: &str) -> std::result::Result<String, String> {
+    if value.len() == 2 && value.chars().all(|x| x.is_ascii_alphabetic()) {
+        Ok(value.to_ascii_uppercase())
+    } else {
+        Err("expected an ISO 3166-1 alpha-2 country code, e.g. US".to_owned())
+    }
+}
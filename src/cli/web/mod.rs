@@ -0,0 +1,254 @@
+use std::{fmt::Display, path::PathBuf};
+
+use anyhow::Result;
+
+use crate::{
+    api::{RemoteArtifacts, RunOptions},
+    cli::{self, AnalyticsArgs, ApiArgs, CommonRunArgs, RetryArgs},
+    errors::{ConfigurationError, InputError},
+    filtering,
+    interactor::TriggerTestRunInteractor,
+    retries, timeouts,
+};
+
+const SUPPORTED_BUNDLES: [&str; 3] = ["zip", "tgz", "tar.gz"];
+
+#[derive(Debug, clap::ValueEnum, Clone)]
+pub enum Flavor {
+    #[clap(name = "playwright")]
+    Playwright,
+    #[clap(name = "cypress")]
+    Cypress,
+}
+
+impl Display for Flavor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Flavor::Playwright => f.write_str("playwright"),
+            Flavor::Cypress => f.write_str("cypress"),
+        }
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum Browser {
+    #[clap(name = "chromium")]
+    Chromium,
+    #[clap(name = "chrome")]
+    Chrome,
+    #[clap(name = "firefox")]
+    Firefox,
+    #[clap(name = "webkit")]
+    Webkit,
+    #[clap(name = "edge")]
+    Edge,
+}
+
+impl Display for Browser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Browser::Chromium => f.write_str("chromium"),
+            Browser::Chrome => f.write_str("chrome"),
+            Browser::Firefox => f.write_str("firefox"),
+            Browser::Webkit => f.write_str("webkit"),
+            Browser::Edge => f.write_str("edge"),
+        }
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Eq)]
+pub enum Platform {
+    #[clap(name = "linux")]
+    Linux,
+    #[clap(name = "windows")]
+    Windows,
+    #[clap(name = "macos")]
+    MacOs,
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Platform::Linux => f.write_str("linux"),
+            Platform::Windows => f.write_str("windows"),
+            Platform::MacOs => f.write_str("macos"),
+        }
+    }
+}
+
+fn is_supported_bundle(path: &std::path::Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    SUPPORTED_BUNDLES
+        .iter()
+        .any(|extension| name.ends_with(&format!(".{}", extension)))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run(
+    test_bundle: PathBuf,
+    flavor: Flavor,
+    browser: Vec<Browser>,
+    os: Vec<Platform>,
+    env: Option<Vec<String>>,
+    common: CommonRunArgs,
+    api_args: ApiArgs,
+    retry_args: RetryArgs,
+    analytics_args: AnalyticsArgs,
+) -> Result<bool> {
+    let mut checks = cli::validate::Checks::new(common.validate_only);
+    if !test_bundle.is_file() {
+        checks.fail(InputError::InvalidFileName {
+            path: test_bundle.clone(),
+        })?;
+    } else if !is_supported_bundle(&test_bundle) {
+        checks.fail(InputError::InvalidFileExtension {
+            extension: test_bundle
+                .extension()
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            supported: SUPPORTED_BUNDLES.join(","),
+        })?;
+    }
+
+    if common.app_data.is_some() {
+        checks.fail(ConfigurationError::UnsupportedRunConfiguration {
+            message: "App data isn't supported for web runs".into(),
+        })?;
+    }
+
+    let filter_file = common.filter_file.map(filtering::convert::convert);
+    let filtering_configuration = match filter_file {
+        Some(future) => checks.check(future.await)?,
+        None => None,
+    };
+    let filtering_configuration = filtering::convert::with_allowlist_filter(
+        filtering_configuration,
+        common.test_filter_args.filter(),
+    );
+    let filtering_configuration = filtering::convert::with_allowlist_filter(
+        filtering_configuration,
+        checks.check(common.sharding_args.filter())?.flatten(),
+    );
+
+    let retry_args = cli::validate::retry_args(retry_args);
+    checks.check(cli::validate::result_file_args(&common.result_file_args))?;
+
+    if let Some(limit) = common.concurrency_limit {
+        if limit == 0 {
+            checks.fail(InputError::NonPositiveValue {
+                arg: "--concurrency-limit".to_owned(),
+            })?;
+        }
+    }
+
+    let present_wait: bool = match common.wait {
+        None => true,
+        Some(true) => true,
+        Some(false) => false,
+    };
+
+    //Every browser runs on every operating system of the matrix
+    let run_options = RunOptions {
+        browsers: Some(browser.iter().map(|x| x.to_string()).collect()),
+        browser_os: (!os.is_empty()).then(|| os.iter().map(|x| x.to_string()).collect()),
+        retry_quota_overrides: match &retry_args.retry_policy {
+            Some(path) => checks.check(retries::load(path).await)?,
+            None => None,
+        },
+        record_video: common.record_video.as_ref().map(|x| x.to_string()),
+        screenshot_on_failure: common.screenshot_on_failure.then_some(true),
+        test_timeout_overrides: match &common.test_timeout_overrides {
+            Some(path) => checks.check(timeouts::load(path, None).await)?,
+            None => None,
+        },
+        ..Default::default()
+    };
+
+    if common.validate_only {
+        return cli::validate::uploads(
+            checks,
+            [test_bundle],
+            None,
+            common.upload_checksum,
+            common.max_upload_size,
+        )
+        .await;
+    }
+
+    TriggerTestRunInteractor {}
+        .execute(
+            &api_args.base_url,
+            &api_args.api_key,
+            common.name,
+            common.link,
+            common.branch,
+            present_wait,
+            common.stream_logs,
+            common.cancel_on_interrupt,
+            common.run_timeout,
+            common.tui,
+            common.ndjson_progress,
+            common.upload_retries,
+            common.upload_cache_ttl,
+            common.upload_checksum,
+            common.max_upload_size,
+            common.isolated,
+            common.ignore_test_failures,
+            common.code_coverage,
+            retry_args.retry_quota_test_uncompleted,
+            retry_args.retry_quota_test_preventive,
+            retry_args.retry_quota_test_reactive,
+            analytics_args.analytics_read_only,
+            false,
+            false,
+            filtering_configuration,
+            &common.output,
+            common.artifact_download_args.download_options(),
+            None,
+            Some(test_bundle),
+            None,
+            None,
+            None,
+            None,
+            Some(flavor.to_string()),
+            "Web".to_owned(),
+            common.progress_args.no_progress_bars,
+            common.polling_args.poll_interval(),
+            common.result_file_args.result_file,
+            env,
+            None,
+            None,
+            None,
+            None,
+            common.concurrency_limit,
+            None,
+            None,
+            common.project,
+            None,
+            None,
+            None,
+            None,
+            RemoteArtifacts::default(),
+            run_options,
+            None,
+            common.skip_if_passed,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_supported_bundle() {
+        assert!(is_supported_bundle(std::path::Path::new("e2e.zip")));
+        assert!(is_supported_bundle(std::path::Path::new("dist/e2e.TAR.GZ")));
+        assert!(is_supported_bundle(std::path::Path::new("e2e.tgz")));
+        assert!(!is_supported_bundle(std::path::Path::new("e2e.tar")));
+        assert!(!is_supported_bundle(std::path::Path::new("gz")));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use log::debug;
+use walkdir::DirEntry;
+
+use crate::{cache::unix_seconds, hash::md5_str};
+
+const MAX_CACHED_ARCHIVES: usize = 8;
+//Archives used more recently may still be uploaded by a concurrent invocation, e.g. of a matrix
+const IN_USE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+//Hard limits which apply to archives in use as well, the most recently used one is always kept
+const MAX_ARCHIVES: usize = 32;
+const MAX_CACHE_SIZE: u64 = 20 * 1024 * 1024 * 1024;
+const LAST_USED: &str = ".last-used";
+
+/// Archives generated from directories, stored under a key derived from the directory listing
+pub struct ZipCache {
+    dir: PathBuf,
+}
+
+impl ZipCache {
+    pub fn new(dir: PathBuf) -> ZipCache {
+        ZipCache { dir }
+    }
+
+    pub fn default_location() -> Option<ZipCache> {
+        crate::cache::dir().map(|dir| ZipCache::new(dir.join("zips")))
+    }
+
+    pub fn get(&self, key: &str, file_name: &Path) -> Option<PathBuf> {
+        let path = self.dir.join(key).join(file_name);
+        if !path.is_file() {
+            return None;
+        }
+        mark_used(&self.dir.join(key), SystemTime::now());
+        Some(path)
+    }
+
+    /// Copies the archive into the cache and returns the cached location
+    pub fn store(&self, key: &str, archive: &Path) -> Result<PathBuf> {
+        let file_name = archive.file_name().unwrap_or_default();
+        //Concurrent invocations may store the same key, the entry only becomes visible complete
+        let staging = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        fs::create_dir_all(&staging)?;
+        fs::copy(archive, staging.join(file_name))?;
+        let entry = self.dir.join(key);
+        if fs::rename(&staging, &entry).is_err() {
+            fs::remove_dir_all(&staging)?;
+        }
+        let now = SystemTime::now();
+        mark_used(&entry, now);
+        self.prune(now);
+        Ok(entry.join(file_name))
+    }
+
+    fn prune(&self, now: SystemTime) {
+        self.prune_to(now, MAX_ARCHIVES, MAX_CACHE_SIZE)
+    }
+
+    //Best-effort, keeps the most recently used archives and the archives which may still be in
+    //use, up to `max_archives` and `max_size` bytes in total
+    fn prune_to(&self, now: SystemTime, max_archives: usize, max_size: u64) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut entries: Vec<(u64, PathBuf)> = entries
+            .flatten()
+            .filter(|entry| !entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .filter_map(|entry| Some((last_used(&entry.path())?, entry.path())))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let expired = unix_seconds(now).saturating_sub(IN_USE_TTL.as_secs());
+        let mut total = 0;
+        for (index, (used, path)) in entries.into_iter().enumerate() {
+            total += size(&path);
+            let wanted = index < MAX_CACHED_ARCHIVES || used >= expired;
+            let fits = index < max_archives && total <= max_size;
+            if index == 0 || (wanted && fits) {
+                continue;
+            }
+            if let Err(error) = fs::remove_dir_all(&path) {
+                debug!(
+                    "Failed to remove cached archive {}: {}",
+                    path.display(),
+                    error
+                );
+            }
+        }
+    }
+}
+
+fn mark_used(entry: &Path, now: SystemTime) {
+    if let Err(error) = fs::write(entry.join(LAST_USED), unix_seconds(now).to_string()) {
+        debug!("Failed to mark {} as used: {}", entry.display(), error);
+    }
+}
+
+fn size(entry: &Path) -> u64 {
+    fs::read_dir(entry)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|file| file.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+//Entries stored by older versions have no marker, their modification time is used instead
+fn last_used(entry: &Path) -> Option<u64> {
+    match fs::read_to_string(entry.join(LAST_USED)) {
+        Ok(content) => content.trim().parse().ok(),
+        Err(_) => Some(unix_seconds(fs::metadata(entry).ok()?.modified().ok()?)),
+    }
+}
+
+/// Key for the archive of `entries` built with the given options, derived from relative paths,
+/// sizes, modification times and executable bits instead of reading file contents
+pub fn fingerprint(entries: &[DirEntry], prefix: &Path, options: &str) -> Result<String> {
+    let mut listing = String::from(options);
+    for entry in entries {
+        let metadata = entry.metadata()?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        listing.push_str(&format!(
+            "\n{}:{}:{}:{}",
+            entry.path().strip_prefix(prefix)?.to_string_lossy(),
+            metadata.len(),
+            modified,
+            is_executable(&metadata)
+        ));
+    }
+    Ok(md5_str(&listing))
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use walkdir::WalkDir;
+
+    fn listing(dir: &Path) -> Vec<DirEntry> {
+        WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_content_and_options() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), b"a").unwrap();
+        let first = fingerprint(&listing(dir.path()), dir.path(), "default").unwrap();
+
+        assert_eq!(
+            fingerprint(&listing(dir.path()), dir.path(), "default").unwrap(),
+            first
+        );
+        assert_ne!(
+            fingerprint(&listing(dir.path()), dir.path(), "store").unwrap(),
+            first
+        );
+        std::fs::write(dir.path().join("a"), b"aa").unwrap();
+        assert_ne!(
+            fingerprint(&listing(dir.path()), dir.path(), "default").unwrap(),
+            first
+        );
+    }
+
+    #[test]
+    fn test_store_and_get() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let archive = source.path().join("Sample.zip");
+        std::fs::write(&archive, b"zip").unwrap();
+        let cache = ZipCache::new(cache_dir.path().to_path_buf());
+
+        assert_eq!(cache.get("key", Path::new("Sample.zip")), None);
+        let cached = cache.store("key", &archive).unwrap();
+
+        assert_eq!(
+            cache.get("key", Path::new("Sample.zip")),
+            Some(cached.clone())
+        );
+        assert_eq!(std::fs::read(cached).unwrap(), b"zip");
+    }
+
+    #[test]
+    fn test_prune_keeps_archives_in_use() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let archive = source.path().join("Sample.zip");
+        std::fs::write(&archive, b"zip").unwrap();
+        let cache = ZipCache::new(cache_dir.path().to_path_buf());
+        let keys: Vec<String> = (0..MAX_CACHED_ARCHIVES + 2)
+            .map(|i| format!("key-{}", i))
+            .collect();
+        for key in &keys {
+            cache.store(key, &archive).unwrap();
+        }
+        //Recently used archives may be uploaded by another invocation
+        assert!(keys
+            .iter()
+            .all(|key| cache.get(key, Path::new("Sample.zip")).is_some()));
+
+        let now = SystemTime::now();
+        let old = now - IN_USE_TTL - Duration::from_secs(60);
+        for key in &keys[..3] {
+            mark_used(&cache_dir.path().join(key), old);
+        }
+        cache.prune(now);
+
+        //Only stale archives beyond the limit are removed
+        assert!(keys[3..]
+            .iter()
+            .all(|key| cache_dir.path().join(key).exists()));
+        assert_eq!(
+            std::fs::read_dir(cache_dir.path()).unwrap().count(),
+            MAX_CACHED_ARCHIVES
+        );
+    }
+
+    #[test]
+    fn test_prune_limits_archives_in_use() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let archive = source.path().join("Sample.zip");
+        std::fs::write(&archive, b"zip").unwrap();
+        let cache = ZipCache::new(cache_dir.path().to_path_buf());
+        let now = SystemTime::now();
+        let keys: Vec<String> = (0..MAX_CACHED_ARCHIVES + 4)
+            .map(|i| format!("key-{}", i))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            cache.store(key, &archive).unwrap();
+            mark_used(
+                &cache_dir.path().join(key),
+                now - Duration::from_secs((keys.len() - i) as u64),
+            );
+        }
+
+        cache.prune_to(now, MAX_CACHED_ARCHIVES + 2, u64::MAX);
+        assert_eq!(
+            std::fs::read_dir(cache_dir.path()).unwrap().count(),
+            MAX_CACHED_ARCHIVES + 2
+        );
+        assert!(!cache_dir.path().join(&keys[0]).exists());
+
+        //Every archive has 3 bytes and a marker, the latest is kept even above the limit
+        cache.prune_to(now, MAX_ARCHIVES, 0);
+        assert_eq!(std::fs::read_dir(cache_dir.path()).unwrap().count(), 1);
+        assert!(cache_dir.path().join(keys.last().unwrap()).exists());
+    }
+}
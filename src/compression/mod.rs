@@ -1,45 +1,335 @@
-use std::path::Path;
+mod cache;
+
+use std::{fs::Metadata, path::Path};
 
 use anyhow::Context;
-use async_zip::{tokio::write::ZipFileWriter, Compression, ZipEntryBuilder};
+use async_zip::{
+    tokio::write::ZipFileWriter, Compression, DeflateOption, ZipDateTime, ZipDateTimeBuilder,
+    ZipEntryBuilder,
+};
+use futures::AsyncWriteExt;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use log::debug;
 use tokio::{fs::File, io::AsyncReadExt};
-use walkdir::DirEntry;
+use walkdir::{DirEntry, WalkDir};
+
+pub use cache::{fingerprint, ZipCache};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// How directories are turned into archives
+pub struct ZipOptions {
+    exclude: GlobSet,
+    exclude_patterns: Vec<String>,
+    pub level: CompressionLevel,
+    pub cache: Option<ZipCache>,
+    progress: Option<MultiProgress>,
+}
+
+impl ZipOptions {
+    pub fn new(
+        exclude_patterns: Vec<String>,
+        level: CompressionLevel,
+        cache: Option<ZipCache>,
+    ) -> anyhow::Result<ZipOptions> {
+        let mut exclude = GlobSetBuilder::new();
+        for pattern in &exclude_patterns {
+            exclude.add(Glob::new(pattern)?);
+        }
+        Ok(ZipOptions {
+            exclude: exclude.build()?,
+            exclude_patterns,
+            level,
+            cache,
+            progress: None,
+        })
+    }
+
+    /// Shows a progress bar for every zipped directory in `progress`
+    pub fn with_progress(self, progress: MultiProgress) -> ZipOptions {
+        ZipOptions {
+            progress: Some(progress),
+            ..self
+        }
+    }
+
+    /// Progress bar for zipping the directory described by `prefix`, hidden unless enabled
+    pub fn progress_bar(&self, prefix: String) -> anyhow::Result<ProgressBar> {
+        let Some(progress) = &self.progress else {
+            return Ok(ProgressBar::hidden());
+        };
+        let style = ProgressStyle::with_template(
+            "{prefix} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta}) {msg}",
+        )?
+        .progress_chars("#>-");
+        Ok(progress.add(ProgressBar::new(0).with_style(style).with_prefix(prefix)))
+    }
+
+    /// `name` is relative to the folder containing the zipped directory
+    pub fn is_excluded(&self, name: &Path) -> bool {
+        self.exclude.is_match(name)
+    }
 
+    /// Describes everything besides the input files which affects the archive contents
+    pub fn cache_key_prefix(&self) -> String {
+        format!("{:?}|{}", self.level, self.exclude_patterns.join("|"))
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionLevel {
+    /// No compression, fastest for contents which are already compressed
+    #[clap(name = "store")]
+    Store,
+    #[clap(name = "fast")]
+    Fast,
+    #[clap(name = "default")]
+    Default,
+    #[clap(name = "best")]
+    Best,
+}
+
+impl CompressionLevel {
+    fn builder(self, name: String) -> ZipEntryBuilder {
+        match self {
+            CompressionLevel::Store => ZipEntryBuilder::new(name.into(), Compression::Stored),
+            CompressionLevel::Fast => ZipEntryBuilder::new(name.into(), Compression::Deflate)
+                .deflate_option(DeflateOption::Fast),
+            CompressionLevel::Default => ZipEntryBuilder::new(name.into(), Compression::Deflate),
+            CompressionLevel::Best => ZipEntryBuilder::new(name.into(), Compression::Deflate)
+                .deflate_option(DeflateOption::Maximum),
+        }
+    }
+}
+
+/// Files of the `path` directory which end up in its archive, `prefix` is the folder containing it.
+/// Excluded directories are pruned together with everything inside them
+pub fn bundle_entries(path: &Path, prefix: &Path, options: &ZipOptions) -> Vec<DirEntry> {
+    WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry
+                .path()
+                .strip_prefix(prefix)
+                .map_or(true, |name| !options.is_excluded(name))
+        })
+        .filter_map(|e| e.ok())
+        .collect()
+}
+
+/// Archives are byte-identical for identical input: entries are sorted by name, timestamps are fixed
+/// and permissions are normalized, so that content hashes match across machines.
+/// `progress` tracks the number of zipped bytes and the file being zipped
 pub async fn zip_dir<T>(
-    it: &mut dyn Iterator<Item = DirEntry>,
+    it: &mut (dyn Iterator<Item = DirEntry> + Send),
     prefix: &str,
     mut writer: T,
+    level: CompressionLevel,
+    progress: &ProgressBar,
 ) -> anyhow::Result<()>
 where
     T: tokio::io::AsyncWrite + Unpin,
 {
-    let unix_permissions = 0o755;
-    let compression_method = Compression::Deflate;
     let mut zip = ZipFileWriter::with_tokio(&mut writer);
 
     let prefix = Path::new(prefix);
-    let mut buffer = Vec::new();
+    let mut entries = Vec::new();
     for entry in it {
-        let path = entry.path();
-        let name = path.strip_prefix(prefix)?;
+        let name = entry.path().strip_prefix(prefix)?;
         let path_as_string = name
-            .to_str()
-            .map(str::to_owned)
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<&str>>>()
+            .map(|components| components.join("/"))
             .with_context(|| format!("{name:?} Is a Non UTF-8 Path"))?;
+        entries.push((path_as_string, entry));
+    }
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let total_size: u64 = entries
+        .iter()
+        .filter_map(|(_, entry)| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum();
+    progress.set_length(total_size);
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    for (path_as_string, entry) in entries {
+        let path = entry.path();
+        let name = &path_as_string;
 
         if path.is_file() {
             debug!("adding file {path:?} as {name:?} ...");
+            progress.set_message(name.clone());
             let mut f = File::open(path).await?;
-            f.read_to_end(&mut buffer).await?;
-
-            let builder = ZipEntryBuilder::new(path_as_string.into(), compression_method)
-                .unix_permissions(unix_permissions);
-            zip.write_entry_whole(builder, &buffer).await?;
+            let unix_permissions = unix_mode(&f.metadata().await?);
 
-            buffer.clear();
+            let builder = level
+                .builder(path_as_string)
+                .unix_permissions(unix_permissions)
+                .last_modification_date(fixed_timestamp());
+            //Files are streamed in chunks so that large bundles are never fully loaded in memory
+            let mut entry_writer = zip.write_entry_stream(builder).await?;
+            loop {
+                let read = f.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                entry_writer.write_all(&buffer[..read]).await?;
+                progress.inc(read as u64);
+            }
+            entry_writer.close().await?;
         }
     }
     zip.close().await?;
+    //Files complete their writes in the background, the archive is only usable once flushed
+    tokio::io::AsyncWriteExt::shutdown(&mut writer).await?;
     Ok(())
 }
+
+//Earliest date representable in a zip entry
+fn fixed_timestamp() -> ZipDateTime {
+    ZipDateTimeBuilder::new().year(1980).month(1).day(1).build()
+}
+
+//Only the executable bit is kept, other permission bits depend on the umask of the machine
+#[cfg(unix)]
+fn unix_mode(metadata: &Metadata) -> u16 {
+    use std::os::unix::fs::PermissionsExt;
+    if metadata.permissions().mode() & 0o111 != 0 {
+        0o100755
+    } else {
+        0o100644
+    }
+}
+
+//Executable bit can't be determined on other platforms, so everything stays executable
+#[cfg(not(unix))]
+fn unix_mode(_metadata: &Metadata) -> u16 {
+    0o100755
+}
+
+/// Writes a zip archive of `entries` to `path`, a fixture for tests of archive inputs
+#[cfg(test)]
+pub(crate) async fn zip(path: &Path, entries: &[(&str, &[u8])]) -> anyhow::Result<()> {
+    let file = File::create(path).await?;
+    let mut writer = ZipFileWriter::with_tokio(file);
+    for (name, data) in entries {
+        let entry = ZipEntryBuilder::new((*name).into(), Compression::Stored);
+        writer.write_entry_whole(entry, data).await?;
+    }
+    writer.close().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_zip::base::read::mem::ZipFileReader;
+    use walkdir::WalkDir;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_zip_dir_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("Sample.app");
+        std::fs::create_dir(&app).unwrap();
+        std::fs::write(app.join("Sample"), b"binary").unwrap();
+        std::fs::set_permissions(app.join("Sample"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        std::fs::write(app.join("Info.plist"), b"plist").unwrap();
+        std::fs::set_permissions(
+            app.join("Info.plist"),
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        let mut archive = Vec::new();
+        zip_dir(
+            &mut WalkDir::new(&app).into_iter().filter_map(|e| e.ok()),
+            dir.path().to_str().unwrap(),
+            &mut archive,
+            CompressionLevel::Default,
+            &ProgressBar::hidden(),
+        )
+        .await
+        .unwrap();
+
+        let reader = ZipFileReader::new(archive).await.unwrap();
+        let mode = |name: &str| {
+            reader
+                .file()
+                .entries()
+                .iter()
+                .find(|entry| entry.filename().as_str().unwrap() == name)
+                .and_then(|entry| entry.unix_permissions())
+                .map(|mode| mode & 0o777)
+        };
+        assert_eq!(mode("Sample.app/Sample"), Some(0o755));
+        assert_eq!(mode("Sample.app/Info.plist"), Some(0o644));
+    }
+
+    #[tokio::test]
+    async fn test_zip_dir_is_deterministic() {
+        let first = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("b"), b"b").unwrap();
+        std::fs::write(first.path().join("a"), b"a").unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(second.path().join("a"), b"a").unwrap();
+        std::fs::write(second.path().join("b"), b"b").unwrap();
+
+        let mut archives = Vec::new();
+        for dir in [&first, &second] {
+            let mut archive = Vec::new();
+            //Directory listing order is platform dependent, reverse it to be sure it doesn't matter
+            let mut entries: Vec<DirEntry> = WalkDir::new(dir.path())
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .collect();
+            if archives.is_empty() {
+                entries.reverse();
+            }
+            zip_dir(
+                &mut entries.into_iter(),
+                dir.path().to_str().unwrap(),
+                &mut archive,
+                CompressionLevel::Default,
+                &ProgressBar::hidden(),
+            )
+            .await
+            .unwrap();
+            archives.push(archive);
+        }
+
+        assert_eq!(archives[0], archives[1]);
+    }
+
+    #[tokio::test]
+    async fn test_zip_dir_store_only() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), vec![b'a'; 4096]).unwrap();
+
+        let mut archive = Vec::new();
+        let progress = ProgressBar::hidden();
+        zip_dir(
+            &mut WalkDir::new(dir.path()).into_iter().filter_map(|e| e.ok()),
+            dir.path().to_str().unwrap(),
+            &mut archive,
+            CompressionLevel::Store,
+            &progress,
+        )
+        .await
+        .unwrap();
+        assert_eq!(progress.length(), Some(4096));
+        assert_eq!(progress.position(), 4096);
+
+        let reader = ZipFileReader::new(archive).await.unwrap();
+        let entry = &reader.file().entries()[0];
+        assert_eq!(entry.compression(), Compression::Stored);
+        assert_eq!(entry.compressed_size(), 4096);
+    }
+}
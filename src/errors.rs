@@ -24,6 +24,27 @@ pub enum ApiError {
     InvalidAuthenticationToken { error: ReqwestError },
 }
 
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("Failed to upload {path} after {attempts} attempt(s) due to a network error\nerror = {error}")]
+    Network {
+        path: PathBuf,
+        attempts: u32,
+        error: ReqwestError,
+    },
+    #[error("Failed to upload {path} after {attempts} attempt(s)\nstatus_code = {status_code}, body = {body}")]
+    RequestFailedWithCode {
+        path: PathBuf,
+        attempts: u32,
+        status_code: StatusCode,
+        body: String,
+    },
+    #[error("Failed to upload {path} after {attempts} attempt(s), the storage reported a checksum mismatch")]
+    ChecksumMismatch { path: PathBuf, attempts: u32 },
+    #[error("Upload of {path} was rejected by the storage, the presigned upload url has expired. Please retry and check that your system clock is correct")]
+    PresignedUrlExpired { path: PathBuf },
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum EnvArgError {
     #[error("Invalid environment or testing environment variable. Double check you've supplied correct value\nvalue = {env_arg}")]
@@ -44,6 +65,23 @@ pub enum PullArgError {
     InvalidRootType { used_type: String },
 }
 
+#[derive(Error, Debug, PartialEq)]
+pub enum PushArgError {
+    #[error("Invalid format for --push-file argument. Expected format: LOCAL_PATH:DEVICE_PATH. Your format: {arg}")]
+    InvalidFormat { arg: String },
+
+    #[error("Device path of --push-file argument should be absolute, e.g. /sdcard/Download/image.png. Your path: {device_path}")]
+    RelativeDevicePath { device_path: String },
+
+    #[error("Local file of --push-file argument doesn't exist\npath = {path}")]
+    MissingFile { path: PathBuf },
+
+    #[error(
+        "Several files of --push-file argument are pushed to the same device path {device_path}"
+    )]
+    DuplicateDevicePath { device_path: String },
+}
+
 #[derive(Error, Debug)]
 pub enum ArtifactError {
     #[error("Failed to retrieve artifact list.\nerror = {error}")]
@@ -51,6 +89,13 @@ pub enum ArtifactError {
 
     #[error("Failed to download artifacts.\nerror = {error}")]
     DownloadFailed { error: JoinError },
+
+    #[error("Failed to download {failed} of {total} artifacts:\n{summary}")]
+    IncompleteDownload {
+        failed: usize,
+        total: usize,
+        summary: String,
+    },
 }
 
 #[derive(Error, Debug)]
@@ -67,9 +112,88 @@ pub enum InputError {
     #[error("Invalid xctestplan file: no test targets specified. Double check you've supplied correct path")]
     XctestplanMissingTargets,
 
+    #[error("Test target of the xctestplan file can't be detected from the test application. Please specify --xctestplan-target-name\ntargets = {targets:?}")]
+    XctestplanAmbiguousTarget { targets: Vec<String> },
+
+    #[error("Invalid xctestrun file: {message}\npath = {path}")]
+    InvalidXctestrun { path: PathBuf, message: String },
+
+    #[error("Invalid xcarchive: {message}\npath = {path}")]
+    InvalidXcarchive { path: PathBuf, message: String },
+
+    #[error("Invalid Detox configuration file: {message}\npath = {path}")]
+    InvalidDetoxConfig { path: PathBuf, message: String },
+
+    #[error("No files match {pattern}")]
+    NoGlobMatches { pattern: String },
+
+    #[error("Invalid glob {pattern}: {message}")]
+    InvalidGlob { pattern: String, message: String },
+
+    #[error("Gradle outputs of variant {variant} not found: {message}\nmodule = {module}")]
+    GradleOutputNotFound {
+        module: PathBuf,
+        variant: String,
+        message: String,
+    },
+
+    #[error("Build products not found in DerivedData: {message}\npath = {path}")]
+    DerivedDataProductsNotFound { path: PathBuf, message: String },
+
+    #[error("Invalid bundle file: {message}\npath = {path}")]
+    InvalidBundleFile { path: PathBuf, message: String },
+
+    #[error("Invalid app data archive: {message}\npath = {path}")]
+    InvalidAppData { path: PathBuf, message: String },
+
+    #[error("Invalid retry policy: {message}\npath = {path}")]
+    InvalidRetryPolicy { path: PathBuf, message: String },
+
+    #[error("Invalid test timeout overrides: {message}\npath = {path}")]
+    InvalidTimeoutOverrides { path: PathBuf, message: String },
+
+    #[error("Invalid module map: {message}\npath = {path}")]
+    InvalidModuleMap { path: PathBuf, message: String },
+
+    #[error("Changes since {reference} could not be determined with git: {message}")]
+    GitDiffFailed { reference: String, message: String },
+
+    #[error("Invalid matrix configuration file: {message}\npath = {path}")]
+    InvalidMatrixConfig { path: PathBuf, message: String },
+
+    #[error("Invalid Android App Bundle: {message}\npath = {path}")]
+    InvalidAab { path: PathBuf, message: String },
+
+    #[error("bundletool is required to convert .aab files but could not be started. Install bundletool or set the path of its jar with --bundletool\nerror = {error}")]
+    BundletoolNotFound { error: io::Error },
+
+    #[error("bundletool failed with {status}\n{stderr}")]
+    BundletoolFailed {
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+
     #[error("Invalid input file. All file paths should be valid UTF8\npath = {path}")]
     NonUTF8Path { path: PathBuf },
 
+    #[error("No completed test run found matching {filter}")]
+    NoMatchingRun { filter: String },
+
+    #[error("Invalid coverage file: {message}\npath = {path}")]
+    InvalidCoverageFile { path: PathBuf, message: String },
+
+    #[error("Input path doesn't exist. Double check you've supplied correct path\npath = {path}")]
+    PathNotFound { path: PathBuf },
+
+    #[error("Output path already exists and isn't a result bundle, it won't be overwritten\npath = {path}")]
+    OutputExists { path: PathBuf },
+
+    #[error("Output path would overwrite one of the inputs\npath = {path}")]
+    OutputOverwritesInput { path: PathBuf },
+
+    #[error("No {format} reports found\npaths = {paths:?}")]
+    NoReportsFound { format: String, paths: Vec<PathBuf> },
+
     #[error("Unsupported artifact format. Should be either {supported_files} file or {supported_folders} folder\npath = {path}")]
     UnsupportedArtifact {
         path: PathBuf,
@@ -85,6 +209,19 @@ pub enum InputError {
         supported: String,
     },
 
+    #[error("File is too large to upload: {size} exceeds the limit of {limit}. Consider stripping unused resources or debug symbols\npath = {path}")]
+    FileTooLarge {
+        path: PathBuf,
+        size: String,
+        limit: String,
+    },
+
+    #[error("The filters match none of the {total} tests in {path}. Double check the filters or run without --strict-filter")]
+    FilterMatchesNoTests { path: PathBuf, total: usize },
+
+    #[error("--shard-index should be less than --shard-count, got index {index} of {count} shards. Shards are numbered from 0")]
+    InvalidShard { index: u32, count: u32 },
+
     #[error("{arg} arg should be a positive number")]
     NonPositiveValue { arg: String },
 
@@ -97,6 +234,30 @@ Available permissions: calendar, contacts-limited, contacts, location, location-
 pub enum ConfigurationError {
     #[error("Unsupported run configuration: {message}")]
     UnsupportedRunConfiguration { message: String },
+    #[error("{failed} of {total} environment checks failed")]
+    EnvironmentChecksFailed { failed: usize, total: usize },
+}
+
+#[derive(Error, Debug)]
+pub enum ReportError {
+    #[error("{tool} is required to process reports but could not be started. It is only available on macOS with Xcode installed\nerror = {error}")]
+    ToolNotFound { tool: String, error: io::Error },
+    #[error("{tool} failed with {status}\n{stderr}")]
+    ToolFailed {
+        tool: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum RunError {
+    #[error("Test run {id} was cancelled")]
+    Cancelled { id: String },
+    #[error("Interrupted while waiting for test run {id}")]
+    Interrupted { id: String },
+    #[error("Test run {id} did not finish within {timeout}")]
+    TimedOut { id: String, timeout: String },
 }
 
 #[derive(Error, Debug)]
@@ -107,10 +268,77 @@ pub enum FilteringConfigurationError {
     InvalidFilterType { mtype: String },
     #[error("Invalid configuration for filter {mtype}: {message}")]
     InvalidFilterConfiguration { mtype: String, message: String },
+    #[error("Invalid filter file {path}\n{error}")]
+    InvalidFilterFile { path: PathBuf, error: String },
     #[error("The following mandatory fields in --filter-file were missed: {fields}")]
     MissedMandatoryFields { fields: String },
 }
 
+/// Process exit codes returned by the CLI. These values are part of the public contract
+/// (see README) and should never be renumbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    TestFailures = 1,
+    Configuration = 2,
+    Authentication = 3,
+    Api = 4,
+    RunCancelled = 5,
+    RunTimedOut = 6,
+    Unexpected = 10,
+    Interrupted = 130,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    //Looks at the whole chain since errors are often wrapped with additional context
+    pub fn from_error(error: &anyhow::Error) -> ExitCode {
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<ApiError>() {
+                return match error {
+                    ApiError::InvalidAuthenticationToken { .. } => ExitCode::Authentication,
+                    _ => ExitCode::Api,
+                };
+            }
+            if let Some(error) = cause.downcast_ref::<RunError>() {
+                return match error {
+                    RunError::Cancelled { .. } => ExitCode::RunCancelled,
+                    RunError::Interrupted { .. } => ExitCode::Interrupted,
+                    RunError::TimedOut { .. } => ExitCode::RunTimedOut,
+                };
+            }
+            if let Some(error) = cause.downcast_ref::<ReportError>() {
+                return match error {
+                    ReportError::ToolNotFound { .. } => ExitCode::Configuration,
+                    ReportError::ToolFailed { .. } => ExitCode::Unexpected,
+                };
+            }
+            if cause.is::<ReqwestError>()
+                || cause.is::<ArtifactError>()
+                || cause.is::<UploadError>()
+            {
+                return ExitCode::Api;
+            }
+            if cause.is::<ConfigurationError>()
+                || cause.is::<InputError>()
+                || cause.is::<EnvArgError>()
+                || cause.is::<PullArgError>()
+                || cause.is::<PushArgError>()
+                || cause.is::<FilteringConfigurationError>()
+                || cause.is::<serde_yaml::Error>()
+                || cause.is::<serde_json::Error>()
+                || cause.is::<globset::Error>()
+            {
+                return ExitCode::Configuration;
+            }
+        }
+        ExitCode::Unexpected
+    }
+}
+
 //Dumps the error to output recursively by looking at the source()
 pub fn default_error_handler(
     error: Box<dyn std::error::Error + Send + 'static>,
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use log::debug;
 use regex::Regex;
 use shellexpand;
 use std::path::{Path, PathBuf};
@@ -7,10 +8,13 @@ use tokio::{
     io::AsyncReadExt,
 };
 
-use crate::errors::{FilteringConfigurationError, InputError};
+use crate::{
+    errors::{FilteringConfigurationError, InputError},
+    formatter::{Formatter, StandardFormatter},
+};
 
 use super::{
-    model::{Filter, FilteringConfiguration, SparseMarathonfile},
+    model::{self, Filter, FilteringConfiguration, SparseMarathonfile, XctestplanOptions},
     xctestplan,
 };
 
@@ -27,12 +31,22 @@ pub async fn convert(cnf: PathBuf) -> Result<SparseMarathonfile> {
                 error,
             })?;
 
-    let mut filtering_configuration: SparseMarathonfile = serde_yaml::from_str(&content)?;
+    //Unknown fields are rejected by the model, errors carry the path and line of the offending value
+    let parsed: std::result::Result<SparseMarathonfile, String> =
+        if is_json(&expanded_path, &content) {
+            serde_json::from_str(&content).map_err(|error| error.to_string())
+        } else {
+            serde_yaml::from_str(&content).map_err(|error| error.to_string())
+        };
+    let mut filtering_configuration =
+        parsed.map_err(|error| FilteringConfigurationError::InvalidFilterFile {
+            path: PathBuf::from(&expanded_path),
+            error,
+        })?;
 
     let absolute_path = fs::canonicalize(&expanded_path).await?;
     let workdir = absolute_path.parent().unwrap_or(Path::new(""));
     validate(
-        &content,
         &mut filtering_configuration.filtering_configuration,
         workdir,
     )
@@ -41,10 +55,15 @@ pub async fn convert(cnf: PathBuf) -> Result<SparseMarathonfile> {
     Ok(filtering_configuration)
 }
 
-pub async fn convert_xctestplan(
-    cnf: PathBuf,
-    target_name: Option<String>,
-) -> Result<SparseMarathonfile> {
+//Filter files generated by tools are often JSON, which gives better error messages when parsed as such
+fn is_json(path: &str, content: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        || content.trim_start().starts_with('{')
+}
+
+async fn read_xctestplan(cnf: &Path) -> Result<xctestplan::SparseTestPlan> {
     let path = cnf.to_str().ok_or(InputError::NonUTF8Path {
         path: cnf.to_owned(),
     })?;
@@ -57,16 +76,24 @@ pub async fn convert_xctestplan(
                 error,
             })?;
 
-    let xctestplan: xctestplan::SparseTestPlan = serde_json::from_str(&content)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Filters of the plan's test target. Without `target_name` plans with multiple targets use the
+/// target built into `test_application`
+pub async fn convert_xctestplan(
+    cnf: PathBuf,
+    target_name: Option<String>,
+    test_application: Option<&Path>,
+) -> Result<SparseMarathonfile> {
+    let xctestplan = read_xctestplan(&cnf).await?;
     let targets = xctestplan.test_targets;
     let target = match target_name {
         Some(target_name) => targets
             .iter()
             .find(|x| x.target.name == target_name)
             .ok_or(InputError::XctestplanMissingTargets)?,
-        None => targets
-            .first()
-            .ok_or(InputError::XctestplanMissingTargets)?,
+        None => detect_xctestplan_target(&targets, test_application).await?,
     };
 
     let allowlist = target
@@ -89,6 +116,130 @@ pub async fn convert_xctestplan(
     Ok(marathonfile)
 }
 
+async fn detect_xctestplan_target<'a>(
+    targets: &'a [xctestplan::TestTarget],
+    test_application: Option<&Path>,
+) -> Result<&'a xctestplan::TestTarget> {
+    let enabled: Vec<&xctestplan::TestTarget> = targets
+        .iter()
+        .filter(|x| x.enabled != Some(false))
+        .collect();
+    match (&enabled[..], targets) {
+        (_, []) => return Err(InputError::XctestplanMissingTargets.into()),
+        ([target], _) => return Ok(target),
+        ([], [target, ..]) => return Ok(target),
+        _ => {}
+    }
+
+    let products = match test_application {
+        Some(path) => test_bundle_products(path).await?,
+        None => vec![],
+    };
+    let matching: Vec<&xctestplan::TestTarget> = enabled
+        .iter()
+        .copied()
+        .filter(|x| products.contains(&x.target.name))
+        .collect();
+    debug!(
+        "Test application contains products {:?}, matching test targets: {}",
+        products,
+        matching.len()
+    );
+    match &matching[..] {
+        [target] => Ok(target),
+        _ => Err(InputError::XctestplanAmbiguousTarget {
+            targets: enabled.iter().map(|x| x.target.name.clone()).collect(),
+        }
+        .into()),
+    }
+}
+
+//Product names of the test bundles in a .xctest or UI test runner .app folder, or an archive of
+//them. The product name is the target name unless PRODUCT_NAME is customized
+async fn test_bundle_products(path: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    if path.is_dir() {
+        for entry in walkdir::WalkDir::new(path).max_depth(2) {
+            names.extend(bundle_product(entry?.path()));
+        }
+    } else {
+        let reader = async_zip::tokio::read::fs::ZipFileReader::new(path).await?;
+        for entry in reader.file().entries() {
+            let filename = entry.filename().as_str()?;
+            names.extend(Path::new(filename).ancestors().filter_map(bundle_product));
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+fn bundle_product(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    match path.extension()?.to_str()? {
+        "xctest" => Some(stem.to_owned()),
+        "app" => stem.strip_suffix("-Runner").map(str::to_owned),
+        _ => None,
+    }
+}
+
+/// Environment, launch arguments, locale and timeouts of the test plan. Options of the plan's
+/// configuration override the default options when the plan has a single configuration
+pub async fn xctestplan_options(cnf: &Path) -> Result<XctestplanOptions> {
+    let xctestplan = read_xctestplan(cnf).await?;
+    let default_options = xctestplan.default_options.unwrap_or_default();
+    let configuration = match &xctestplan.configurations[..] {
+        [configuration] => Some(&configuration.options),
+        configurations => {
+            debug!(
+                "Using default options of the test plan, it has {} configurations",
+                configurations.len()
+            );
+            None
+        }
+    };
+    let defaults = &default_options;
+
+    let env = plan_option(configuration, defaults, |x| {
+        x.environmnent_variables.as_ref()
+    })
+    .into_iter()
+    .flatten()
+    .filter(|x| x.enabled != Some(false))
+    .map(|x| format!("{}={}", x.key, x.value))
+    .collect();
+    let command_line_args = plan_option(configuration, defaults, |x| {
+        x.command_line_arguments.as_ref()
+    })
+    .into_iter()
+    .flatten()
+    .filter(|x| x.enabled != Some(false))
+    .map(|x| x.argument.clone())
+    .collect();
+    let timeouts_enabled =
+        plan_option(configuration, defaults, |x| x.test_timeouts_enabled).unwrap_or(false);
+    let timeout = |get: fn(&xctestplan::SparseOptions) -> Option<u32>| {
+        plan_option(configuration, defaults, get).filter(|_| timeouts_enabled)
+    };
+
+    Ok(XctestplanOptions {
+        env,
+        command_line_args,
+        language: plan_option(configuration, defaults, |x| x.language.clone()),
+        region: plan_option(configuration, defaults, |x| x.region.clone()),
+        test_timeout_default: timeout(|x| x.default_test_execution_time_allowance),
+        test_timeout_max: timeout(|x| x.maximum_test_execution_time_allowance),
+    })
+}
+
+fn plan_option<'a, T>(
+    configuration: Option<&'a xctestplan::SparseOptions>,
+    defaults: &'a xctestplan::SparseOptions,
+    get: impl Fn(&'a xctestplan::SparseOptions) -> Option<T>,
+) -> Option<T> {
+    configuration.and_then(&get).or_else(|| get(defaults))
+}
+
 //Identifiers contain a mix of class names and class name with method signature
 //Sometimes you can see separator \/ and sometimes / for the class and method
 //Also sometime ending () are present for method filtering
@@ -98,8 +249,7 @@ fn xctestplan_ids_to_filter(ids: &[String]) -> Filter {
 
     ids.iter().for_each(|id| {
         if id.contains("/") || id.contains("(") {
-            let marathon_id = id.replace("\\/", "#").replace("/", "#").replace("()", "");
-            simple_test_names.push(marathon_id);
+            simple_test_names.push(normalize_test_id(id));
         } else {
             class_names.push(id.clone());
         }
@@ -110,61 +260,108 @@ fn xctestplan_ids_to_filter(ids: &[String]) -> Filter {
         let class_name_filter = Filter {
             mtype: "simple-class-name".into(),
             values: Some(class_names),
-            op: None,
-            file: None,
-            regex: None,
-            filters: None,
+            ..Default::default()
         };
 
         let simple_qualified_test_name_filter = Filter {
             mtype: "simple-test-name".into(),
             values: Some(simple_test_names),
-            op: None,
-            file: None,
-            regex: None,
-            filters: None,
+            ..Default::default()
         };
         Filter {
             mtype: "composition".into(),
-            values: None,
             op: Some("UNION".into()),
             filters: Some(vec![class_name_filter, simple_qualified_test_name_filter]),
-            regex: None,
-            file: None,
+            ..Default::default()
         }
     } else if !class_names.is_empty() {
         Filter {
             mtype: "simple-class-name".into(),
             values: Some(class_names),
-            op: None,
-            file: None,
-            regex: None,
-            filters: None,
+            ..Default::default()
         }
     } else {
         Filter {
             mtype: "simple-test-name".into(),
             values: Some(simple_test_names),
-            op: None,
-            file: None,
-            regex: None,
-            filters: None,
+            ..Default::default()
         }
     }
 }
 
-pub async fn validate(
-    original_content: &String,
-    cnf: &mut FilteringConfiguration,
-    workdir: &Path,
-) -> Result<()> {
-    if !original_content.contains("filteringConfiguration") {
-        anyhow::bail!(FilteringConfigurationError::MissedMandatoryFields {
-            fields: "filteringConfiguration".to_string()
-        });
+/// Allowlist filter for tests selected on the command line. Tests matching any of the values
+/// are run
+pub fn inline_filter(
+    tests: Vec<String>,
+    classes: Vec<String>,
+    packages: Vec<String>,
+    annotations: Vec<String>,
+) -> Option<Filter> {
+    let mut filters: Vec<Filter> = [
+        ("fully-qualified-test-name", tests),
+        ("fully-qualified-class-name", classes),
+        ("package", packages),
+        ("annotation", annotations),
+    ]
+    .into_iter()
+    .filter(|(_, values)| !values.is_empty())
+    .map(|(mtype, mut values)| {
+        normalize_test_ids(mtype, &mut values);
+        (mtype, values)
+    })
+    .map(|(mtype, values)| Filter {
+        mtype: mtype.into(),
+        values: Some(values),
+        ..Default::default()
+    })
+    .collect();
+
+    match filters.len() {
+        0 => None,
+        1 => filters.pop(),
+        _ => Some(Filter {
+            mtype: "composition".into(),
+            op: Some("UNION".into()),
+            filters: Some(filters),
+            ..Default::default()
+        }),
     }
+}
 
-    if !original_content.contains("allowlist") && !original_content.contains("blocklist") {
+/// Allowlist filter that keeps the `index`-th of `count` equal parts of the test suite.
+/// Fragmentation filters are only generated by the CLI, they aren't accepted in filter files
+pub fn fragmentation_filter(index: u32, count: u32) -> Filter {
+    Filter {
+        mtype: "fragmentation".into(),
+        index: Some(index),
+        count: Some(count),
+        ..Default::default()
+    }
+}
+
+/// Adds `filter` to the allowlist of `cnf`, creating the configuration if there is none
+pub fn with_allowlist_filter(
+    cnf: Option<SparseMarathonfile>,
+    filter: Option<Filter>,
+) -> Option<SparseMarathonfile> {
+    let Some(filter) = filter else {
+        return cnf;
+    };
+    let mut cnf = cnf.unwrap_or(SparseMarathonfile {
+        filtering_configuration: FilteringConfiguration {
+            allowlist: None,
+            blocklist: None,
+        },
+    });
+    cnf.filtering_configuration
+        .allowlist
+        .get_or_insert_with(Vec::new)
+        .push(filter);
+    Some(cnf)
+}
+
+pub async fn validate(cnf: &mut FilteringConfiguration, workdir: &Path) -> Result<()> {
+    if cnf.allowlist.is_none() && cnf.blocklist.is_none() {
         anyhow::bail!(FilteringConfigurationError::MissedMandatoryFields {
             fields: "At least one of 'allowlist' and 'blocklist' should be presented".to_string()
         });
@@ -178,13 +375,19 @@ pub async fn validate(
         "package",
         "method",
         "annotation",
+        "annotationData",
+        "allure",
     ];
-    let unsupported_types = vec!["allure", "fragmentation", "annotationData"];
+    let unsupported_types = vec!["fragmentation"];
 
-    for list in [&mut cnf.allowlist, &mut cnf.blocklist] {
+    for (name, list) in [
+        ("allowlist", &mut cnf.allowlist),
+        ("blocklist", &mut cnf.blocklist),
+    ] {
         match list {
             Some(filters) => {
-                validate_filters(filters, &supported_types, &unsupported_types, workdir).await?
+                validate_filters(filters, name, &supported_types, &unsupported_types, workdir)
+                    .await?
             }
             None => continue,
         }
@@ -195,12 +398,29 @@ pub async fn validate(
 
 async fn validate_filters(
     filters: &mut [Filter],
+    list: &str,
     supported_types: &[&str],
     unsupported_types: &[&str],
     workdir: &Path,
 ) -> Result<()> {
-    for filter in filters.iter_mut() {
+    for (index, filter) in filters.iter_mut().enumerate() {
+        let location = format!("{}[{}]", list, index);
         if filter.mtype == "composition" {
+            for (field, present) in [
+                ("regex", filter.regex.is_some()),
+                ("values", filter.values.is_some()),
+                ("file", filter.file.is_some()),
+            ] {
+                if present {
+                    anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
+                        mtype: filter.mtype.clone(),
+                        message: format!(
+                            "'{}' at {} is not supported, use 'filters'",
+                            field, location
+                        )
+                    });
+                }
+            }
             if filter.op.is_none() {
                 anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
                     mtype: filter.mtype.clone(),
@@ -214,9 +434,16 @@ async fn validate_filters(
             } else {
                 match filter.filters.as_mut() {
                     Some(filters) => {
-                        for filter in filters.iter_mut() {
-                            validate_filter(filter, supported_types, unsupported_types, workdir)
-                                .await?;
+                        for (index, filter) in filters.iter_mut().enumerate() {
+                            let location = format!("{}.filters[{}]", location, index);
+                            validate_filter(
+                                filter,
+                                &location,
+                                supported_types,
+                                unsupported_types,
+                                workdir,
+                            )
+                            .await?;
                         }
                     }
                     None => {
@@ -228,7 +455,14 @@ async fn validate_filters(
                 }
             }
         } else {
-            validate_filter(filter, supported_types, unsupported_types, workdir).await?;
+            validate_filter(
+                filter,
+                &location,
+                supported_types,
+                unsupported_types,
+                workdir,
+            )
+            .await?;
         }
     }
     Ok(())
@@ -236,6 +470,7 @@ async fn validate_filters(
 
 async fn validate_filter(
     filter: &mut Filter,
+    location: &str,
     supported_types: &[&str],
     unsupported_types: &[&str],
     workdir: &Path,
@@ -250,6 +485,57 @@ async fn validate_filter(
         });
     }
 
+    //Fields of other filter types are most likely misplaced, e.g. indented under the wrong filter
+    for (field, present, owner) in [
+        ("filters", filter.filters.is_some(), "composition"),
+        ("op", filter.op.is_some(), "composition"),
+        ("index", filter.index.is_some(), "fragmentation"),
+        ("count", filter.count.is_some(), "fragmentation"),
+        ("nameRegex", filter.name_regex.is_some(), "annotationData"),
+        ("valueRegex", filter.value_regex.is_some(), "annotationData"),
+    ] {
+        if present && filter.mtype != owner {
+            anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
+                mtype: filter.mtype.clone(),
+                message: format!(
+                    "'{}' at {} is only supported by {} filters",
+                    field, location, owner
+                )
+            });
+        }
+    }
+
+    for (field, pattern) in [
+        ("regex", &filter.regex),
+        ("nameRegex", &filter.name_regex),
+        ("valueRegex", &filter.value_regex),
+    ] {
+        if let Some(pattern) = pattern {
+            validate_regex(pattern, field, &filter.mtype, location);
+        }
+    }
+
+    if filter.mtype == "annotationData" {
+        return validate_annotation_data_filter(filter);
+    }
+    if filter.mtype == "allure" {
+        if filter.regex.is_some() {
+            anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
+                mtype: filter.mtype.clone(),
+                message: "regex is not supported, use values with Allure test ids or file".into()
+            })
+        }
+        if let Some(path) = filter
+            .file
+            .as_ref()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        {
+            let ids = read_allure_testplan(&filter.mtype, &workdir.join(path)).await?;
+            filter.values = Some(ids);
+            filter.file = None;
+        }
+    }
+
     match (&filter.regex, &filter.values, &filter.file) {
         (None, None, None) => {
             anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
@@ -293,12 +579,27 @@ async fn validate_filter(
                     let value = comment_regex.replace_all(value, "");
                     values.push(value.as_ref().to_owned());
                 }
-                filter.values = Some(values);
+                normalize_test_ids(&filter.mtype, &mut values);
+                if values.iter().any(|value| value.contains('*')) {
+                    let regex = wildcard_regex(&values);
+                    debug!(
+                        "Values of {} contain wildcards, using regex {}",
+                        location, regex
+                    );
+                    filter.regex = Some(regex);
+                } else {
+                    filter.values = Some(values);
+                }
                 filter.file = None;
             }
             Ok(())
         }
-        (None, Some(_), None) => Ok(()),
+        (None, Some(_), None) => {
+            if let Some(values) = filter.values.as_mut() {
+                normalize_test_ids(&filter.mtype, values);
+            }
+            Ok(())
+        }
         (Some(_), None, None) => Ok(()),
 
         _ => anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
@@ -308,12 +609,113 @@ async fn validate_filter(
     }
 }
 
+//Test names are matched as Class#method, but XCTest and IDEs also spell them Class/method,
+//Class\/method or Class.method()
+fn normalize_test_id(id: &str) -> String {
+    let id = id.replace("\\/", "/");
+    let (id, has_parentheses) = match id.strip_suffix("()") {
+        Some(id) => (id.to_owned(), true),
+        None => (id, false),
+    };
+    if id.contains('#') {
+        id
+    } else if let Some((class, method)) = id.rsplit_once('/') {
+        format!("{}#{}", class.replace('/', "."), method)
+    } else if let (true, Some((class, method))) = (has_parentheses, id.rsplit_once('.')) {
+        format!("{}#{}", class, method)
+    } else {
+        id
+    }
+}
+
+fn normalize_test_ids(mtype: &str, values: &mut [String]) {
+    if mtype == "fully-qualified-test-name" || mtype == "simple-test-name" {
+        for value in values.iter_mut() {
+            *value = normalize_test_id(value);
+        }
+    }
+}
+
+//Values filters only match exact names, lists with wildcards become a single regex matching any
+//of the values, e.g. [com.example.checkout.*, *LoginTest] -> com\.example\.checkout\..*|.*LoginTest
+fn wildcard_regex(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            value
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".*")
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+//Patterns are evaluated by the JVM in the cloud. Java syntax like look-around, possessive
+//quantifiers or \p{javaLowerCase} isn't supported by the regex crate, so a pattern which can't
+//be parsed locally is only reported
+fn validate_regex(pattern: &str, field: &str, mtype: &str, location: &str) {
+    if let Err(error) = Regex::new(pattern) {
+        StandardFormatter::new(1).warning(&format!(
+            "{} of filter {} at {} can't be checked locally, make sure it's a valid Java regex\n{}",
+            field, mtype, location, error
+        ));
+    }
+}
+
+//Allure test plans list the selected tests as {"tests": [{"id": "123", "selector": "..."}]}
+async fn read_allure_testplan(mtype: &str, path: &Path) -> Result<Vec<String>> {
+    let invalid = |message: String| FilteringConfigurationError::InvalidFilterConfiguration {
+        mtype: mtype.to_owned(),
+        message,
+    };
+    let content = fs::read_to_string(path).await.map_err(|error| {
+        invalid(format!(
+            "Can't read Allure test plan {}: {}",
+            path.display(),
+            error
+        ))
+    })?;
+    let testplan: model::AllureTestPlan = serde_json::from_str(&content)
+        .map_err(|error| invalid(format!("Invalid Allure test plan: {}", error)))?;
+    let ids: Vec<String> = testplan.tests.into_iter().filter_map(|x| x.id).collect();
+    if ids.is_empty() {
+        anyhow::bail!(invalid("Allure test plan has no test ids".into()))
+    }
+    Ok(ids)
+}
+
+//Matches annotations by name and value instead of regex, values or file
+fn validate_annotation_data_filter(filter: &Filter) -> Result<()> {
+    if filter.regex.is_some() || filter.values.is_some() || filter.file.is_some() {
+        anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
+            mtype: filter.mtype.clone(),
+            message: "regex, values and file are not supported, use nameRegex and valueRegex"
+                .into()
+        })
+    }
+    match (&filter.name_regex, &filter.value_regex) {
+        (Some(_), Some(_)) => Ok(()),
+        _ => anyhow::bail!(FilteringConfigurationError::InvalidFilterConfiguration {
+            mtype: filter.mtype.clone(),
+            message: "both nameRegex and valueRegex should be specified".into()
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
     use std::path::{self, Path};
 
-    use crate::filtering::convert::{convert, convert_xctestplan};
+    use crate::{
+        errors::InputError,
+        filtering::convert::{
+            bundle_product, convert, convert_xctestplan, fragmentation_filter, inline_filter,
+            normalize_test_id, wildcard_regex, with_allowlist_filter, xctestplan_options,
+        },
+    };
 
     #[tokio::test]
     async fn test_valid() -> Result<()> {
@@ -548,7 +950,7 @@ mod tests {
             .join("filtering")
             .join("xctestplan")
             .join("1.json");
-        let result = convert_xctestplan(fixture, None).await?;
+        let result = convert_xctestplan(fixture, None, None).await?;
         let result = serde_json::to_string(&result)?;
         assert_eq!(
             result,
@@ -568,7 +970,7 @@ mod tests {
             .join("filtering")
             .join("xctestplan")
             .join("test plan with spaces.xctestplan");
-        let result = convert_xctestplan(fixture, None).await?;
+        let result = convert_xctestplan(fixture, None, None).await?;
         let result = serde_json::to_string(&result)?;
         assert_eq!(
             result,
@@ -576,4 +978,280 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_inline_filters() -> Result<()> {
+        assert!(inline_filter(vec![], vec![], vec![], vec![]).is_none());
+
+        let single = inline_filter(
+            vec!["com.example.MainTest#testA".into()],
+            vec![],
+            vec![],
+            vec![],
+        );
+        let result = serde_json::to_string(&with_allowlist_filter(None, single))?;
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"fully-qualified-test-name","values":["com.example.MainTest#testA"]}]}}"#
+        );
+
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("valid.yaml");
+        let combined = inline_filter(
+            vec![],
+            vec!["com.example.MainTest".into()],
+            vec![],
+            vec!["com.example.Smoke".into()],
+        );
+        let result = with_allowlist_filter(Some(convert(fixture).await?), combined);
+        let result = serde_json::to_string(&result)?;
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"fully-qualified-test-name","regex":".*Test"},{"type":"composition","filters":[{"type":"fully-qualified-class-name","values":["com.example.MainTest"]},{"type":"annotation","values":["com.example.Smoke"]}],"op":"UNION"}]}}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fragmentation_filter() -> Result<()> {
+        let result = with_allowlist_filter(None, Some(fragmentation_filter(1, 4)));
+        let result = serde_json::to_string(&result)?;
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"fragmentation","index":1,"count":4}]}}"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_annotation_data() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("annotationData.yaml");
+        let result = convert(fixture).await?;
+        let result = serde_json::to_string(&result)?;
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"annotationData","nameRegex":"com.example.Priority","valueRegex":"HIGH|MEDIUM"}]}}"#
+        );
+
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("annotationDataMissingValue.yaml");
+        assert!(convert(fixture).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_allure() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("allure.yaml");
+        let result = convert(fixture).await?;
+        let result = serde_json::to_string(&result)?;
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"allure","values":["1001","1002"]}],"blocklist":[{"type":"allure","values":["42"]}]}}"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_regex_is_left_to_the_cloud() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("invalidRegex.yaml");
+        assert!(convert(fixture).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_java_regex_is_not_rejected() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("javaRegex.yaml");
+        assert!(convert(fixture).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_valid_json() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("validComplex.json");
+        let result = convert(fixture).await?;
+        let result = serde_json::to_string(&result)?;
+
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"package","values":["com.example.tests"]},{"type":"composition","filters":[{"type":"method","regex":"test.*"},{"type":"annotation","values":["com.example.MyAnnotation"]}],"op":"UNION"}],"blocklist":[{"type":"package","values":["com.example.tests2"]}]}}"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_schema_errors_have_locations() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let error = |name: &str| {
+            let fixture = Path::new(&manifest_dir)
+                .join("fixture")
+                .join("filtering")
+                .join(name);
+            async move { convert(fixture).await.err().unwrap().to_string() }
+        };
+
+        let unknown_field = error("unknownField.yaml").await;
+        assert!(
+            unknown_field.contains("filteringConfiguration.allowlist[0]: unknown field `regexp`"),
+            "{}",
+            unknown_field
+        );
+        assert!(unknown_field.contains("line 4"), "{}", unknown_field);
+
+        let wrong_type = error("wrongValuesType.yaml").await;
+        assert!(
+            wrong_type.contains("filteringConfiguration.blocklist[0].values: invalid type"),
+            "{}",
+            wrong_type
+        );
+        assert!(wrong_type.contains("line 5"), "{}", wrong_type);
+
+        let misplaced = error("misplacedField.yaml").await;
+        assert!(
+            misplaced.contains("'op' at allowlist[0] is only supported by composition filters"),
+            "{}",
+            misplaced
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filetype_with_wildcards() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("filetype_with_wildcards.yaml");
+        let result = convert(fixture).await?;
+        let result = serde_json::to_string(&result)?;
+        assert_eq!(
+            result,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"fully-qualified-class-name","regex":"com\\.example\\.MainTest|com\\.example\\.checkout\\..*|.*LoginTest"}]}}"#
+        );
+
+        let regex = regex::Regex::new(&format!("^(?:{})$", wildcard_regex(&["a.b*".into()])))?;
+        assert!(regex.is_match("a.bc"));
+        assert!(!regex.is_match("axbc"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_test_id() {
+        for id in [
+            "com.example.MainTest#testLogin",
+            "com.example.MainTest/testLogin",
+            "com.example.MainTest\\/testLogin",
+            "com.example.MainTest.testLogin()",
+            "com.example.MainTest/testLogin()",
+        ] {
+            assert_eq!(
+                normalize_test_id(id),
+                "com.example.MainTest#testLogin",
+                "{}",
+                id
+            );
+        }
+        assert_eq!(
+            normalize_test_id("AppTests/LoginTests/testLogin"),
+            "AppTests.LoginTests#testLogin"
+        );
+        assert_eq!(
+            normalize_test_id("com.example.MainTest"),
+            "com.example.MainTest"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xctestplan_options() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("xctestplan")
+            .join("options.xctestplan");
+        let options = xctestplan_options(&fixture).await?;
+
+        assert_eq!(options.env, vec!["API_URL=https://staging.example.com"]);
+        assert_eq!(options.command_line_args, vec!["-uitesting"]);
+        assert_eq!(options.language.as_deref(), Some("de"));
+        assert_eq!(options.region.as_deref(), Some("DE"));
+        assert_eq!(options.test_timeout_default, Some(120));
+        assert_eq!(options.test_timeout_max, Some(600));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xctestplan_target_detection() -> Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest_dir)
+            .join("fixture")
+            .join("filtering")
+            .join("xctestplan")
+            .join("multiple-targets.xctestplan");
+        let workdir = tempfile::tempdir()?;
+        let runner = workdir.path().join("sample-appUITests-Runner.app");
+        std::fs::create_dir_all(runner.join("PlugIns").join("sample-appUITests.xctest"))?;
+        let unit_tests = workdir.path().join("sample-appTests.xctest");
+        std::fs::create_dir_all(&unit_tests)?;
+
+        let result = convert_xctestplan(fixture.clone(), None, Some(&runner)).await?;
+        assert_eq!(
+            serde_json::to_string(&result)?,
+            r#"{"filteringConfiguration":{"allowlist":[{"type":"simple-class-name","values":["LoginUITests"]}]}}"#
+        );
+        let result = convert_xctestplan(fixture.clone(), None, Some(&unit_tests)).await?;
+        assert_eq!(
+            serde_json::to_string(&result)?,
+            r#"{"filteringConfiguration":{"blocklist":[{"type":"simple-class-name","values":["SlowTests"]}]}}"#
+        );
+
+        let Err(error) = convert_xctestplan(fixture, None, None).await else {
+            panic!("target should be ambiguous without the test application");
+        };
+        match error.downcast_ref::<InputError>() {
+            Some(InputError::XctestplanAmbiguousTarget { targets }) => {
+                assert_eq!(targets, &vec!["sample-appTests", "sample-appUITests"])
+            }
+            _ => panic!("unexpected error {error}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_product() {
+        assert_eq!(
+            bundle_product(Path::new("Build/sample-appUITests-Runner.app")).as_deref(),
+            Some("sample-appUITests")
+        );
+        assert_eq!(
+            bundle_product(Path::new("sample-appTests.xctest")).as_deref(),
+            Some("sample-appTests")
+        );
+        assert_eq!(bundle_product(Path::new("sample-app.app")), None);
+        assert_eq!(bundle_product(Path::new("Info.plist")), None);
+    }
 }
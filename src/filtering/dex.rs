@@ -0,0 +1,480 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use async_zip::base::read::mem::ZipFileReader;
+
+const JUNIT4_TEST: &str = "Lorg/junit/Test;";
+const RUN_WITH: &str = "Lorg/junit/runner/RunWith;";
+//Tests of these runners are named at runtime, e.g. testLogin[0]
+const PARAMETERIZED_RUNNERS: [&str; 3] = [
+    "Lorg/junit/runners/Parameterized;",
+    "Ljunitparams/JUnitParamsRunner;",
+    "Lcom/google/testing/junit/testparameterinjector/TestParameterInjector;",
+];
+const ACC_ABSTRACT: u32 = 0x400;
+const NO_INDEX: u32 = 0xFFFF_FFFF;
+const VALUE_TYPE: u8 = 0x18;
+
+/// Test method found in the dex files of a test APK
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestMethod {
+    pub pkg: String,
+    pub class: String,
+    pub method: String,
+    /// Annotations of the method and its class, e.g. `com.example.Smoke`
+    pub annotations: Vec<String>,
+}
+
+/// JUnit4 tests of the test APK at `path`. Test methods inherited from other classes
+/// of the APK are reported for each concrete subclass. Fails for APKs with JUnit3 or
+/// parameterized tests, their names aren't known before the run
+pub async fn list_tests(path: &Path) -> Result<Vec<TestMethod>> {
+    let reader = ZipFileReader::new(tokio::fs::read(path).await?).await?;
+    let mut classes = HashMap::new();
+    for index in 0..reader.file().entries().len() {
+        let entry = &reader.file().entries()[index];
+        let name = entry.filename().as_str()?;
+        if !(name.starts_with("classes") && name.ends_with(".dex") && !name.contains('/')) {
+            continue;
+        }
+        let mut data = Vec::new();
+        reader
+            .reader_with_entry(index)
+            .await?
+            .read_to_end_checked(&mut data)
+            .await?;
+        read_classes(&data, &mut classes)?;
+    }
+    collect_tests(&classes)
+}
+
+#[derive(Debug, Default)]
+struct DexClass {
+    superclass: Option<String>,
+    is_abstract: bool,
+    annotations: Vec<String>,
+    //Runner of @RunWith
+    runner: Option<String>,
+    //Methods annotated with @Test and all of their annotations
+    tests: Vec<(String, Vec<String>)>,
+}
+
+fn collect_tests(classes: &HashMap<String, DexClass>) -> Result<Vec<TestMethod>> {
+    let mut tests = Vec::new();
+    let mut names: Vec<&String> = classes.keys().collect();
+    names.sort();
+    for name in names {
+        let class = &classes[name];
+        if class.is_abstract {
+            continue;
+        }
+        if let Some(kind) = runtime_named(classes, class) {
+            bail!("{} has {} tests", java_name(name), kind);
+        }
+        let (pkg, simple_name) = match java_name(name).rsplit_once('.') {
+            Some((pkg, simple_name)) => (pkg.to_owned(), simple_name.to_owned()),
+            None => (String::new(), java_name(name)),
+        };
+        let mut current = Some(class);
+        let mut seen = Vec::new();
+        while let Some(class) = current {
+            for (method, annotations) in &class.tests {
+                if seen.contains(method) {
+                    continue;
+                }
+                seen.push(method.clone());
+                let mut annotations = annotations.clone();
+                for annotation in &classes[name].annotations {
+                    if !annotations.contains(annotation) {
+                        annotations.push(annotation.clone());
+                    }
+                }
+                tests.push(TestMethod {
+                    pkg: pkg.clone(),
+                    class: simple_name.clone(),
+                    method: method.clone(),
+                    annotations,
+                });
+            }
+            current = class.superclass.as_ref().and_then(|x| classes.get(x));
+        }
+    }
+    Ok(tests)
+}
+
+//@RunWith is inherited, JUnit3 tests extend TestCase. Superclasses outside of the APK are
+//only known by name
+fn runtime_named(classes: &HashMap<String, DexClass>, class: &DexClass) -> Option<&'static str> {
+    let mut current = Some(class);
+    while let Some(class) = current {
+        if class
+            .runner
+            .as_ref()
+            .is_some_and(|x| PARAMETERIZED_RUNNERS.contains(&x.as_str()))
+        {
+            return Some("parameterized");
+        }
+        let superclass = class.superclass.as_deref()?;
+        if superclass == "Ljunit/framework/TestCase;" || superclass.starts_with("Landroid/test/") {
+            return Some("JUnit3");
+        }
+        current = classes.get(superclass);
+    }
+    None
+}
+
+//Lcom/example/MainTest; -> com.example.MainTest
+fn java_name(descriptor: &str) -> String {
+    descriptor
+        .trim_start_matches('L')
+        .trim_end_matches(';')
+        .replace('/', ".")
+}
+
+struct Dex<'a> {
+    data: &'a [u8],
+}
+
+impl Dex<'_> {
+    //Offsets are read from the file, so they are checked instead of slicing
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| anyhow!("dex file is truncated"))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(offset, 4)?.try_into()?))
+    }
+
+    fn uleb128(&self, offset: &mut usize) -> Result<u32> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.bytes(*offset, 1)?[0];
+            *offset += 1;
+            value |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("malformed uleb128 value")
+    }
+
+    fn string(&self, index: u32) -> Result<String> {
+        let ids_off = self.u32(0x3C)? as usize;
+        let mut offset = self.u32(ids_off + index as usize * 4)? as usize;
+        self.uleb128(&mut offset)?;
+        let data = self
+            .data
+            .get(offset..)
+            .ok_or_else(|| anyhow!("dex file is truncated"))?;
+        let end = data
+            .iter()
+            .position(|x| *x == 0)
+            .ok_or_else(|| anyhow!("unterminated dex string"))?;
+        Ok(String::from_utf8_lossy(&data[..end]).into_owned())
+    }
+
+    fn type_name(&self, index: u32) -> Result<String> {
+        let ids_off = self.u32(0x44)? as usize;
+        self.string(self.u32(ids_off + index as usize * 4)?)
+    }
+
+    //Name of a method_id_item
+    fn method(&self, index: u32) -> Result<String> {
+        let ids_off = self.u32(0x5C)? as usize;
+        self.string(self.u32(ids_off + index as usize * 8 + 4)?)
+    }
+
+    fn annotation_set(&self, offset: u32) -> Result<Vec<String>> {
+        if offset == 0 {
+            return Ok(vec![]);
+        }
+        let offset = offset as usize;
+        let mut annotations = Vec::new();
+        for i in 0..self.u32(offset)? as usize {
+            //annotation_item starts with a visibility byte followed by the encoded annotation
+            let mut item = self.u32(offset + 4 + i * 4)? as usize + 1;
+            let type_idx = self.uleb128(&mut item)?;
+            annotations.push(self.type_name(type_idx)?);
+        }
+        Ok(annotations)
+    }
+
+    //Class of the first element of `annotation` in the set, e.g. the runner of @RunWith
+    fn class_value(&self, offset: u32, annotation: &str) -> Result<Option<String>> {
+        if offset == 0 {
+            return Ok(None);
+        }
+        let offset = offset as usize;
+        for i in 0..self.u32(offset)? as usize {
+            let mut item = self.u32(offset + 4 + i * 4)? as usize + 1;
+            if self.type_name(self.uleb128(&mut item)?)? != annotation {
+                continue;
+            }
+            if self.uleb128(&mut item)? == 0 {
+                return Ok(None);
+            }
+            //name_idx of the element, followed by an encoded_value
+            self.uleb128(&mut item)?;
+            let header = self.bytes(item, 1)?[0];
+            if header & 0x1F != VALUE_TYPE {
+                return Ok(None);
+            }
+            let index = self
+                .bytes(item + 1, (header >> 5) as usize + 1)?
+                .iter()
+                .rev()
+                .fold(0u32, |value, byte| value << 8 | *byte as u32);
+            return Ok(Some(self.type_name(index)?));
+        }
+        Ok(None)
+    }
+}
+
+fn read_classes(data: &[u8], classes: &mut HashMap<String, DexClass>) -> Result<()> {
+    if !data.starts_with(b"dex\n") {
+        bail!("not a dex file");
+    }
+    let dex = Dex { data };
+    let class_defs_size = dex.u32(0x60)? as usize;
+    let class_defs_off = dex.u32(0x64)? as usize;
+    for i in 0..class_defs_size {
+        let def = class_defs_off + i * 32;
+        let name = dex.type_name(dex.u32(def)?)?;
+        let superclass = match dex.u32(def + 8)? {
+            NO_INDEX => None,
+            index => Some(dex.type_name(index)?),
+        };
+        let mut class = DexClass {
+            superclass,
+            is_abstract: dex.u32(def + 4)? & ACC_ABSTRACT != 0,
+            ..Default::default()
+        };
+
+        let mut method_annotations = HashMap::new();
+        let annotations_off = dex.u32(def + 20)? as usize;
+        if annotations_off != 0 {
+            let class_set = dex.u32(annotations_off)?;
+            class.annotations = dex
+                .annotation_set(class_set)?
+                .iter()
+                .map(|x| java_name(x))
+                .collect();
+            class.runner = dex.class_value(class_set, RUN_WITH)?;
+            let fields_size = dex.u32(annotations_off + 4)? as usize;
+            let methods_size = dex.u32(annotations_off + 8)? as usize;
+            let methods_off = annotations_off + 16 + fields_size * 8;
+            for m in 0..methods_size {
+                let method_idx = dex.u32(methods_off + m * 8)?;
+                let set = dex.annotation_set(dex.u32(methods_off + m * 8 + 4)?)?;
+                method_annotations.insert(method_idx, set);
+            }
+        }
+        for (method_idx, annotations) in method_annotations {
+            if annotations.iter().any(|x| x == JUNIT4_TEST) {
+                class.tests.push((
+                    dex.method(method_idx)?,
+                    annotations
+                        .iter()
+                        .filter(|x| *x != JUNIT4_TEST)
+                        .map(|x| java_name(x))
+                        .collect(),
+                ));
+            }
+        }
+        class.tests.sort();
+        classes.insert(name, class);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uleb128(out: &mut Vec<u8>, mut value: u32) {
+        while value >= 0x80 {
+            out.push((value as u8 & 0x7F) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn put(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    //Dex file with an abstract base class declaring one test and a subclass declaring another.
+    //The base class is annotated with @RunWith(runner)
+    fn sample_dex(runner: &str) -> Vec<u8> {
+        let strings = [
+            "Lcom/example/BaseTest;",
+            "Lcom/example/LoginTest;",
+            "Lcom/example/Smoke;",
+            "Lorg/junit/Test;",
+            "testBase",
+            "testLogin",
+            RUN_WITH,
+            runner,
+            "value",
+        ];
+        let mut data = vec![0u8; 0x70];
+        data[..8].copy_from_slice(b"dex\n035\0");
+
+        let string_ids = data.len();
+        data.resize(string_ids + strings.len() * 4, 0);
+        for (i, string) in strings.iter().enumerate() {
+            let offset = data.len() as u32;
+            put(&mut data, string_ids + i * 4, offset);
+            uleb128(&mut data, string.len() as u32);
+            data.extend(string.as_bytes());
+            data.push(0);
+        }
+        put(&mut data, 0x3C, string_ids as u32);
+
+        //Types 0..4 are the first four strings, types 4 and 5 are RunWith and the runner
+        let type_ids = data.len();
+        for i in [0u32, 1, 2, 3, 6, 7] {
+            data.extend(i.to_le_bytes());
+        }
+        put(&mut data, 0x44, type_ids as u32);
+
+        //Method 0 is BaseTest.testBase, method 1 LoginTest.testLogin
+        let method_ids = data.len();
+        for (class, name) in [(0u16, 4u32), (1, 5)] {
+            data.extend(class.to_le_bytes());
+            data.extend(0u16.to_le_bytes());
+            data.extend(name.to_le_bytes());
+        }
+        put(&mut data, 0x5C, method_ids as u32);
+
+        let annotation = |data: &mut Vec<u8>, type_idx: u32| {
+            let offset = data.len() as u32;
+            data.push(1);
+            uleb128(data, type_idx);
+            uleb128(data, 0);
+            offset
+        };
+        let test = annotation(&mut data, 3);
+        let smoke = annotation(&mut data, 2);
+        let run_with = data.len() as u32;
+        data.extend([1, 4, 1, 8, VALUE_TYPE, 5]);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        let set = |data: &mut Vec<u8>, items: &[u32]| {
+            let offset = data.len() as u32;
+            data.extend((items.len() as u32).to_le_bytes());
+            for item in items {
+                data.extend(item.to_le_bytes());
+            }
+            offset
+        };
+        let test_set = set(&mut data, &[test]);
+        let smoke_test_set = set(&mut data, &[test, smoke]);
+        let smoke_set = set(&mut data, &[smoke]);
+        let run_with_set = set(&mut data, &[run_with]);
+
+        let directory = |data: &mut Vec<u8>, class_set: u32, method: u32, method_set: u32| {
+            let offset = data.len() as u32;
+            for value in [class_set, 0, 1, 0, method, method_set] {
+                data.extend(value.to_le_bytes());
+            }
+            offset
+        };
+        let base_annotations = directory(&mut data, run_with_set, 0, test_set);
+        let login_annotations = directory(&mut data, smoke_set, 1, smoke_test_set);
+
+        let class_defs = data.len();
+        for (class, flags, superclass, annotations) in [
+            (0u32, ACC_ABSTRACT, NO_INDEX, base_annotations),
+            (1, 0, 0, login_annotations),
+        ] {
+            for value in [class, flags, superclass, 0, NO_INDEX, annotations, 0, 0] {
+                data.extend(value.to_le_bytes());
+            }
+        }
+        put(&mut data, 0x60, 2);
+        put(&mut data, 0x64, class_defs as u32);
+        data
+    }
+
+    #[test]
+    fn test_read_classes() {
+        let mut classes = HashMap::new();
+        read_classes(
+            &sample_dex("Landroidx/test/ext/junit/runners/AndroidJUnit4;"),
+            &mut classes,
+        )
+        .unwrap();
+
+        let tests = collect_tests(&classes).unwrap();
+        let test = |method: &str, annotations: &[&str]| TestMethod {
+            pkg: "com.example".to_owned(),
+            class: "LoginTest".to_owned(),
+            method: method.to_owned(),
+            annotations: annotations.iter().map(|x| x.to_string()).collect(),
+        };
+        assert_eq!(
+            tests,
+            vec![
+                test("testLogin", &["com.example.Smoke"]),
+                test("testBase", &["com.example.Smoke"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_classes_rejects_other_files() {
+        assert!(read_classes(b"PK\x03\x04", &mut HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_read_classes_of_truncated_files() {
+        let data = sample_dex("Lorg/junit/runners/JUnit4;");
+        for len in 0..data.len() {
+            //Trailing fields of the class definitions aren't read
+            let _ = read_classes(&data[..len], &mut HashMap::new());
+        }
+        assert!(read_classes(&data[..0x80], &mut HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_parameterized_tests_are_not_listed() {
+        let mut classes = HashMap::new();
+        read_classes(
+            &sample_dex("Lorg/junit/runners/Parameterized;"),
+            &mut classes,
+        )
+        .unwrap();
+
+        let error = collect_tests(&classes).unwrap_err().to_string();
+        assert_eq!(error, "com.example.LoginTest has parameterized tests");
+    }
+
+    #[test]
+    fn test_junit3_tests_are_not_listed() {
+        let classes = HashMap::from([
+            (
+                "Lcom/example/BaseTest;".to_owned(),
+                DexClass {
+                    superclass: Some("Ljunit/framework/TestCase;".to_owned()),
+                    is_abstract: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "Lcom/example/LoginTest;".to_owned(),
+                DexClass {
+                    superclass: Some("Lcom/example/BaseTest;".to_owned()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let error = collect_tests(&classes).unwrap_err().to_string();
+        assert_eq!(error, "com.example.LoginTest has JUnit3 tests");
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use anyhow::Result;
+use log::debug;
+use regex::Regex;
+
+use crate::{
+    errors::InputError,
+    formatter::{Formatter, StandardFormatter},
+};
+
+use super::{
+    dex::{self, TestMethod},
+    model::{Filter, FilteringConfiguration},
+};
+
+/// Checks the filters against the tests of the test APK before the run is submitted, so that an
+/// over-restrictive allowlist doesn't result in a run that executes nothing. Fails with `strict`,
+/// warns otherwise. Filters that can only be evaluated in the cloud skip the check
+pub async fn check_android(
+    cnf: &FilteringConfiguration,
+    test_application: &Path,
+    strict: bool,
+) -> Result<()> {
+    let tests = match dex::list_tests(test_application).await {
+        Ok(tests) => tests,
+        Err(error) => {
+            debug!(
+                "Skipping local filter check, can't list tests of {}: {}",
+                test_application.display(),
+                error
+            );
+            return Ok(());
+        }
+    };
+    match count_matching(cnf, &tests) {
+        Some(0) if !tests.is_empty() => {
+            if strict {
+                return Err(InputError::FilterMatchesNoTests {
+                    path: test_application.to_path_buf(),
+                    total: tests.len(),
+                })?;
+            }
+            StandardFormatter::new(1).warning(&format!(
+                "the filters match none of the {} tests in {}, the run will not execute any tests",
+                tests.len(),
+                test_application.display()
+            ));
+        }
+        Some(count) => debug!("Filters match {} of {} tests", count, tests.len()),
+        None => debug!("Filters can't be evaluated locally, skipping the check"),
+    }
+    Ok(())
+}
+
+/// Number of `tests` selected by the configuration, None if it uses filters that can't be
+/// evaluated locally
+pub fn count_matching(cnf: &FilteringConfiguration, tests: &[TestMethod]) -> Option<usize> {
+    let mut count = 0;
+    for test in tests {
+        let mut selected = true;
+        for filter in cnf.allowlist.iter().flatten() {
+            selected &= matches(filter, test)?;
+        }
+        for filter in cnf.blocklist.iter().flatten() {
+            selected &= !matches(filter, test)?;
+        }
+        if selected {
+            count += 1;
+        }
+    }
+    Some(count)
+}
+
+fn matches(filter: &Filter, test: &TestMethod) -> Option<bool> {
+    if filter.mtype == "composition" {
+        let results = filter
+            .filters
+            .as_ref()?
+            .iter()
+            .map(|x| matches(x, test))
+            .collect::<Option<Vec<bool>>>()?;
+        return match filter.op.as_deref()? {
+            "UNION" => Some(results.iter().any(|x| *x)),
+            "INTERSECTION" => Some(results.iter().all(|x| *x)),
+            "SUBTRACT" => {
+                Some(results.first().copied().unwrap_or(false) && !results[1..].iter().any(|x| *x))
+            }
+            _ => None,
+        };
+    }
+
+    let keys = match filter.mtype.as_str() {
+        "fully-qualified-test-name" => vec![format!("{}#{}", qualified_class(test), test.method)],
+        "fully-qualified-class-name" => vec![qualified_class(test)],
+        "simple-class-name" => vec![test.class.clone()],
+        "simple-test-name" => vec![format!("{}#{}", test.class, test.method)],
+        "package" => vec![test.pkg.clone()],
+        "method" => vec![test.method.clone()],
+        "annotation" => test.annotations.clone(),
+        _ => return None,
+    };
+    match (&filter.values, &filter.regex) {
+        (Some(values), None) => Some(keys.iter().any(|key| values.contains(key))),
+        //Filters are applied by the JVM with full matches
+        (None, Some(regex)) => {
+            let regex = Regex::new(&format!("^(?:{})$", regex)).ok()?;
+            Some(keys.iter().any(|key| regex.is_match(key)))
+        }
+        _ => None,
+    }
+}
+
+fn qualified_class(test: &TestMethod) -> String {
+    if test.pkg.is_empty() {
+        test.class.clone()
+    } else {
+        format!("{}.{}", test.pkg, test.class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(class: &str, method: &str, annotations: &[&str]) -> TestMethod {
+        TestMethod {
+            pkg: "com.example".to_owned(),
+            class: class.to_owned(),
+            method: method.to_owned(),
+            annotations: annotations.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    fn configuration(yaml: &str) -> FilteringConfiguration {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_count_matching() {
+        let tests = vec![
+            test("LoginTest", "testLogin", &["com.example.Smoke"]),
+            test("LoginTest", "testLogout", &[]),
+            test("CheckoutTest", "testPay", &["com.example.Smoke"]),
+        ];
+
+        let cnf = configuration(
+            r#"
+allowlist:
+  - type: "annotation"
+    values: ["com.example.Smoke"]
+blocklist:
+  - type: "simple-class-name"
+    regex: "Checkout.*"
+"#,
+        );
+        assert_eq!(count_matching(&cnf, &tests), Some(1));
+
+        let cnf = configuration(
+            r#"
+allowlist:
+  - type: "composition"
+    op: "UNION"
+    filters:
+      - type: "fully-qualified-test-name"
+        values: ["com.example.LoginTest#testLogout"]
+      - type: "method"
+        regex: "testPay"
+"#,
+        );
+        assert_eq!(count_matching(&cnf, &tests), Some(2));
+
+        let cnf = configuration(
+            r#"
+allowlist:
+  - type: "package"
+    values: ["com.example.login"]
+"#,
+        );
+        assert_eq!(count_matching(&cnf, &tests), Some(0));
+    }
+
+    #[test]
+    fn test_count_matching_skips_cloud_only_filters() {
+        let cnf = configuration(
+            r#"
+allowlist:
+  - type: "allure"
+    values: ["1001"]
+"#,
+        );
+        assert_eq!(
+            count_matching(&cnf, &[test("LoginTest", "testLogin", &[])]),
+            None
+        );
+    }
+}
@@ -1,3 +1,5 @@
 pub mod convert;
+pub mod dex;
+pub mod matcher;
 pub mod model;
 mod xctestplan;
@@ -4,14 +4,15 @@ use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
 #[skip_serializing_none]
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct SparseMarathonfile {
     #[serde(rename = "filteringConfiguration")]
     pub filtering_configuration: FilteringConfiguration,
 }
 
 #[skip_serializing_none]
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct FilteringConfiguration {
     #[serde(rename = "allowlist")]
     pub allowlist: Option<Vec<Filter>>,
@@ -21,7 +22,8 @@ pub struct FilteringConfiguration {
 
 // Very simplstic and flattened representation of https://github.com/MarathonLabs/marathon/blob/0.9.1/configuration/src/main/kotlin/com/malinskiy/marathon/config/FilteringConfiguration.kt
 #[skip_serializing_none]
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Filter {
     #[serde(rename = "type")]
     pub mtype: String,
@@ -37,4 +39,40 @@ pub struct Filter {
     pub filters: Option<Vec<Filter>>,
     #[serde[rename = "op"]]
     pub op: Option<String>,
+
+    #[serde[rename = "index"]]
+    pub index: Option<u32>,
+    #[serde[rename = "count"]]
+    pub count: Option<u32>,
+
+    #[serde[rename = "nameRegex"]]
+    pub name_regex: Option<String>,
+    #[serde[rename = "valueRegex"]]
+    pub value_regex: Option<String>,
+}
+
+/// Allure TestOps test plan, only the ids of the selected tests are used
+#[derive(Deserialize)]
+pub struct AllureTestPlan {
+    #[serde(rename = "tests")]
+    pub tests: Vec<AllureTestPlanEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct AllureTestPlanEntry {
+    #[serde(rename = "id")]
+    pub id: Option<String>,
+}
+
+/// Run settings of an xctestplan that are applied in the cloud as well
+#[derive(Debug, Default, PartialEq)]
+pub struct XctestplanOptions {
+    /// Environment variables as KEY=VALUE
+    pub env: Vec<String>,
+    pub command_line_args: Vec<String>,
+    pub language: Option<String>,
+    pub region: Option<String>,
+    /// Test timeouts in seconds
+    pub test_timeout_default: Option<u32>,
+    pub test_timeout_max: Option<u32>,
 }
@@ -2,6 +2,7 @@ use serde::Deserialize;
 
 //Version 1
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct TestPlan {
     #[serde[rename = "configurations"]]
     pub configurations: Vec<Configuration>,
@@ -16,12 +17,43 @@ pub struct TestPlan {
 #[derive(Deserialize)]
 pub struct SparseTestPlan {
     #[serde[rename = "configurations"]]
-    pub configurations: Vec<Configuration>,
+    pub configurations: Vec<SparseConfiguration>,
+    #[serde[rename = "defaultOptions", default]]
+    pub default_options: Option<SparseOptions>,
     #[serde[rename = "testTargets"]]
     pub test_targets: Vec<TestTarget>,
 }
 
 #[derive(Deserialize)]
+pub struct SparseConfiguration {
+    #[allow(dead_code)]
+    #[serde[rename = "name"]]
+    pub name: String,
+    #[serde[rename = "options"]]
+    pub options: SparseOptions,
+}
+
+//Options which change how tests behave in the cloud
+#[derive(Deserialize, Default)]
+pub struct SparseOptions {
+    #[serde[rename = "environmentVariableEntries"]]
+    pub environmnent_variables: Option<Vec<EnvironmentVariableEntry>>,
+    #[serde[rename = "commandLineArgumentEntries"]]
+    pub command_line_arguments: Option<Vec<CommandLineArgumentEntry>>,
+    #[serde[rename = "language"]]
+    pub language: Option<String>,
+    #[serde[rename = "region"]]
+    pub region: Option<String>,
+    #[serde[rename = "testTimeoutsEnabled"]]
+    pub test_timeouts_enabled: Option<bool>,
+    #[serde[rename = "defaultTestExecutionTimeAllowance"]]
+    pub default_test_execution_time_allowance: Option<u32>,
+    #[serde[rename = "maximumTestExecutionTimeAllowance"]]
+    pub maximum_test_execution_time_allowance: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct Configuration {
     #[serde[rename = "id"]]
     pub id: String,
@@ -32,6 +64,7 @@ pub struct Configuration {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct Options {
     #[serde[rename = "environmentVariableEntries"]]
     pub environmnent_variables: Option<Vec<EnvironmentVariableEntry>>,
@@ -93,8 +126,10 @@ pub struct Options {
 
 #[derive(Deserialize)]
 pub struct Target {
+    #[allow(dead_code)]
     #[serde[rename = "containerPath"]]
     pub container_path: String,
+    #[allow(dead_code)]
     #[serde[rename = "identifier"]]
     pub identifier: String,
     #[serde[rename = "name"]]
@@ -120,6 +155,7 @@ pub struct EnvironmentVariableEntry {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct LocationScenario {
     #[serde[rename = "identifier"]]
     pub identifier: String,
@@ -129,6 +165,7 @@ pub struct LocationScenario {
 
 #[derive(Deserialize)]
 pub struct TestTarget {
+    #[allow(dead_code)]
     #[serde[rename = "parallelizable"]]
     pub parallelizable: Option<bool>,
     #[serde[rename = "skippedTests"]]
@@ -142,6 +179,7 @@ pub struct TestTarget {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct SparseTestTarget {
     #[serde[rename = "skippedTests"]]
     pub skipped_tests: Option<Vec<String>>,
@@ -152,6 +190,7 @@ pub struct SparseTestTarget {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct AddressSanitizer {
     #[serde[rename = "detectStackUseAfterReturn"]]
     pub detect_stack_use_after_return: Option<bool>,
@@ -160,6 +199,7 @@ pub struct AddressSanitizer {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct MallocStackLoggingOptions {
     #[serde[rename = "loggingType"]]
     pub logging_type: Option<MallocStackLoggingType>,
@@ -167,6 +207,7 @@ pub struct MallocStackLoggingOptions {
 
 //None is represented as null value
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum MallocStackLoggingType {
     #[serde[rename = "liveAllocations"]]
     LiveAllocationsOnly,
@@ -175,6 +216,7 @@ pub enum MallocStackLoggingType {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum TestExecutionOrdering {
     #[serde[rename = "random"]]
     Random,
@@ -183,6 +225,7 @@ pub enum TestExecutionOrdering {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum AttachmentLifetime {
     #[serde[rename = "keepAlways"]]
     OnAndKeepAll,
@@ -193,6 +236,7 @@ pub enum AttachmentLifetime {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum TestRepetitionMode {
     #[serde[rename = "untilFailure"]]
     UntilFailure,
@@ -205,6 +249,7 @@ pub enum TestRepetitionMode {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum ScreenCaptureFormat {
     #[serde[rename = "screenshot"]]
     Screenshot,
@@ -213,6 +258,7 @@ pub enum ScreenCaptureFormat {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum DiagnosticCollectionPolicy {
     #[serde[rename = "xcodebuild"]]
     WhenTestingWithXcodebuild,
@@ -223,6 +269,7 @@ pub enum DiagnosticCollectionPolicy {
 }
 
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub enum LocationReferenceType {
     #[serde[rename = "built-in"]]
     BuiltIn,
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::hash::{digest_file, md5_str, HashAlgorithm};
+
+/// Fingerprint of a run: the md5 of every binary, in order, and of the test configuration.
+/// Runs with the same fingerprint execute the same tests on the same devices
+pub async fn fingerprint(binaries: &[&Path], configuration: &impl Serialize) -> Result<String> {
+    let mut lines = Vec::new();
+    for binary in binaries {
+        lines.push(digest(binary).await?);
+    }
+    lines.push(serde_json::to_string(configuration)?);
+    Ok(md5_str(&lines.join("\n")))
+}
+
+//Bundles of iOS applications are directories, every file is part of the digest
+async fn digest(path: &Path) -> Result<String> {
+    if !path.is_dir() {
+        return md5(path).await;
+    }
+    let mut lines = Vec::new();
+    for entry in WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let relative = entry.path().strip_prefix(path)?;
+        lines.push(format!(
+            "{} {}",
+            md5(entry.path()).await?,
+            relative.to_string_lossy()
+        ));
+    }
+    Ok(md5_str(&lines.join("\n")))
+}
+
+async fn md5(path: &Path) -> Result<String> {
+    let digests = digest_file(path, &[HashAlgorithm::Md5]).await?;
+    Ok(digests[&HashAlgorithm::Md5].hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fingerprint() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let app = dir.path().join("sample.app");
+        tokio::fs::create_dir_all(app.join("Frameworks")).await?;
+        tokio::fs::write(app.join("sample"), b"binary").await?;
+        tokio::fs::write(app.join("Frameworks/library"), b"library").await?;
+        let apk = dir.path().join("test.apk");
+        tokio::fs::write(&apk, b"apk").await?;
+
+        let configuration = serde_json::json!({"os_version": "17.5"});
+        let expected = fingerprint(&[&app, &apk], &configuration).await?;
+        assert_eq!(fingerprint(&[&app, &apk], &configuration).await?, expected);
+        assert_ne!(fingerprint(&[&apk, &app], &configuration).await?, expected);
+        assert_ne!(
+            fingerprint(&[&app, &apk], &serde_json::json!({"os_version": "18.0"})).await?,
+            expected
+        );
+
+        tokio::fs::write(app.join("Frameworks/library"), b"changed").await?;
+        assert_ne!(fingerprint(&[&app, &apk], &configuration).await?, expected);
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use console::style;
 pub trait Formatter {
     fn stage(&mut self, message: &str);
     fn message(&self, message: &str);
+    fn warning(&self, message: &str);
 }
 
 pub struct StandardFormatter {
@@ -32,4 +33,8 @@ impl Formatter for StandardFormatter {
     fn message(&self, message: &str) {
         println!("{}", &message);
     }
+
+    fn warning(&self, message: &str) {
+        eprintln!("{} {}", style("Warning:").yellow().bold(), message);
+    }
 }
@@ -0,0 +1,153 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::{fs::File, io::AsyncReadExt};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::errors::InputError;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Content hash used to deduplicate and verify uploads.
+/// Only md5 allows deduplication, sha256 is meant for integrity and xxh3 for speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum HashAlgorithm {
+    #[serde(rename = "md5")]
+    #[clap(name = "md5")]
+    Md5,
+    #[serde(rename = "sha256")]
+    #[clap(name = "sha256")]
+    Sha256,
+    #[serde(rename = "xxh3")]
+    #[clap(name = "xxh3")]
+    Xxh3,
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashAlgorithm::Md5 => f.write_str("md5"),
+            HashAlgorithm::Sha256 => f.write_str("sha256"),
+            HashAlgorithm::Xxh3 => f.write_str("xxh3"),
+        }
+    }
+}
+
+pub struct FileDigest(Vec<u8>);
+
+impl FileDigest {
+    pub fn hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn base64(&self) -> String {
+        STANDARD.encode(&self.0)
+    }
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+    Xxh3(Box<Xxh3>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Hasher {
+        match algorithm {
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Xxh3 => Hasher::Xxh3(Box::new(Xxh3::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> FileDigest {
+        match self {
+            Hasher::Md5(hasher) => FileDigest(hasher.finalize().to_vec()),
+            Hasher::Sha256(hasher) => FileDigest(hasher.finalize().to_vec()),
+            Hasher::Xxh3(hasher) => FileDigest(hasher.digest128().to_be_bytes().to_vec()),
+        }
+    }
+}
+
+/// Computes the requested digests of the file contents in a single pass
+pub async fn digest_file(
+    path: &Path,
+    algorithms: &[HashAlgorithm],
+) -> Result<HashMap<HashAlgorithm, FileDigest>> {
+    let mut file = File::open(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_path_buf(),
+            error,
+        })?;
+    let mut hashers: Vec<(HashAlgorithm, Hasher)> = algorithms
+        .iter()
+        .map(|algorithm| (*algorithm, Hasher::new(*algorithm)))
+        .collect();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        for (_, hasher) in hashers.iter_mut() {
+            hasher.update(&buffer[..read]);
+        }
+    }
+    Ok(hashers
+        .into_iter()
+        .map(|(algorithm, hasher)| (algorithm, hasher.finalize()))
+        .collect())
+}
+
+pub fn md5_str(value: &str) -> String {
+    format!("{:x}", Md5::digest(value.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_digest_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"hello").unwrap();
+
+        let digests = digest_file(
+            file.path(),
+            &[
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha256,
+                HashAlgorithm::Xxh3,
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            digests[&HashAlgorithm::Md5].hex(),
+            "5d41402abc4b2a76b9719d911017c592"
+        );
+        assert_eq!(
+            digests[&HashAlgorithm::Md5].base64(),
+            "XUFAKrxLKna5cZ2REBfFkg=="
+        );
+        assert_eq!(
+            digests[&HashAlgorithm::Sha256].hex(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(digests[&HashAlgorithm::Xxh3].hex().len(), 32);
+    }
+}
@@ -1,56 +1,126 @@
-use crate::{bundle::ApplicationBundle, cli::model::Platform, pull::PullFileConfig};
+use crate::{
+    app_data,
+    bundle::{ApplicationBundle, LibraryBundle},
+    cli::model::{DeviceClass, Platform},
+    pull::PullFileConfig,
+    push::PushFile,
+};
 use anyhow::Result;
-use globset::Glob;
-use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use futures::{future::join_all, stream::BoxStream, StreamExt};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use indicatif::{HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
 use serde::Serialize;
 use std::{
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
-use url::{Position, Url};
 
 use log::debug;
 use tokio::{
     fs::File,
     io::AsyncWriteExt,
-    time::{sleep, Instant},
+    time::{sleep, sleep_until, timeout, Instant},
 };
 
 use crate::{
-    api::{Artifact, RapiClient, RapiReqwestClient},
-    artifacts::{download_artifacts, fetch_artifact_list, patch_allure_paths},
-    errors::InputError,
+    api::{
+        Artifact, RapiClient, RapiReqwestClient, RemoteArtifacts, RunFilter, RunOptions, TestRun,
+        TestRunStateChanged,
+    },
+    artifacts::{
+        download_artifacts, fetch_artifact_list, patch_allure_paths, select_failed, DownloadOptions,
+    },
+    backoff::Backoff,
+    compression::ZipOptions,
+    errors::{InputError, RunError},
     filtering::model::SparseMarathonfile,
+    fingerprint::fingerprint,
     formatter::{Formatter, StandardFormatter},
-    progress::{TestRunFinished, TestRunStarted},
+    hash::HashAlgorithm,
+    interrupt::{self, Interruption, Pause},
+    progress::{
+        estimate_remaining, TestRunFinished, TestRunProgress, TestRunStarted, TestRunsFinished,
+        TestTally,
+    },
+    report::report_url,
+    tui::RunMonitor,
 };
 
+const CANCELLATION_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
 pub struct DownloadArtifactsInteractor {}
 
 impl DownloadArtifactsInteractor {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn execute(
         &self,
         base_url: &str,
         api_key: &str,
-        id: &str,
+        id: Option<String>,
+        latest: Option<RunFilter>,
         wait: bool,
-        output: &PathBuf,
-        glob: Option<String>,
+        output: &Path,
+        glob: Option<Vec<String>>,
+        exclude_glob: Option<Vec<String>>,
+        download_options: DownloadOptions,
         no_progress_bars: bool,
+        poll_interval: Duration,
     ) -> Result<()> {
         let started = Instant::now();
         let mut formatter = StandardFormatter::new(4);
         formatter.stage("Checking test run state...");
 
         let client = RapiReqwestClient::new(base_url, api_key);
-        let stat = client.get_run(id).await?;
+        let id = match (id, latest) {
+            (Some(id), _) => id,
+            (None, filter) => {
+                let filter = filter.unwrap_or_default();
+                let run = client.find_latest_run(&filter).await?.ok_or_else(|| {
+                    InputError::NoMatchingRun {
+                        filter: filter.to_string(),
+                    }
+                })?;
+                formatter.message(&format!("Latest matching test run is {}", run.id));
+                run.id
+            }
+        };
+        let id = id.as_str();
+        let mut stat = client.get_run(id).await?;
         if stat.completed.is_none() && wait {
-            loop {
-                if stat.completed.is_some() {
-                    break;
+            let spinner = if !no_progress_bars {
+                let pb = ProgressBar::new_spinner();
+                pb.enable_steady_tick(Duration::from_millis(80));
+                pb.set_style(
+                    ProgressStyle::with_template("{spinner:.blue} [{elapsed_precise}] {msg}")
+                        .unwrap()
+                        .tick_strings(&["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"]),
+                );
+                Some(pb)
+            } else {
+                None
+            };
+            let mut backoff = Backoff::polling(poll_interval);
+            while stat.completed.is_none() {
+                match &spinner {
+                    Some(s) => s.set_message(waiting_message(&stat)),
+                    None => debug!("{}", waiting_message(&stat)),
                 }
-                sleep(Duration::new(5, 0)).await;
+                sleep(backoff.next_delay()).await;
+                stat = client.get_run(id).await?;
+            }
+            if let Some(s) = spinner {
+                s.finish_and_clear();
             }
+            formatter.message(&format!(
+                "Test run {} finished with state {}",
+                id, stat.state
+            ));
+        } else if stat.completed.is_none() {
+            formatter.message(&format!(
+                "Test run {} is still {}, downloading the artifacts available so far",
+                id, stat.state
+            ));
         } else {
             debug!("Test run {} finished", &id);
         }
@@ -59,45 +129,80 @@ impl DownloadArtifactsInteractor {
         let token = client.get_token().await?;
         let artifacts = fetch_artifact_list(&client, id, &token).await?;
         let test_run_id_prefix = format!("{}/", id);
-        let artifacts = filter_artifact_list(artifacts, glob, &test_run_id_prefix)?;
+        let artifacts = if download_options.only_failed {
+            select_failed(
+                &client,
+                id,
+                artifacts,
+                &token,
+                &download_options,
+                no_progress_bars,
+            )
+            .await?
+        } else {
+            artifacts
+        };
+        let artifacts = filter_artifact_list(artifacts, glob, exclude_glob, &test_run_id_prefix)?;
 
         formatter.stage("Downloading files...");
-        download_artifacts(&client, id, artifacts, output, &token, no_progress_bars).await?;
+        let downloaded = download_artifacts(
+            &client,
+            id,
+            artifacts,
+            output,
+            &token,
+            &download_options,
+            no_progress_bars,
+        )
+        .await?;
         formatter.stage("Patching local relative paths...");
-        patch_allure_paths(output).await?;
+        patch_allure_paths(output, &downloaded).await?;
 
         formatter.message(&format!("Done in {}", HumanDuration(started.elapsed())));
         Ok(())
     }
 }
 
-fn filter_artifact_list(
+//Files matching any of the inclusion globs are kept, exclusions take precedence
+pub(crate) fn filter_artifact_list(
     artifacts: Vec<Artifact>,
-    glob: Option<String>,
+    glob: Option<Vec<String>>,
+    exclude_glob: Option<Vec<String>>,
     prefix: &str,
 ) -> Result<Vec<crate::api::Artifact>> {
-    match glob {
-        Some(glob) => {
-            let matcher = Glob::new(&glob)?.compile_matcher();
-            Ok(artifacts
-                .into_iter()
-                .filter(|x| -> bool {
-                    let predicate_result =
-                        matcher.is_match(x.id.strip_prefix(prefix).unwrap_or(&x.id));
-                    if !predicate_result {
-                        debug!("Filtered out download of {}", &x.id);
-                    }
-                    predicate_result
-                })
-                .collect())
-        }
-        None => Ok(artifacts),
+    let include = match glob {
+        Some(patterns) => Some(glob_set(patterns)?),
+        None => None,
+    };
+    let exclude = glob_set(exclude_glob.unwrap_or_default())?;
+    Ok(artifacts
+        .into_iter()
+        .filter(|x| -> bool {
+            let path = x.id.strip_prefix(prefix).unwrap_or(&x.id);
+            let predicate_result = include
+                .as_ref()
+                .map_or(true, |include| include.is_match(path))
+                && !exclude.is_match(path);
+            if !predicate_result {
+                debug!("Filtered out download of {}", &x.id);
+            }
+            predicate_result
+        })
+        .collect())
+}
+
+fn glob_set(patterns: Vec<String>) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(&pattern)?);
     }
+    Ok(builder.build()?)
 }
 
 pub struct TriggerTestRunInteractor {}
 
 impl TriggerTestRunInteractor {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn execute(
         &self,
         base_url: &str,
@@ -106,6 +211,15 @@ impl TriggerTestRunInteractor {
         link: Option<String>,
         branch: Option<String>,
         wait: bool,
+        stream_logs: bool,
+        cancel_on_interrupt: bool,
+        run_timeout: Option<Duration>,
+        tui: bool,
+        ndjson_progress: bool,
+        upload_retries: u32,
+        upload_cache_ttl: Duration,
+        upload_checksum: HashAlgorithm,
+        max_upload_size: u32,
         isolated: Option<bool>,
         ignore_test_failures: Option<bool>,
         code_coverage: Option<bool>,
@@ -117,6 +231,7 @@ impl TriggerTestRunInteractor {
         mock_location: bool,
         filtering_configuration: Option<SparseMarathonfile>,
         output: &Option<PathBuf>,
+        download_options: DownloadOptions,
         application: Option<PathBuf>,
         test_application: Option<PathBuf>,
         os_version: Option<String>,
@@ -126,19 +241,32 @@ impl TriggerTestRunInteractor {
         flavor: Option<String>,
         platform: String,
         no_progress_bars: bool,
+        poll_interval: Duration,
         result_file: Option<PathBuf>,
         env_args: Option<Vec<String>>,
         test_env_args: Option<Vec<String>>,
         pull_file_config: Option<PullFileConfig>,
+        push_files: Option<Vec<PushFile>>,
+        app_data: Option<PathBuf>,
         concurrency_limit: Option<u32>,
         test_timeout_default: Option<u32>,
         test_timeout_max: Option<u32>,
         project: Option<String>,
         application_bundle: Option<Vec<ApplicationBundle>>,
-        library_bundle: Option<Vec<PathBuf>>,
+        library_bundle: Option<Vec<LibraryBundle>>,
+        additional_apks: Option<Vec<PathBuf>>,
         granted_permission: Option<Vec<String>>,
+        remote_artifacts: RemoteArtifacts,
+        run_options: RunOptions,
+        archive_streaming: Option<Arc<ZipOptions>>,
+        skip_if_passed: bool,
     ) -> Result<bool> {
-        let client = RapiReqwestClient::new(base_url, api_key);
+        let client = RapiReqwestClient::new(base_url, api_key)
+            .with_upload_retries(upload_retries)
+            .with_upload_cache(upload_cache_ttl)
+            .with_upload_checksum(upload_checksum)
+            .with_max_upload_size(u64::from(max_upload_size) * 1024 * 1024)
+            .with_archive_streaming(archive_streaming);
         let steps = match (wait, output) {
             (true, Some(_)) => 5,
             (true, None) => 2,
@@ -146,48 +274,165 @@ impl TriggerTestRunInteractor {
         };
         let mut formatter = StandardFormatter::new(steps);
 
+        if let Some(app_data) = &app_data {
+            app_data::validate(app_data).await?;
+        }
+
         let token = client.get_token().await?;
 
-        formatter.stage("Submitting new run...");
-        let id = client
-            .create_run(
-                application,
-                test_application,
-                name,
-                link,
-                branch,
-                platform,
-                os_version,
-                system_image,
-                device,
-                xcode_version,
-                isolated,
-                code_coverage,
-                retry_quota_test_uncompleted,
-                retry_quota_test_preventive,
-                retry_quota_test_reactive,
-                analytics_read_only,
-                profiling,
-                mock_location,
-                filtering_configuration,
-                no_progress_bars,
-                flavor,
-                env_args,
-                test_env_args,
-                pull_file_config,
-                concurrency_limit,
-                test_timeout_default,
-                test_timeout_max,
-                project,
-                application_bundle,
-                library_bundle,
-                granted_permission,
-            )
-            .await?;
+        //Binaries fetched by Marathon Cloud aren't known to the CLI, so they can't be fingerprinted
+        let remote =
+            remote_artifacts.application.is_some() || remote_artifacts.test_application.is_some();
+        if skip_if_passed && remote {
+            formatter.warning(
+                "--skip-if-passed is ignored, binaries fetched by Marathon Cloud can't be compared with previous runs",
+            );
+        }
+        //Submitted with the run as well, so that later runs can find it
+        let fingerprint = if skip_if_passed && !remote {
+            let binaries: Vec<&Path> = application
+                .iter()
+                .chain(test_application.iter())
+                .chain(
+                    application_bundle
+                        .iter()
+                        .flatten()
+                        .flat_map(|bundle| [&bundle.app_path, &bundle.test_app_path]),
+                )
+                .chain(
+                    library_bundle
+                        .iter()
+                        .flatten()
+                        .map(|bundle| &bundle.test_app_path),
+                )
+                .chain(additional_apks.iter().flatten())
+                .chain(push_files.iter().flatten().map(|x| &x.local_path))
+                .chain(app_data.iter())
+                .map(PathBuf::as_path)
+                .collect();
+            let bundles: Vec<_> = application_bundle
+                .iter()
+                .flatten()
+                .map(|x| (&x.filtering_configuration, &x.flavor))
+                .chain(
+                    library_bundle
+                        .iter()
+                        .flatten()
+                        .map(|x| (&x.filtering_configuration, &x.flavor)),
+                )
+                .collect();
+            let configuration = serde_json::json!({
+                "platform": platform,
+                "os_version": os_version,
+                "system_image": system_image,
+                "device": device,
+                "xcode_version": xcode_version,
+                "flavor": flavor,
+                "isolated": isolated,
+                "code_coverage": code_coverage,
+                "mock_location": mock_location,
+                "filtering_configuration": filtering_configuration,
+                "bundles": bundles,
+                "env_args": env_args,
+                "test_env_args": test_env_args,
+                "granted_permission": granted_permission,
+                "push_files": push_files
+                    .iter()
+                    .flatten()
+                    .map(|x| &x.device_path)
+                    .collect::<Vec<_>>(),
+                "pull_file_config": pull_file_config,
+                "test_timeout_default": test_timeout_default,
+                "test_timeout_max": test_timeout_max,
+                "options": run_options,
+            });
+            Some(fingerprint(&binaries, &configuration).await?)
+        } else {
+            None
+        };
+        let previous = match &fingerprint {
+            Some(fingerprint) => {
+                client
+                    .find_latest_run(&RunFilter {
+                        fingerprint: Some(fingerprint.clone()),
+                        state: Some("passed".to_owned()),
+                        ..Default::default()
+                    })
+                    .await?
+            }
+            None => None,
+        };
+
+        let (history_project, history_branch) = (project.clone(), branch.clone());
+        let run_name = name.clone();
+        let id = match previous {
+            Some(run) => {
+                formatter.stage("Reusing previous run...");
+                formatter.message(&format!(
+                    "Test run {} with identical binaries and configuration already passed",
+                    run.id
+                ));
+                run.id
+            }
+            None => {
+                formatter.stage("Submitting new run...");
+                client
+                    .create_run(
+                        application,
+                        test_application,
+                        name,
+                        link,
+                        branch,
+                        platform,
+                        os_version,
+                        system_image,
+                        device,
+                        xcode_version,
+                        isolated,
+                        code_coverage,
+                        retry_quota_test_uncompleted,
+                        retry_quota_test_preventive,
+                        retry_quota_test_reactive,
+                        analytics_read_only,
+                        profiling,
+                        mock_location,
+                        filtering_configuration,
+                        no_progress_bars,
+                        flavor,
+                        env_args,
+                        test_env_args,
+                        pull_file_config,
+                        push_files,
+                        app_data,
+                        concurrency_limit,
+                        test_timeout_default,
+                        test_timeout_max,
+                        project,
+                        application_bundle,
+                        library_bundle,
+                        additional_apks,
+                        granted_permission,
+                        remote_artifacts,
+                        RunOptions {
+                            fingerprint,
+                            ..run_options
+                        },
+                    )
+                    .await?
+            }
+        };
+        let submitted = Instant::now();
+        let deadline = run_timeout.map(|timeout| submitted + timeout);
 
         if wait {
             formatter.stage("Waiting for test run to finish...");
-            let spinner = if !no_progress_bars {
+            //Live views would be mixed with the events
+            let mut monitor = if tui && !ndjson_progress {
+                RunMonitor::start(&id)
+            } else {
+                None
+            };
+            let spinner = if !no_progress_bars && !ndjson_progress && monitor.is_none() {
                 let pb = ProgressBar::new_spinner();
                 pb.enable_steady_tick(Duration::from_millis(80));
                 pb.set_style(
@@ -195,33 +440,121 @@ impl TriggerTestRunInteractor {
                         .unwrap()
                         .tick_strings(&["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"]),
                 );
-                pb.set_message("Test execution in progress...");
+                pb.set_message(progress_message(&TestTally::default(), None));
                 Some(pb)
             } else {
                 None
             };
+            let history = match spinner.is_some() || ndjson_progress {
+                true => client
+                    .get_run_durations(
+                        &token,
+                        history_project.as_deref(),
+                        history_branch.as_deref(),
+                    )
+                    .await
+                    .unwrap_or_else(|error| {
+                        debug!("Run duration history is not available: {}", error);
+                        Vec::new()
+                    }),
+                false => Vec::new(),
+            };
+            let mut fetch_events = stream_logs || monitor.is_some() || spinner.is_some();
+            let mut tally = TestTally::default();
+            let mut events_cursor: Option<u64> = None;
+            let mut subscription = match client.subscribe_run(&token, &id).await {
+                Ok(subscription) => Some(subscription),
+                Err(error) => {
+                    debug!("Falling back to polling for run status: {}", error);
+                    None
+                }
+            };
+            let view: Option<Arc<dyn Pause>> = match (&monitor, &spinner) {
+                (Some(m), _) => Some(m.pause_handle()),
+                (None, Some(s)) => Some(Arc::new(s.clone())),
+                (None, None) => None,
+            };
+            let mut interruptions = interrupt::subscribe(&id, cancel_on_interrupt, view);
+            let mut backoff = Backoff::polling(poll_interval);
+            let mut last_state: Option<String> = None;
+            let mut cancel_requested = false;
+            let mut timed_out = false;
             loop {
                 let stat = client.get_run(&id).await?;
+                if last_state.as_ref() != Some(&stat.state) {
+                    //Check more often right after a transition, e.g. when the run has just started
+                    backoff.reset();
+                    last_state = Some(stat.state.clone());
+                }
+                if let Some(m) = &monitor {
+                    m.on_run(&stat);
+                }
+                if let Some(s) = &spinner {
+                    show_test_progress(s, &stat);
+                }
+                if fetch_events {
+                    match client.get_run_events(&token, &id, events_cursor).await {
+                        Ok(events) => {
+                            for event in &events.events {
+                                tally.record(event);
+                                match (&monitor, &spinner) {
+                                    (Some(m), _) => m.on_event(event),
+                                    (None, _) if !stream_logs => {}
+                                    (None, Some(s)) => s.println(format!("{}", event)),
+                                    (None, None) => formatter.message(&format!("{}", event)),
+                                }
+                            }
+                            events_cursor = events.cursor.or(events_cursor);
+                        }
+                        Err(error) => {
+                            //Streaming is best-effort, the run result is still reported when finished
+                            debug!("Disabling live test log: {}", error);
+                            fetch_events = false;
+                        }
+                    }
+                }
+                //Keep the cancellation notice on the spinner line once requested
+                if let (Some(s), false) = (&spinner, cancel_requested) {
+                    let eta = estimate_remaining(&history, submitted.elapsed());
+                    s.set_message(progress_message(&tally, eta));
+                }
+                if ndjson_progress {
+                    let event = TestRunProgress {
+                        id: id.clone(),
+                        state: stat.state.clone(),
+                        passed: stat.passed,
+                        failed: stat.failed,
+                        ignored: stat.ignored,
+                        total: stat.total,
+                        eta_seconds: estimate_remaining(&history, submitted.elapsed())
+                            .filter(|_| stat.completed.is_none())
+                            .map(|x| x.as_secs()),
+                    };
+                    formatter.message(&serde_json::to_string(&event)?);
+                }
                 if stat.completed.is_some() {
                     if let Some(s) = spinner {
                         s.finish_and_clear()
                     }
-
-                    let base_report_url = Url::parse(base_url)?;
-                    let base_report_url = &base_report_url[..Position::AfterPort];
+                    if let Some(m) = monitor.take() {
+                        m.finish();
+                    }
+                    //Ctrl+C exits right away once no run is waited for
+                    drop(interruptions);
 
                     let state = stat.state.clone();
-                    let report = format!("{}/runs/{}/report", base_report_url, id);
+                    let report = report_url(base_url, &id)?;
                     let passed = stat.passed;
                     let failed = stat.failed;
                     let ignored = stat.ignored;
                     let billable_time = stat
                         .total_run_time_seconds
-                        .map(|t| Duration::from_secs_f64(t))
+                        .map(Duration::from_secs_f64)
                         .unwrap_or(Duration::from_secs(0));
 
                     let event = TestRunFinished {
                         id: id.clone(),
+                        name: run_name,
                         state,
                         report,
                         passed,
@@ -231,48 +564,120 @@ impl TriggerTestRunInteractor {
                     };
                     formatter.message(&format!("{}", event));
                     if let Some(result_file) = result_file {
-                        let mut file = File::create(&result_file).await?;
-                        let data = serialize_event(&result_file, &event)?;
-                        file.write_all(data.as_bytes()).await?;
-                        file.flush().await?;
+                        write_result_file(&result_file, &event).await?;
                     }
                     if let Some(error_message) = stat.error_message {
-                        formatter.message(&format!("Error message:"));
+                        formatter.message("Error message:");
                         let formatted_error_message = error_message.replace("\n", "\n\t");
                         formatter.message(&format!("\t{}", formatted_error_message));
                     }
 
                     if let Some(output) = output {
-                        formatter.stage("Fetching file list...");
-                        let artifacts = fetch_artifact_list(&client, &id, &token).await?;
-                        formatter.stage("Downloading files...");
-                        download_artifacts(
+                        download_outputs(
                             &client,
                             &id,
-                            artifacts,
-                            output,
                             &token,
+                            output,
+                            &download_options,
                             no_progress_bars,
+                            &mut formatter,
                         )
                         .await?;
-                        formatter.stage("Patching local relative paths...");
-                        patch_allure_paths(output).await?;
+                    }
+                    if timed_out {
+                        return Err(RunError::TimedOut {
+                            id,
+                            timeout: humantime::format_duration(run_timeout.unwrap_or_default())
+                                .to_string(),
+                        }
+                        .into());
                     }
                     return match (stat.state.as_str(), ignore_test_failures) {
+                        ("cancelled", _) => Err(RunError::Cancelled { id }.into()),
                         ("failure", Some(false) | None) => Ok(false),
                         (_, _) => Ok(true),
                     };
                 }
-                sleep(Duration::new(5, 0)).await;
+                let timeout_sleep = match deadline {
+                    Some(deadline) if !timed_out => sleep_until(deadline),
+                    //Give the backend some time to finalize the cancelled run before giving up
+                    Some(deadline) => sleep_until(deadline + CANCELLATION_GRACE_PERIOD),
+                    None => sleep(Duration::MAX),
+                };
+                tokio::select! {
+                    _ = wait_for_update(&mut subscription, &mut backoff) => {}
+                    _ = timeout_sleep => {
+                        //Further progress is reported as plain output
+                        if let Some(m) = monitor.take() {
+                            m.finish();
+                            fetch_events = stream_logs;
+                        }
+                        let timeout = humantime::format_duration(run_timeout.unwrap_or_default())
+                            .to_string();
+                        if timed_out {
+                            if let Some(s) = spinner {
+                                s.finish_and_clear()
+                            }
+                            if let Some(output) = output {
+                                download_outputs(
+                                    &client,
+                                    &id,
+                                    &token,
+                                    output,
+                                    &download_options,
+                                    no_progress_bars,
+                                    &mut formatter,
+                                )
+                                .await?;
+                            }
+                            return Err(RunError::TimedOut { id, timeout }.into());
+                        }
+                        if !cancel_requested {
+                            client.cancel_run(&id).await?;
+                            cancel_requested = true;
+                        }
+                        timed_out = true;
+                        let message = format!("Test run exceeded --run-timeout {}, cancelling...", timeout);
+                        match &spinner {
+                            Some(s) => s.set_message(message),
+                            None => formatter.message(&message),
+                        }
+                    }
+                    interruption = interruptions.changed() => {
+                        if let Some(m) = monitor.take() {
+                            m.finish();
+                            fetch_events = stream_logs;
+                        }
+                        if interruption == Interruption::Cancel && !cancel_requested {
+                            client.cancel_run(&id).await?;
+                            cancel_requested = true;
+                            let message = "Cancellation requested, waiting for partial results...";
+                            match &spinner {
+                                Some(s) => s.set_message(message),
+                                None => formatter.message(message),
+                            }
+                        } else if interruption == Interruption::Cancel {
+                            //Already cancelled because of --run-timeout
+                        } else if cancel_requested {
+                            return Err(RunError::Interrupted { id }.into());
+                        } else {
+                            if let Some(s) = spinner {
+                                s.finish_and_clear()
+                            }
+                            formatter.message(&format!(
+                                "Test run {} is still running in Marathon Cloud",
+                                id
+                            ));
+                            return Err(RunError::Interrupted { id }.into());
+                        }
+                    }
+                }
             }
         } else {
             let event = TestRunStarted { id };
             formatter.message(&format!("{}", event));
             if let Some(result_file) = result_file {
-                let mut file = File::create(&result_file).await?;
-                let data = serialize_event(&result_file, &event)?;
-                file.write_all(data.as_bytes()).await?;
-                file.flush().await?;
+                write_result_file(&result_file, &event).await?;
             }
 
             Ok(true)
@@ -280,6 +685,299 @@ impl TriggerTestRunInteractor {
     }
 }
 
+pub struct WaitRunsInteractor {}
+
+impl WaitRunsInteractor {
+    /// Waits for the test runs `ids` concurrently. Artifacts of every run are downloaded into
+    /// a subfolder of `output` named after the run id
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn execute(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        ids: Vec<String>,
+        output: &Option<PathBuf>,
+        download_options: DownloadOptions,
+        no_progress_bars: bool,
+        poll_interval: Duration,
+        result_file: Option<PathBuf>,
+    ) -> Result<bool> {
+        let started = Instant::now();
+        let steps = match output {
+            Some(_) => 1 + 3 * ids.len() as u32,
+            None => 1,
+        };
+        let mut formatter = StandardFormatter::new(steps);
+        formatter.stage("Waiting for test runs to finish...");
+
+        let client = RapiReqwestClient::new(base_url, api_key);
+        let multi = (!no_progress_bars).then(MultiProgress::new);
+        let rows: Vec<Option<ProgressBar>> = ids
+            .iter()
+            .map(|_| {
+                multi.as_ref().map(|m| {
+                    let pb = m.add(ProgressBar::new_spinner());
+                    pb.enable_steady_tick(Duration::from_millis(80));
+                    pb.set_style(
+                        ProgressStyle::with_template("{spinner:.blue} {msg}")
+                            .unwrap()
+                            .tick_strings(&["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"]),
+                    );
+                    pb
+                })
+            })
+            .collect();
+        let mut runs: Vec<Option<TestRun>> = ids.iter().map(|_| None).collect();
+        let mut backoff = Backoff::polling(poll_interval);
+        loop {
+            //Finished runs aren't fetched again
+            let pending: Vec<usize> = (0..ids.len())
+                .filter(|index| {
+                    runs[*index]
+                        .as_ref()
+                        .map_or(true, |run| run.completed.is_none())
+                })
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            let fetched = join_all(pending.iter().map(|index| client.get_run(&ids[*index]))).await;
+            for (index, run) in pending.into_iter().zip(fetched) {
+                let run = run?;
+                let changed = runs[index]
+                    .as_ref()
+                    .map_or(true, |previous| previous.state != run.state);
+                let row = status_row(&run);
+                match (&rows[index], run.completed) {
+                    (Some(pb), Some(_)) => pb.finish_with_message(row),
+                    (Some(pb), None) => pb.set_message(row),
+                    (None, _) if changed => formatter.message(&row),
+                    (None, _) => debug!("{}", row),
+                }
+                runs[index] = Some(run);
+            }
+            if runs
+                .iter()
+                .all(|run| run.as_ref().is_some_and(|x| x.completed.is_some()))
+            {
+                break;
+            }
+            sleep(backoff.next_delay()).await;
+        }
+        let runs: Vec<TestRun> = runs.into_iter().flatten().collect();
+        if let Some(multi) = multi {
+            let _ = multi.clear();
+        }
+        formatter.message(&status_table(&runs));
+        if let Some(result_file) = result_file {
+            let finished = runs
+                .iter()
+                .map(|run| {
+                    Ok(TestRunFinished {
+                        id: run.id.clone(),
+                        name: None,
+                        report: report_url(base_url, &run.id)?,
+                        state: run.state.clone(),
+                        passed: run.passed,
+                        failed: run.failed,
+                        ignored: run.ignored,
+                        billable_time: run
+                            .total_run_time_seconds
+                            .map(Duration::from_secs_f64)
+                            .unwrap_or(Duration::from_secs(0)),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            write_result_file(&result_file, TestRunsFinished::from(finished)).await?;
+        }
+
+        if let Some(output) = output {
+            let token = client.get_token().await?;
+            for run in &runs {
+                download_outputs(
+                    &client,
+                    &run.id,
+                    &token,
+                    &output.join(&run.id),
+                    &download_options,
+                    no_progress_bars,
+                    &mut formatter,
+                )
+                .await?;
+            }
+        }
+        formatter.message(&format!("Done in {}", HumanDuration(started.elapsed())));
+
+        if let Some(run) = runs.iter().find(|run| run.state == "cancelled") {
+            return Err(RunError::Cancelled { id: run.id.clone() }.into());
+        }
+        Ok(runs.iter().all(|run| run.state != "failure"))
+    }
+}
+
+//Counts are unknown until the run has started
+fn count(x: Option<u32>) -> String {
+    x.map(|x| x.to_string()).unwrap_or("-".to_owned())
+}
+
+fn status_row(run: &TestRun) -> String {
+    format!(
+        "{} {}: {} passed, {} failed, {} ignored",
+        run.id,
+        run.state,
+        count(run.passed),
+        count(run.failed),
+        count(run.ignored)
+    )
+}
+
+fn status_table(runs: &[TestRun]) -> String {
+    let width = runs
+        .iter()
+        .map(|run| run.id.len())
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let mut table = format!(
+        "{:<width$}  {:<9}  {:>6}  {:>6}  {:>7}",
+        "ID",
+        "STATE",
+        "PASSED",
+        "FAILED",
+        "IGNORED",
+        width = width
+    );
+    for run in runs {
+        table.push_str(&format!(
+            "\n{:<width$}  {:<9}  {:>6}  {:>6}  {:>7}",
+            run.id,
+            run.state,
+            count(run.passed),
+            count(run.failed),
+            count(run.ignored),
+            width = width
+        ));
+    }
+    table
+}
+
+async fn download_outputs(
+    client: &RapiReqwestClient,
+    id: &str,
+    token: &str,
+    output: &Path,
+    download_options: &DownloadOptions,
+    no_progress_bars: bool,
+    formatter: &mut StandardFormatter,
+) -> Result<()> {
+    formatter.stage("Fetching file list...");
+    let artifacts = fetch_artifact_list(client, id, token).await?;
+    let artifacts = if download_options.only_failed {
+        select_failed(
+            client,
+            id,
+            artifacts,
+            token,
+            download_options,
+            no_progress_bars,
+        )
+        .await?
+    } else {
+        artifacts
+    };
+    formatter.stage("Downloading files...");
+    let downloaded = download_artifacts(
+        client,
+        id,
+        artifacts,
+        output,
+        token,
+        download_options,
+        no_progress_bars,
+    )
+    .await?;
+    formatter.stage("Patching local relative paths...");
+    patch_allure_paths(output, &downloaded).await?;
+    Ok(())
+}
+
+async fn wait_for_update(
+    subscription: &mut Option<BoxStream<'static, Result<TestRunStateChanged>>>,
+    backoff: &mut Backoff,
+) {
+    match subscription.as_mut() {
+        //Every pushed state change (or a quiet period) triggers a re-fetch of the run
+        Some(events) => match timeout(Duration::from_secs(60), events.next()).await {
+            Ok(Some(Ok(event))) => debug!("Test run state changed to {}", event.state),
+            Ok(Some(Err(error))) => {
+                debug!("Falling back to polling for run status: {}", error);
+                *subscription = None;
+            }
+            Ok(None) => {
+                debug!("Run status subscription closed, falling back to polling");
+                *subscription = None;
+            }
+            Err(_) => {}
+        },
+        None => sleep(backoff.next_delay()).await,
+    }
+}
+
+//Switches the spinner to a progress bar once the backend reports the number of tests
+fn show_test_progress(pb: &ProgressBar, run: &TestRun) {
+    let Some(total) = run.total.filter(|total| *total > 0) else {
+        return;
+    };
+    if pb.length() != Some(total.into()) {
+        pb.set_style(
+            ProgressStyle::with_template("{spinner:.blue} [{bar:30.cyan/blue}] {pos}/{len} {msg}")
+                .unwrap()
+                .tick_strings(&["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"])
+                .progress_chars("=> "),
+        );
+        pb.set_length(total.into());
+    }
+    let finished = run.passed.unwrap_or(0) + run.failed.unwrap_or(0) + run.ignored.unwrap_or(0);
+    pb.set_position(finished.min(total).into());
+}
+
+fn waiting_message(run: &TestRun) -> String {
+    let mut message = format!("Waiting for test run {}, state: {}", run.id, run.state);
+    let finished = [
+        (run.passed, "passed"),
+        (run.failed, "failed"),
+        (run.ignored, "ignored"),
+    ]
+    .into_iter()
+    .filter_map(|(count, label)| count.map(|count| format!("{} {}", count, label)))
+    .collect::<Vec<_>>();
+    if !finished.is_empty() {
+        message.push_str(&format!(", {}", finished.join(", ")));
+    }
+    message
+}
+
+fn progress_message(tally: &TestTally, eta: Option<Duration>) -> String {
+    let mut message = "Test execution in progress...".to_owned();
+    if !tally.is_empty() {
+        message.push_str(&format!(" {}", tally));
+    }
+    match eta {
+        Some(Duration::ZERO) => message.push_str(" (taking longer than usual)"),
+        Some(eta) => message.push_str(&format!(" (ETA {})", HumanDuration(eta))),
+        None => {}
+    }
+    message
+}
+
+pub(crate) async fn write_result_file<T: Serialize>(path: &Path, event: T) -> Result<()> {
+    let data = serialize_event(path, event)?;
+    let mut file = File::create(path).await?;
+    file.write_all(data.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
 fn serialize_event<T: Serialize>(path: &Path, event: T) -> Result<String> {
     match path.extension().map(|f| f.to_str()) {
         //If no extension then treat as json
@@ -305,6 +1003,7 @@ impl GetDeviceCatalogInteractor {
         base_url: &str,
         api_key: &str,
         platform: &Platform,
+        device_class: &DeviceClass,
         no_progress_bar: bool,
     ) -> Result<()> {
         let formatter = StandardFormatter::new(1);
@@ -325,14 +1024,167 @@ impl GetDeviceCatalogInteractor {
         let client = RapiReqwestClient::new(base_url, api_key);
 
         let token = client.get_token().await?;
-        let devices = match platform {
-            Platform::Android => client.get_devices_android(&token).await?,
-            Platform::iOS => todo!(),
+        let devices = match (platform, device_class) {
+            (Platform::Android, DeviceClass::Simulator) => {
+                serde_yaml::to_string(&client.get_devices_android(&token).await?)?
+            }
+            (Platform::iOS, DeviceClass::Simulator) => {
+                serde_yaml::to_string(&client.get_devices_ios(&token).await?)?
+            }
+            (_, DeviceClass::Real) => serde_yaml::to_string(
+                &client
+                    .get_devices_real(&token, &platform.to_string().to_lowercase())
+                    .await?,
+            )?,
         };
         if let Some(progress_bar) = progress_bar {
             progress_bar.finish_and_clear();
         }
-        println!("{}", serde_yaml::to_string(&devices)?);
+        println!("{}", devices);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(id: &str) -> Artifact {
+        Artifact {
+            id: id.to_owned(),
+            is_file: true,
+            size: None,
+        }
+    }
+
+    fn filtered(glob: Option<&[&str]>, exclude_glob: &[&str]) -> Vec<String> {
+        let artifacts = vec![
+            artifact("run-1/tests/junit.xml"),
+            artifact("run-1/video/test.mp4"),
+            artifact("run-1/report/allure-results/result.json"),
+        ];
+        let exclude_glob = Some(exclude_glob.iter().map(|x| x.to_string()).collect());
+        filter_artifact_list(
+            artifacts,
+            glob.map(|glob| glob.iter().map(|x| x.to_string()).collect()),
+            exclude_glob,
+            "run-1/",
+        )
+        .unwrap()
+        .into_iter()
+        .map(|x| x.id)
+        .collect()
+    }
+
+    #[test]
+    fn test_filter_artifact_list_excludes() {
+        assert_eq!(
+            filtered(None, &["video/**"]),
+            vec![
+                "run-1/tests/junit.xml",
+                "run-1/report/allure-results/result.json"
+            ]
+        );
+        assert_eq!(
+            filtered(Some(&["**/*.xml"]), &["tests/**"]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_filter_artifact_list_combines_globs() {
+        assert_eq!(
+            filtered(Some(&["tests/**", "report/allure-results/**"]), &[]),
+            vec![
+                "run-1/tests/junit.xml",
+                "run-1/report/allure-results/result.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_status_table() {
+        let run = |id: &str, state: &str, failed: Option<u32>| TestRun {
+            id: id.to_owned(),
+            state: state.to_owned(),
+            passed: failed.map(|_| 3),
+            failed,
+            ignored: failed.map(|_| 0),
+            total: None,
+            completed: None,
+            total_run_time_seconds: None,
+            error_message: None,
+        };
+        assert_eq!(
+            status_table(&[
+                run("run-1", "passed", Some(0)),
+                run("run-22", "failure", Some(1)),
+                run("run-3", "running", None)
+            ]),
+            "ID      STATE      PASSED  FAILED  IGNORED
+run-1   passed          3       0        0
+run-22  failure         3       1        0
+run-3   running         -       -        -"
+        );
+    }
+
+    #[test]
+    fn test_waiting_message() {
+        let mut run = TestRun {
+            id: "run-1".to_owned(),
+            state: "running".to_owned(),
+            passed: None,
+            failed: None,
+            ignored: None,
+            total: None,
+            completed: None,
+            total_run_time_seconds: None,
+            error_message: None,
+        };
+        assert_eq!(
+            waiting_message(&run),
+            "Waiting for test run run-1, state: running"
+        );
+
+        run.passed = Some(3);
+        run.failed = Some(1);
+        assert_eq!(
+            waiting_message(&run),
+            "Waiting for test run run-1, state: running, 3 passed, 1 failed"
+        );
+    }
+
+    #[test]
+    fn test_show_test_progress() {
+        let pb = ProgressBar::hidden();
+        let mut run = TestRun {
+            id: "run-1".to_owned(),
+            state: "running".to_owned(),
+            passed: Some(3),
+            failed: None,
+            ignored: None,
+            total: None,
+            completed: None,
+            total_run_time_seconds: None,
+            error_message: None,
+        };
+        show_test_progress(&pb, &run);
+        assert_eq!(pb.length(), None);
+
+        run.total = Some(10);
+        run.ignored = Some(2);
+        show_test_progress(&pb, &run);
+        assert_eq!(pb.length(), Some(10));
+        assert_eq!(pb.position(), 5);
+
+        //Retries can report more results than scheduled tests
+        run.failed = Some(8);
+        show_test_progress(&pb, &run);
+        assert_eq!(pb.position(), 10);
+
+        run.total = Some(0);
+        run.passed = None;
+        show_test_progress(&pb, &run);
+        assert_eq!(pb.length(), Some(10));
+    }
+}
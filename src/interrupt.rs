@@ -0,0 +1,210 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, OnceLock,
+};
+
+use console::Term;
+use indicatif::ProgressBar;
+use tokio::{signal, sync::watch};
+
+use crate::errors::ExitCode;
+
+/// What the user decided after Ctrl+C. The decision applies to every run this process waits for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interruption {
+    //Cancel the runs in Marathon Cloud and keep waiting for their partial results
+    Cancel,
+    //Stop waiting, the runs keep running in Marathon Cloud
+    Detach,
+}
+
+/// Live output which is hidden while the user is asked whether to cancel
+pub trait Pause: Send + Sync {
+    /// Runs `f` with the output hidden and draws it again afterwards
+    fn suspend(&self, f: &mut dyn FnMut());
+}
+
+//Bars keep their draw target, e.g. a MultiProgress or a hidden one
+impl Pause for ProgressBar {
+    fn suspend(&self, f: &mut dyn FnMut()) {
+        ProgressBar::suspend(self, f)
+    }
+}
+
+struct Waiter {
+    key: usize,
+    id: String,
+    view: Option<Arc<dyn Pause>>,
+}
+
+struct Handler {
+    state: watch::Sender<Option<Interruption>>,
+    waiters: Mutex<Vec<Waiter>>,
+    next_key: AtomicUsize,
+    cancel_on_interrupt: AtomicBool,
+}
+
+static HANDLER: OnceLock<Handler> = OnceLock::new();
+
+/// Registers a run which is waited for. Ctrl+C is handled by a single listener for the whole
+/// process, so concurrent runs share one prompt. Without registered runs Ctrl+C exits right away
+pub fn subscribe(
+    id: &str,
+    cancel_on_interrupt: bool,
+    view: Option<Arc<dyn Pause>>,
+) -> Subscription {
+    let handler = HANDLER.get_or_init(|| {
+        tokio::spawn(listen());
+        Handler {
+            state: watch::Sender::new(None),
+            waiters: Mutex::new(Vec::new()),
+            next_key: AtomicUsize::new(0),
+            cancel_on_interrupt: AtomicBool::new(false),
+        }
+    });
+    if cancel_on_interrupt {
+        handler.cancel_on_interrupt.store(true, Ordering::SeqCst);
+    }
+    let key = handler.next_key.fetch_add(1, Ordering::SeqCst);
+    if let Ok(mut waiters) = handler.waiters.lock() {
+        waiters.push(Waiter {
+            key,
+            id: id.to_owned(),
+            view,
+        });
+    }
+    let mut receiver = handler.state.subscribe();
+    //Runs registered after the user decided follow the same decision
+    if receiver.borrow().is_some() {
+        receiver.mark_changed();
+    }
+    Subscription { key, receiver }
+}
+
+pub struct Subscription {
+    key: usize,
+    receiver: watch::Receiver<Option<Interruption>>,
+}
+
+impl Subscription {
+    /// Resolves with the decision of the user once Ctrl+C is pressed
+    pub async fn changed(&mut self) -> Interruption {
+        loop {
+            if self.receiver.changed().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+            if let Some(interruption) = *self.receiver.borrow_and_update() {
+                return interruption;
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(Ok(mut waiters)) = HANDLER.get().map(|x| x.waiters.lock()) {
+            waiters.retain(|x| x.key != self.key);
+        }
+    }
+}
+
+async fn listen() {
+    while signal::ctrl_c().await.is_ok() {
+        let Some(handler) = HANDLER.get() else {
+            continue;
+        };
+        let (ids, views): (Vec<String>, Vec<Arc<dyn Pause>>) = match handler.waiters.lock() {
+            Ok(waiters) => (
+                waiters.iter().map(|x| x.id.clone()).collect(),
+                waiters.iter().filter_map(|x| x.view.clone()).collect(),
+            ),
+            Err(_) => (Vec::new(), Vec::new()),
+        };
+        if ids.is_empty() {
+            std::process::exit(ExitCode::Interrupted.code());
+        }
+        let current = *handler.state.borrow();
+        let next = match current {
+            None if handler.cancel_on_interrupt.load(Ordering::SeqCst) => Interruption::Cancel,
+            None => tokio::task::spawn_blocking(move || confirm_cancellation(&ids, &views))
+                .await
+                .unwrap_or(Interruption::Detach),
+            //A second interrupt stops waiting for the cancelled runs
+            Some(_) => Interruption::Detach,
+        };
+        handler.state.send_replace(Some(next));
+    }
+}
+
+//Without an interactive terminal there is nobody to answer, so the runs are left running
+fn confirm_cancellation(ids: &[String], views: &[Arc<dyn Pause>]) -> Interruption {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Interruption::Detach;
+    }
+    let prompt = match ids {
+        [id] => format!("Cancel test run {} in Marathon Cloud? [y/N] ", id),
+        _ => format!(
+            "Cancel test runs {} in Marathon Cloud? [y/N] ",
+            ids.join(", ")
+        ),
+    };
+    let mut cancel = false;
+    suspended(views, &mut || {
+        let _ = term.clear_line();
+        let _ = term.write_str(&prompt);
+        cancel = term
+            .read_line()
+            .map(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+            .unwrap_or(false);
+    });
+    if cancel {
+        Interruption::Cancel
+    } else {
+        Interruption::Detach
+    }
+}
+
+//Every view is hidden while `f` runs
+fn suspended(views: &[Arc<dyn Pause>], f: &mut dyn FnMut()) {
+    match views.split_first() {
+        Some((view, rest)) => view.suspend(&mut || suspended(rest, f)),
+        None => f(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Pause for Recorder {
+        fn suspend(&self, f: &mut dyn FnMut()) {
+            self.1.lock().unwrap().push(format!("hide {}", self.0));
+            f();
+            self.1.lock().unwrap().push(format!("show {}", self.0));
+        }
+    }
+
+    #[test]
+    fn test_every_view_is_hidden_while_prompting() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let views: Vec<Arc<dyn Pause>> = vec![
+            Arc::new(Recorder("a", events.clone())),
+            Arc::new(Recorder("b", events.clone())),
+        ];
+        suspended(&views, &mut || events.lock().unwrap().push("prompt".into()));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["hide a", "hide b", "prompt", "show b", "show a"]
+        );
+    }
+
+    #[test]
+    fn test_hidden_bar_stays_hidden() {
+        let bar = ProgressBar::hidden();
+        Pause::suspend(&bar, &mut || {});
+        assert!(bar.is_hidden());
+    }
+}
@@ -1,11 +1,25 @@
 mod api;
+mod app_data;
 mod artifacts;
+mod backoff;
 mod bundle;
+mod cache;
 pub mod cli;
 mod compression;
 mod errors;
 mod filtering;
+mod fingerprint;
 mod formatter;
+mod hash;
 mod interactor;
+mod interrupt;
+mod plist;
 mod progress;
 mod pull;
+mod push;
+mod report;
+mod retries;
+mod timeouts;
+mod tui;
+mod upload_cache;
+mod xml;
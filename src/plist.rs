@@ -0,0 +1,250 @@
+use anyhow::{anyhow, bail, Result};
+
+/// Value of an XML property list. Numbers, dates and data are kept as their text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Dict(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    String(String),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Dict(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+/// Parses an XML property list. Binary property lists aren't supported
+pub fn parse(content: &[u8]) -> Result<Value> {
+    if content.starts_with(b"bplist") {
+        bail!("binary property lists aren't supported, convert it with 'plutil -convert xml1'");
+    }
+    let content = std::str::from_utf8(content)?;
+    let mut parser = Parser {
+        content,
+        position: 0,
+    };
+    match parser.next_tag()? {
+        Tag::Open(name) if name == "plist" => {}
+        _ => bail!("missing <plist> element"),
+    }
+    let value = parser.value()?;
+    match parser.next_tag()? {
+        Tag::Close(name) if name == "plist" => Ok(value),
+        _ => bail!("unexpected content after the value of <plist>"),
+    }
+}
+
+enum Tag {
+    Open(String),
+    Close(String),
+    Empty(String),
+}
+
+struct Parser<'a> {
+    content: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.content[self.position..]
+    }
+
+    //Skips text, declarations and comments up to the next element tag
+    fn next_tag(&mut self) -> Result<Tag> {
+        loop {
+            let start = self
+                .rest()
+                .find('<')
+                .ok_or_else(|| anyhow!("unexpected end of property list"))?;
+            self.position += start;
+            let (skip, end) = if self.rest().starts_with("<!--") {
+                (true, "-->")
+            } else if self.rest().starts_with("<?") {
+                (true, "?>")
+            } else if self.rest().starts_with("<!") {
+                (true, ">")
+            } else {
+                (false, ">")
+            };
+            let length = self
+                .rest()
+                .find(end)
+                .ok_or_else(|| anyhow!("unterminated tag in property list"))?;
+            let tag = &self.rest()[1..length];
+            self.position += length + end.len();
+            if skip {
+                continue;
+            }
+            return Ok(if let Some(name) = tag.strip_prefix('/') {
+                Tag::Close(name.trim().to_owned())
+            } else if let Some(name) = tag.strip_suffix('/') {
+                Tag::Empty(name.trim().to_owned())
+            } else {
+                Tag::Open(tag.split_whitespace().next().unwrap_or_default().to_owned())
+            });
+        }
+    }
+
+    fn text(&mut self, name: &str) -> Result<String> {
+        let end = format!("</{}>", name);
+        let length = self
+            .rest()
+            .find(&end)
+            .ok_or_else(|| anyhow!("missing {}", end))?;
+        let text = unescape(&self.rest()[..length])?;
+        self.position += length + end.len();
+        Ok(text)
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let tag = self.next_tag()?;
+        self.value_of(tag)
+    }
+
+    fn value_of(&mut self, tag: Tag) -> Result<Value> {
+        match tag {
+            Tag::Empty(name) => match name.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "dict" => Ok(Value::Dict(vec![])),
+                "array" => Ok(Value::Array(vec![])),
+                "string" | "data" => Ok(Value::String(String::new())),
+                _ => bail!("unexpected element <{}/>", name),
+            },
+            Tag::Open(name) => match name.as_str() {
+                "dict" => {
+                    let mut entries = Vec::new();
+                    loop {
+                        match self.next_tag()? {
+                            Tag::Close(name) if name == "dict" => return Ok(Value::Dict(entries)),
+                            Tag::Open(name) if name == "key" => {
+                                let key = self.text("key")?;
+                                entries.push((key, self.value()?));
+                            }
+                            _ => bail!("expected <key> in <dict>"),
+                        }
+                    }
+                }
+                "array" => {
+                    let mut values = Vec::new();
+                    loop {
+                        match self.next_tag()? {
+                            Tag::Close(name) if name == "array" => return Ok(Value::Array(values)),
+                            tag => values.push(self.value_of(tag)?),
+                        }
+                    }
+                }
+                "string" | "integer" | "real" | "date" | "data" => {
+                    Ok(Value::String(self.text(&name)?))
+                }
+                _ => bail!("unexpected element <{}>", name),
+            },
+            Tag::Close(name) => bail!("unexpected </{}>", name),
+        }
+    }
+}
+
+fn unescape(text: &str) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(';')
+            .ok_or_else(|| anyhow!("unterminated entity in property list"))?;
+        let entity = &rest[start + 1..start + end];
+        let character = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|x| u32::from_str_radix(x, 16))
+                .or_else(|| entity.strip_prefix('#').map(|x| x.parse()))
+                .and_then(|x| x.ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| anyhow!("unknown entity &{};", entity))?,
+        };
+        result.push(character);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<!-- Comment -->
+	<key>Name</key>
+	<string>Tom &amp; Jerry &#x263A;</string>
+	<key>Enabled</key>
+	<true/>
+	<key>Count</key>
+	<integer>2</integer>
+	<key>Items</key>
+	<array>
+		<string>a</string>
+		<dict/>
+	</array>
+</dict>
+</plist>
+"#;
+        let value = parse(content.as_bytes()).unwrap();
+        assert_eq!(
+            value.get("Name").and_then(Value::as_str),
+            Some("Tom & Jerry \u{263A}")
+        );
+        assert_eq!(value.get("Enabled").and_then(Value::as_bool), Some(true));
+        assert_eq!(value.get("Count").and_then(Value::as_str), Some("2"));
+        assert_eq!(
+            value.get("Items").and_then(Value::as_array),
+            Some(&[Value::String("a".to_owned()), Value::Dict(vec![])][..])
+        );
+        assert!(parse(b"bplist00").is_err());
+        assert!(parse(b"<plist><dict><string>a</string></dict></plist>").is_err());
+    }
+}
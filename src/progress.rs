@@ -1,9 +1,48 @@
+use console::style;
 use serde_with::DurationSecondsWithFrac;
-use std::{fmt::Display, time::Duration};
+use std::{collections::BTreeMap, fmt::Display, time::Duration};
 
-use serde::Serialize;
+use crate::api::{TestEvent, TestEventType};
+
+use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let s = duration.as_secs();
+    let ms = duration.subsec_millis();
+    let (h, s) = (s / 3600, s % 3600);
+    let (m, s) = (s / 60, s % 60);
+    format!("{:02}:{:02}:{:02}.{:03}", h, m, s, ms)
+}
+
+/// Estimates the time left for a run from the median duration of previous runs
+pub(crate) fn estimate_remaining(history: &[f64], elapsed: Duration) -> Option<Duration> {
+    let mut history: Vec<f64> = history
+        .iter()
+        .copied()
+        .filter(|x| x.is_finite() && *x > 0.0)
+        .collect();
+    if history.is_empty() {
+        return None;
+    }
+    history.sort_by(f64::total_cmp);
+    let median = Duration::try_from_secs_f64(history[history.len() / 2]).ok()?;
+    Some(median.saturating_sub(elapsed))
+}
+
+/// Status of a run while it's waited for, printed as a line of NDJSON with --ndjson-progress
+#[derive(Serialize)]
+pub struct TestRunProgress {
+    pub id: String,
+    pub state: String,
+    pub passed: Option<u32>,
+    pub failed: Option<u32>,
+    pub ignored: Option<u32>,
+    pub total: Option<u32>,
+    /// Estimated seconds until the run is finished, see `estimate_remaining`
+    pub eta_seconds: Option<u64>,
+}
+
 #[derive(Serialize)]
 pub struct TestRunStarted {
     pub id: String,
@@ -16,9 +55,11 @@ impl Display for TestRunStarted {
 }
 
 #[serde_as]
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TestRunFinished {
     pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub report: String,
     pub state: String,
     pub passed: Option<u32>,
@@ -33,6 +74,7 @@ impl Display for TestRunFinished {
         match self.state.as_ref() {
             "passed" => f.write_str("Marathon Cloud execution finished\n")?,
             "failure" => f.write_str("Marathon Cloud execution finished with failures\n")?,
+            "cancelled" => f.write_str("Marathon Cloud execution was cancelled\n")?,
             _ => f.write_str("Marathon cloud execution crashed\n")?,
         };
         f.write_fmt(format_args!("\tstate: {}\n", self.state))?;
@@ -57,16 +99,207 @@ impl Display for TestRunFinished {
                 .unwrap_or("missing".to_owned()),
         ))?;
 
-        let s = self.billable_time.as_secs();
-        let ms = self.billable_time.subsec_millis();
-        let (h, s) = (s / 3600, s % 3600);
-        let (m, s) = (s / 60, s % 60);
-        let formatted_billable_time = format!("{:02}:{:02}:{:02}.{:03}", h, m, s, ms);
-
         f.write_fmt(format_args!(
             "\tbillable time: {}\n",
-            formatted_billable_time
+            format_duration(self.billable_time)
         ))?;
         Ok(())
     }
 }
+
+/// Result of several test runs, e.g. of a matrix or of `wait`
+#[derive(Serialize, Debug)]
+pub struct TestRunsFinished {
+    pub runs: Vec<TestRunFinished>,
+    pub totals: TestRunTotals,
+}
+
+#[serde_as]
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TestRunTotals {
+    pub runs: usize,
+    /// The worst state of the runs
+    pub state: String,
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub billable_time: Duration,
+}
+
+impl From<Vec<TestRunFinished>> for TestRunsFinished {
+    fn from(runs: Vec<TestRunFinished>) -> Self {
+        //Crashed runs have states other than these
+        let severity = |state: &str| match state {
+            "passed" => 0,
+            "failure" => 1,
+            "cancelled" => 2,
+            _ => 3,
+        };
+        let state = runs
+            .iter()
+            .map(|run| run.state.as_str())
+            .max_by_key(|state| severity(state))
+            .unwrap_or("passed")
+            .to_owned();
+        let sum =
+            |count: fn(&TestRunFinished) -> Option<u32>| runs.iter().filter_map(count).sum::<u32>();
+        let totals = TestRunTotals {
+            runs: runs.len(),
+            state,
+            passed: sum(|run| run.passed),
+            failed: sum(|run| run.failed),
+            ignored: sum(|run| run.ignored),
+            billable_time: runs.iter().map(|run| run.billable_time).sum(),
+        };
+        TestRunsFinished { runs, totals }
+    }
+}
+
+impl Display for TestEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.event_type {
+            TestEventType::Started => style("STARTED").dim(),
+            TestEventType::Passed => style("PASSED ").green(),
+            TestEventType::Failed => style("FAILED ").red(),
+            TestEventType::Ignored => style("IGNORED").yellow(),
+        };
+        match &self.device {
+            Some(device) => f.write_fmt(format_args!("{} {} on {}", status, self.test, device)),
+            None => f.write_fmt(format_args!("{} {}", status, self.test)),
+        }
+    }
+}
+
+/// Running totals of the live test events of a run
+#[derive(Default)]
+pub struct TestTally {
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+    /// Test currently executing on each device
+    pub running: BTreeMap<String, String>,
+}
+
+impl TestTally {
+    pub fn record(&mut self, event: &TestEvent) {
+        let device = event.device.clone().unwrap_or_else(|| "unknown".to_owned());
+        match event.event_type {
+            TestEventType::Started => {
+                self.running.insert(device, event.test.clone());
+                return;
+            }
+            TestEventType::Passed => self.passed += 1,
+            TestEventType::Failed => self.failed += 1,
+            TestEventType::Ignored => self.ignored += 1,
+        }
+        if self.running.get(&device) == Some(&event.test) {
+            self.running.remove(&device);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passed + self.failed + self.ignored == 0 && self.running.is_empty()
+    }
+}
+
+impl Display for TestTally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} passed, {} failed, ",
+            style(self.passed).green(),
+            style(self.failed).red()
+        ))?;
+        if self.ignored > 0 {
+            f.write_fmt(format_args!("{} ignored, ", style(self.ignored).yellow()))?;
+        }
+        f.write_fmt(format_args!("{} running", self.running.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: TestEventType, test: &str, device: &str) -> TestEvent {
+        TestEvent {
+            event_type,
+            test: test.to_owned(),
+            device: Some(device.to_owned()),
+        }
+    }
+
+    fn finished(state: &str, passed: Option<u32>, failed: Option<u32>) -> TestRunFinished {
+        TestRunFinished {
+            id: "run-1".to_owned(),
+            name: None,
+            report: "https://cloud.marathonlabs.io/report/run-1".to_owned(),
+            state: state.to_owned(),
+            passed,
+            failed,
+            ignored: Some(1),
+            billable_time: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_test_runs_finished_totals() {
+        let result = TestRunsFinished::from(vec![
+            finished("passed", Some(3), Some(0)),
+            finished("failure", Some(2), Some(1)),
+            finished("passed", None, None),
+        ]);
+        assert_eq!(
+            result.totals,
+            TestRunTotals {
+                runs: 3,
+                state: "failure".to_owned(),
+                passed: 5,
+                failed: 1,
+                ignored: 3,
+                billable_time: Duration::from_secs(180),
+            }
+        );
+        let result = TestRunsFinished::from(vec![
+            finished("crashed", None, None),
+            finished("cancelled", None, None),
+        ]);
+        assert_eq!(result.totals.state, "crashed");
+        assert_eq!(TestRunsFinished::from(vec![]).totals.state, "passed");
+    }
+
+    #[test]
+    fn test_estimate_remaining() {
+        assert_eq!(estimate_remaining(&[], Duration::from_secs(10)), None);
+        assert_eq!(
+            estimate_remaining(&[300.0, 100.0, 200.0], Duration::from_secs(50)),
+            Some(Duration::from_secs(150))
+        );
+        assert_eq!(
+            estimate_remaining(&[100.0, -1.0], Duration::from_secs(150)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(estimate_remaining(&[1e300], Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn test_tally_summary() {
+        console::set_colors_enabled(false);
+        let mut tally = TestTally::default();
+        assert!(tally.is_empty());
+
+        tally.record(&event(TestEventType::Started, "A#a", "emulator-1"));
+        tally.record(&event(TestEventType::Started, "B#b", "emulator-2"));
+        tally.record(&event(TestEventType::Passed, "A#a", "emulator-1"));
+        tally.record(&event(TestEventType::Failed, "C#c", "emulator-3"));
+
+        assert_eq!(tally.to_string(), "1 passed, 1 failed, 1 running");
+
+        tally.record(&event(TestEventType::Ignored, "B#b", "emulator-2"));
+
+        assert_eq!(
+            tally.to_string(),
+            "1 passed, 1 failed, 1 ignored, 0 running"
+        );
+    }
+}
@@ -3,13 +3,13 @@ use serde::{Deserialize, Serialize};
 
 const AGGREGATION_MODE_TEST_RUN: &str = "TEST_RUN";
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct PullFileConfig {
     #[serde(rename = "pull")]
     pub pull_items: Vec<PullFileItem>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct PullFileItem {
     #[serde(rename = "relativePath")]
     pub relative_path: String,
@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use crate::errors::PushArgError;
+
+/// Local file which is pushed to every device before the tests are executed
+#[derive(Debug, PartialEq, Clone)]
+pub struct PushFile {
+    pub local_path: PathBuf,
+    pub device_path: String,
+}
+
+pub fn parse_push_args(push_args: Vec<String>) -> Result<Vec<PushFile>, PushArgError> {
+    let mut pushes: Vec<PushFile> = Vec::new();
+    for arg in push_args {
+        //Local paths may contain a colon on Windows, e.g. C:\fixtures\db.sqlite
+        let (local_path, device_path) = match arg.rsplit_once(':') {
+            Some((local_path, device_path))
+                if !local_path.is_empty() && !device_path.is_empty() =>
+            {
+                (PathBuf::from(local_path), device_path.to_string())
+            }
+            _ => return Err(PushArgError::InvalidFormat { arg }),
+        };
+        if !device_path.starts_with('/') {
+            return Err(PushArgError::RelativeDevicePath { device_path });
+        }
+        if !local_path.is_file() {
+            return Err(PushArgError::MissingFile { path: local_path });
+        }
+        if pushes.iter().any(|x| x.device_path == device_path) {
+            return Err(PushArgError::DuplicateDevicePath { device_path });
+        }
+        pushes.push(PushFile {
+            local_path,
+            device_path,
+        });
+    }
+    Ok(pushes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_push_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("image.png");
+        std::fs::write(&image, b"png").unwrap();
+        let push_args = vec![
+            format!("{}:/sdcard/Pictures/image.png", image.display()),
+            format!("{}:/sdcard/Download/", image.display()),
+        ];
+        let pushes = parse_push_args(push_args).unwrap();
+        assert_eq!(
+            pushes,
+            vec![
+                PushFile {
+                    local_path: image.clone(),
+                    device_path: "/sdcard/Pictures/image.png".to_string(),
+                },
+                PushFile {
+                    local_path: image,
+                    device_path: "/sdcard/Download/".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_push_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("image.png");
+        std::fs::write(&image, b"png").unwrap();
+
+        assert_eq!(
+            parse_push_args(vec!["image.png".to_string()]),
+            Err(PushArgError::InvalidFormat {
+                arg: "image.png".to_string()
+            })
+        );
+        assert_eq!(
+            parse_push_args(vec![format!("{}:sdcard/image.png", image.display())]),
+            Err(PushArgError::RelativeDevicePath {
+                device_path: "sdcard/image.png".to_string()
+            })
+        );
+        assert_eq!(
+            parse_push_args(vec!["missing.png:/sdcard/image.png".to_string()]),
+            Err(PushArgError::MissingFile {
+                path: PathBuf::from("missing.png")
+            })
+        );
+        assert_eq!(
+            parse_push_args(vec![
+                format!("{}:/sdcard/image.png", image.display()),
+                format!("{}:/sdcard/image.png", image.display()),
+            ]),
+            Err(PushArgError::DuplicateDevicePath {
+                device_path: "/sdcard/image.png".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use log::debug;
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+use crate::errors::{InputError, ReportError};
+
+/// Coverage formats produced by Marathon Cloud devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CoverageFormat {
+    /// JaCoCo execution data of Android runs
+    Jacoco,
+    /// LLVM raw or indexed profiles of iOS runs
+    Llvm,
+    /// lcov tracefiles
+    Lcov,
+}
+
+impl CoverageFormat {
+    fn of(path: &Path) -> Option<CoverageFormat> {
+        match path.extension()?.to_str()? {
+            "exec" | "ec" => Some(CoverageFormat::Jacoco),
+            "profraw" | "profdata" => Some(CoverageFormat::Llvm),
+            "info" | "lcov" => Some(CoverageFormat::Lcov),
+            _ => None,
+        }
+    }
+}
+
+/// Coverage files in `inputs` grouped by format. Folders are searched recursively
+pub fn find_files(inputs: &[PathBuf]) -> BTreeMap<PathBuf, CoverageFormat> {
+    inputs
+        .iter()
+        .flat_map(|input| WalkDir::new(input).sort_by_file_name())
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let format = CoverageFormat::of(entry.path())?;
+            Some((entry.into_path(), format))
+        })
+        .collect()
+}
+
+const JACOCO_BLOCK_HEADER: u8 = 0x01;
+const JACOCO_BLOCK_SESSION_INFO: u8 = 0x10;
+const JACOCO_BLOCK_EXECUTION_DATA: u8 = 0x11;
+const JACOCO_MAGIC: u16 = 0xC0C0;
+const JACOCO_VERSION: u16 = 0x1007;
+
+#[derive(Debug, PartialEq)]
+struct SessionInfo {
+    id: String,
+    start: i64,
+    dump: i64,
+}
+
+#[derive(Debug, PartialEq)]
+struct ClassExecution {
+    name: String,
+    probes: Vec<bool>,
+}
+
+/// Execution data of one or more JaCoCo `.exec` files. Probes of the same class are combined,
+/// so a line is covered if it was covered on any device
+#[derive(Debug, Default, PartialEq)]
+pub struct JacocoData {
+    sessions: Vec<SessionInfo>,
+    classes: BTreeMap<i64, ClassExecution>,
+}
+
+impl JacocoData {
+    pub fn read(&mut self, data: &[u8]) -> std::result::Result<(), String> {
+        let mut reader = JacocoReader { data, position: 0 };
+        while let Some(block) = reader.byte_or_end() {
+            match block {
+                JACOCO_BLOCK_HEADER => {
+                    if reader.u16()? != JACOCO_MAGIC {
+                        return Err("not a JaCoCo execution data file".to_owned());
+                    }
+                    let version = reader.u16()?;
+                    if version != JACOCO_VERSION {
+                        return Err(format!("unsupported version {:#x}", version));
+                    }
+                }
+                JACOCO_BLOCK_SESSION_INFO => self.sessions.push(SessionInfo {
+                    id: reader.utf()?,
+                    start: reader.i64()?,
+                    dump: reader.i64()?,
+                }),
+                JACOCO_BLOCK_EXECUTION_DATA => {
+                    let id = reader.i64()?;
+                    let name = reader.utf()?;
+                    let probes = reader.booleans()?;
+                    self.merge_class(id, name, probes)?;
+                }
+                block => return Err(format!("unknown block type {:#x}", block)),
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_class(
+        &mut self,
+        id: i64,
+        name: String,
+        probes: Vec<bool>,
+    ) -> std::result::Result<(), String> {
+        match self.classes.get_mut(&id) {
+            None => {
+                self.classes.insert(id, ClassExecution { name, probes });
+            }
+            Some(existing) => {
+                if existing.name != name || existing.probes.len() != probes.len() {
+                    return Err(format!(
+                        "incompatible execution data for class {} with id {:016x}",
+                        name, id
+                    ));
+                }
+                for (probe, hit) in existing.probes.iter_mut().zip(probes) {
+                    *probe |= hit;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut out = vec![JACOCO_BLOCK_HEADER];
+        out.extend(JACOCO_MAGIC.to_be_bytes());
+        out.extend(JACOCO_VERSION.to_be_bytes());
+        for session in &self.sessions {
+            out.push(JACOCO_BLOCK_SESSION_INFO);
+            write_utf(&mut out, &session.id);
+            out.extend(session.start.to_be_bytes());
+            out.extend(session.dump.to_be_bytes());
+        }
+        for (id, class) in &self.classes {
+            out.push(JACOCO_BLOCK_EXECUTION_DATA);
+            out.extend(id.to_be_bytes());
+            write_utf(&mut out, &class.name);
+            write_var_int(&mut out, class.probes.len() as u32);
+            for chunk in class.probes.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (bit, hit)| byte | ((*hit as u8) << bit));
+                out.push(byte);
+            }
+        }
+        out
+    }
+}
+
+//Reads the primitives of java.io.DataInput and JaCoCo's CompactDataInput
+struct JacocoReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl JacocoReader<'_> {
+    fn byte_or_end(&mut self) -> Option<u8> {
+        let byte = self.data.get(self.position).copied()?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn bytes(&mut self, count: usize) -> std::result::Result<&[u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or("unexpected end of file")?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> std::result::Result<u16, String> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> std::result::Result<i64, String> {
+        Ok(i64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    //Class names and session ids are ASCII in practice, modified UTF-8 only differs for NUL
+    //and supplementary characters
+    fn utf(&mut self) -> std::result::Result<String, String> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    fn var_int(&mut self) -> std::result::Result<u32, String> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte_or_end().ok_or("unexpected end of file")?;
+            value |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("malformed variable length integer".to_owned())
+    }
+
+    fn booleans(&mut self) -> std::result::Result<Vec<bool>, String> {
+        let length = self.var_int()? as usize;
+        let bytes = self.bytes(length.div_ceil(8))?;
+        Ok((0..length)
+            .map(|i| bytes[i / 8] & (1 << (i % 8)) != 0)
+            .collect())
+    }
+}
+
+fn write_utf(out: &mut Vec<u8>, value: &str) {
+    out.extend((value.len() as u16).to_be_bytes());
+    out.extend(value.as_bytes());
+}
+
+fn write_var_int(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[derive(Debug, Default)]
+struct SourceFile {
+    lines: BTreeMap<u32, u64>,
+    functions: BTreeMap<String, (u32, u64)>,
+    branches: BTreeMap<(u32, u32, u32), Option<u64>>,
+}
+
+/// Combined lcov tracefiles. Hit counts of the same source file are summed up
+#[derive(Debug, Default)]
+pub struct LcovData {
+    files: BTreeMap<String, SourceFile>,
+}
+
+impl LcovData {
+    pub fn read(&mut self, tracefile: &str) -> std::result::Result<(), String> {
+        let mut current: Option<&mut SourceFile> = None;
+        for (number, line) in tracefile.lines().enumerate() {
+            let line = line.trim();
+            let invalid = || format!("invalid record at line {}: {}", number + 1, line);
+            let (record, value) = line.split_once(':').unwrap_or((line, ""));
+            let fields: Vec<&str> = value.split(',').collect();
+            match record {
+                "SF" => current = Some(self.files.entry(value.to_owned()).or_default()),
+                "end_of_record" => current = None,
+                "DA" | "FN" | "FNDA" | "BRDA" => {
+                    let file = current.as_deref_mut().ok_or_else(invalid)?;
+                    let number = |index: usize| -> std::result::Result<u64, String> {
+                        fields
+                            .get(index)
+                            .and_then(|x| x.parse().ok())
+                            .ok_or_else(invalid)
+                    };
+                    match record {
+                        "DA" => *file.lines.entry(number(0)? as u32).or_default() += number(1)?,
+                        "FN" => {
+                            let name = fields[1..].join(",");
+                            file.functions.entry(name).or_default().0 = number(0)? as u32;
+                        }
+                        "FNDA" => {
+                            let name = fields[1..].join(",");
+                            file.functions.entry(name).or_default().1 += number(0)?;
+                        }
+                        _ => {
+                            let key = (number(0)? as u32, number(1)? as u32, number(2)? as u32);
+                            let taken = match fields.get(3) {
+                                Some(&"-") => None,
+                                _ => Some(number(3)?),
+                            };
+                            let entry = file.branches.entry(key).or_default();
+                            *entry = match (*entry, taken) {
+                                (None, None) => None,
+                                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+                            };
+                        }
+                    }
+                }
+                //Summaries are recomputed on write, test names are dropped
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self) -> String {
+        let mut out = String::new();
+        for (path, file) in &self.files {
+            let _ = writeln!(out, "SF:{}", path);
+            for (name, (line, _)) in &file.functions {
+                let _ = writeln!(out, "FN:{},{}", line, name);
+            }
+            for (name, (_, hits)) in &file.functions {
+                let _ = writeln!(out, "FNDA:{},{}", hits, name);
+            }
+            let functions_hit = file.functions.values().filter(|x| x.1 > 0).count();
+            let _ = writeln!(out, "FNF:{}\nFNH:{}", file.functions.len(), functions_hit);
+            for ((line, block, branch), taken) in &file.branches {
+                let taken = taken.map_or("-".to_owned(), |x| x.to_string());
+                let _ = writeln!(out, "BRDA:{},{},{},{}", line, block, branch, taken);
+            }
+            let branches_hit = file
+                .branches
+                .values()
+                .filter(|x| x.is_some_and(|x| x > 0))
+                .count();
+            let _ = writeln!(out, "BRF:{}\nBRH:{}", file.branches.len(), branches_hit);
+            for (line, hits) in &file.lines {
+                let _ = writeln!(out, "DA:{},{}", line, hits);
+            }
+            let lines_hit = file.lines.values().filter(|x| **x > 0).count();
+            let _ = writeln!(out, "LF:{}\nLH:{}", file.lines.len(), lines_hit);
+            out.push_str("end_of_record\n");
+        }
+        out
+    }
+}
+
+/// Merges coverage `files` of a single format into `output`. LLVM profiles are merged with
+/// `llvm-profdata` and converted to lcov with `llvm-cov` when `lcov_binary` is set
+pub async fn merge(
+    files: &[PathBuf],
+    format: CoverageFormat,
+    output: &Path,
+    lcov_binary: Option<&Path>,
+) -> Result<()> {
+    let invalid = |path: &Path, message: String| InputError::InvalidCoverageFile {
+        path: path.to_path_buf(),
+        message,
+    };
+    match format {
+        CoverageFormat::Jacoco => {
+            let mut data = JacocoData::default();
+            for file in files {
+                debug!("Reading {}", file.display());
+                data.read(&tokio::fs::read(file).await?)
+                    .map_err(|message| invalid(file, message))?;
+            }
+            tokio::fs::write(output, data.write()).await?;
+        }
+        CoverageFormat::Lcov => {
+            let mut data = LcovData::default();
+            for file in files {
+                debug!("Reading {}", file.display());
+                data.read(&tokio::fs::read_to_string(file).await?)
+                    .map_err(|message| invalid(file, message))?;
+            }
+            tokio::fs::write(output, data.write()).await?;
+        }
+        CoverageFormat::Llvm => {
+            let Some(binary) = lcov_binary else {
+                return xcrun(&["llvm-profdata", "merge", "-sparse"], files, output).await;
+            };
+            let profile = tempfile::Builder::new().suffix(".profdata").tempfile()?;
+            xcrun(
+                &["llvm-profdata", "merge", "-sparse"],
+                files,
+                profile.path(),
+            )
+            .await?;
+            let lcov = xcrun_output(&[
+                "llvm-cov".as_ref(),
+                "export".as_ref(),
+                "-format=lcov".as_ref(),
+                "-instr-profile".as_ref(),
+                profile.path().as_os_str(),
+                binary.as_os_str(),
+            ])
+            .await?;
+            tokio::fs::write(output, lcov).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn xcrun(args: &[&str], inputs: &[PathBuf], output: &Path) -> Result<()> {
+    let mut all: Vec<&std::ffi::OsStr> = args.iter().map(|x| x.as_ref()).collect();
+    all.extend(inputs.iter().map(|x| x.as_os_str()));
+    all.push("-o".as_ref());
+    all.push(output.as_os_str());
+    xcrun_output(&all).await.map(|_| ())
+}
+
+async fn xcrun_output(args: &[&std::ffi::OsStr]) -> Result<Vec<u8>> {
+    let tool = format!("xcrun {}", args[0].to_string_lossy());
+    let mut command = Command::new("xcrun");
+    command.args(args);
+    debug!("Running {:?}", command);
+    let result = command
+        .output()
+        .await
+        .map_err(|error| ReportError::ToolNotFound {
+            tool: tool.clone(),
+            error,
+        })?;
+    if !result.status.success() {
+        return Err(ReportError::ToolFailed {
+            tool,
+            status: result.status,
+            stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+        })?;
+    }
+    Ok(result.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exec(session: &str, probes: &[bool]) -> Vec<u8> {
+        let mut data = JacocoData::default();
+        data.sessions.push(SessionInfo {
+            id: session.to_owned(),
+            start: 1,
+            dump: 2,
+        });
+        data.classes.insert(
+            0x1234,
+            ClassExecution {
+                name: "com/example/Main".to_owned(),
+                probes: probes.to_vec(),
+            },
+        );
+        data.write()
+    }
+
+    #[test]
+    fn test_jacoco_probes_are_combined() {
+        let mut first = vec![false; 10];
+        first[0] = true;
+        let mut second = vec![false; 10];
+        second[9] = true;
+
+        let mut merged = JacocoData::default();
+        merged.read(&exec("emulator-1", &first)).unwrap();
+        merged.read(&exec("emulator-2", &second)).unwrap();
+
+        let mut expected = vec![false; 10];
+        expected[0] = true;
+        expected[9] = true;
+        assert_eq!(merged.sessions.len(), 2);
+        assert_eq!(merged.classes[&0x1234].probes, expected);
+
+        let mut reread = JacocoData::default();
+        reread.read(&merged.write()).unwrap();
+        assert_eq!(reread, merged);
+    }
+
+    #[test]
+    fn test_jacoco_rejects_incompatible_classes() {
+        let mut merged = JacocoData::default();
+        merged.read(&exec("emulator-1", &[true; 3])).unwrap();
+        assert!(merged.read(&exec("emulator-2", &[true; 4])).is_err());
+        assert!(JacocoData::default().read(b"\x01\x00\x00").is_err());
+    }
+
+    #[test]
+    fn test_lcov_hits_are_summed() {
+        let mut merged = LcovData::default();
+        merged
+            .read("TN:\nSF:src/main.rs\nFN:1,main\nFNDA:0,main\nBRDA:2,0,0,-\nDA:1,0\nDA:2,1\nend_of_record\n")
+            .unwrap();
+        merged
+            .read("SF:src/main.rs\nFN:1,main\nFNDA:2,main\nBRDA:2,0,0,3\nDA:1,2\nend_of_record\n")
+            .unwrap();
+
+        assert_eq!(
+            merged.write(),
+            "SF:src/main.rs\nFN:1,main\nFNDA:2,main\nFNF:1\nFNH:1\nBRDA:2,0,0,3\nBRF:1\nBRH:1\nDA:1,2\nDA:2,1\nLF:2\nLH:2\nend_of_record\n"
+        );
+    }
+}
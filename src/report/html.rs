@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use crate::{api::TestRun, progress::format_duration, xml::escape};
+
+use super::junit::TestCase;
+
+const SLOWEST_TESTS: usize = 10;
+
+const STYLE: &str =
+    "body{font-family:-apple-system,Helvetica,Arial,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse}td,th{padding:4px 12px;text-align:left;border-bottom:1px solid #ddd}\
+.passed{color:#2e7d32}.failed{color:#c62828}.ignored{color:#f9a825}\
+pre{background:#f5f5f5;padding:1em;overflow-x:auto;white-space:pre-wrap}";
+
+/// Single-file summary of a run. `logs` holds log excerpts of failed tests by test id
+pub fn render(
+    run: &TestRun,
+    report_url: &str,
+    testcases: &[TestCase],
+    logs: &HashMap<String, String>,
+) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>Marathon Cloud run {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&run.id),
+        STYLE
+    ));
+    html.push_str(&format!(
+        "<h1>Marathon Cloud run {}</h1>\n<p>State: <b>{}</b> &middot; <a href=\"{}\">Full report</a></p>\n",
+        escape(&run.id),
+        escape(&run.state),
+        escape(report_url)
+    ));
+    let count = |value: Option<u32>| value.map_or("missing".to_owned(), |x| x.to_string());
+    html.push_str(&format!(
+        "<p><span class=\"passed\">{} passed</span>, <span class=\"failed\">{} failed</span>, <span class=\"ignored\">{} ignored</span>",
+        count(run.passed),
+        count(run.failed),
+        count(run.ignored)
+    ));
+    if let Some(time) = run.total_run_time_seconds {
+        html.push_str(&format!(
+            " &middot; billable time {}",
+            format_duration(std::time::Duration::from_secs_f64(time))
+        ));
+    }
+    html.push_str("</p>\n");
+
+    let mut slowest: Vec<&TestCase> = testcases.iter().collect();
+    slowest.sort_by(|a, b| b.time.total_cmp(&a.time));
+    if !slowest.is_empty() {
+        html.push_str("<h2>Slowest tests</h2>\n<table>\n<tr><th>Test</th><th>Time</th></tr>\n");
+        for testcase in slowest.into_iter().take(SLOWEST_TESTS) {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{:.3}s</td></tr>\n",
+                escape(&testcase.id()),
+                testcase.time
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    let failures: Vec<&TestCase> = testcases.iter().filter(|x| x.is_failure()).collect();
+    if !failures.is_empty() {
+        html.push_str(&format!("<h2>Failures ({})</h2>\n", failures.len()));
+        for testcase in failures {
+            let id = testcase.id();
+            html.push_str(&format!(
+                "<h3 class=\"failed\">{}</h3>\n<pre>{}</pre>\n",
+                escape(&id),
+                escape(&testcase.message())
+            ));
+            if let Some(log) = logs.get(&id) {
+                html.push_str(&format!(
+                    "<details><summary>Log excerpt</summary><pre>{}</pre></details>\n",
+                    escape(log)
+                ));
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::junit;
+
+    #[test]
+    fn test_render_escapes_and_lists_failures() {
+        let run = TestRun {
+            id: "run-1".to_owned(),
+            state: "failure".to_owned(),
+            passed: Some(1),
+            failed: Some(1),
+            ignored: Some(0),
+            total: Some(2),
+            completed: None,
+            total_run_time_seconds: Some(61.5),
+            error_message: None,
+        };
+        let testcases = junit::parse(
+            r#"<testsuite>
+  <testcase name="testPassed" classname="com.example.MainTest" time="1.0"/>
+  <testcase name="testFailed" classname="com.example.MainTest" time="2.0">
+    <failure message="expected &lt;1&gt;">java.lang.AssertionError: expected &lt;1&gt;</failure>
+  </testcase>
+</testsuite>"#,
+        );
+        let logs = HashMap::from([(
+            "com.example.MainTest#testFailed".to_owned(),
+            "E/TestRunner: <boom>".to_owned(),
+        )]);
+
+        let html = render(
+            &run,
+            "https://example.com/runs/run-1/report",
+            &testcases,
+            &logs,
+        );
+
+        assert!(html.contains("1 passed"));
+        assert!(html.contains("billable time 00:01:01.500"));
+        assert!(html.contains("<h2>Failures (1)</h2>"));
+        assert!(html.contains("java.lang.AssertionError: expected &lt;1&gt;"));
+        assert!(html.contains("E/TestRunner: &lt;boom&gt;"));
+        assert!(!html.contains("<boom>"));
+    }
+}
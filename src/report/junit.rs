@@ -0,0 +1,261 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use regex::Regex;
+
+use crate::xml::{escape, unescape};
+
+fn testcase_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?s)<testcase\b([^>]*?)(?:/>|>(.*?)</testcase\s*>)").unwrap())
+}
+
+//Attribute values may be quoted with either double or single quotes
+fn attribute_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap())
+}
+
+fn tag_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"<[^>]*>").unwrap())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    Error,
+    Skipped,
+}
+
+impl Outcome {
+    //A retried test counts as passed once any attempt passed
+    fn rank(self) -> u8 {
+        match self {
+            Outcome::Passed => 3,
+            Outcome::Failed | Outcome::Error => 2,
+            Outcome::Skipped => 1,
+        }
+    }
+}
+
+/// Single `<testcase>` of a JUnit report
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    pub classname: String,
+    pub name: String,
+    pub time: f64,
+    pub outcome: Outcome,
+    /// Inner XML of the test case as found in the report, e.g. the failure message
+    pub body: String,
+}
+
+impl TestCase {
+    /// Identifier used by Marathon for files of this test
+    pub fn id(&self) -> String {
+        format!("{}#{}", self.classname, self.name)
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self.outcome, Outcome::Failed | Outcome::Error)
+    }
+
+    /// Text of the failure, error or skip reason without markup
+    pub fn message(&self) -> String {
+        unescape(tag_regex().replace_all(&self.body, "").trim()).into_owned()
+    }
+}
+
+/// Reads the test cases of a JUnit report, the structure of suites is ignored
+pub fn parse(report: &str) -> Vec<TestCase> {
+    testcase_regex()
+        .captures_iter(report)
+        .filter_map(|captures| {
+            let attributes = &captures[1];
+            let body = captures.get(2).map_or("", |body| body.as_str());
+            let outcome = if body.contains("<failure") {
+                Outcome::Failed
+            } else if body.contains("<error") {
+                Outcome::Error
+            } else if body.contains("<skipped") {
+                Outcome::Skipped
+            } else {
+                Outcome::Passed
+            };
+            Some(TestCase {
+                classname: attribute(attributes, "classname").unwrap_or_default(),
+                name: attribute(attributes, "name")?,
+                time: attribute(attributes, "time")
+                    .and_then(|time| time.parse().ok())
+                    .unwrap_or_default(),
+                outcome,
+                body: body.trim().to_owned(),
+            })
+        })
+        .collect()
+}
+
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    attribute_regex()
+        .captures_iter(attributes)
+        .find(|captures| &captures[1] == name)
+        .and_then(|captures| captures.get(2).or_else(|| captures.get(3)))
+        .map(|value| unescape(value.as_str()).into_owned())
+}
+
+/// Keeps a single result per test. Retries are resolved in favour of a passing attempt,
+/// otherwise the first failure is kept
+pub fn deduplicate(testcases: impl IntoIterator<Item = TestCase>) -> Vec<TestCase> {
+    let mut unique: BTreeMap<(String, String), TestCase> = BTreeMap::new();
+    for testcase in testcases {
+        let key = (testcase.classname.clone(), testcase.name.clone());
+        match unique.get(&key) {
+            Some(existing) if existing.outcome.rank() >= testcase.outcome.rank() => {}
+            _ => {
+                unique.insert(key, testcase);
+            }
+        }
+    }
+    unique.into_values().collect()
+}
+
+/// Renders `testcases` as a report with one suite per class
+pub fn render(name: &str, testcases: &[TestCase]) -> String {
+    let mut suites: BTreeMap<&str, Vec<&TestCase>> = BTreeMap::new();
+    for testcase in testcases {
+        suites
+            .entry(testcase.classname.as_str())
+            .or_default()
+            .push(testcase);
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"{}\" {}>\n",
+        escape(name),
+        totals(testcases.iter())
+    ));
+    for (classname, testcases) in suites {
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" {}>\n",
+            escape(classname),
+            totals(testcases.iter().copied())
+        ));
+        for testcase in testcases {
+            let attributes = format!(
+                "name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&testcase.name),
+                escape(&testcase.classname),
+                testcase.time
+            );
+            if testcase.body.is_empty() {
+                xml.push_str(&format!("    <testcase {}/>\n", attributes));
+            } else {
+                xml.push_str(&format!(
+                    "    <testcase {}>\n      {}\n    </testcase>\n",
+                    attributes, testcase.body
+                ));
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn totals<'a>(testcases: impl Iterator<Item = &'a TestCase>) -> String {
+    let (mut tests, mut failures, mut errors, mut skipped, mut time) = (0, 0, 0, 0, 0.0);
+    for testcase in testcases {
+        tests += 1;
+        time += testcase.time;
+        match testcase.outcome {
+            Outcome::Failed => failures += 1,
+            Outcome::Error => errors += 1,
+            Outcome::Skipped => skipped += 1,
+            Outcome::Passed => {}
+        }
+    }
+    format!(
+        "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+        tests, failures, errors, skipped, time
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="omni" tests="3" failures="1" errors="1">
+  <testcase name="testPassed" classname="com.example.MainTest" time="1.0"/>
+  <testcase name="testFailed" classname="com.example.MainTest" time="2.5">
+    <failure>java.lang.AssertionError</failure>
+  </testcase>
+  <testcase name="testCrashed[a &amp; b]" classname="com.example.OtherTest" time="1.0">
+    <error>java.lang.RuntimeException</error>
+  </testcase>
+</testsuite>"#;
+
+    #[test]
+    fn test_parse() {
+        let testcases = parse(REPORT);
+        let failed: Vec<String> = testcases
+            .iter()
+            .filter(|testcase| testcase.is_failure())
+            .map(TestCase::id)
+            .collect();
+
+        assert_eq!(testcases.len(), 3);
+        assert_eq!(testcases[1].time, 2.5);
+        assert_eq!(
+            failed,
+            vec![
+                "com.example.MainTest#testFailed",
+                "com.example.OtherTest#testCrashed[a & b]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_single_quoted_attributes() {
+        let report = r#"<testsuite>
+  <testcase classname='com.example.MainTest' name='testQuoted["a"]' time='0.5' />
+  <testcase testname="ignored" name="testSpaced" classname = "com.example.MainTest"></testcase >
+</testsuite>"#;
+
+        let testcases = parse(report);
+
+        assert_eq!(testcases.len(), 2);
+        assert_eq!(testcases[0].id(), r#"com.example.MainTest#testQuoted["a"]"#);
+        assert_eq!(testcases[0].time, 0.5);
+        assert_eq!(testcases[1].id(), "com.example.MainTest#testSpaced");
+    }
+
+    #[test]
+    fn test_retried_test_passes_once_any_attempt_passed() {
+        let retry = r#"<testsuite>
+  <testcase name="testFailed" classname="com.example.MainTest" time="1.0"/>
+</testsuite>"#;
+
+        let testcases = deduplicate(parse(REPORT).into_iter().chain(parse(retry)));
+
+        assert_eq!(testcases.len(), 3);
+        let retried = testcases
+            .iter()
+            .find(|testcase| testcase.name == "testFailed")
+            .unwrap();
+        assert_eq!(retried.outcome, Outcome::Passed);
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let testcases = deduplicate(parse(REPORT));
+
+        let merged = render("merged", &testcases);
+
+        assert!(merged.contains(
+            r#"<testsuites name="merged" tests="3" failures="1" errors="1" skipped="0" time="4.500">"#
+        ));
+        assert_eq!(deduplicate(parse(&merged)), testcases);
+    }
+}
@@ -0,0 +1,315 @@
+pub mod coverage;
+pub mod html;
+pub mod junit;
+pub mod xcresult;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use log::debug;
+use url::{Position, Url};
+use walkdir::WalkDir;
+
+use crate::{
+    api::{RapiClient, RapiReqwestClient},
+    artifacts::{
+        download_artifacts, download_test_results, fetch_artifact_list, relative_path,
+        select_failed_test_artifacts, test_candidates, ArtifactType, DownloadOptions,
+    },
+    errors::{ConfigurationError, InputError},
+    formatter::{Formatter, StandardFormatter},
+    interactor::filter_artifact_list,
+};
+
+//Lines of the end of a test log shown for failures
+const LOG_EXCERPT_LINES: usize = 50;
+
+/// Link to the web report of run `id`
+pub(crate) fn report_url(base_url: &str, id: &str) -> Result<String> {
+    let base_report_url = Url::parse(base_url)?;
+    let base_report_url = &base_report_url[..Position::AfterPort];
+    Ok(format!("{}/runs/{}/report", base_report_url, id))
+}
+
+/// Merges the JUnit reports found in `inputs` into a single report at `output`.
+/// Folders are searched recursively for xml files, e.g. the output folders of several runs
+pub(crate) async fn junit_merge(inputs: Vec<PathBuf>, output: &Path) -> Result<()> {
+    let mut reports = Vec::new();
+    for input in &inputs {
+        if !input.exists() {
+            return Err(InputError::PathNotFound {
+                path: input.clone(),
+            })?;
+        }
+        reports.extend(
+            WalkDir::new(input)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "xml")),
+        );
+    }
+    if reports.is_empty() {
+        return Err(InputError::NoReportsFound {
+            format: "JUnit".to_owned(),
+            paths: inputs,
+        })?;
+    }
+
+    let mut testcases = Vec::new();
+    for report in &reports {
+        debug!("Reading {}", report.display());
+        let content = tokio::fs::read_to_string(report).await?;
+        testcases.extend(junit::parse(&content));
+    }
+    let parsed = testcases.len();
+    let testcases = junit::deduplicate(testcases);
+
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(output, junit::render("marathon-cloud", &testcases)).await?;
+    StandardFormatter::new(1).message(&format!(
+        "Merged {} results of {} tests from {} reports into {}",
+        parsed,
+        testcases.len(),
+        reports.len(),
+        output.display()
+    ));
+    Ok(())
+}
+
+/// Writes a single-file HTML summary of run `id` to `output`. Only the JUnit reports and
+/// logs of failed tests are downloaded for it
+pub(crate) async fn html_report(
+    base_url: &str,
+    api_key: &str,
+    id: &str,
+    output: &Path,
+    no_progress_bars: bool,
+) -> Result<()> {
+    let mut formatter = StandardFormatter::new(3);
+    formatter.stage("Checking test run state...");
+    let client = RapiReqwestClient::new(base_url, api_key);
+    let run = client.get_run(id).await?;
+
+    formatter.stage("Downloading test results...");
+    let token = client.get_token().await?;
+    let artifacts = fetch_artifact_list(&client, id, &token).await?;
+    let work_dir = tempfile::tempdir()?;
+    let options = DownloadOptions::default();
+    let testcases = junit::deduplicate(
+        download_test_results(
+            &client,
+            id,
+            &artifacts,
+            work_dir.path(),
+            &token,
+            &options,
+            no_progress_bars,
+        )
+        .await?,
+    );
+    let failed = testcases
+        .iter()
+        .filter(|testcase| testcase.is_failure())
+        .map(junit::TestCase::id)
+        .collect();
+    let log_artifacts: Vec<_> = select_failed_test_artifacts(artifacts, id, &failed)
+        .into_iter()
+        .filter(|artifact| artifact.id.ends_with(".log"))
+        .collect();
+    let log_paths: Vec<(String, PathBuf)> = log_artifacts
+        .iter()
+        .map(|artifact| {
+            let relative_path = relative_path(&artifact.id, id);
+            (
+                relative_path.to_owned(),
+                work_dir.path().join(relative_path),
+            )
+        })
+        .collect();
+    download_artifacts(
+        &client,
+        id,
+        log_artifacts,
+        work_dir.path(),
+        &token,
+        &options,
+        no_progress_bars,
+    )
+    .await?;
+
+    formatter.stage("Rendering report...");
+    let mut logs = HashMap::new();
+    for testcase in testcases.iter().filter(|testcase| testcase.is_failure()) {
+        let test_id = testcase.id();
+        let Some((_, path)) = log_paths
+            .iter()
+            .find(|(relative_path, _)| test_candidates(relative_path).any(|x| x == test_id))
+        else {
+            continue;
+        };
+        //Device logs may contain arbitrary bytes
+        let log = tokio::fs::read(path).await?;
+        let log = String::from_utf8_lossy(&log);
+        let lines: Vec<&str> = log.lines().collect();
+        let excerpt = lines[lines.len().saturating_sub(LOG_EXCERPT_LINES)..].join("\n");
+        logs.insert(test_id, excerpt);
+    }
+
+    let html = html::render(&run, &report_url(base_url, id)?, &testcases, &logs);
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(output, html).await?;
+    formatter.message(&format!("Report written to {}", output.display()));
+    Ok(())
+}
+
+/// Merges the `.xcresult` bundles found in `inputs` into a single result bundle at `output`
+pub(crate) async fn xcresult_merge(inputs: Vec<PathBuf>, output: &Path) -> Result<()> {
+    for input in &inputs {
+        if !input.exists() {
+            return Err(InputError::PathNotFound {
+                path: input.clone(),
+            })?;
+        }
+    }
+    merge_xcresults(&inputs, output).await
+}
+
+/// Downloads the per-device `.xcresult` bundles of iOS run `id` and merges them into a single
+/// result bundle at `output`
+pub(crate) async fn xcresult_report(
+    base_url: &str,
+    api_key: &str,
+    id: &str,
+    output: &Path,
+    no_progress_bars: bool,
+) -> Result<()> {
+    let mut formatter = StandardFormatter::new(2);
+    formatter.stage("Downloading result bundles...");
+    let client = RapiReqwestClient::new(base_url, api_key);
+    let token = client.get_token().await?;
+    let artifacts = fetch_artifact_list(&client, id, &token).await?;
+    let globs = ArtifactType::Xcresult
+        .globs()
+        .iter()
+        .map(|glob| glob.to_string())
+        .collect();
+    let artifacts = filter_artifact_list(artifacts, Some(globs), None, &format!("{}/", id))?;
+    //Result bundles are only generated by iOS runs
+    if artifacts.is_empty() {
+        return Err(ConfigurationError::UnsupportedRunConfiguration {
+            message: format!(
+                "test run {} has no result bundles, xcresult reports are only available for iOS runs",
+                id
+            ),
+        })?;
+    }
+    let work_dir = tempfile::tempdir()?;
+    download_artifacts(
+        &client,
+        id,
+        artifacts,
+        work_dir.path(),
+        &token,
+        &DownloadOptions::default(),
+        no_progress_bars,
+    )
+    .await?;
+
+    formatter.stage("Merging result bundles...");
+    merge_xcresults(&[work_dir.path().to_path_buf()], output).await
+}
+
+async fn merge_xcresults(inputs: &[PathBuf], output: &Path) -> Result<()> {
+    let bundles = xcresult::find_bundles(inputs)?;
+    if bundles.is_empty() {
+        return Err(InputError::NoReportsFound {
+            format: "xcresult".to_owned(),
+            paths: inputs.to_vec(),
+        })?;
+    }
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    xcresult::merge(&bundles, output).await?;
+    StandardFormatter::new(1).message(&format!(
+        "Merged {} result bundles into {}",
+        bundles.len(),
+        output.display()
+    ));
+    Ok(())
+}
+
+/// Merges the per-device coverage files found in `inputs` into a single file at `output`
+pub(crate) async fn coverage_merge(
+    inputs: Vec<PathBuf>,
+    output: &Path,
+    lcov_binary: Option<PathBuf>,
+) -> Result<()> {
+    for input in &inputs {
+        if !input.exists() {
+            return Err(InputError::PathNotFound {
+                path: input.clone(),
+            })?;
+        }
+    }
+    let files = coverage::find_files(&inputs);
+    let mut formats: Vec<_> = files.values().copied().collect();
+    formats.sort();
+    formats.dedup();
+    let format = match formats[..] {
+        [] => {
+            return Err(InputError::NoReportsFound {
+                format: "coverage".to_owned(),
+                paths: inputs,
+            })?
+        }
+        [format] => format,
+        _ => {
+            return Err(ConfigurationError::UnsupportedRunConfiguration {
+                message: format!(
+                    "coverage files of different formats can't be merged together: {:?}",
+                    formats
+                ),
+            })?
+        }
+    };
+    if lcov_binary.is_some() && format != coverage::CoverageFormat::Llvm {
+        return Err(ConfigurationError::UnsupportedRunConfiguration {
+            message: "--lcov-binary can only be used with LLVM profiles of iOS runs".into(),
+        })?;
+    }
+
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let files: Vec<PathBuf> = files.into_keys().collect();
+    coverage::merge(&files, format, output, lcov_binary.as_deref()).await?;
+    StandardFormatter::new(1).message(&format!(
+        "Merged {} coverage files into {}",
+        files.len(),
+        output.display()
+    ));
+    Ok(())
+}
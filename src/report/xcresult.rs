@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::debug;
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+use crate::errors::{InputError, ReportError};
+
+const XCRESULT_EXTENSION: &str = "xcresult";
+
+fn is_bundle(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == XCRESULT_EXTENSION)
+}
+
+/// Result bundles in `inputs`. Folders are searched recursively, bundles themselves are not
+/// descended into
+pub fn find_bundles(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut bundles = Vec::new();
+    for input in inputs {
+        let mut it = WalkDir::new(input).sort_by_file_name().into_iter();
+        while let Some(entry) = it.next() {
+            let entry = entry?;
+            if entry.file_type().is_dir() && is_bundle(entry.path()) {
+                bundles.push(entry.into_path());
+                it.skip_current_dir();
+            }
+        }
+    }
+    Ok(bundles)
+}
+
+/// Merges `bundles` into a single result bundle at `output` with `xcresulttool`.
+/// A single bundle is copied as is. Only a result bundle is replaced at `output`
+pub async fn merge(bundles: &[PathBuf], output: &Path) -> Result<()> {
+    if output.exists() {
+        if !(output.is_dir() && is_bundle(output)) {
+            return Err(InputError::OutputExists {
+                path: output.to_path_buf(),
+            })?;
+        }
+        let output = output.canonicalize()?;
+        for bundle in bundles {
+            if bundle.canonicalize()?.starts_with(&output) {
+                return Err(InputError::OutputOverwritesInput {
+                    path: bundle.clone(),
+                })?;
+            }
+        }
+        tokio::fs::remove_dir_all(&output).await?;
+    }
+    if let [bundle] = bundles {
+        return copy_dir(bundle, output).await;
+    }
+
+    let tool = "xcrun xcresulttool".to_owned();
+    let mut command = Command::new("xcrun");
+    command
+        .arg("xcresulttool")
+        .arg("merge")
+        .args(bundles)
+        .arg("--output-path")
+        .arg(output);
+    debug!("Running {:?}", command);
+    let result = command
+        .output()
+        .await
+        .map_err(|error| ReportError::ToolNotFound {
+            tool: tool.clone(),
+            error,
+        })?;
+    if !result.status.success() {
+        return Err(ReportError::ToolFailed {
+            tool,
+            status: result.status,
+            stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+        })?;
+    }
+    Ok(())
+}
+
+async fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            tokio::fs::create_dir_all(&target).await?;
+        } else {
+            tokio::fs::copy(entry.path(), &target).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_bundles_does_not_descend_into_bundles() {
+        let dir = tempdir().unwrap();
+        let first = dir.path().join("emulator-1/tests.xcresult");
+        let second = dir.path().join("emulator-2/tests.xcresult");
+        fs::create_dir_all(first.join("Data/nested.xcresult")).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        fs::write(
+            dir.path().join("emulator-2/report.xcresult"),
+            b"not a bundle",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_bundles(&[dir.path().to_path_buf()]).unwrap(),
+            vec![first, second]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_copies_single_bundle() {
+        let dir = tempdir().unwrap();
+        let bundle = dir.path().join("tests.xcresult");
+        fs::create_dir_all(bundle.join("Data")).unwrap();
+        fs::write(bundle.join("Info.plist"), b"plist").unwrap();
+        fs::write(bundle.join("Data/data.0"), b"data").unwrap();
+        let output = dir.path().join("out/merged.xcresult");
+
+        merge(&[bundle], &output).await.unwrap();
+
+        assert_eq!(fs::read(output.join("Info.plist")).unwrap(), b"plist");
+        assert_eq!(fs::read(output.join("Data/data.0")).unwrap(), b"data");
+    }
+
+    #[tokio::test]
+    async fn test_merge_replaces_only_result_bundles() {
+        let dir = tempdir().unwrap();
+        let bundle = dir.path().join("tests.xcresult");
+        fs::create_dir_all(&bundle).unwrap();
+        fs::write(bundle.join("Info.plist"), b"plist").unwrap();
+        let previous = dir.path().join("merged.xcresult");
+        fs::create_dir_all(&previous).unwrap();
+        fs::write(previous.join("stale"), b"stale").unwrap();
+        let folder = dir.path().join("results");
+        fs::create_dir_all(&folder).unwrap();
+
+        merge(std::slice::from_ref(&bundle), &previous)
+            .await
+            .unwrap();
+        assert!(!previous.join("stale").exists());
+        assert!(previous.join("Info.plist").is_file());
+
+        assert!(merge(std::slice::from_ref(&bundle), &folder).await.is_err());
+        assert!(folder.is_dir());
+        assert!(merge(std::slice::from_ref(&bundle), &bundle).await.is_err());
+        assert!(bundle.join("Info.plist").is_file());
+    }
+}
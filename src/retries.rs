@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::errors::InputError;
+
+/// Retry quotas of the tests matching `pattern`, e.g. `com.example.unit.*`, overriding the
+/// quotas of the run. `*` matches any sequence of characters, the first matching pattern applies
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RetryQuotaOverride {
+    #[serde(rename = "pattern")]
+    pub pattern: String,
+    #[serde(rename = "retry_quota_test_uncompleted")]
+    pub uncompleted: Option<u32>,
+    #[serde(rename = "retry_quota_test_preventive")]
+    pub preventive: Option<u32>,
+    #[serde(rename = "retry_quota_test_reactive")]
+    pub reactive: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyEntry {
+    pattern: String,
+    uncompleted: Option<u32>,
+    preventive: Option<u32>,
+    reactive: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetryPolicy {
+    overrides: Vec<PolicyEntry>,
+}
+
+/// Reads the retry quota overrides of the policy file `path`
+pub async fn load(path: &Path) -> Result<Vec<RetryQuotaOverride>, InputError> {
+    let content = tokio::fs::read(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+    let invalid = |message: String| InputError::InvalidRetryPolicy {
+        path: path.to_owned(),
+        message,
+    };
+    let policy: RetryPolicy =
+        serde_yaml::from_slice(&content).map_err(|error| invalid(error.to_string()))?;
+    let overrides: Vec<RetryQuotaOverride> = policy
+        .overrides
+        .into_iter()
+        .map(|x| RetryQuotaOverride {
+            pattern: x.pattern,
+            uncompleted: x.uncompleted,
+            preventive: x.preventive,
+            reactive: x.reactive,
+        })
+        .collect();
+    validate(&overrides).map_err(invalid)?;
+    Ok(overrides)
+}
+
+fn validate(overrides: &[RetryQuotaOverride]) -> Result<(), String> {
+    for (index, entry) in overrides.iter().enumerate() {
+        if entry.pattern.trim().is_empty() {
+            return Err(format!("pattern of override {} is empty", index + 1));
+        }
+        if entry.uncompleted.is_none() && entry.preventive.is_none() && entry.reactive.is_none() {
+            return Err(format!(
+                "{} sets none of uncompleted, preventive and reactive",
+                entry.pattern
+            ));
+        }
+        if overrides[..index]
+            .iter()
+            .any(|x| x.pattern == entry.pattern)
+        {
+            return Err(format!("pattern {} is repeated", entry.pattern));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("retries.yml");
+        tokio::fs::write(
+            &path,
+            "overrides:\n  - pattern: com.example.unit.*\n    preventive: 0\n    reactive: 0\n  - pattern: com.example.e2e.*\n    reactive: 3\n",
+        )
+        .await
+        .unwrap();
+        let overrides = load(&path).await.unwrap();
+        assert_eq!(
+            overrides[0],
+            RetryQuotaOverride {
+                pattern: "com.example.unit.*".to_owned(),
+                uncompleted: None,
+                preventive: Some(0),
+                reactive: Some(0),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&overrides[1]).unwrap(),
+            serde_json::json!({"pattern": "com.example.e2e.*", "retry_quota_test_reactive": 3})
+        );
+
+        tokio::fs::write(&path, "overrides:\n  - pattern: com.example.unit.*\n")
+            .await
+            .unwrap();
+        assert!(matches!(
+            load(&path).await,
+            Err(InputError::InvalidRetryPolicy { .. })
+        ));
+    }
+}
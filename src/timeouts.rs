@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::InputError;
+
+/// Timeout in seconds of the tests matching `pattern`, e.g. `com.example.video.*` or
+/// `VideoTests/PlayerTests/*`. `*` matches any sequence of characters, the first matching
+/// pattern applies
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TestTimeoutOverride {
+    #[serde(rename = "pattern")]
+    pub pattern: String,
+    #[serde(rename = "timeout")]
+    pub timeout: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimeoutOverrides {
+    overrides: Vec<TestTimeoutOverride>,
+}
+
+/// Reads the overrides of `path`, none of them may exceed `test_timeout_max`
+pub async fn load(
+    path: &Path,
+    test_timeout_max: Option<u32>,
+) -> Result<Vec<TestTimeoutOverride>, InputError> {
+    let content = tokio::fs::read(path)
+        .await
+        .map_err(|error| InputError::OpenFileFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+    let invalid = |message: String| InputError::InvalidTimeoutOverrides {
+        path: path.to_owned(),
+        message,
+    };
+    let file: TimeoutOverrides =
+        serde_yaml::from_slice(&content).map_err(|error| invalid(error.to_string()))?;
+    validate(&file.overrides, test_timeout_max).map_err(invalid)?;
+    Ok(file.overrides)
+}
+
+fn validate(
+    overrides: &[TestTimeoutOverride],
+    test_timeout_max: Option<u32>,
+) -> Result<(), String> {
+    for (index, entry) in overrides.iter().enumerate() {
+        if entry.pattern.trim().is_empty() {
+            return Err(format!("pattern of override {} is empty", index + 1));
+        }
+        if entry.timeout == 0 {
+            return Err(format!(
+                "timeout of {} should be a positive number",
+                entry.pattern
+            ));
+        }
+        if let Some(max) = test_timeout_max.filter(|max| entry.timeout > *max) {
+            return Err(format!(
+                "timeout of {} is {}s, more than --test-timeout-max {}s",
+                entry.pattern, entry.timeout, max
+            ));
+        }
+        if overrides[..index]
+            .iter()
+            .any(|x| x.pattern == entry.pattern)
+        {
+            return Err(format!("pattern {} is repeated", entry.pattern));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, timeout: u32) -> TestTimeoutOverride {
+        TestTimeoutOverride {
+            pattern: pattern.to_owned(),
+            timeout,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overrides.yml");
+        tokio::fs::write(
+            &path,
+            "overrides:\n  - pattern: com.example.video.*\n    timeout: 600\n  - pattern: com.example.LoginTest#testSlowLogin\n    timeout: 300\n",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            load(&path, None).await.unwrap(),
+            vec![
+                entry("com.example.video.*", 600),
+                entry("com.example.LoginTest#testSlowLogin", 300)
+            ]
+        );
+        assert!(matches!(
+            load(&path, Some(400)).await,
+            Err(InputError::InvalidTimeoutOverrides { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[entry("com.example.*", 60)], Some(60)).is_ok());
+        assert!(validate(&[entry(" ", 60)], None).is_err());
+        assert!(validate(&[entry("com.example.*", 0)], None).is_err());
+        assert!(validate(
+            &[entry("com.example.*", 60), entry("com.example.*", 120)],
+            None
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use console::{style, Term};
+use tokio::{task::JoinHandle, time::Instant};
+
+use crate::{
+    api::{TestEvent, TestEventType, TestRun},
+    interrupt::Pause,
+    progress::{format_duration, TestTally},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_LISTED_FAILURES: usize = 5;
+
+/// Live view of a test run which is redrawn in place until the run finishes.
+pub struct RunMonitor {
+    state: Arc<Mutex<MonitorState>>,
+    term: Term,
+    renderer: JoinHandle<()>,
+}
+
+struct MonitorState {
+    view: RunView,
+    drawn_lines: usize,
+    paused: bool,
+}
+
+impl RunMonitor {
+    /// Returns None when stdout is not an interactive terminal
+    pub fn start(id: &str) -> Option<RunMonitor> {
+        let term = Term::stdout();
+        if !term.is_term() {
+            return None;
+        }
+        let state = Arc::new(Mutex::new(MonitorState {
+            view: RunView::new(id, Instant::now()),
+            drawn_lines: 0,
+            paused: false,
+        }));
+        let renderer = {
+            let state = state.clone();
+            let term = term.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    if let Ok(mut state) = state.lock() {
+                        if state.paused {
+                            continue;
+                        }
+                        let (_, width) = term.size();
+                        let lines = state.view.render(Instant::now(), width as usize);
+                        let _ = term.clear_last_lines(state.drawn_lines);
+                        for line in &lines {
+                            let _ = term.write_line(line);
+                        }
+                        state.drawn_lines = lines.len();
+                    }
+                }
+            })
+        };
+        Some(RunMonitor {
+            state,
+            term,
+            renderer,
+        })
+    }
+
+    pub fn on_run(&self, run: &TestRun) {
+        if let Ok(mut state) = self.state.lock() {
+            state.view.on_run(run);
+        }
+    }
+
+    pub fn on_event(&self, event: &TestEvent) {
+        if let Ok(mut state) = self.state.lock() {
+            state.view.on_event(event);
+        }
+    }
+
+    /// Handle which hides the view while the user is prompted
+    pub fn pause_handle(&self) -> Arc<dyn Pause> {
+        Arc::new(MonitorPause {
+            state: self.state.clone(),
+            term: self.term.clone(),
+        })
+    }
+
+    pub fn finish(self) {
+        self.renderer.abort();
+        if let Ok(mut state) = self.state.lock() {
+            let _ = self.term.clear_last_lines(state.drawn_lines);
+            state.drawn_lines = 0;
+        }
+    }
+}
+
+struct MonitorPause {
+    state: Arc<Mutex<MonitorState>>,
+    term: Term,
+}
+
+impl Pause for MonitorPause {
+    fn suspend(&self, f: &mut dyn FnMut()) {
+        if let Ok(mut state) = self.state.lock() {
+            let _ = self.term.clear_last_lines(state.drawn_lines);
+            state.drawn_lines = 0;
+            state.paused = true;
+        }
+        f();
+        if let Ok(mut state) = self.state.lock() {
+            state.paused = false;
+        }
+    }
+}
+
+struct RunView {
+    id: String,
+    started: Instant,
+    state: String,
+    billable_time: Duration,
+    devices: BTreeSet<String>,
+    tally: TestTally,
+    failures: Vec<String>,
+}
+
+impl RunView {
+    fn new(id: &str, started: Instant) -> Self {
+        Self {
+            id: id.to_owned(),
+            started,
+            state: "pending".to_owned(),
+            billable_time: Duration::ZERO,
+            devices: BTreeSet::new(),
+            tally: TestTally::default(),
+            failures: Vec::new(),
+        }
+    }
+
+    fn on_run(&mut self, run: &TestRun) {
+        self.state = run.state.clone();
+        if let Some(seconds) = run.total_run_time_seconds {
+            self.billable_time = Duration::from_secs_f64(seconds);
+        }
+    }
+
+    fn on_event(&mut self, event: &TestEvent) {
+        let device = event.device.clone().unwrap_or_else(|| "unknown".to_owned());
+        if let TestEventType::Failed = event.event_type {
+            self.failures.push(format!("{} on {}", event.test, device));
+        }
+        self.devices.insert(device);
+        self.tally.record(event);
+    }
+
+    fn render(&self, now: Instant, width: usize) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "{} {} ({})",
+                style("Test run").bold(),
+                self.id,
+                style(&self.state).cyan()
+            ),
+            format!(
+                "Elapsed: {}  Billable: {}",
+                format_duration(now.duration_since(self.started)),
+                format_duration(self.billable_time)
+            ),
+            format!(
+                "Devices: {}/{} busy",
+                self.tally.running.len(),
+                self.devices.len()
+            ),
+            format!("Tests: {}", self.tally),
+        ];
+        for (device, test) in &self.tally.running {
+            lines.push(format!("  {} {}", style(device).dim(), test));
+        }
+        if !self.failures.is_empty() {
+            lines.push(format!("{}", style("Failures:").red().bold()));
+            let skip = self.failures.len().saturating_sub(MAX_LISTED_FAILURES);
+            if skip > 0 {
+                lines.push(format!("  ... and {} more", skip));
+            }
+            for failure in self.failures.iter().skip(skip) {
+                lines.push(format!("  {}", failure));
+            }
+        }
+        //A wrapped line would take more rows than are cleared on the next redraw
+        lines.into_iter().map(|x| truncate(&x, width)).collect()
+    }
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    console::truncate_str(line, width.max(1), "…").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: TestEventType, test: &str, device: &str) -> TestEvent {
+        TestEvent {
+            event_type,
+            test: test.to_owned(),
+            device: Some(device.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_render_tracks_devices_and_failures() {
+        console::set_colors_enabled(false);
+        let started = Instant::now();
+        let mut view = RunView::new("run-1", started);
+
+        view.on_event(&event(TestEventType::Started, "A#a", "emulator-1"));
+        view.on_event(&event(TestEventType::Started, "B#b", "emulator-2"));
+        view.on_event(&event(TestEventType::Passed, "A#a", "emulator-1"));
+        view.on_event(&event(TestEventType::Started, "C#c", "emulator-1"));
+        view.on_event(&event(TestEventType::Failed, "B#b", "emulator-2"));
+
+        let lines = view.render(started + Duration::from_secs(61), 80);
+
+        assert_eq!(
+            lines,
+            vec![
+                "Test run run-1 (pending)",
+                "Elapsed: 00:01:01.000  Billable: 00:00:00.000",
+                "Devices: 1/2 busy",
+                "Tests: 1 passed, 1 failed, 1 running",
+                "  emulator-1 C#c",
+                "Failures:",
+                "  B#b on emulator-2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_limits_failures() {
+        console::set_colors_enabled(false);
+        let started = Instant::now();
+        let mut view = RunView::new("run-1", started);
+
+        for i in 0..7 {
+            view.on_event(&event(TestEventType::Failed, &format!("T#{}", i), "d"));
+        }
+
+        let lines = view.render(started, 80);
+
+        assert_eq!(lines[5], "  ... and 2 more");
+        assert_eq!(lines[6], "  T#2 on d");
+        assert_eq!(lines.len(), 11);
+    }
+
+    #[test]
+    fn test_render_fits_terminal_width() {
+        console::set_colors_enabled(false);
+        let started = Instant::now();
+        let mut view = RunView::new("a-very-long-run-identifier", started);
+        view.on_event(&event(TestEventType::Started, "A#a", "emulator-1"));
+
+        let lines = view.render(started, 20);
+
+        assert_eq!(lines[0], "Test run a-very-lon…");
+        assert!(lines.iter().all(|x| console::measure_text_width(x) <= 20));
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::unix_seconds;
+
+const MAX_ENTRIES: usize = 256;
+
+/// On-disk map of file content hashes to the storage path returned by a previous upload,
+/// so identical artifacts submitted again within the TTL are not uploaded twice
+pub struct UploadCache {
+    path: PathBuf,
+    ttl: Duration,
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    file_path: String,
+    uploaded_at: u64,
+}
+
+impl UploadCache {
+    pub fn default_location() -> Option<PathBuf> {
+        crate::cache::dir().map(|dir| dir.join("uploads.json"))
+    }
+
+    /// A missing or unreadable cache file results in an empty cache
+    pub fn load(path: &Path, ttl: Duration) -> UploadCache {
+        let entries = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        UploadCache {
+            path: path.to_path_buf(),
+            ttl,
+            entries,
+        }
+    }
+
+    pub fn get(&self, key: &str, now: SystemTime) -> Option<&str> {
+        self.entries
+            .get(key)
+            .filter(|entry| !self.is_expired(entry, now))
+            .map(|entry| entry.file_path.as_str())
+    }
+
+    pub fn insert(&mut self, key: String, file_path: String, now: SystemTime) {
+        self.entries.insert(
+            key,
+            CacheEntry {
+                file_path,
+                uploaded_at: unix_seconds(now),
+            },
+        );
+    }
+
+    /// Drops expired entries and keeps only the most recent ones before writing
+    pub fn save(&mut self, now: SystemTime) -> Result<()> {
+        let ttl = self.ttl;
+        self.entries.retain(|_, entry| !is_expired(entry, ttl, now));
+        if self.entries.len() > MAX_ENTRIES {
+            let mut entries: Vec<(String, CacheEntry)> = self.entries.drain().collect();
+            entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.uploaded_at));
+            entries.truncate(MAX_ENTRIES);
+            self.entries = entries.into_iter().collect();
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        //Write to a temporary file first so that concurrent CLI invocations never observe a partial cache
+        let tmp = self
+            .path
+            .with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&tmp, serde_json::to_string(&self.entries)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn is_expired(&self, entry: &CacheEntry, now: SystemTime) -> bool {
+        is_expired(entry, self.ttl, now)
+    }
+}
+
+fn is_expired(entry: &CacheEntry, ttl: Duration, now: SystemTime) -> bool {
+    unix_seconds(now).saturating_sub(entry.uploaded_at) >= ttl.as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        let mut cache =
+            UploadCache::load(&dir.path().join("uploads.json"), Duration::from_secs(60));
+
+        cache.insert("md5".to_owned(), "uploads/app.apk".to_owned(), now);
+
+        assert_eq!(cache.get("md5", now), Some("uploads/app.apk"));
+        assert_eq!(cache.get("md5", now + Duration::from_secs(60)), None);
+        assert_eq!(cache.get("other", now), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("uploads.json");
+        let now = SystemTime::now();
+        let mut cache = UploadCache::load(&path, Duration::from_secs(60));
+        cache.insert("fresh".to_owned(), "uploads/fresh.apk".to_owned(), now);
+        cache.insert(
+            "stale".to_owned(),
+            "uploads/stale.apk".to_owned(),
+            now - Duration::from_secs(120),
+        );
+
+        cache.save(now).unwrap();
+        let cache = UploadCache::load(&path, Duration::from_secs(60));
+
+        assert_eq!(cache.get("fresh", now), Some("uploads/fresh.apk"));
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_corrupted_cache_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("uploads.json");
+        fs::write(&path, "not json").unwrap();
+
+        let cache = UploadCache::load(&path, Duration::from_secs(60));
+
+        assert!(cache.entries.is_empty());
+    }
+}
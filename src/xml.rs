@@ -0,0 +1,87 @@
+use std::borrow::Cow;
+
+/// Escapes `value` for use in XML and HTML text and attribute values
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Resolves predefined and numeric character references. Unknown or malformed references
+/// are kept as they are, reports written by other tools aren't always well-formed
+pub fn unescape(value: &str) -> Cow<'_, str> {
+    if !value.contains('&') {
+        return Cow::Borrowed(value);
+    }
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let character = rest
+            .find(';')
+            .and_then(|end| Some((character(&rest[1..end])?, end)));
+        match character {
+            Some((character, end)) => {
+                result.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    Cow::Owned(result)
+}
+
+fn character(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => entity
+            .strip_prefix("#x")
+            .or_else(|| entity.strip_prefix("#X"))
+            .map(|x| u32::from_str_radix(x, 16))
+            .or_else(|| entity.strip_prefix('#').map(|x| x.parse()))
+            .and_then(|x| x.ok())
+            .and_then(char::from_u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_round_trip() {
+        let value = r#"a < b && c > "d" 'e'"#;
+
+        assert_eq!(
+            escape(value),
+            "a &lt; b &amp;&amp; c &gt; &quot;d&quot; &apos;e&apos;"
+        );
+        assert_eq!(unescape(&escape(value)), value);
+    }
+
+    #[test]
+    fn test_unescape_character_references() {
+        assert_eq!(
+            unescape("Tom &amp; Jerry &#x263A; &#65;"),
+            "Tom & Jerry \u{263A} A"
+        );
+        assert_eq!(unescape("&amp;lt;"), "&lt;");
+    }
+
+    #[test]
+    fn test_unescape_keeps_unknown_references() {
+        assert_eq!(unescape("a & b &nbsp; &#xZZ; &"), "a & b &nbsp; &#xZZ; &");
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{finished_run_server, run_android};
+
+fn catalog_server() -> String {
+    finished_run_server("passed", |path| {
+        path.starts_with("/api/v1/devices/android").then(|| {
+            (
+                200,
+                r#"[{"name":"Pixel 7","id":"pixel-7","manufacturer":"Google","width":1080,"height":2400,"dpi":420,"max_ram_mb":8192,"max_storage_mb":16384}]"#
+                    .to_owned(),
+            )
+        })
+    })
+}
+
+#[test]
+fn test_device_of_the_catalog_is_accepted() {
+    let (code, _) = run_android(&catalog_server(), &["--device", "pixel-7"]);
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_unknown_device_exits_with_2_before_upload() {
+    let (code, stdout) = run_android(&catalog_server(), &["--device", "pixel7"]);
+    assert_eq!(code, 2);
+    assert!(!stdout.contains("Submitting"), "{}", stdout);
+}
+
+#[test]
+fn test_every_device_of_a_device_list_is_validated() {
+    let server = catalog_server();
+    assert_eq!(run_android(&server, &["--device", "phone,pixel-7"]).0, 0);
+    assert_eq!(
+        run_android(&server, &["--device", "pixel-7", "--device", "pixel7"]).0,
+        2
+    );
+}
+
+#[test]
+fn test_device_storage_is_limited_by_the_catalog() {
+    let server = catalog_server();
+    assert_eq!(
+        run_android(
+            &server,
+            &[
+                "--device",
+                "pixel-7",
+                "--device-ram",
+                "8G",
+                "--device-storage",
+                "16384"
+            ]
+        )
+        .0,
+        0
+    );
+    assert_eq!(
+        run_android(&server, &["--device", "pixel-7", "--device-storage", "32G"]).0,
+        2
+    );
+    assert_eq!(run_android(&server, &["--device-ram", "lots"]).0, 2);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::fs;
+
+use common::{finished_run_server, marathon_cloud};
+use tempfile::tempdir;
+
+fn run_bundles(bundle_file: &str, args: &[&str]) -> i32 {
+    let workdir = tempdir().unwrap();
+    fs::create_dir_all(workdir.path().join("apks")).unwrap();
+    for apk in ["app.apk", "app-test.apk", "library,test.apk"] {
+        fs::write(workdir.path().join("apks").join(apk), b"apk").unwrap();
+    }
+    fs::write(workdir.path().join("apks/bundles.yml"), bundle_file).unwrap();
+    let base_url = finished_run_server("passed", |_| None);
+    let mut run_args = vec![
+        "run",
+        "android",
+        "--no-progress-bars",
+        "--base-url",
+        &base_url,
+    ];
+    run_args.extend_from_slice(args);
+    let output = marathon_cloud(&run_args, workdir.path());
+    output.status.code().unwrap()
+}
+
+fn run_bundle_file(bundle_file: &str) -> i32 {
+    run_bundles(bundle_file, &["--bundle-file", "apks/bundles.yml"])
+}
+
+#[test]
+fn test_bundles_of_bundle_file_are_submitted() {
+    let code = run_bundle_file(
+        r#"
+application-bundles:
+  - application: app.apk
+    test-application: app-test.apk
+    flavor: native
+library-bundles:
+  - test-application: "library,test.apk"
+"#,
+    );
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_missing_apk_of_bundle_file_exits_with_2() {
+    let code = run_bundle_file("library-bundles:\n  - test-application: missing.apk\n");
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_unsupported_flavor_of_bundle_exits_with_2() {
+    let code = run_bundle_file(
+        "library-bundles:\n  - test-application: \"library,test.apk\"\n    flavor: espresso\n",
+    );
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_library_bundle_glob_is_expanded() {
+    assert_eq!(run_bundles("", &["--library-bundle", "apks/*test.apk"]), 0);
+}
+
+#[test]
+fn test_library_bundle_glob_without_matches_exits_with_2() {
+    assert_eq!(run_bundles("", &["--library-bundle", "apks/**/*.aab"]), 2);
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{finished_run_server, marathon_cloud};
+use tempfile::tempdir;
+
+const MODULE_MAP: &str = "modules:
+  feature/login: [apks/login-androidTest.apk]
+  feature/profile: [apks/profile-androidTest.apk]
+";
+
+fn git(workdir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(workdir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn repository() -> tempfile::TempDir {
+    let workdir = tempdir().unwrap();
+    for dir in ["apks", "feature/login", "feature/profile"] {
+        fs::create_dir_all(workdir.path().join(dir)).unwrap();
+    }
+    for apk in ["login-androidTest.apk", "profile-androidTest.apk"] {
+        fs::write(workdir.path().join("apks").join(apk), b"apk").unwrap();
+    }
+    fs::write(workdir.path().join("feature/login/Login.kt"), "class Login").unwrap();
+    fs::write(
+        workdir.path().join("feature/profile/Profile.kt"),
+        "class Profile",
+    )
+    .unwrap();
+    fs::write(workdir.path().join("modules.yml"), MODULE_MAP).unwrap();
+    git(workdir.path(), &["init", "-q"]);
+    git(workdir.path(), &["add", "."]);
+    git(workdir.path(), &["commit", "-q", "-m", "initial"]);
+    workdir
+}
+
+fn run_changed_since(workdir: &Path) -> (i32, String) {
+    let base_url = finished_run_server("passed", |_| None);
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+            "--library-bundle",
+            "apks/*.apk",
+            "--changed-since",
+            "HEAD",
+            "--module-map",
+            "modules.yml",
+        ],
+        workdir,
+    );
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_bundles_of_changed_modules_are_submitted() {
+    let workdir = repository();
+    fs::write(
+        workdir.path().join("feature/login/Login.kt"),
+        "class Login {}",
+    )
+    .unwrap();
+    let (code, stdout) = run_changed_since(workdir.path());
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains("1 of 2 bundles changed since HEAD"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_untracked_files_select_their_modules() {
+    let workdir = repository();
+    fs::write(
+        workdir.path().join("feature/profile/Avatar.kt"),
+        "class Avatar",
+    )
+    .unwrap();
+    fs::write(workdir.path().join(".gitignore"), "*.log\n").unwrap();
+    fs::write(workdir.path().join("feature/login/build.log"), "log").unwrap();
+    let (code, stdout) = run_changed_since(workdir.path());
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains("1 of 2 bundles changed since HEAD"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_run_without_changed_modules_is_skipped() {
+    let workdir = repository();
+    let (code, stdout) = run_changed_since(workdir.path());
+    assert_eq!(code, 0);
+    assert!(stdout.contains("skipping the run"), "{}", stdout);
+}
+
+#[test]
+fn test_unknown_revision_exits_with_2() {
+    let workdir = repository();
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--library-bundle",
+            "apks/*.apk",
+            "--changed-since",
+            "origin/unknown",
+            "--module-map",
+            "modules.yml",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_revision_is_not_parsed_as_an_option() {
+    let workdir = repository();
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--library-bundle",
+            "apks/*.apk",
+            "--changed-since=--output=changes.txt",
+            "--module-map",
+            "modules.yml",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!workdir.path().join("changes.txt").exists());
+}
@@ -0,0 +1,237 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{Command, Output},
+    sync::{Arc, OnceLock},
+    thread,
+};
+
+use tempfile::tempdir;
+
+/// Request as seen by the handlers of `http_mock_server`. Header names are lowercase
+pub struct Request {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// Minimal HTTP server answering every request with the response returned by `handler`
+/// for the request path. Returns the base url of the server.
+pub fn mock_server<F>(handler: F) -> String
+where
+    F: Fn(&str) -> (u16, String) + Send + 'static,
+{
+    http_mock_server(move |request| {
+        let (status, body) = handler(&request.path);
+        Response::new(status, body)
+    })
+}
+
+/// Same as `mock_server`, but `handler` also sees the request headers and sets response headers
+pub fn http_mock_server<F>(handler: F) -> String
+where
+    F: Fn(&Request) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            serve(stream, &handler);
+        }
+    });
+    format!("http://127.0.0.1:{}/api", port)
+}
+
+/// Same as `mock_server`, but every request is handled on its own thread, so that requests
+/// sent in parallel are also answered in parallel
+pub fn concurrent_mock_server<F>(handler: F) -> String
+where
+    F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handler = handler.clone();
+            thread::spawn(move || {
+                serve(stream, &|request: &Request| {
+                    let (status, body) = handler(&request.path);
+                    Response::new(status, body)
+                })
+            });
+        }
+    });
+    format!("http://127.0.0.1:{}/api", port)
+}
+
+fn serve<F>(mut stream: TcpStream, handler: &F)
+where
+    F: Fn(&Request) -> Response,
+{
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).is_err() || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+        }
+    }
+    let chunked = headers
+        .get("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"));
+    if chunked {
+        read_chunked_body(&mut reader);
+    } else {
+        let content_length = headers
+            .get("content-length")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; content_length];
+        let _ = reader.read_exact(&mut body);
+    }
+
+    let request = Request {
+        path: request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("/")
+            .to_owned(),
+        headers,
+    };
+    let response = handler(&request);
+    let headers: String = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    let response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        headers,
+        response.body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+//Consumes the body, so that the connection isn't reset while the client is still sending it
+fn read_chunked_body(reader: &mut impl BufRead) {
+    loop {
+        let mut size = String::new();
+        if reader.read_line(&mut size).is_err() {
+            return;
+        }
+        let size = usize::from_str_radix(size.trim(), 16).unwrap_or(0);
+        //Chunk data is followed by CRLF, the last chunk by an empty trailer line
+        let mut chunk = vec![0; size + 2];
+        if reader.read_exact(&mut chunk).is_err() || size == 0 {
+            return;
+        }
+    }
+}
+
+pub fn marathon_cloud(args: &[&str], workdir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_marathon-cloud"))
+        .args(args)
+        .current_dir(workdir)
+        .env("MARATHON_CLOUD_API_KEY", "test-api-key")
+        .env("MARATHON_CLOUD_CACHE_DIR", workdir.join(".cache"))
+        .output()
+        .unwrap()
+}
+
+/// Mock Marathon Cloud backend which accepts uploads and reports the run as finished with `state`.
+/// `routes` can override responses for specific paths.
+pub fn finished_run_server<F>(state: &'static str, routes: F) -> String
+where
+    F: Fn(&str) -> Option<(u16, String)> + Send + 'static,
+{
+    let base_url = Arc::new(OnceLock::<String>::new());
+    let upload_base_url = base_url.clone();
+    let url = mock_server(move |path| {
+        if let Some(response) = routes(path) {
+            response
+        } else if path.starts_with("/api/v1/user/jwt") {
+            (200, r#"{"token":"jwt"}"#.to_owned())
+        } else if path.starts_with("/api/v2/upload/presigned-url") {
+            let url = format!("{}/upload", upload_base_url.get().unwrap());
+            (
+                200,
+                format!(r#"{{"file_path":"uploads/app.apk","url":"{}"}}"#, url),
+            )
+        } else if path.starts_with("/api/upload") {
+            (200, String::new())
+        } else if path.starts_with("/api/v2/run") {
+            (200, r#"{"run_id":"run-1","status":"queued"}"#.to_owned())
+        } else if path.starts_with("/api/v1/run/run-1") {
+            (
+                200,
+                format!(
+                    r#"{{"id":"run-1","state":"{}","passed":1,"failed":0,"ignored":0,"completed":"2024-01-01T00:00:00Z","total_run_time":1.0,"error_message":null}}"#,
+                    state
+                ),
+            )
+        } else {
+            (404, String::new())
+        }
+    });
+    base_url.set(url.clone()).unwrap();
+    url
+}
+
+/// Submits an android run with dummy APKs and returns the exit code and stdout
+pub fn run_android(base_url: &str, args: &[&str]) -> (i32, String) {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let mut run_args = vec![
+        "run",
+        "android",
+        "--application",
+        "app.apk",
+        "--test-application",
+        "test.apk",
+        "--no-progress-bars",
+        "--base-url",
+        base_url,
+    ];
+    run_args.extend_from_slice(args);
+    let output = marathon_cloud(&run_args, workdir.path());
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
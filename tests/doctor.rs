@@ -0,0 +1,50 @@
+mod common;
+
+use common::{marathon_cloud, mock_server};
+use tempfile::tempdir;
+
+#[test]
+fn test_healthy_environment() {
+    let base_url = mock_server(|path| {
+        if path.starts_with("/api/v1/user/jwt") {
+            (200, r#"{"token":"jwt"}"#.to_owned())
+        } else {
+            (404, String::new())
+        }
+    });
+    let workdir = tempdir().unwrap();
+    let output = marathon_cloud(&["doctor", "--base-url", &base_url], workdir.path());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(stdout.contains("API key is valid"));
+    assert!(stdout.contains("is reachable"));
+}
+
+#[test]
+fn test_rejected_api_key() {
+    let base_url = mock_server(|path| {
+        if path.starts_with("/api/v1/user/jwt") {
+            (401, String::new())
+        } else {
+            (404, String::new())
+        }
+    });
+    let workdir = tempdir().unwrap();
+    let output = marathon_cloud(&["doctor", "--base-url", &base_url], workdir.path());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    assert!(stdout.contains("API key was rejected"));
+    assert!(stdout.contains("Double check the API key"));
+}
+
+#[test]
+fn test_unreachable_api() {
+    let workdir = tempdir().unwrap();
+    let output = marathon_cloud(
+        &["doctor", "--base-url", "http://127.0.0.1:1/api"],
+        workdir.path(),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    assert!(stdout.contains("Check the network connection"));
+}
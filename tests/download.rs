@@ -0,0 +1,583 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    process::Output,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use common::{
+    concurrent_mock_server, finished_run_server, http_mock_server, marathon_cloud, Response,
+};
+use md5::{Digest, Md5};
+use tempfile::tempdir;
+
+/// Mock backend serving `artifacts` of run-1 like storage does: with the MD5 as ETag and support
+/// for conditional and range requests. Returns the base url and the number of bytes sent
+fn artifact_server(
+    artifacts: &'static [(&'static str, &'static str)],
+) -> (String, Arc<AtomicUsize>) {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let counter = sent.clone();
+    let base_url = http_mock_server(move |request| {
+        let path = request.path.as_str();
+        if path.starts_with("/api/v1/user/jwt") {
+            Response::new(200, r#"{"token":"jwt"}"#)
+        } else if path.starts_with("/api/v1/run/run-1") {
+            Response::new(
+                200,
+                r#"{"id":"run-1","state":"passed","passed":1,"failed":0,"ignored":0,"completed":"2024-01-01T00:00:00Z","total_run_time":1.0,"error_message":null}"#,
+            )
+        } else if path.starts_with("/api/v1/artifact/run-1") {
+            let list: Vec<String> = artifacts
+                .iter()
+                .map(|(id, content)| {
+                    format!(
+                        r#"{{"id":"{}","name":"{}","is_file":true,"size":{}}}"#,
+                        id,
+                        id.rsplit('/').next().unwrap(),
+                        content.len()
+                    )
+                })
+                .collect();
+            Response::new(200, format!("[{}]", list.join(",")))
+        } else if let Some(query) = path.strip_prefix("/api/v1/artifact?") {
+            let key = url::form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == "key")
+                .map(|(_, value)| value.into_owned())
+                .unwrap();
+            let content = artifacts.iter().find(|(id, _)| *id == key).unwrap().1;
+            let etag = format!("\"{:x}\"", Md5::digest(content.as_bytes()));
+            let header = |name: &str| request.headers.get(name).map(String::as_str);
+            if header("if-none-match") == Some(etag.as_str()) {
+                return Response::new(304, "");
+            }
+            let offset = header("range")
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.strip_suffix('-'))
+                .and_then(|offset| offset.parse::<usize>().ok())
+                .filter(|_| header("if-range").map_or(true, |x| x == etag));
+            let response = match offset {
+                Some(offset) => Response::new(206, &content[offset..]).header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", offset, content.len() - 1, content.len()),
+                ),
+                None => Response::new(200, content),
+            };
+            counter.fetch_add(response.body.len(), Ordering::SeqCst);
+            response.header("ETag", etag)
+        } else {
+            Response::new(404, "")
+        }
+    });
+    (base_url, sent)
+}
+
+fn download(base_url: &str, workdir: &Path) -> Output {
+    marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--no-progress-bars",
+            "--base-url",
+            base_url,
+        ],
+        workdir,
+    )
+}
+
+#[test]
+fn test_complete_files_are_not_downloaded_again() {
+    let (base_url, sent) = artifact_server(&[
+        ("run-1/tests/junit.xml", "<xml>"),
+        ("run-1/logs/test.log", "log"),
+    ]);
+    let workdir = tempdir().unwrap();
+    fs::create_dir_all(workdir.path().join("out/tests")).unwrap();
+    fs::write(workdir.path().join("out/tests/junit.xml"), b"<xml>").unwrap();
+
+    let output = download(&base_url, workdir.path());
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(sent.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        fs::read_to_string(workdir.path().join("out/tests/junit.xml")).unwrap(),
+        "<xml>"
+    );
+    assert_eq!(
+        fs::read_to_string(workdir.path().join("out/logs/test.log")).unwrap(),
+        "log"
+    );
+}
+
+#[test]
+fn test_stale_files_are_downloaded_again() {
+    let (base_url, _) = artifact_server(&[
+        ("run-1/logs/same-size.log", "new"),
+        ("run-1/logs/smaller.log", "complete"),
+    ]);
+    let workdir = tempdir().unwrap();
+    fs::create_dir_all(workdir.path().join("out/logs")).unwrap();
+    fs::write(workdir.path().join("out/logs/same-size.log"), b"old").unwrap();
+    fs::write(workdir.path().join("out/logs/smaller.log"), b"comp").unwrap();
+
+    let output = download(&base_url, workdir.path());
+
+    assert_eq!(output.status.code(), Some(0));
+    let logs = workdir.path().join("out/logs");
+    assert_eq!(
+        fs::read_to_string(logs.join("same-size.log")).unwrap(),
+        "new"
+    );
+    assert_eq!(
+        fs::read_to_string(logs.join("smaller.log")).unwrap(),
+        "complete"
+    );
+}
+
+#[test]
+fn test_partial_download_is_resumed() {
+    let (base_url, sent) = artifact_server(&[("run-1/logs/test.log", "hello")]);
+    let workdir = tempdir().unwrap();
+    let logs = workdir.path().join("out/logs");
+    fs::create_dir_all(&logs).unwrap();
+    fs::write(logs.join("test.log.part"), b"hel").unwrap();
+    fs::write(
+        logs.join("test.log.part.etag"),
+        format!("\"{:x}\"", Md5::digest(b"hello")),
+    )
+    .unwrap();
+
+    let output = download(&base_url, workdir.path());
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(sent.load(Ordering::SeqCst), 2);
+    assert_eq!(fs::read_to_string(logs.join("test.log")).unwrap(), "hello");
+    assert!(!logs.join("test.log.part").exists());
+    assert!(!logs.join("test.log.part.etag").exists());
+}
+
+#[test]
+fn test_partial_download_of_changed_file_is_restarted() {
+    let (base_url, sent) = artifact_server(&[("run-1/logs/test.log", "hello")]);
+    let workdir = tempdir().unwrap();
+    let logs = workdir.path().join("out/logs");
+    fs::create_dir_all(&logs).unwrap();
+    fs::write(logs.join("test.log.part"), b"HEL").unwrap();
+    fs::write(logs.join("test.log.part.etag"), b"\"previous\"").unwrap();
+
+    let output = download(&base_url, workdir.path());
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(sent.load(Ordering::SeqCst), 5);
+    assert_eq!(fs::read_to_string(logs.join("test.log")).unwrap(), "hello");
+}
+
+#[test]
+fn test_keep_going_downloads_remaining_files_and_fails() {
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/logs/broken.log","name":"broken.log","is_file":true},{"id":"run-1/logs/test.log","name":"test.log","is_file":true}]"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") && path.contains("broken") {
+            Some((500, String::new()))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "log".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--keep-going",
+            "--download-concurrency",
+            "1",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to download 1 of 2 artifacts"));
+    assert!(stderr.contains("run-1/logs/broken.log"));
+    assert!(workdir.path().join("out/logs/test.log").is_file());
+}
+
+#[test]
+fn test_latest_run_is_resolved_by_branch() {
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v1/run/latest") && path.contains("branch=develop") {
+            Some((
+                200,
+                r#"{"id":"run-1","state":"passed","passed":1,"failed":0,"ignored":0,"completed":"2024-01-01T00:00:00Z","total_run_time":1.0,"error_message":null}"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact/run-1") {
+            Some((200, "[]".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+    let download = |branch: &str| {
+        marathon_cloud(
+            &[
+                "download",
+                "--latest",
+                "--branch",
+                branch,
+                "--output",
+                "out",
+                "--no-progress-bars",
+                "--base-url",
+                &base_url,
+            ],
+            workdir.path(),
+        )
+    };
+
+    let output = download("develop");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Latest matching test run is run-1"));
+
+    let output = download("feature");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_artifact_types_select_remote_folders() {
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/tests/omni/emulator-1/junit.xml","name":"junit.xml","is_file":true},{"id":"run-1/logs/omni/emulator-1/test.log","name":"test.log","is_file":true},{"id":"run-1/video/omni/emulator-1/test.mp4","name":"test.mp4","is_file":true}]"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "content".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--artifact-type",
+            "junit,logs",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let out = workdir.path().join("out");
+    assert!(out.join("tests/omni/emulator-1/junit.xml").is_file());
+    assert!(out.join("logs/omni/emulator-1/test.log").is_file());
+    assert!(!out.join("video").exists());
+}
+
+#[test]
+fn test_profiling_artifact_type_selects_instruments_traces() {
+    let base_url = finished_run_server("passed", |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/profiling/omni/simulator-1/LoginTests.trace/Trace1.run","name":"Trace1.run","is_file":true},{"id":"run-1/logs/omni/simulator-1/test.log","name":"test.log","is_file":true}]"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "content".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--artifact-type",
+            "profiling",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let out = workdir.path().join("out");
+    assert!(out
+        .join("profiling/omni/simulator-1/LoginTests.trace/Trace1.run")
+        .is_file());
+    assert!(!out.join("logs").exists());
+}
+
+#[test]
+fn test_download_concurrency_limits_parallel_downloads() {
+    let max_parallel_downloads = |concurrency: &str| {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (active_downloads, peak_downloads) = (active.clone(), peak.clone());
+        let base_url = concurrent_mock_server(move |path| {
+            if path.starts_with("/api/v1/user/jwt") {
+                (200, r#"{"token":"jwt"}"#.to_owned())
+            } else if path.starts_with("/api/v1/run/run-1") {
+                (
+                    200,
+                    r#"{"id":"run-1","state":"passed","passed":1,"failed":0,"ignored":0,"completed":"2024-01-01T00:00:00Z","total_run_time":1.0,"error_message":null}"#
+                        .to_owned(),
+                )
+            } else if path.starts_with("/api/v1/artifact/run-1") {
+                let artifacts: Vec<String> = (0..4)
+                    .map(|x| {
+                        format!(
+                            r#"{{"id":"run-1/logs/{0}.log","name":"{0}.log","is_file":true}}"#,
+                            x
+                        )
+                    })
+                    .collect();
+                (200, format!("[{}]", artifacts.join(",")))
+            } else if path.starts_with("/api/v1/artifact?") {
+                let downloads = active_downloads.fetch_add(1, Ordering::SeqCst) + 1;
+                peak_downloads.fetch_max(downloads, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(200));
+                active_downloads.fetch_sub(1, Ordering::SeqCst);
+                (200, "log".to_owned())
+            } else {
+                (404, String::new())
+            }
+        });
+        let workdir = tempdir().unwrap();
+
+        let output = marathon_cloud(
+            &[
+                "download",
+                "--id",
+                "run-1",
+                "--output",
+                "out",
+                "--download-concurrency",
+                concurrency,
+                "--no-progress-bars",
+                "--base-url",
+                &base_url,
+            ],
+            workdir.path(),
+        );
+
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(
+            fs::read_dir(workdir.path().join("out/logs"))
+                .unwrap()
+                .count(),
+            4
+        );
+        peak.load(Ordering::SeqCst)
+    };
+
+    assert_eq!(max_parallel_downloads("1"), 1);
+    assert_eq!(max_parallel_downloads("2"), 2);
+}
+
+#[test]
+fn test_download_concurrency_must_be_positive() {
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &["download", "--id", "run-1", "--download-concurrency", "0"],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_only_failed_downloads_files_of_failed_tests() {
+    let base_url = finished_run_server("failure", move |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/tests/omni/emulator-1/junit.xml","name":"junit.xml","is_file":true},{"id":"run-1/logs/omni/emulator-1/com.example.MainTest#testLogin-1.log","name":"com.example.MainTest#testLogin-1.log","is_file":true},{"id":"run-1/logs/omni/emulator-1/com.example.MainTest#testLoginTwice-1.log","name":"com.example.MainTest#testLoginTwice-1.log","is_file":true}]"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") && path.contains("junit.xml") {
+            Some((
+                200,
+                r#"<testsuite><testcase classname="com.example.MainTest" name="testLogin"><failure>boom</failure></testcase><testcase classname="com.example.MainTest" name="testLoginTwice"/></testsuite>"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "log".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+    let download = |output: &str, args: &[&str]| {
+        let mut download_args = vec![
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            output,
+            "--only-failed",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ];
+        download_args.extend_from_slice(args);
+        marathon_cloud(&download_args, workdir.path())
+    };
+
+    let output = download("all", &[]);
+    assert_eq!(output.status.code(), Some(0));
+    let logs = workdir.path().join("all/logs/omni/emulator-1");
+    assert!(logs.join("com.example.MainTest#testLogin-1.log").is_file());
+    assert!(!logs
+        .join("com.example.MainTest#testLoginTwice-1.log")
+        .exists());
+    assert!(workdir
+        .path()
+        .join("all/tests/omni/emulator-1/junit.xml")
+        .is_file());
+
+    let output = download("logs", &["--exclude-glob", "tests/**"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(workdir
+        .path()
+        .join("logs/logs/omni/emulator-1/com.example.MainTest#testLogin-1.log")
+        .is_file());
+    assert!(!workdir.path().join("logs/tests").exists());
+}
+
+//Allure results reference the logs of the device they were recorded on
+fn allure_run_server() -> String {
+    finished_run_server("passed", |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/report/allure-results/result.json","name":"result.json","is_file":true},{"id":"run-1/logs/omni/emulator-1/test.log","name":"test.log","is_file":true},{"id":"run-1/logs/retry/emulator-1/test.log","name":"test.log","is_file":true}]"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") && path.contains("result.json") {
+            Some((
+                200,
+                r#"{"attachments":[{"source":"/marathon/logs/omni/emulator-1/test.log"}]}"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "log".to_owned()))
+        } else {
+            None
+        }
+    })
+}
+
+fn attachment_source(result: &Path) -> String {
+    let result: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(result).unwrap()).unwrap();
+    result["attachments"][0]["source"]
+        .as_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn test_flatten_keeps_allure_attachments_resolvable() {
+    let base_url = allure_run_server();
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--flatten",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let out = workdir.path().join("out");
+    assert!(out.join("logs_omni_emulator-1_test.log").is_file());
+    assert!(out.join("logs_retry_emulator-1_test.log").is_file());
+    assert_eq!(
+        attachment_source(&out.join("result.json")),
+        "logs_omni_emulator-1_test.log"
+    );
+}
+
+#[test]
+fn test_path_template_keeps_files_apart_and_allure_attachments_resolvable() {
+    let base_url = allure_run_server();
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--path-template",
+            "{device}/{filename}",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let out = workdir.path().join("out");
+    assert!(out
+        .join("emulator-1/logs_omni_emulator-1_test.log")
+        .is_file());
+    assert!(out
+        .join("emulator-1/logs_retry_emulator-1_test.log")
+        .is_file());
+    assert_eq!(
+        attachment_source(&out.join("unknown/result.json")),
+        "../emulator-1/logs_omni_emulator-1_test.log"
+    );
+}
@@ -0,0 +1,360 @@
+mod common;
+
+use common::{finished_run_server, marathon_cloud, mock_server, run_android};
+use tempfile::tempdir;
+
+#[test]
+fn test_passed_run_exits_with_0() {
+    assert_eq!(
+        run_android(&finished_run_server("passed", |_| None), &[]).0,
+        0
+    );
+}
+
+#[test]
+fn test_failed_run_exits_with_1() {
+    assert_eq!(
+        run_android(&finished_run_server("failure", |_| None), &[]).0,
+        1
+    );
+}
+
+#[test]
+fn test_cancelled_run_exits_with_5() {
+    assert_eq!(
+        run_android(&finished_run_server("cancelled", |_| None), &[]).0,
+        5
+    );
+}
+
+#[test]
+fn test_missing_apks_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    let output = marathon_cloud(&["run", "android"], workdir.path());
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_non_existing_apk_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_module_without_gradle_outputs_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    std::fs::create_dir_all(workdir.path().join("app")).unwrap();
+    let output = marathon_cloud(&["run", "android", "--from-gradle", "app"], workdir.path());
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_empty_derived_data_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    std::fs::create_dir_all(workdir.path().join("DerivedData")).unwrap();
+    let output = marathon_cloud(
+        &["run", "ios", "--from-derived-data", "DerivedData"],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_invalid_locale_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(run_android(&base_url, &["--language", "german"]).0, 2);
+    assert_eq!(run_android(&base_url, &["--country", "DEU"]).0, 2);
+    assert_eq!(
+        run_android(&base_url, &["--language", "DE", "--country", "de"]).0,
+        0
+    );
+}
+
+#[test]
+fn test_unknown_network_profile_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(run_android(&base_url, &["--network-profile", "5g"]).0, 2);
+    assert_eq!(run_android(&base_url, &["--network-profile", "3g"]).0, 0);
+}
+
+#[test]
+fn test_unknown_orientation_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(&base_url, &["--orientation", "upside-down"]).0,
+        2
+    );
+    assert_eq!(run_android(&base_url, &["--orientation", "landscape"]).0, 0);
+}
+
+#[test]
+fn test_invalid_simulated_location_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    for location in ["52.52", "91,13.405", "north,east"] {
+        let output = marathon_cloud(
+            &["run", "ios", "--simulated-location", location],
+            workdir.path(),
+        );
+        assert_eq!(output.status.code(), Some(2), "{}", location);
+    }
+}
+
+#[test]
+fn test_unknown_android_permission_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(
+            &base_url,
+            &["--grant-permission", "android.permission.INTERNET"]
+        )
+        .0,
+        2
+    );
+    assert_eq!(
+        run_android(
+            &base_url,
+            &[
+                "--grant-permission",
+                "CAMERA",
+                "--grant-all-runtime-permissions"
+            ]
+        )
+        .0,
+        2
+    );
+    assert_eq!(
+        run_android(
+            &base_url,
+            &[
+                "--grant-permission",
+                "android.permission.CAMERA",
+                "--grant-permission",
+                "POST_NOTIFICATIONS"
+            ]
+        )
+        .0,
+        0
+    );
+}
+
+#[test]
+fn test_invalid_test_timeout_overrides_exit_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    let dir = tempdir().unwrap();
+    let overrides = dir.path().join("overrides.yml");
+    std::fs::write(
+        &overrides,
+        "overrides:\n  - pattern: com.example.video.*\n    timeout: 0\n",
+    )
+    .unwrap();
+    let args = ["--test-timeout-overrides", overrides.to_str().unwrap()];
+    assert_eq!(run_android(&base_url, &args).0, 2);
+
+    std::fs::write(
+        &overrides,
+        "overrides:\n  - pattern: com.example.video.*\n    timeout: 600\n",
+    )
+    .unwrap();
+    assert_eq!(run_android(&base_url, &args).0, 0);
+}
+
+#[test]
+fn test_invalid_retry_policy_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    let dir = tempdir().unwrap();
+    let policy = dir.path().join("retries.yml");
+    std::fs::write(&policy, "overrides:\n  - pattern: com.example.e2e.*\n").unwrap();
+    let policy = policy.to_str().unwrap();
+    assert_eq!(run_android(&base_url, &["--retry-policy", policy]).0, 2);
+
+    std::fs::write(
+        dir.path().join("retries.yml"),
+        "overrides:\n  - pattern: com.example.e2e.*\n    reactive: 3\n",
+    )
+    .unwrap();
+    assert_eq!(run_android(&base_url, &["--retry-policy", policy]).0, 0);
+    assert_eq!(
+        run_android(&base_url, &["--retry-policy", policy, "--no-retries"]).0,
+        2
+    );
+}
+
+#[test]
+fn test_unknown_video_policy_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(&base_url, &["--record-video", "sometimes"]).0,
+        2
+    );
+    assert_eq!(
+        run_android(
+            &base_url,
+            &["--record-video", "on-failure", "--screenshot-on-failure"]
+        )
+        .0,
+        0
+    );
+}
+
+#[test]
+fn test_invalid_app_data_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    let dir = tempdir().unwrap();
+    let archive = dir.path().join("data.zip");
+    std::fs::write(&archive, "not a zip").unwrap();
+    assert_eq!(
+        run_android(&base_url, &["--app-data", archive.to_str().unwrap()]).0,
+        2
+    );
+}
+
+#[test]
+fn test_invalid_push_file_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(&base_url, &["--push-file", "missing.png:/sdcard/image.png"]).0,
+        2
+    );
+    assert_eq!(
+        run_android(&base_url, &["--push-file", "app.apk:sdcard/app.apk"]).0,
+        2
+    );
+    assert_eq!(
+        run_android(
+            &base_url,
+            &["--push-file", "app.apk:/sdcard/Download/app.apk"]
+        )
+        .0,
+        0
+    );
+}
+
+#[test]
+fn test_orchestrator_of_non_native_flavor_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(
+            &base_url,
+            &["--use-orchestrator", "--flavor", "js-jest-appium"]
+        )
+        .0,
+        2
+    );
+    assert_eq!(run_android(&base_url, &["--clear-package-data"]).0, 2);
+    assert_eq!(
+        run_android(&base_url, &["--use-orchestrator", "--clear-package-data"]).0,
+        0
+    );
+}
+
+#[test]
+fn test_unsupported_emulator_arg_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(&base_url, &["--emulator-arg", "wipe-data"]).0,
+        2
+    );
+    assert_eq!(
+        run_android(
+            &base_url,
+            &[
+                "--emulator-arg",
+                "gpu=host",
+                "--emulator-arg",
+                "no-snapshot"
+            ]
+        )
+        .0,
+        0
+    );
+}
+
+#[test]
+fn test_non_existing_additional_apk_exits_with_2() {
+    assert_eq!(
+        run_android(
+            &finished_run_server("passed", |_| None),
+            &["--additional-apk", "feature.apk"]
+        )
+        .0,
+        2
+    );
+}
+
+#[test]
+fn test_unsupported_web_test_bundle_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    std::fs::write(workdir.path().join("e2e.tar"), b"").unwrap();
+    let output = marathon_cloud(
+        &[
+            "run",
+            "web",
+            "--test-bundle",
+            "e2e.tar",
+            "--flavor",
+            "playwright",
+            "--browser",
+            "chromium",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_invalid_api_key_exits_with_3() {
+    let workdir = tempdir().unwrap();
+    let base_url = mock_server(|_| (401, r#"{"error":"unauthorized"}"#.to_owned()));
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_api_failure_exits_with_4() {
+    let workdir = tempdir().unwrap();
+    let base_url = mock_server(|_| (500, r#"{"error":"internal"}"#.to_owned()));
+    let output = marathon_cloud(
+        &[
+            "download",
+            "--id",
+            "run-1",
+            "--output",
+            "out",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn test_invalid_shard_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    assert_eq!(
+        run_android(&base_url, &["--shard-index", "4", "--shard-count", "4"]).0,
+        2
+    );
+}
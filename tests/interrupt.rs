@@ -0,0 +1,159 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    fs,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use common::finished_run_server;
+use tempfile::tempdir;
+
+#[test]
+fn test_interrupt_cancels_remote_run() {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let polls = Arc::new(AtomicUsize::new(0));
+    let (server_cancelled, server_polls) = (cancelled.clone(), polls.clone());
+    let base_url = finished_run_server("cancelled", move |path| {
+        if path.starts_with("/api/v1/run/run-1/cancel") {
+            server_cancelled.store(true, Ordering::SeqCst);
+            Some((200, String::new()))
+        } else if path.starts_with("/api/v1/run/run-1?") && !server_cancelled.load(Ordering::SeqCst)
+        {
+            server_polls.fetch_add(1, Ordering::SeqCst);
+            Some((
+                200,
+                r#"{"id":"run-1","state":"running","passed":null,"failed":null,"ignored":null,"completed":null,"total_run_time":null,"error_message":null}"#.to_owned(),
+            ))
+        } else {
+            None
+        }
+    });
+
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_marathon-cloud"))
+        .args([
+            "run",
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+            "--no-progress-bars",
+            "--poll-interval",
+            "1",
+            "--cancel-on-interrupt",
+            "--base-url",
+            &base_url,
+        ])
+        .current_dir(workdir.path())
+        .env("MARATHON_CLOUD_API_KEY", "test-api-key")
+        .env("MARATHON_CLOUD_CACHE_DIR", workdir.path().join(".cache"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    //Second poll guarantees that the interrupt handler has been installed
+    while polls.load(Ordering::SeqCst) < 2 {
+        thread::sleep(Duration::from_millis(50));
+    }
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+
+    assert!(cancelled.load(Ordering::SeqCst));
+    assert_eq!(status.code(), Some(5));
+}
+
+#[test]
+fn test_interrupt_cancels_every_fanned_out_run_once() {
+    let cancellations = Arc::new(AtomicUsize::new(0));
+    let created = Arc::new(AtomicUsize::new(0));
+    let polled = Arc::new([AtomicBool::new(false), AtomicBool::new(false)]);
+    let (server_cancellations, server_polled) = (cancellations.clone(), polled.clone());
+    let base_url = finished_run_server("cancelled", move |path| {
+        let run = ["run-1", "run-2"]
+            .iter()
+            .position(|id| path.starts_with(&format!("/api/v1/run/{}", id)));
+        if path.starts_with("/api/v2/run") {
+            let index = created.fetch_add(1, Ordering::SeqCst) + 1;
+            Some((
+                200,
+                format!(r#"{{"run_id":"run-{}","status":"queued"}}"#, index),
+            ))
+        } else if path.contains("/cancel") {
+            server_cancellations.fetch_add(1, Ordering::SeqCst);
+            Some((200, String::new()))
+        } else if let Some(run) = run.filter(|_| path.contains('?')) {
+            server_polled[run].store(true, Ordering::SeqCst);
+            let state = if server_cancellations.load(Ordering::SeqCst) < 2 {
+                r#""state":"running","completed":null"#
+            } else {
+                r#""state":"cancelled","completed":"2024-01-01T00:00:00Z""#
+            };
+            Some((
+                200,
+                format!(
+                    r#"{{"id":"run-{}",{},"passed":null,"failed":null,"ignored":null,"total_run_time":null,"error_message":null}}"#,
+                    run + 1,
+                    state
+                ),
+            ))
+        } else {
+            None
+        }
+    });
+
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_marathon-cloud"))
+        .args([
+            "run",
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+            "--os-version",
+            "13,14",
+            "--no-progress-bars",
+            "--poll-interval",
+            "1",
+            "--cancel-on-interrupt",
+            "--base-url",
+            &base_url,
+        ])
+        .current_dir(workdir.path())
+        .env("MARATHON_CLOUD_API_KEY", "test-api-key")
+        .env("MARATHON_CLOUD_CACHE_DIR", workdir.path().join(".cache"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    //Interrupt handler is installed before a run is polled
+    while !polled.iter().all(|x| x.load(Ordering::SeqCst)) {
+        thread::sleep(Duration::from_millis(50));
+    }
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+
+    assert_eq!(cancellations.load(Ordering::SeqCst), 2);
+    assert_eq!(status.code(), Some(5));
+}
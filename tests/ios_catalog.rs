@@ -0,0 +1,60 @@
+mod common;
+
+use common::{marathon_cloud, mock_server};
+use tempfile::tempdir;
+
+fn list_configs(base_url: &str, format: &str) -> (i32, String) {
+    let workdir = tempdir().unwrap();
+    let output = marathon_cloud(
+        &[
+            "run",
+            "ios",
+            "--list-configs",
+            "--format",
+            format,
+            "--base-url",
+            base_url,
+        ],
+        workdir.path(),
+    );
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_list_configs_prints_catalog_of_the_api() {
+    let base_url = mock_server(|path| {
+        if path.starts_with("/api/v1/user/jwt") {
+            (200, r#"{"token":"jwt"}"#.to_owned())
+        } else if path.starts_with("/api/v1/devices/ios") {
+            (
+                200,
+                r#"[{"device":"iPhone-17","deviceType":"com.apple.CoreSimulator.SimDeviceType.iPhone-17","xcodeVersion":"26.0","osVersion":"26.0","runtime":"com.apple.CoreSimulator.SimRuntime.iOS-26-0"}]"#.to_owned(),
+            )
+        } else {
+            (404, String::new())
+        }
+    });
+
+    let (code, stdout) = list_configs(&base_url, "json");
+
+    assert_eq!(code, 0);
+    let configs: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(configs[0]["device"], "iPhone-17");
+    assert_eq!(configs[0]["xcodeVersion"], "26.0");
+    assert_eq!(configs.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_list_configs_falls_back_to_builtin_catalog() {
+    let base_url = mock_server(|_| (500, String::new()));
+
+    let (code, stdout) = list_configs(&base_url, "text");
+
+    assert_eq!(code, 0);
+    assert!(stdout
+        .lines()
+        .any(|line| line == "--xcode-version 15.4 --os-version 17.5 --device iPhone-15"));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use std::fs;
+
+use common::{finished_run_server, run_android};
+use tempfile::tempdir;
+
+#[test]
+fn test_run_per_language_is_summarized() {
+    let (code, stdout) = run_android(
+        &finished_run_server("passed", |_| None),
+        &["--language", "de,fr", "--language", "de"],
+    );
+    assert_eq!(code, 0);
+    assert!(stdout.contains("[2/2]"), "{}", stdout);
+    assert!(stdout.contains("de: passed"), "{}", stdout);
+    assert!(stdout.contains("fr: passed"), "{}", stdout);
+}
+
+#[test]
+fn test_run_per_os_version_and_language() {
+    let dir = tempdir().unwrap();
+    let result_file = dir.path().join("result.yaml");
+    let (code, _) = run_android(
+        &finished_run_server("passed", |_| None),
+        &[
+            "--name",
+            "smoke",
+            "--os-version",
+            "13,14",
+            "--language",
+            "de,ja",
+            "--result-file",
+            result_file.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(code, 0);
+    let result: serde_yaml::Value =
+        serde_yaml::from_slice(&fs::read(&result_file).unwrap()).unwrap();
+    assert_eq!(result["totals"]["runs"], 4);
+    assert_eq!(result["runs"][0]["name"], "smoke (Android 13, de)");
+    assert_eq!(result["runs"][3]["name"], "smoke (Android 14, ja)");
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::fs;
+
+use common::{finished_run_server, marathon_cloud};
+use tempfile::tempdir;
+
+const MATRIX: &str = r#"
+configurations:
+  - name: phones
+    platform: android
+    application: app.apk
+    test-application: test.apk
+    os-versions: [13, 14]
+  - name: e2e
+    platform: web
+    test-application: e2e.zip
+    flavor: playwright
+    devices: [chromium]
+"#;
+
+fn run_matrix(base_url: &str, matrix: &str) -> (i32, String, serde_json::Value) {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    fs::write(workdir.path().join("e2e.zip"), b"e2e").unwrap();
+    fs::write(workdir.path().join("matrix.yml"), matrix).unwrap();
+    let output = marathon_cloud(
+        &[
+            "run",
+            "matrix",
+            "--config",
+            "matrix.yml",
+            "--result-file",
+            "result.json",
+            "--base-url",
+            base_url,
+        ],
+        workdir.path(),
+    );
+    let result = fs::read(workdir.path().join("result.json"))
+        .ok()
+        .and_then(|x| serde_json::from_slice(&x).ok())
+        .unwrap_or_default();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        result,
+    )
+}
+
+#[test]
+fn test_matrix_results_are_aggregated() {
+    let (code, stdout, result) = run_matrix(&finished_run_server("passed", |_| None), MATRIX);
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(stdout.contains("phones (13): passed"), "{}", stdout);
+    assert!(stdout.contains("phones (14): passed"), "{}", stdout);
+    assert!(stdout.contains("e2e: passed"), "{}", stdout);
+
+    let runs = result["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 3);
+    assert_eq!(runs[0]["name"], "phones (13)");
+    assert_eq!(runs[0]["id"], "run-1");
+    assert_eq!(runs[2]["name"], "e2e");
+    assert_eq!(result["totals"]["runs"], 3);
+    assert_eq!(result["totals"]["passed"], 3);
+    assert_eq!(result["totals"]["state"], "passed");
+}
+
+#[test]
+fn test_failed_configuration_exits_with_1() {
+    let (code, _, result) = run_matrix(&finished_run_server("failure", |_| None), MATRIX);
+    assert_eq!(code, 1);
+    assert_eq!(result["runs"][1]["state"], "failure");
+    assert_eq!(result["totals"]["state"], "failure");
+}
+
+#[test]
+fn test_invalid_matrix_exits_with_2() {
+    let base_url = finished_run_server("passed", |_| None);
+    let (code, _, _) = run_matrix(&base_url, "configurations: []");
+    assert_eq!(code, 2);
+    let matrix = "configurations:\n  - platform: android\n    args: [\"--unknown\"]\n";
+    let (code, _, _) = run_matrix(&base_url, matrix);
+    assert_eq!(code, 2);
+}
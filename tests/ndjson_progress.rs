@@ -0,0 +1,60 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use common::{finished_run_server, run_android};
+
+#[test]
+fn test_status_is_printed_as_json_lines() {
+    let base_url = finished_run_server("passed", |path| {
+        if path.starts_with("/api/v1/run/durations") {
+            Some((200, r#"{"durations":[600.0]}"#.to_owned()))
+        } else {
+            None
+        }
+    });
+    let (code, stdout) = run_android(&base_url, &["--ndjson-progress"]);
+    assert_eq!(code, 0, "{}", stdout);
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .filter(|x| x.starts_with('{'))
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1, "{}", stdout);
+    assert_eq!(events[0]["id"], "run-1");
+    assert_eq!(events[0]["state"], "passed");
+    assert_eq!(events[0]["passed"], 1);
+    //Finished runs have nothing left to wait for
+    assert!(events[0]["eta_seconds"].is_null());
+}
+
+#[test]
+fn test_status_of_a_running_run_has_an_estimate() {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v1/run/durations") {
+            Some((200, r#"{"durations":[600.0]}"#.to_owned()))
+        } else if path.split('?').next() == Some("/api/v1/run/run-1")
+            && polls.fetch_add(1, Ordering::SeqCst) == 0
+        {
+            Some((
+                200,
+                r#"{"id":"run-1","state":"running","passed":1,"failed":0,"ignored":0,"total":4,"completed":null,"total_run_time":null,"error_message":null}"#
+                    .to_owned(),
+            ))
+        } else {
+            None
+        }
+    });
+    let (code, stdout) = run_android(&base_url, &["--ndjson-progress", "--poll-interval", "1"]);
+    assert_eq!(code, 0, "{}", stdout);
+    let running: serde_json::Value =
+        serde_json::from_str(stdout.lines().find(|x| x.starts_with('{')).unwrap()).unwrap();
+    assert_eq!(running["state"], "running");
+    assert_eq!(running["total"], 4);
+    let eta = running["eta_seconds"].as_u64().unwrap();
+    assert!((590..=600).contains(&eta), "{}", eta);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use common::{finished_run_server, run_android};
+use tempfile::tempdir;
+
+#[test]
+fn test_run_per_os_version_is_summarized() {
+    let (code, stdout) = run_android(
+        &finished_run_server("passed", |_| None),
+        &["--os-version", "13,14"],
+    );
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Summary:"), "{}", stdout);
+    assert!(stdout.contains("Android 13: passed"), "{}", stdout);
+    assert!(stdout.contains("Android 14: passed"), "{}", stdout);
+}
+
+#[test]
+fn test_applications_are_uploaded_once_for_all_os_versions() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let (code, _) = run_android(
+        &finished_run_server("passed", move |path| {
+            if path.starts_with("/api/upload") {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            None
+        }),
+        &["--os-version", "12,13,14"],
+    );
+    assert_eq!(code, 0);
+    assert_eq!(uploads.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_results_of_all_os_versions_are_written_into_one_result_file() {
+    let dir = tempdir().unwrap();
+    let result_file = dir.path().join("result.yaml");
+    let (code, _) = run_android(
+        &finished_run_server("passed", |_| None),
+        &[
+            "--os-version",
+            "13,14",
+            "--result-file",
+            result_file.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(code, 0);
+    let result: serde_yaml::Value =
+        serde_yaml::from_slice(&fs::read(&result_file).unwrap()).unwrap();
+    assert_eq!(result["runs"][0]["name"], "Android 13");
+    assert_eq!(result["runs"][1]["name"], "Android 14");
+    assert_eq!(result["totals"]["runs"], 2);
+    assert_eq!(result["totals"]["state"], "passed");
+}
+
+#[test]
+fn test_failed_run_of_any_os_version_exits_with_1() {
+    let (code, stdout) = run_android(
+        &finished_run_server("failure", |_| None),
+        &["--os-version", "13", "--os-version", "14"],
+    );
+    assert_eq!(code, 1);
+    assert!(stdout.contains("Android 13: failed"), "{}", stdout);
+}
+
+#[test]
+fn test_unsupported_os_version_of_fan_out_exits_with_2() {
+    let (code, _) = run_android(
+        &finished_run_server("passed", |_| None),
+        &["--os-version", "14,15"],
+    );
+    assert_eq!(code, 2);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{finished_run_server, run_android};
+
+fn real_device_server() -> String {
+    finished_run_server("passed", |path| {
+        path.starts_with("/api/v1/devices/real?platform=android")
+            .then(|| {
+                (
+                    200,
+                    r#"[{"id":"pixel-8","name":"Pixel 8","manufacturer":"Google","osVersion":"14"}]"#
+                        .to_owned(),
+                )
+            })
+    })
+}
+
+#[test]
+fn test_real_device_of_the_catalog_is_accepted() {
+    let (code, _) = run_android(
+        &real_device_server(),
+        &["--device-class", "real", "--device", "pixel-8"],
+    );
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_unknown_real_device_exits_with_2() {
+    let (code, _) = run_android(
+        &real_device_server(),
+        &["--device-class", "real", "--device", "pixel-9"],
+    );
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_mock_location_on_real_device_exits_with_2() {
+    let (code, _) = run_android(
+        &real_device_server(),
+        &[
+            "--device-class",
+            "real",
+            "--device",
+            "pixel-8",
+            "--mock-location",
+        ],
+    );
+    assert_eq!(code, 2);
+}
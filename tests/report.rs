@@ -0,0 +1,147 @@
+mod common;
+
+use std::fs;
+
+use common::{finished_run_server, marathon_cloud};
+use tempfile::tempdir;
+
+#[test]
+fn test_junit_merge_combines_reports() {
+    let workdir = tempdir().unwrap();
+    fs::create_dir_all(workdir.path().join("run-1/tests")).unwrap();
+    fs::write(
+        workdir.path().join("run-1/tests/junit.xml"),
+        r#"<testsuite>
+  <testcase name="testFailed" classname="com.example.MainTest" time="1.0">
+    <failure>java.lang.AssertionError</failure>
+  </testcase>
+</testsuite>"#,
+    )
+    .unwrap();
+    fs::write(
+        workdir.path().join("retry.xml"),
+        r#"<testsuite>
+  <testcase name='testFailed' classname='com.example.MainTest' time='1.0'/>
+  <testcase name='testOther' classname='com.example.MainTest' time='1.0'/>
+</testsuite>"#,
+    )
+    .unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "report",
+            "junit-merge",
+            "run-1",
+            "retry.xml",
+            "--output",
+            "merged.xml",
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Merged 3 results of 2 tests from 2 reports into merged.xml"));
+    let merged = fs::read_to_string(workdir.path().join("merged.xml")).unwrap();
+    assert!(merged.contains(r#"tests="2" failures="0""#));
+}
+
+#[test]
+fn test_junit_merge_reports_missing_inputs() {
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &["report", "junit-merge", "missing", "--output", "merged.xml"],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Input path doesn't exist"));
+}
+
+#[test]
+fn test_html_report_shows_the_log_of_each_failure() {
+    let base_url = finished_run_server("failure", move |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/tests/omni/emulator-1/junit.xml","name":"junit.xml","is_file":true},{"id":"run-1/logs/omni/emulator-1/com.example.MainTest#testLoginTwice-1.log","name":"com.example.MainTest#testLoginTwice-1.log","is_file":true},{"id":"run-1/logs/omni/emulator-1/com.example.MainTest#testLogin-1.log","name":"com.example.MainTest#testLogin-1.log","is_file":true}]"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") && path.contains("junit.xml") {
+            Some((
+                200,
+                r#"<testsuite><testcase classname="com.example.MainTest" name="testLogin"><failure>boom</failure></testcase><testcase classname="com.example.MainTest" name="testLoginTwice"><failure>boom</failure></testcase></testsuite>"#
+                    .to_owned(),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") && path.contains("testLoginTwice") {
+            Some((200, "log of testLoginTwice".to_owned()))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "log of testLogin".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "report",
+            "html",
+            "--id",
+            "run-1",
+            "--output",
+            "report.html",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let html = fs::read_to_string(workdir.path().join("report.html")).unwrap();
+    let failure = |test: &str| {
+        let start = html.find(&format!("MainTest#{}</h3>", test)).unwrap();
+        let end = html[start..].find("</details>").unwrap();
+        html[start..start + end].to_owned()
+    };
+    assert!(failure("testLogin").contains("<pre>log of testLogin</pre>"));
+    assert!(failure("testLoginTwice").contains("<pre>log of testLoginTwice</pre>"));
+}
+
+#[test]
+fn test_xcresult_report_rejects_runs_without_result_bundles() {
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v1/artifact/run-1") {
+            Some((
+                200,
+                r#"[{"id":"run-1/tests/omni/emulator-1/junit.xml","is_file":true}]"#.to_owned(),
+            ))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+    fs::create_dir_all(workdir.path().join("merged.xcresult")).unwrap();
+    fs::write(workdir.path().join("merged.xcresult/Info.plist"), b"plist").unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "report",
+            "xcresult",
+            "--id",
+            "run-1",
+            "--output",
+            "merged.xcresult",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only available for iOS runs"));
+    assert!(workdir.path().join("merged.xcresult/Info.plist").is_file());
+}
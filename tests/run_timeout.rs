@@ -0,0 +1,34 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use common::{finished_run_server, run_android};
+
+#[test]
+fn test_run_timeout_cancels_remote_run() {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let server_cancelled = cancelled.clone();
+    let base_url = finished_run_server("cancelled", move |path| {
+        if path.starts_with("/api/v1/run/run-1/cancel") {
+            server_cancelled.store(true, Ordering::SeqCst);
+            Some((200, String::new()))
+        } else if path.starts_with("/api/v1/run/run-1?") && !server_cancelled.load(Ordering::SeqCst)
+        {
+            Some((
+                200,
+                r#"{"id":"run-1","state":"running","passed":null,"failed":null,"ignored":null,"completed":null,"total_run_time":null,"error_message":null}"#.to_owned(),
+            ))
+        } else {
+            None
+        }
+    });
+
+    let (code, stdout) = run_android(&base_url, &["--poll-interval", "1", "--run-timeout", "1s"]);
+
+    assert!(cancelled.load(Ordering::SeqCst));
+    assert!(stdout.contains("Test run exceeded --run-timeout 1s"));
+    assert_eq!(code, 6);
+}
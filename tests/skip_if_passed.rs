@@ -0,0 +1,70 @@
+mod common;
+
+use std::fs;
+
+use common::{finished_run_server, marathon_cloud, run_android};
+use tempfile::tempdir;
+
+const PREVIOUS_RUN: &str = r#"{"id":"run-0","state":"passed","passed":3,"failed":0,"ignored":0,"completed":"2024-01-01T00:00:00Z","total_run_time":1.0,"error_message":null}"#;
+
+#[test]
+fn test_previous_passed_run_is_reused() {
+    let base_url = finished_run_server("passed", |path| {
+        let latest = path.starts_with("/api/v1/run/latest")
+            && path.contains("fingerprint=")
+            && path.contains("state=passed");
+        if latest || path.starts_with("/api/v1/run/run-0") {
+            Some((200, PREVIOUS_RUN.to_owned()))
+        } else if path.starts_with("/api/v2/run") {
+            Some((500, "unexpected submission".to_owned()))
+        } else {
+            None
+        }
+    });
+    let (code, stdout) = run_android(&base_url, &["--skip-if-passed"]);
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(
+        stdout.contains("Test run run-0 with identical binaries and configuration already passed")
+    );
+}
+
+#[test]
+fn test_run_is_submitted_without_previous_passed_run() {
+    let base_url = finished_run_server("passed", |_| None);
+    let (code, stdout) = run_android(&base_url, &["--skip-if-passed"]);
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(stdout.contains("Submitting new run"));
+}
+
+#[test]
+fn test_remote_binaries_are_never_reused() {
+    let base_url = finished_run_server("passed", |path| {
+        if path.starts_with("/api/v1/run/latest") {
+            Some((200, PREVIOUS_RUN.to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application-url",
+            "https://example.com/test.apk",
+            "--skip-if-passed",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Submitting new run"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--skip-if-passed is ignored"));
+}
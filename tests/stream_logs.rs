@@ -0,0 +1,48 @@
+mod common;
+
+use common::{finished_run_server, run_android};
+
+#[test]
+fn test_stream_logs_prints_test_events() {
+    let base_url = finished_run_server("passed", |path| {
+        path.starts_with("/api/v1/run/run-1/events").then(|| {
+            (
+                200,
+                r#"{"events":[{"type":"started","test":"com.example.Test#test1","device":"emulator-5554"},{"type":"passed","test":"com.example.Test#test1","device":"emulator-5554"}],"cursor":2}"#.to_owned(),
+            )
+        })
+    });
+
+    let (code, stdout) = run_android(&base_url, &["--stream-logs"]);
+
+    assert_eq!(code, 0);
+    assert!(stdout.contains("STARTED com.example.Test#test1 on emulator-5554"));
+    assert!(stdout.contains("PASSED  com.example.Test#test1 on emulator-5554"));
+}
+
+#[test]
+fn test_stream_logs_unavailable_does_not_fail_run() {
+    let base_url = finished_run_server("passed", |_| None);
+
+    let (code, _) = run_android(&base_url, &["--stream-logs"]);
+
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_tui_falls_back_to_stream_logs_when_not_a_terminal() {
+    let base_url = finished_run_server("passed", |path| {
+        path.starts_with("/api/v1/run/run-1/events").then(|| {
+            (
+                200,
+                r#"{"events":[{"type":"failed","test":"com.example.Test#test1","device":"emulator-5554"}],"cursor":1}"#.to_owned(),
+            )
+        })
+    });
+
+    let (code, stdout) = run_android(&base_url, &["--tui", "--stream-logs"]);
+
+    assert_eq!(code, 0);
+    assert!(stdout.contains("FAILED  com.example.Test#test1 on emulator-5554"));
+    assert!(stdout.contains("Marathon Cloud execution finished"));
+}
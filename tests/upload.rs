@@ -0,0 +1,496 @@
+mod common;
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use common::{finished_run_server, marathon_cloud, run_android};
+use tempfile::tempdir;
+
+#[test]
+fn test_transient_upload_failure_is_retried() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        (path.starts_with("/api/upload") && counter.fetch_add(1, Ordering::SeqCst) == 0)
+            .then(|| (503, String::new()))
+    });
+
+    let (code, _) = run_android(&base_url, &[]);
+
+    assert_eq!(code, 0);
+    //Two files are uploaded, one of them twice
+    assert_eq!(uploads.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_expired_presigned_url_is_not_retried() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        path.starts_with("/api/upload").then(|| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            (403, String::new())
+        })
+    });
+
+    let (code, _) = run_android(&base_url, &[]);
+
+    assert_eq!(code, 4);
+    assert_eq!(uploads.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_upload_gives_up_after_configured_retries() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        path.starts_with("/api/upload").then(|| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            (500, String::new())
+        })
+    });
+
+    let (code, _) = run_android(&base_url, &["--upload-retries", "0"]);
+
+    assert_eq!(code, 4);
+    assert_eq!(uploads.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_upload_is_skipped_when_server_has_the_file() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        path.starts_with("/api/v2/upload/md5/")
+            .then(|| (200, r#"{"file_path":"uploads/existing.apk"}"#.to_owned()))
+    });
+
+    let (code, _) = run_android(&base_url, &[]);
+
+    assert_eq!(code, 0);
+    assert_eq!(uploads.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_md5_is_not_used_with_another_checksum() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let counter = lookups.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        path.starts_with("/api/v2/upload/md5/").then(|| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            (200, r#"{"file_path":"uploads/existing.apk"}"#.to_owned())
+        })
+    });
+
+    let (code, _) = run_android(&base_url, &["--upload-checksum", "xxh3"]);
+
+    assert_eq!(code, 0);
+    assert_eq!(lookups.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_md5_is_computed_when_the_server_falls_back_to_it() {
+    let server_url = Arc::new(OnceLock::<String>::new());
+    let upload_url = server_url.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        path.starts_with("/api/v2/upload/presigned-url").then(|| {
+            (
+                200,
+                format!(
+                    r#"{{"file_path":"uploads/app.apk","url":"{}/upload","checksum_algorithm":"md5"}}"#,
+                    upload_url.get().unwrap()
+                ),
+            )
+        })
+    });
+    server_url.set(base_url.clone()).unwrap();
+
+    let (code, _) = run_android(&base_url, &["--upload-checksum", "sha256"]);
+
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_repeated_upload_is_served_from_local_cache() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let args = [
+        "run",
+        "android",
+        "--application",
+        "app.apk",
+        "--test-application",
+        "test.apk",
+        "--no-progress-bars",
+        "--upload-cache-ttl",
+        "12h",
+        "--base-url",
+        &base_url,
+    ];
+
+    assert_eq!(marathon_cloud(&args, workdir.path()).status.code(), Some(0));
+    assert_eq!(marathon_cloud(&args, workdir.path()).status.code(), Some(0));
+
+    assert_eq!(uploads.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_upload_cache_is_disabled_by_default() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let args = [
+        "run",
+        "android",
+        "--application",
+        "app.apk",
+        "--test-application",
+        "test.apk",
+        "--no-progress-bars",
+        "--base-url",
+        &base_url,
+    ];
+
+    assert_eq!(marathon_cloud(&args, workdir.path()).status.code(), Some(0));
+    assert_eq!(marathon_cloud(&args, workdir.path()).status.code(), Some(0));
+
+    assert_eq!(uploads.load(Ordering::SeqCst), 4);
+    assert!(!workdir.path().join(".cache/uploads.json").exists());
+}
+
+#[test]
+fn test_remote_artifacts_are_not_uploaded() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/upload") || path.starts_with("/api/v2/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--application-run-id",
+            "run-0",
+            "--test-application-url",
+            "https://example.com/test.apk",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(uploads.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_remote_artifact_url_must_be_http() {
+    let workdir = tempdir().unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "ios",
+            "--application-url",
+            "file:///tmp/app.zip",
+            "--test-application-run-id",
+            "run-0",
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_oversized_file_fails_before_upload() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    let workdir = tempdir().unwrap();
+    fs::File::create(workdir.path().join("app.apk"))
+        .unwrap()
+        .set_len(5 * 1024 * 1024 * 1024)
+        .unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File is too large to upload"));
+    assert_eq!(uploads.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_bundle_folders_are_streamed_without_dedup() {
+    let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        recorded.lock().unwrap().push(path.to_owned());
+        None
+    });
+    let workdir = tempdir().unwrap();
+    for bundle in ["Sample.app", "SampleUITests.xctest"] {
+        fs::create_dir(workdir.path().join(bundle)).unwrap();
+        fs::write(workdir.path().join(bundle).join("Info.plist"), b"plist").unwrap();
+    }
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "ios",
+            "--application",
+            "Sample.app",
+            "--test-application",
+            "SampleUITests.xctest",
+            "--stream-archives",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|path| path.starts_with("/api/upload"))
+            .count(),
+        2
+    );
+    assert!(!requests
+        .iter()
+        .any(|path| path.starts_with("/api/v2/upload/md5/")));
+    assert!(!workdir.path().join(".cache/zips").exists());
+}
+
+#[test]
+fn test_max_upload_size_is_configurable() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), vec![0; 2 * 1024 * 1024]).unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+            "--max-upload-size",
+            "1M",
+            "--no-progress-bars",
+            "--base-url",
+            &base_url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File is too large to upload"));
+    assert_eq!(uploads.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_upload_limit_of_the_server_takes_precedence() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = Arc::new(OnceLock::<String>::new());
+    let upload_base_url = base_url.clone();
+    let url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v2/upload/presigned-url") {
+            let url = format!("{}/upload", upload_base_url.get().unwrap());
+            return Some((
+                200,
+                format!(
+                    r#"{{"file_path":"uploads/app.apk","url":"{}","max_file_size":2}}"#,
+                    url
+                ),
+            ));
+        }
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    base_url.set(url.clone()).unwrap();
+
+    let (code, _) = run_android(&url, &[]);
+
+    assert_eq!(code, 2);
+    assert_eq!(uploads.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_configured_upload_limit_applies_below_the_server_limit() {
+    let base_url = Arc::new(OnceLock::<String>::new());
+    let upload_base_url = base_url.clone();
+    let url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v2/upload/presigned-url") {
+            let url = format!("{}/upload", upload_base_url.get().unwrap());
+            return Some((
+                200,
+                format!(
+                    r#"{{"file_path":"uploads/app.zip","url":"{}","max_file_size":1073741824}}"#,
+                    url
+                ),
+            ));
+        }
+        None
+    });
+    base_url.set(url.clone()).unwrap();
+    let workdir = tempdir().unwrap();
+    for bundle in ["Sample.app", "SampleUITests.xctest"] {
+        fs::create_dir(workdir.path().join(bundle)).unwrap();
+        fs::write(workdir.path().join(bundle).join("Info.plist"), b"plist").unwrap();
+    }
+    let mut seed = 1u32;
+    let binary: Vec<u8> = (0..2 * 1024 * 1024)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect();
+    fs::write(workdir.path().join("Sample.app/Sample"), binary).unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "ios",
+            "--application",
+            "Sample.app",
+            "--test-application",
+            "SampleUITests.xctest",
+            "--stream-archives",
+            "--max-upload-size",
+            "1M",
+            "--no-progress-bars",
+            "--base-url",
+            &url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File is too large to upload"));
+}
+
+#[test]
+fn test_streamed_archive_is_aborted_above_the_limit() {
+    let uploads = Arc::new(AtomicUsize::new(0));
+    let counter = uploads.clone();
+    let base_url = Arc::new(OnceLock::<String>::new());
+    let upload_base_url = base_url.clone();
+    let url = finished_run_server("passed", move |path| {
+        if path.starts_with("/api/v2/upload/presigned-url") {
+            let url = format!("{}/upload", upload_base_url.get().unwrap());
+            return Some((
+                200,
+                format!(
+                    r#"{{"file_path":"uploads/app.zip","url":"{}","max_file_size":65536}}"#,
+                    url
+                ),
+            ));
+        }
+        if path.starts_with("/api/upload") {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        None
+    });
+    base_url.set(url.clone()).unwrap();
+    let workdir = tempdir().unwrap();
+    for bundle in ["Sample.app", "SampleUITests.xctest"] {
+        fs::create_dir(workdir.path().join(bundle)).unwrap();
+        fs::write(workdir.path().join(bundle).join("Info.plist"), b"plist").unwrap();
+    }
+    //Pseudo-random content doesn't shrink when it's compressed
+    let mut seed = 1u32;
+    let binary: Vec<u8> = (0..1024 * 1024)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect();
+    fs::write(workdir.path().join("Sample.app/Sample"), binary).unwrap();
+
+    let output = marathon_cloud(
+        &[
+            "run",
+            "ios",
+            "--application",
+            "Sample.app",
+            "--test-application",
+            "SampleUITests.xctest",
+            "--stream-archives",
+            "--no-progress-bars",
+            "--base-url",
+            &url,
+        ],
+        workdir.path(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File is too large to upload"));
+    //The small bundle may be uploaded before the large one is aborted, but nothing is retried
+    assert!(uploads.load(Ordering::SeqCst) <= 2);
+}
@@ -0,0 +1,144 @@
+use std::{fs, path::Path, process::Output};
+
+use tempfile::tempdir;
+
+//Neither an API key nor a reachable backend are needed
+fn validate(args: &[&str], workdir: &Path) -> Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_marathon-cloud"))
+        .arg("validate")
+        .args(args)
+        .args(["--base-url", "http://127.0.0.1:1"])
+        .current_dir(workdir)
+        .env_remove("MARATHON_CLOUD_API_KEY")
+        .env("MARATHON_CLOUD_CACHE_DIR", workdir.join(".cache"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_valid_run_is_not_submitted() {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let output = validate(
+        &[
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+            "--device",
+            "pixel-7",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Configuration is valid, nothing was submitted"));
+}
+
+#[test]
+fn test_invalid_run_exits_with_2() {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let output = validate(
+        &[
+            "android",
+            "--application",
+            "missing.apk",
+            "--test-application",
+            "test.apk",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.apk"));
+}
+
+#[test]
+fn test_every_run_of_a_matrix_is_validated() {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), b"app").unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    fs::write(
+        workdir.path().join("matrix.yaml"),
+        "configurations:\n  - platform: android\n    application: app.apk\n    test-application: test.apk\n    os-versions: [13, 14]\n",
+    )
+    .unwrap();
+    let output = validate(&["matrix", "--config", "matrix.yaml"], workdir.path());
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout
+            .matches("Configuration is valid, nothing was submitted")
+            .count(),
+        2,
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_every_failed_check_is_reported() {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let output = validate(
+        &[
+            "android",
+            "--application",
+            "missing.apk",
+            "--test-application",
+            "test.apk",
+            "--result-file",
+            "result.txt",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 checks failed"), "{}", stderr);
+    assert!(stderr.contains("missing.apk"), "{}", stderr);
+    assert!(stderr.contains("Invalid file extension"), "{}", stderr);
+}
+
+#[test]
+fn test_files_exceeding_the_upload_limit_are_reported() {
+    let workdir = tempdir().unwrap();
+    fs::write(workdir.path().join("app.apk"), vec![0; 2 * 1024 * 1024]).unwrap();
+    fs::write(workdir.path().join("test.apk"), b"test").unwrap();
+    let output = validate(
+        &[
+            "android",
+            "--application",
+            "app.apk",
+            "--test-application",
+            "test.apk",
+            "--max-upload-size",
+            "1M",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File is too large to upload"));
+}
+
+#[test]
+fn test_bundles_are_checked_without_zipping() {
+    let workdir = tempdir().unwrap();
+    for bundle in ["Sample.app", "SampleUITests.xctest"] {
+        fs::create_dir(workdir.path().join(bundle)).unwrap();
+        fs::write(workdir.path().join(bundle).join("Info.plist"), b"plist").unwrap();
+    }
+    let output = validate(
+        &[
+            "ios",
+            "--application",
+            "Sample.app",
+            "--test-application",
+            "SampleUITests.xctest",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!workdir.path().join(".cache/marathon-cloud/zips").exists());
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::fs;
+
+use common::{finished_run_server, marathon_cloud};
+use tempfile::tempdir;
+
+fn run(id: &str, state: &str) -> Option<(u16, String)> {
+    Some((
+        200,
+        format!(
+            r#"{{"id":"{}","state":"{}","passed":2,"failed":0,"ignored":0,"completed":"2024-01-01T00:00:00Z","total_run_time":1.0,"error_message":null}}"#,
+            id, state
+        ),
+    ))
+}
+
+fn wait(base_url: &str, args: &[&str], workdir: &std::path::Path) -> (i32, String) {
+    let mut wait_args = vec!["wait", "--no-progress-bars", "--base-url", base_url];
+    wait_args.extend_from_slice(args);
+    let output = marathon_cloud(&wait_args, workdir);
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_artifacts_of_every_run_are_downloaded_into_subfolders() {
+    let base_url = finished_run_server("passed", |path| {
+        if path.starts_with("/api/v1/run/run-2") {
+            run("run-2", "passed")
+        } else if let Some(id) = path
+            .strip_prefix("/api/v1/artifact/")
+            .filter(|x| !x.contains('?'))
+        {
+            Some((
+                200,
+                format!(
+                    r#"[{{"id":"{}/logs/test.log","name":"test.log","is_file":true}}]"#,
+                    id
+                ),
+            ))
+        } else if path.starts_with("/api/v1/artifact?") {
+            Some((200, "log".to_owned()))
+        } else {
+            None
+        }
+    });
+    let workdir = tempdir().unwrap();
+    let (code, stdout) = wait(
+        &base_url,
+        &[
+            "--id",
+            "run-1",
+            "--id",
+            "run-2",
+            "--output",
+            "out",
+            "--result-file",
+            "result.json",
+        ],
+        workdir.path(),
+    );
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(stdout.contains("STATE"), "{}", stdout);
+    let result: serde_json::Value =
+        serde_json::from_slice(&fs::read(workdir.path().join("result.json")).unwrap()).unwrap();
+    assert_eq!(result["runs"][1]["id"], "run-2");
+    assert_eq!(result["totals"]["runs"], 2);
+    assert_eq!(result["totals"]["passed"], 3);
+    for id in ["run-1", "run-2"] {
+        assert_eq!(
+            fs::read_to_string(workdir.path().join("out").join(id).join("logs/test.log")).unwrap(),
+            "log"
+        );
+    }
+}
+
+#[test]
+fn test_failure_of_any_run_exits_with_1() {
+    let base_url = finished_run_server("passed", |path| {
+        path.starts_with("/api/v1/run/run-2")
+            .then(|| run("run-2", "failure"))
+            .flatten()
+    });
+    let workdir = tempdir().unwrap();
+    let (code, stdout) = wait(
+        &base_url,
+        &["--id", "run-1", "--id", "run-2"],
+        workdir.path(),
+    );
+    assert_eq!(code, 1);
+    assert!(stdout.contains("run-2  failure"), "{}", stdout);
+}
+
+#[test]
+fn test_cancelled_run_exits_with_5() {
+    let base_url = finished_run_server("passed", |path| {
+        path.starts_with("/api/v1/run/run-2")
+            .then(|| run("run-2", "cancelled"))
+            .flatten()
+    });
+    let workdir = tempdir().unwrap();
+    let (code, _) = wait(
+        &base_url,
+        &["--id", "run-1", "--id", "run-2"],
+        workdir.path(),
+    );
+    assert_eq!(code, 5);
+}